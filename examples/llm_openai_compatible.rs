@@ -0,0 +1,15 @@
+use langchain_rust::{language_models::llm::LLM, llm::openai::OpenAI};
+
+#[tokio::main]
+async fn main() {
+    // Any OpenAI-compatible server works: vLLM, LM Studio, Together, Groq...
+    // Here we use a local vLLM server started with:
+    // vllm serve meta-llama/Meta-Llama-3-8B-Instruct
+    let llm = OpenAI::default()
+        .with_api_base("http://localhost:8000/v1")
+        .with_api_key("EMPTY")
+        .with_model("meta-llama/Meta-Llama-3-8B-Instruct");
+
+    let response = llm.invoke("Why is the sky blue?").await.unwrap();
+    println!("{}", response);
+}
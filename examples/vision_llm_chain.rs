@@ -2,7 +2,7 @@ use base64::prelude::*;
 use langchain_rust::chain::{Chain, LLMChainBuilder};
 use langchain_rust::llm::OpenAI;
 use langchain_rust::prompt::HumanMessagePromptTemplate;
-use langchain_rust::schemas::Message;
+use langchain_rust::schemas::{ImageSource, Message};
 use langchain_rust::{fmt_message, fmt_template, message_formatter, prompt_args, template_fstring};
 
 #[tokio::main]
@@ -15,9 +15,10 @@ async fn main() {
         fmt_template!(HumanMessagePromptTemplate::new(template_fstring!(
             "{input}", "input"
         ))),
-        fmt_message!(Message::new_human_message_with_images(vec![format!(
-            "data:image/jpeg;base64,{image_base64}"
-        )])),
+        fmt_message!(Message::new_human_message_with_images(
+            "",
+            vec![ImageSource::base64("image/jpeg", image_base64)]
+        )),
     ];
 
     // let open_ai = OpenAI::new(langchain_rust::llm::ollama::openai::OllamaConfig::default())
@@ -1,15 +1,25 @@
-use std::sync::Arc;
+use std::{pin::Pin, sync::Arc};
 
 use async_trait::async_trait;
+use futures::{stream, Stream};
+use serde_json::json;
 
 use crate::{
-    prompt::PromptArgs,
-    schemas::agent::{AgentAction, AgentEvent},
-    tools::Tool,
+    chain::LLMChain,
+    prompt::{
+        MessageOrTemplate, PromptArgs, PromptTemplate, SystemMessagePromptTemplate, TemplateFormat,
+    },
+    schemas::{
+        agent::{AgentAction, AgentEvent, AgentPlanChunk},
+        messages::Message,
+    },
+    tools::{normalize_tool_name, Tool},
 };
 
 use super::AgentError;
 
+pub type AgentPlanStream = Pin<Box<dyn Stream<Item = Result<AgentPlanChunk, AgentError>> + Send>>;
+
 #[async_trait]
 pub trait Agent: Send + Sync {
     async fn plan(
@@ -18,5 +28,92 @@ pub trait Agent: Send + Sync {
         inputs: PromptArgs,
     ) -> Result<AgentEvent, AgentError>;
 
+    /// Same as `plan`, streaming the LLM call: when it turns out to produce the final
+    /// answer, its tokens are yielded as they are generated, before the event. Used by
+    /// `AgentExecutor::stream_final_answer`. The default implementation calls `plan` and
+    /// only yields the event.
+    async fn plan_stream(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<AgentPlanStream, AgentError> {
+        let event = self.plan(intermediate_steps, inputs).await?;
+        Ok(Box::pin(stream::iter(vec![Ok(AgentPlanChunk::Event(
+            event,
+        ))])))
+    }
+
     fn get_tools(&self) -> Vec<Arc<dyn Tool>>;
+
+    /// Whether the agent's prompt declares the `chat_history` placeholder. The executor
+    /// only injects the conversation history when this returns true.
+    fn uses_chat_history(&self) -> bool {
+        true
+    }
+
+    /// Renders the messages `plan` would send to the LLM, without calling it. Used by
+    /// `AgentExecutor::with_dry_run`; agents that can't render their prompt return an error.
+    fn render_plan_prompt(
+        &self,
+        _intermediate_steps: &[(AgentAction, String)],
+        _inputs: PromptArgs,
+    ) -> Result<Vec<Message>, AgentError> {
+        Err(AgentError::OtherError(
+            "This agent doesn't support rendering its prompt".to_string(),
+        ))
+    }
+}
+
+/// Builds the system message of an agent prompt. When `templated`, a prefix referencing
+/// variables, e.g. `Today is {{date}}`, becomes a template rendered on every planning call,
+/// so the values can be passed as inputs or through `AgentExecutor::with_dynamic_context`.
+/// Otherwise the prefix is sent as is, braces included.
+pub(crate) fn system_prefix(prefix: &str, templated: bool) -> MessageOrTemplate {
+    let template = PromptTemplate::new(prefix.to_string(), vec![], TemplateFormat::Jinja2);
+    let variables = template.referenced_variables();
+    if !templated || variables.is_empty() {
+        return MessageOrTemplate::Message(Message::new_system_message(prefix));
+    }
+    MessageOrTemplate::Template(Box::new(SystemMessagePromptTemplate::new(
+        PromptTemplate::new(prefix.to_string(), variables, TemplateFormat::Jinja2),
+    )))
+}
+
+/// Adds the messages of the steps so far as the `agent_scratchpad` of a chat prompt.
+pub(crate) fn scratchpad_inputs(inputs: PromptArgs, scratchpad: Vec<Message>) -> PromptArgs {
+    let mut inputs = inputs;
+    inputs.insert("agent_scratchpad".to_string(), json!(scratchpad));
+    inputs
+}
+
+/// Renders the chat prompt of an agent chain without calling the LLM, for
+/// `Agent::render_plan_prompt`. `chat_history` defaults to an empty history.
+pub(crate) fn render_chat_prompt(
+    chain: &LLMChain,
+    inputs: PromptArgs,
+) -> Result<Vec<Message>, AgentError> {
+    let mut inputs = inputs;
+    inputs
+        .entry("chat_history".to_string())
+        .or_insert_with(|| json!([]));
+    Ok(chain.render_prompt(inputs)?)
+}
+
+/// Fails when two tools have the same name once normalized, as the agent executor
+/// couldn't tell them apart.
+pub(crate) fn check_tool_names(tools: &[Arc<dyn Tool>]) -> Result<(), AgentError> {
+    let mut names: Vec<(String, String)> = Vec::with_capacity(tools.len());
+    for tool in tools {
+        let name = tool.name();
+        let normalized = normalize_tool_name(&name);
+        if let Some((first, _)) = names.iter().find(|(_, other)| *other == normalized) {
+            return Err(AgentError::ToolNameCollision {
+                first: first.clone(),
+                second: name,
+                normalized,
+            });
+        }
+        names.push((name, normalized));
+    }
+    Ok(())
 }
@@ -0,0 +1,82 @@
+use crate::chain::options::ChainCallOptions;
+
+/// Implements the tool setters shared by the agent builders, on a builder with a
+/// `tools: Option<Vec<Arc<dyn Tool>>>` field.
+macro_rules! impl_tool_methods {
+    ($builder:ty) => {
+        impl $builder {
+            /// Adds a tool to the ones set so far, e.g. when assembling a toolset
+            /// conditionally. Wrap tools that are expensive to build in a
+            /// [`crate::tools::LazyTool`], so they're only built if the agent calls them.
+            pub fn add_tool(mut self, tool: std::sync::Arc<dyn $crate::tools::Tool>) -> Self {
+                self.tools.get_or_insert_with(Vec::new).push(tool);
+                self
+            }
+
+            /// Same as `tools`, but each tool can come with a description that replaces
+            /// `Tool::description` for this agent only, see [`crate::tools::DescribedTool`].
+            pub fn tools_with_descriptions(
+                mut self,
+                tools: &[(std::sync::Arc<dyn $crate::tools::Tool>, Option<String>)],
+            ) -> Self {
+                self.tools = Some(
+                    tools
+                        .iter()
+                        .map(|(tool, description)| {
+                            $crate::tools::DescribedTool::wrap(tool.clone(), description.clone())
+                        })
+                        .collect(),
+                );
+                self
+            }
+        }
+    };
+}
+
+/// Options of the agent LLM calls when none are set.
+pub(crate) fn default_options() -> ChainCallOptions {
+    ChainCallOptions::default().with_max_tokens(1000)
+}
+
+/// Implements the setters of the agent LLM call options shared by the agent builders, on
+/// a builder with an `options: Option<ChainCallOptions>` field.
+macro_rules! impl_option_methods {
+    ($builder:ty) => {
+        impl $builder {
+            /// Sets the sampling temperature of the agent LLM calls, on top of the options
+            /// set so far. A later call to `options` replaces it.
+            pub fn temperature(self, temperature: f32) -> Self {
+                self.update_options(|options| options.with_temperature(temperature))
+            }
+
+            /// Sets the nucleus sampling probability of the agent LLM calls.
+            pub fn top_p(self, top_p: f32) -> Self {
+                self.update_options(|options| options.with_top_p(top_p))
+            }
+
+            /// Sets the maximum tokens generated per step. Defaults to 1000.
+            pub fn max_tokens(self, max_tokens: u32) -> Self {
+                self.update_options(|options| options.with_max_tokens(max_tokens))
+            }
+
+            /// Sets the sampling seed, for more deterministic runs on models that support it.
+            pub fn seed(self, seed: usize) -> Self {
+                self.update_options(|options| options.with_seed(seed))
+            }
+
+            fn update_options(
+                mut self,
+                f: impl FnOnce(
+                    $crate::chain::options::ChainCallOptions,
+                ) -> $crate::chain::options::ChainCallOptions,
+            ) -> Self {
+                let options = self
+                    .options
+                    .take()
+                    .unwrap_or_else($crate::agent::builder_methods::default_options);
+                self.options = Some(f(options));
+                self
+            }
+        }
+    };
+}
@@ -1,9 +1,10 @@
 use std::sync::Arc;
 
 use crate::{
-    agent::AgentError,
+    agent::{agent::check_tool_names, AgentError, SystemSegments},
     chain::{llm_chain::LLMChainBuilder, options::ChainCallOptions},
     language_models::llm::LLM,
+    schemas::agent::AgentAction,
     tools::Tool,
 };
 
@@ -16,30 +17,70 @@ use super::{
 pub struct ConversationalAgentBuilder {
     tools: Option<Vec<Arc<dyn Tool>>>,
     prefix: Option<String>,
+    templated_prefix: bool,
+    system_segments: Option<SystemSegments>,
     suffix: Option<String>,
     options: Option<ChainCallOptions>,
+    format_instructions: Option<String>,
+    strict_parsing: bool,
+    example_steps: Vec<(AgentAction, String)>,
 }
 
+impl_tool_methods!(ConversationalAgentBuilder);
+impl_option_methods!(ConversationalAgentBuilder);
+
 impl ConversationalAgentBuilder {
     pub fn new() -> Self {
         Self {
             tools: None,
             prefix: None,
+            templated_prefix: false,
+            system_segments: None,
             suffix: None,
             options: None,
+            format_instructions: None,
+            strict_parsing: false,
+            example_steps: Vec::new(),
         }
     }
 
+    /// Sets the tools of the agent, replacing any added before. Tools that are expensive to
+    /// build can be wrapped in a `LazyTool`, so they are only built when first called.
     pub fn tools(mut self, tools: &[Arc<dyn Tool>]) -> Self {
         self.tools = Some(tools.to_vec());
         self
     }
 
+    /// Sets the system message. It can't be combined with `system_segments` or
+    /// `system_segment`.
     pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
         self.prefix = Some(prefix.into());
         self
     }
 
+    /// Renders the system message as a Jinja2 template on every planning call, so a prefix
+    /// like `Today is {{date}}` gets its values from the inputs or from
+    /// `AgentExecutor::with_dynamic_context`. Off by default: the prefix is sent as is.
+    pub fn templated_prefix(mut self, templated_prefix: bool) -> Self {
+        self.templated_prefix = templated_prefix;
+        self
+    }
+
+    /// Builds the system message from `segments`. It can't be combined with `prefix`.
+    pub fn system_segments(mut self, segments: SystemSegments) -> Self {
+        self.system_segments = Some(segments);
+        self
+    }
+
+    /// Replaces or adds one segment of the system message, keeping the others: the ones
+    /// set with `system_segments`, or `ConversationalAgent::default_system_segments`.
+    pub fn system_segment<N: Into<String>, T: Into<String>>(mut self, name: N, text: T) -> Self {
+        self.system_segments
+            .get_or_insert_with(ConversationalAgent::default_system_segments)
+            .set(name, text);
+        self
+    }
+
     pub fn suffix<S: Into<String>>(mut self, suffix: S) -> Self {
         self.suffix = Some(suffix.into());
         self
@@ -50,25 +91,69 @@ impl ConversationalAgentBuilder {
         self
     }
 
+    /// Overrides the response format instructions placed in the prompt. The agent's
+    /// output parser reports the same instructions, so prompt and parser stay in sync.
+    pub fn format_instructions<S: Into<String>>(mut self, format_instructions: S) -> Self {
+        self.format_instructions = Some(format_instructions.into());
+        self
+    }
+
+    /// Fail with an `AgentError` when the model output isn't a well formed JSON block,
+    /// instead of repairing it or treating it as the final answer.
+    pub fn strict_parsing(mut self, strict: bool) -> Self {
+        self.strict_parsing = strict;
+        self
+    }
+
+    /// Few-shot (action, observation) pairs shown at the start of the scratchpad of every
+    /// step, to demonstrate the expected response format. They aren't real steps: their
+    /// tools are never called and they aren't returned by the executor.
+    pub fn with_example_steps(mut self, example_steps: Vec<(AgentAction, String)>) -> Self {
+        self.example_steps = example_steps;
+        self
+    }
+
     pub fn build<L: Into<Box<dyn LLM>>>(self, llm: L) -> Result<ConversationalAgent, AgentError> {
         let tools = self.tools.unwrap_or_default();
-        let prefix = self.prefix.unwrap_or_else(|| PREFIX.to_string());
+        check_tool_names(&tools)?;
+        let prefix =
+            match (self.system_segments, self.prefix) {
+                (Some(_), Some(_)) => return Err(AgentError::OtherError(
+                    "prefix can't be combined with system segments, set the prefix as a segment"
+                        .to_string(),
+                )),
+                (Some(segments), None) => segments.join(),
+                (None, Some(prefix)) => prefix,
+                (None, None) => PREFIX.to_string(),
+            };
         let suffix = self.suffix.unwrap_or_else(|| SUFFIX.to_string());
 
-        let prompt = ConversationalAgent::create_prompt(&tools, &suffix, &prefix)?;
-        let default_options = ChainCallOptions::default().with_max_tokens(1000);
-        let chain = Box::new(
-            LLMChainBuilder::new()
-                .prompt(prompt)
-                .llm(llm)
-                .options(self.options.unwrap_or(default_options))
-                .build()?,
-        );
+        let mut output_parser = ChatOutputParser::new().with_strict(self.strict_parsing);
+        if let Some(format_instructions) = self.format_instructions {
+            output_parser = output_parser.with_format_instructions(format_instructions);
+        }
+
+        let prompt = ConversationalAgent::build_prompt(
+            &tools,
+            &suffix,
+            &prefix,
+            self.templated_prefix,
+            output_parser.get_format_instructions(),
+        )?;
+        let chain = LLMChainBuilder::new()
+            .prompt(prompt)
+            .llm(llm)
+            .options(
+                self.options
+                    .unwrap_or_else(crate::agent::builder_methods::default_options),
+            )
+            .build()?;
 
         Ok(ConversationalAgent {
             chain,
             tools,
-            output_parser: ChatOutputParser::new(),
+            output_parser,
+            example_steps: self.example_steps,
         })
     }
 }
@@ -1,11 +1,16 @@
 use std::sync::Arc;
 
+use async_stream::stream;
 use async_trait::async_trait;
-use serde_json::json;
+use futures::StreamExt;
 
 use crate::{
-    agent::{agent::Agent, chat::prompt::FORMAT_INSTRUCTIONS, AgentError},
-    chain::chain_trait::Chain,
+    agent::{
+        agent::{render_chat_prompt, scratchpad_inputs, system_prefix, Agent, AgentPlanStream},
+        chat::prompt::{FORMAT_INSTRUCTIONS, PREFIX_CAPABILITIES, PREFIX_ROLE, PREFIX_SUMMARY},
+        AgentError, SystemSegments,
+    },
+    chain::{chain_trait::Chain, LLMChain},
     message_formatter,
     prompt::{
         HumanMessagePromptTemplate, MessageFormatterStruct, MessageOrTemplate, PromptArgs,
@@ -13,19 +18,20 @@ use crate::{
     },
     prompt_args,
     schemas::{
-        agent::{AgentAction, AgentEvent},
+        agent::{AgentAction, AgentEvent, AgentPlanChunk},
         messages::Message,
     },
     template_jinja2,
-    tools::Tool,
+    tools::{normalize_tool_name, Tool},
 };
 
 use super::{output_parser::ChatOutputParser, prompt::TEMPLATE_TOOL_RESPONSE};
 
 pub struct ConversationalAgent {
-    pub(crate) chain: Box<dyn Chain>,
+    pub(crate) chain: LLMChain,
     pub(crate) tools: Vec<Arc<dyn Tool>>,
     pub(crate) output_parser: ChatOutputParser,
+    pub(crate) example_steps: Vec<(AgentAction, String)>,
 }
 
 impl ConversationalAgent {
@@ -33,15 +39,62 @@ impl ConversationalAgent {
         tools: &[Arc<dyn Tool>],
         suffix: &str,
         prefix: &str,
+    ) -> Result<MessageFormatterStruct, AgentError> {
+        Self::create_prompt_with_format_instructions(tools, suffix, prefix, FORMAT_INSTRUCTIONS)
+    }
+
+    /// Same as `create_prompt`, with the system message joined from `segments`.
+    pub fn create_prompt_from_segments(
+        tools: &[Arc<dyn Tool>],
+        suffix: &str,
+        segments: &SystemSegments,
+    ) -> Result<MessageFormatterStruct, AgentError> {
+        Self::create_prompt(tools, suffix, &segments.join())
+    }
+
+    /// The default system message, `PREFIX`, as the `role`, `capabilities` and `summary`
+    /// segments.
+    pub fn default_system_segments() -> SystemSegments {
+        SystemSegments::new()
+            .with_segment("role", PREFIX_ROLE)
+            .with_segment("capabilities", PREFIX_CAPABILITIES)
+            .with_segment("summary", PREFIX_SUMMARY)
+    }
+
+    /// Same as `create_prompt`, but `format_instructions` replaces the default
+    /// `FORMAT_INSTRUCTIONS` in the suffix.
+    pub fn create_prompt_with_format_instructions(
+        tools: &[Arc<dyn Tool>],
+        suffix: &str,
+        prefix: &str,
+        format_instructions: &str,
+    ) -> Result<MessageFormatterStruct, AgentError> {
+        Self::build_prompt(tools, suffix, prefix, false, format_instructions)
+    }
+
+    /// Same as `create_prompt_with_format_instructions`, rendering `prefix` as a template
+    /// when `templated_prefix` is set, see `ConversationalAgentBuilder::templated_prefix`.
+    pub(crate) fn build_prompt(
+        tools: &[Arc<dyn Tool>],
+        suffix: &str,
+        prefix: &str,
+        templated_prefix: bool,
+        format_instructions: &str,
     ) -> Result<MessageFormatterStruct, AgentError> {
         let tool_string = tools
             .iter()
-            .map(|tool| format!("> {}: {}", tool.name(), tool.description()))
+            .map(|tool| {
+                format!(
+                    "> {}: {}",
+                    normalize_tool_name(&tool.name()),
+                    tool.description()
+                )
+            })
             .collect::<Vec<_>>()
             .join("\n");
         let tool_names = tools
             .iter()
-            .map(|tool| tool.name())
+            .map(|tool| normalize_tool_name(&tool.name()))
             .collect::<Vec<_>>()
             .join(", ");
 
@@ -49,13 +102,13 @@ impl ConversationalAgent {
 
         let input_variables_fstring = prompt_args! {
             "tools" => tool_string,
-            "format_instructions" => FORMAT_INSTRUCTIONS,
+            "format_instructions" => format_instructions,
             "tool_names"=>tool_names
         };
 
         let sufix_prompt = sufix_prompt.format(input_variables_fstring)?;
         let formatter = message_formatter![
-            MessageOrTemplate::Message(Message::new_system_message(prefix)),
+            system_prefix(prefix, templated_prefix),
             MessageOrTemplate::MessagesPlaceholder("chat_history".to_string()),
             MessageOrTemplate::Template(
                 HumanMessagePromptTemplate::new(template_jinja2!(
@@ -74,7 +127,7 @@ impl ConversationalAgent {
         intermediate_steps: &[(AgentAction, String)],
     ) -> Result<Vec<Message>, AgentError> {
         let mut thoughts: Vec<Message> = Vec::new();
-        for (action, observation) in intermediate_steps.iter() {
+        for (action, observation) in self.example_steps.iter().chain(intermediate_steps) {
             thoughts.push(Message::new_ai_message(&action.log));
             let tool_response = template_jinja2!(TEMPLATE_TOOL_RESPONSE, "observation")
                 .format(prompt_args!("observation"=>observation))?;
@@ -82,6 +135,31 @@ impl ConversationalAgent {
         }
         Ok(thoughts)
     }
+
+    fn plan_inputs(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<PromptArgs, AgentError> {
+        let scratchpad = self.construct_scratchpad(intermediate_steps)?;
+        Ok(scratchpad_inputs(inputs, scratchpad))
+    }
+
+    /// Renders the messages `plan` would send to the LLM for the given steps and inputs,
+    /// without calling it. `chat_history` defaults to an empty history.
+    ///
+    /// # Usage
+    /// ```rust,ignore
+    /// let messages = agent.render_plan_prompt(&steps, prompt_args! {"input" => "1 + 1"})?;
+    /// assert_eq!(messages.last().unwrap().message_type, MessageType::HumanMessage);
+    /// ```
+    pub fn render_plan_prompt(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<Vec<Message>, AgentError> {
+        render_chat_prompt(&self.chain, self.plan_inputs(intermediate_steps, inputs)?)
+    }
 }
 
 #[async_trait]
@@ -91,17 +169,76 @@ impl Agent for ConversationalAgent {
         intermediate_steps: &[(AgentAction, String)],
         inputs: PromptArgs,
     ) -> Result<AgentEvent, AgentError> {
-        let scratchpad = self.construct_scratchpad(intermediate_steps)?;
-        let mut inputs = inputs.clone();
-        inputs.insert("agent_scratchpad".to_string(), json!(scratchpad));
-        let output = self.chain.call(inputs.clone()).await?.generation;
+        let inputs = self.plan_inputs(intermediate_steps, inputs)?;
+        let output = self
+            .chain
+            .call_with_callbacks(inputs, &[])
+            .await?
+            .generation;
         let parsed_output = self.output_parser.parse(&output)?;
         Ok(parsed_output)
     }
 
+    async fn plan_stream(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<AgentPlanStream, AgentError> {
+        let inputs = self.plan_inputs(intermediate_steps, inputs)?;
+        let mut llm_stream = self.chain.stream(inputs).await?;
+        let output_parser = self.output_parser.clone();
+
+        Ok(Box::pin(stream! {
+            let mut output = String::new();
+            let mut answer = String::new();
+            while let Some(data) = llm_stream.next().await {
+                match data {
+                    Ok(data) => output.push_str(&data.content),
+                    Err(e) => {
+                        yield Err(e.into());
+                        return;
+                    }
+                }
+                if let Some(partial) = output_parser.partial_final_answer(&output) {
+                    if partial.len() > answer.len() && partial.starts_with(answer.as_str()) {
+                        yield Ok(AgentPlanChunk::Answer(partial[answer.len()..].to_string()));
+                        answer = partial;
+                    }
+                }
+            }
+
+            match output_parser.parse(&output) {
+                Ok(AgentEvent::Finish(finish)) => {
+                    // The last tokens may only be known once the whole output is parsed
+                    if let Some(rest) = finish.output.strip_prefix(answer.as_str()) {
+                        if !answer.is_empty() && !rest.is_empty() {
+                            yield Ok(AgentPlanChunk::Answer(rest.to_string()));
+                        }
+                    }
+                    yield Ok(AgentPlanChunk::Event(AgentEvent::Finish(finish)));
+                }
+                event => yield event.map(AgentPlanChunk::Event),
+            }
+        }))
+    }
+
     fn get_tools(&self) -> Vec<Arc<dyn Tool>> {
         self.tools.clone()
     }
+
+    fn uses_chat_history(&self) -> bool {
+        self.chain
+            .get_input_keys()
+            .contains(&"chat_history".to_string())
+    }
+
+    fn render_plan_prompt(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<Vec<Message>, AgentError> {
+        ConversationalAgent::render_plan_prompt(self, intermediate_steps, inputs)
+    }
 }
 
 #[cfg(test)]
@@ -112,12 +249,20 @@ mod tests {
     use serde_json::Value;
 
     use crate::{
-        agent::{chat::builder::ConversationalAgentBuilder, executor::AgentExecutor},
+        agent::{
+            chat::{builder::ConversationalAgentBuilder, prompt::PREFIX, ConversationalAgent},
+            executor::AgentExecutor,
+            AgentError,
+        },
         chain::chain_trait::Chain,
-        llm::openai::{OpenAI, OpenAIModel},
+        llm::{
+            fake::FakeLLM,
+            openai::{OpenAI, OpenAIModel},
+        },
         memory::SimpleMemory,
         prompt_args,
-        tools::Tool,
+        schemas::{agent::AgentAction, messages::MessageType},
+        tools::{LazyTool, Tool},
     };
 
     struct Calc {}
@@ -165,4 +310,256 @@ mod tests {
             Err(e) => panic!("Error invoking LLMChain: {:?}", e),
         }
     }
+
+    #[tokio::test]
+    async fn test_tool_description_override() {
+        let llm = FakeLLM::new().with_responses(vec![
+            "```json\n{\"action\": \"Final Answer\", \"action_input\": \"done\"}\n```",
+        ]);
+        let calc: Arc<dyn Tool> = Arc::new(Calc {});
+        let agent = ConversationalAgentBuilder::new()
+            .tools_with_descriptions(&[(calc, Some("Adds numbers for kids".to_string()))])
+            .build(llm.clone())
+            .unwrap();
+        let executor = AgentExecutor::from_agent(agent);
+
+        let result = executor
+            .invoke(prompt_args! {"input" => "1 + 1"})
+            .await
+            .unwrap();
+
+        assert_eq!(result, "done");
+        let prompt = llm.calls()[0]
+            .iter()
+            .map(|m| m.content.clone())
+            .collect::<String>();
+        assert!(prompt.contains("> Calculator: Adds numbers for kids"));
+        assert!(!prompt.contains("Usefull to make calculations"));
+    }
+
+    #[tokio::test]
+    async fn test_custom_format_instructions() {
+        let llm = FakeLLM::new().with_responses(vec![
+            "```json\n{\"action\": \"Final Answer\", \"action_input\": \"hecho\"}\n```",
+        ]);
+        let instructions = "RESPONDE SIEMPRE con un bloque json con action y action_input";
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Calc {})])
+            .format_instructions(instructions)
+            .build(llm.clone())
+            .unwrap();
+        assert_eq!(agent.output_parser.get_format_instructions(), instructions);
+
+        let executor = AgentExecutor::from_agent(agent);
+        let result = executor
+            .invoke(prompt_args! {"input" => "1 + 1"})
+            .await
+            .unwrap();
+
+        assert_eq!(result, "hecho");
+        let prompt = llm.calls()[0]
+            .iter()
+            .map(|m| m.content.clone())
+            .collect::<String>();
+        assert!(prompt.contains(instructions));
+        assert!(!prompt.contains("RESPONSE FORMAT INSTRUCTIONS"));
+    }
+
+    #[test]
+    fn test_render_plan_prompt() {
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Calc {})])
+            .build(FakeLLM::new())
+            .unwrap();
+        let steps = vec![(
+            AgentAction {
+                tool: "Calculator".to_string(),
+                tool_input: "5 * 5".into(),
+                log: "calculating".to_string(),
+            },
+            "25".to_string(),
+        )];
+
+        let messages = agent
+            .render_plan_prompt(&steps, prompt_args! {"input" => "What is 5 * 5?"})
+            .unwrap();
+
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].message_type, MessageType::SystemMessage);
+        assert!(messages[1].content.contains("What is 5 * 5?"));
+        assert_eq!(messages[2].message_type, MessageType::AIMessage);
+        assert_eq!(messages[2].content, "calculating");
+        assert!(messages[3].content.contains("25"));
+    }
+
+    struct Named(&'static str);
+
+    #[async_trait]
+    impl Tool for Named {
+        fn name(&self) -> String {
+            self.0.to_string()
+        }
+        fn description(&self) -> String {
+            "Searches the web".to_string()
+        }
+        async fn run(&self, _input: Value) -> Result<String, Box<dyn Error>> {
+            Ok("result".to_string())
+        }
+    }
+
+    #[test]
+    fn test_tool_names_are_normalized() {
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Named(" Web Search "))])
+            .build(FakeLLM::new())
+            .unwrap();
+        let messages = agent
+            .render_plan_prompt(&[], prompt_args! {"input" => "hi"})
+            .unwrap();
+        let prompt = messages
+            .iter()
+            .map(|m| m.content.clone())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(prompt.contains("> Web_Search: Searches the web"));
+        assert!(!prompt.contains("Web Search"));
+
+        let err = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Named("Web Search")), Arc::new(Named("Web_Search"))])
+            .build(FakeLLM::new())
+            .err()
+            .unwrap();
+        assert!(matches!(err, AgentError::ToolNameCollision { .. }));
+    }
+
+    #[test]
+    fn test_prefix_is_literal_unless_templated() {
+        let prefix = "Answer with {{\"key\": value}} or {{name}}.";
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Calc {})])
+            .prefix(prefix)
+            .build(FakeLLM::new())
+            .unwrap();
+        let messages = agent
+            .render_plan_prompt(&[], prompt_args! {"input" => "hi"})
+            .unwrap();
+        assert_eq!(messages[0].content, prefix);
+
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Calc {})])
+            .prefix(prefix)
+            .templated_prefix(true)
+            .build(FakeLLM::new())
+            .unwrap();
+        assert!(agent
+            .render_plan_prompt(&[], prompt_args! {"input" => "hi"})
+            .is_err());
+    }
+
+    #[test]
+    fn test_add_lazy_tool() {
+        let search = Arc::new(LazyTool::new("Web Search", "Searches the web", || {
+            Arc::new(Named("Web Search")) as Arc<dyn Tool>
+        }));
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Calc {})])
+            .add_tool(search.clone())
+            .build(FakeLLM::new())
+            .unwrap();
+        let messages = agent
+            .render_plan_prompt(&[], prompt_args! {"input" => "hi"})
+            .unwrap();
+        let prompt = messages
+            .iter()
+            .map(|m| m.content.clone())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(prompt.contains("> Calculator:"));
+        assert!(prompt.contains("> Web_Search: Searches the web"));
+        assert!(!search.is_initialized());
+    }
+
+    #[tokio::test]
+    async fn test_sampling_options() {
+        let llm = FakeLLM::new().with_responses(vec![
+            "```json\n{\"action\": \"Final Answer\", \"action_input\": \"done\"}\n```",
+        ]);
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Calc {})])
+            .temperature(0.0)
+            .seed(42)
+            .build(llm.clone())
+            .unwrap();
+        AgentExecutor::from_agent(agent)
+            .invoke(prompt_args! {"input" => "hi"})
+            .await
+            .unwrap();
+
+        let options = &llm.call_options()[0];
+        assert_eq!(options.temperature, Some(0.0));
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.max_tokens, Some(1000));
+    }
+
+    #[test]
+    fn test_system_segments() {
+        assert_eq!(
+            ConversationalAgent::default_system_segments().join(),
+            PREFIX.trim()
+        );
+
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Calc {})])
+            .system_segment("summary", "")
+            .system_segment("rules", "Always answer in Spanish.")
+            .build(FakeLLM::new())
+            .unwrap();
+        let messages = agent
+            .render_plan_prompt(&[], prompt_args! {"input" => "hi"})
+            .unwrap();
+        let system = &messages[0].content;
+        assert!(system.starts_with("Assistant is designed to be able to assist"));
+        assert!(system.ends_with("\n\nAlways answer in Spanish."));
+        assert!(!system.contains("Overall, Assistant is a powerful system"));
+
+        let conflicting = ConversationalAgentBuilder::new()
+            .prefix("You are a travel assistant.")
+            .system_segment("rules", "Always answer in Spanish.")
+            .build(FakeLLM::new());
+        assert!(matches!(conflicting, Err(AgentError::OtherError(_))));
+    }
+
+    #[test]
+    fn test_example_steps_prepended_to_scratchpad() {
+        let example = (
+            AgentAction {
+                tool: "Calculator".to_string(),
+                tool_input: "2 + 2".into(),
+                log: "example action".to_string(),
+            },
+            "4".to_string(),
+        );
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Calc {})])
+            .with_example_steps(vec![example])
+            .build(FakeLLM::new())
+            .unwrap();
+        let steps = vec![(
+            AgentAction {
+                tool: "Calculator".to_string(),
+                tool_input: "5 * 5".into(),
+                log: "real action".to_string(),
+            },
+            "25".to_string(),
+        )];
+
+        let messages = agent
+            .render_plan_prompt(&steps, prompt_args! {"input" => "What is 5 * 5?"})
+            .unwrap();
+
+        assert_eq!(messages.len(), 6);
+        assert_eq!(messages[2].content, "example action");
+        assert!(messages[3].content.contains('4'));
+        assert_eq!(messages[4].content, "real action");
+    }
 }
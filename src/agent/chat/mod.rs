@@ -5,3 +5,4 @@ mod prompt;
 
 pub use builder::*;
 pub use chat_agent::*;
+pub use output_parser::*;
@@ -1,4 +1,4 @@
-use std::collections::VecDeque;
+use std::sync::OnceLock;
 
 use regex::Regex;
 use serde::Deserialize;
@@ -6,6 +6,7 @@ use serde_json::Value;
 
 use crate::{
     agent::AgentError,
+    output_parsers::{extract_partial_json_block, parse_partial_json},
     schemas::agent::{AgentAction, AgentEvent, AgentFinish},
 };
 
@@ -14,28 +15,73 @@ use super::prompt::FORMAT_INSTRUCTIONS;
 #[derive(Debug, Deserialize)]
 struct AgentOutput {
     action: String,
-    action_input: String,
+    action_input: Value,
 }
 
-pub struct ChatOutputParser {}
+/// What is known so far about an action that is still being streamed.
+/// `action` is only set once the action name has been fully emitted, while
+/// `action_input` may contain the partial input received until now.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PartialAgentAction {
+    pub action: Option<String>,
+    pub action_input: Option<String>,
+    pub is_complete: bool,
+}
+
+#[derive(Clone)]
+pub struct ChatOutputParser {
+    format_instructions: String,
+    strict: bool,
+}
 impl ChatOutputParser {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            format_instructions: FORMAT_INSTRUCTIONS.to_string(),
+            strict: false,
+        }
+    }
+
+    /// In strict mode malformed JSON and outputs without a JSON block are reported as
+    /// errors, instead of closing unbalanced braces or treating the text as the final
+    /// answer. Defaults to `false`.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Replaces the default `FORMAT_INSTRUCTIONS`, the agent uses these same instructions
+    /// in its prompt so both stay in sync.
+    pub fn with_format_instructions<S: Into<String>>(mut self, format_instructions: S) -> Self {
+        self.format_instructions = format_instructions.into();
+        self
     }
 }
 
 impl ChatOutputParser {
     pub fn parse(&self, text: &str) -> Result<AgentEvent, AgentError> {
         log::debug!("Parsing to Agent Action: {}", text);
-        match parse_json_markdown(text) {
+        let value = match extract_json_markdown(text) {
+            Some(json) if self.strict => Some(serde_json::from_str::<Value>(json)?),
+            Some(json) => parse_partial_json(json, false),
+            None if self.strict => {
+                return Err(AgentError::OtherError(format!(
+                    "No JSON block found in agent output: {}",
+                    text
+                )))
+            }
+            None => None,
+        };
+        match value {
             Some(value) => {
                 // Deserialize the Value into AgentOutput
                 let agent_output: AgentOutput = serde_json::from_value(value)?;
 
                 if agent_output.action == "Final Answer" {
-                    Ok(AgentEvent::Finish(AgentFinish {
-                        output: agent_output.action_input,
-                    }))
+                    let output = match agent_output.action_input {
+                        Value::String(output) => clean_final_answer(&output),
+                        output => output.to_string(),
+                    };
+                    Ok(AgentEvent::Finish(AgentFinish::new(output)))
                 } else {
                     Ok(AgentEvent::Action(vec![AgentAction {
                         tool: agent_output.action,
@@ -46,66 +92,288 @@ impl ChatOutputParser {
             }
             None => {
                 log::debug!("No JSON found or malformed JSON in text: {}", text);
-                Ok(AgentEvent::Finish(AgentFinish {
-                    output: text.to_string(),
-                }))
+                Ok(AgentEvent::Finish(AgentFinish::from_unparsed(text)))
+            }
+        }
+    }
+
+    /// Parses an agent output that may still be streaming, returning what is
+    /// confidently known about the action. Returns `None` when nothing can be told yet.
+    ///
+    /// # Usage
+    /// ```rust,ignore
+    /// let partial = parser.try_parse_partial("```json\n{\"action\": \"Search\", \"action_inp");
+    /// assert_eq!(partial.unwrap().action, Some("Search".to_string()));
+    /// ```
+    pub fn try_parse_partial(&self, text: &str) -> Option<PartialAgentAction> {
+        let json = extract_partial_json_block(text)?;
+        let is_complete = serde_json::from_str::<Value>(json).is_ok();
+
+        if let Some(value) = parse_partial_json(json, false) {
+            if let Some(action) = value["action"].as_str() {
+                let action_input = match &value["action_input"] {
+                    Value::Null => None,
+                    Value::String(input) => Some(input.clone()),
+                    input => Some(input.to_string()),
+                };
+                return Some(PartialAgentAction {
+                    action: Some(action.to_string()),
+                    action_input,
+                    is_complete,
+                });
             }
         }
+
+        static ACTION: OnceLock<Regex> = OnceLock::new();
+        static ACTION_INPUT: OnceLock<Regex> = OnceLock::new();
+        let action = ACTION
+            .get_or_init(|| Regex::new(r#""action"\s*:\s*"((?:[^"\\]|\\.)*)""#).unwrap())
+            .captures(json)
+            .and_then(|caps| unescape_json_string(&caps[1]));
+        let action_input = ACTION_INPUT
+            .get_or_init(|| Regex::new(r#""action_input"\s*:\s*"((?:[^"\\]|\\.)*)"#).unwrap())
+            .captures(json)
+            .and_then(|caps| unescape_json_string(caps[1].trim_end_matches('\\')));
+
+        if action.is_none() && action_input.is_none() {
+            return None;
+        }
+
+        Some(PartialAgentAction {
+            action,
+            action_input,
+            is_complete,
+        })
+    }
+
+    /// The final answer received so far while the output is still streaming, or `None`
+    /// until the output is known to be a final answer that `parse` returns unchanged.
+    /// Inputs that aren't strings are never partial, and answers starting with a fence may
+    /// be an agent blob that `parse` unwraps, so both are left to `parse`.
+    pub(crate) fn partial_final_answer(&self, text: &str) -> Option<String> {
+        static STRING_INPUT: OnceLock<Regex> = OnceLock::new();
+        let partial = self.try_parse_partial(text)?;
+        if partial.action.as_deref() != Some("Final Answer") {
+            return None;
+        }
+        let json = extract_partial_json_block(text)?;
+        if !STRING_INPUT
+            .get_or_init(|| Regex::new(r#""action_input"\s*:\s*""#).unwrap())
+            .is_match(json)
+        {
+            return None;
+        }
+        let answer = partial.action_input?;
+        let start = answer.trim_start();
+        if start.starts_with("```") || "```".starts_with(start) {
+            return None;
+        }
+        Some(answer)
     }
 
     pub fn get_format_instructions(&self) -> &str {
-        FORMAT_INSTRUCTIONS
+        &self.format_instructions
     }
 }
 
-fn parse_partial_json(s: &str, strict: bool) -> Option<Value> {
-    // First, attempt to parse the string as-is.
-    match serde_json::from_str::<Value>(s) {
-        Ok(val) => return Some(val),
-        Err(_) if !strict => (),
-        Err(_) => return None,
+fn unescape_json_string(s: &str) -> Option<String> {
+    serde_json::from_str::<String>(&format!("\"{}\"", s)).ok()
+}
+
+/// Returns the JSON blob of the agent output, dropping any prose around it. Fences inside
+/// the blob strings, e.g. a final answer holding a code block, don't end the blob.
+fn extract_json_markdown(json_markdown: &str) -> Option<&str> {
+    if let Some(fence) = json_markdown.find("```") {
+        let block = &json_markdown[fence + 3..];
+        let block = block.strip_prefix("json").unwrap_or(block).trim_start();
+        if block.starts_with('{') {
+            if let Some(object) = balanced_json_object(block) {
+                return Some(object);
+            }
+        }
+        let re = Regex::new(r"```(?:json)?\s*([\s\S]+?)\s*```").unwrap();
+        return re
+            .captures(json_markdown)
+            .and_then(|caps| caps.get(1))
+            .map(|json_str| json_str.as_str());
     }
+    // Without a fence, accept a bare agent JSON object surrounded by prose.
+    let start = json_markdown.find('{')?;
+    balanced_json_object(&json_markdown[start..]).filter(|object| object.contains("\"action\""))
+}
 
-    let mut new_s = String::new();
-    let mut stack: VecDeque<char> = VecDeque::new();
-    let mut is_inside_string = false;
+/// Returns the object starting at the beginning of `text` up to its matching brace,
+/// ignoring braces inside strings.
+fn balanced_json_object(text: &str) -> Option<&str> {
+    let mut depth = 0;
+    let mut in_string = false;
     let mut escaped = false;
-
-    for char in s.chars() {
-        match char {
-            '"' if !escaped => is_inside_string = !is_inside_string,
-            '{' if !is_inside_string => stack.push_back('}'),
-            '[' if !is_inside_string => stack.push_back(']'),
-            '}' | ']' if !is_inside_string => {
-                if let Some(c) = stack.pop_back() {
-                    if c != char {
-                        return None; // Mismatched closing character
-                    }
-                } else {
-                    return None; // Unbalanced closing character
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '{' if !in_string => depth += 1,
+            '}' if !in_string => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&text[..=i]);
                 }
             }
-            '\\' if is_inside_string => escaped = !escaped,
-            _ => escaped = false,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Unwraps a final answer that is itself a fenced agent blob, which models sometimes copy
+/// from the response format, using its input. Any other answer, e.g. a code block, is
+/// returned unchanged.
+fn clean_final_answer(output: &str) -> String {
+    let trimmed = output.trim();
+    let inner = match trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .filter(|inner| !inner.contains("```"))
+    {
+        Some(inner) => inner.trim(),
+        None => return output.to_string(),
+    };
+    match serde_json::from_str::<AgentOutput>(inner) {
+        Ok(AgentOutput {
+            action,
+            action_input: Value::String(input),
+        }) if action == "Final Answer" => input,
+        Ok(AgentOutput {
+            action,
+            action_input,
+        }) if action == "Final Answer" => action_input.to_string(),
+        _ => output.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_parse_partial() {
+        let parser = ChatOutputParser::new();
+
+        assert_eq!(parser.try_parse_partial("```json\n{\"act"), None);
+
+        let partial = parser
+            .try_parse_partial("```json\n{\"action\": \"Sear")
+            .unwrap_or_default();
+        assert_eq!(partial.action, None);
+
+        let partial = parser
+            .try_parse_partial(
+                "```json\n{\"action\": \"Search\", \"action_input\": \"rust \\\"lang",
+            )
+            .unwrap();
+        assert_eq!(partial.action, Some("Search".to_string()));
+        assert_eq!(partial.action_input, Some("rust \"lang".to_string()));
+        assert!(!partial.is_complete);
+
+        let partial = parser
+            .try_parse_partial("```json\n{\"action\": \"Search\", \"action_input\": \"rust\"}\n```")
+            .unwrap();
+        assert_eq!(partial.action_input, Some("rust".to_string()));
+        assert!(partial.is_complete);
+    }
+
+    #[test]
+    fn test_parse_structured_action_input() {
+        let parser = ChatOutputParser::new();
+        let text = r#"```json
+{"action": "Search", "action_input": {"query": "rust", "limit": 3}}
+```"#;
+
+        match parser.parse(text).unwrap() {
+            AgentEvent::Action(actions) => {
+                assert_eq!(actions[0].tool_input["query"], "rust");
+                assert_eq!(actions[0].tool_input["limit"], 3);
+            }
+            event => panic!("expected an action, got {:?}", event),
+        }
+
+        let text = "```json\n{\"action\": \"Search\", \"action_input\": \"rust\"}\n```";
+        match parser.parse(text).unwrap() {
+            AgentEvent::Action(actions) => assert_eq!(actions[0].tool_input, "rust"),
+            event => panic!("expected an action, got {:?}", event),
         }
-        new_s.push(char);
     }
 
-    // Close any open structures.
-    while let Some(c) = stack.pop_back() {
-        new_s.push(c);
+    #[test]
+    fn test_strict_parsing() {
+        let unbalanced = "```json\n{\"action\": \"Search\", \"action_input\": \"rust\"\n```";
+
+        let lenient = ChatOutputParser::new().parse(unbalanced).unwrap();
+        assert!(matches!(lenient, AgentEvent::Action(_)));
+
+        let strict = ChatOutputParser::new().with_strict(true);
+        assert!(matches!(
+            strict.parse(unbalanced),
+            Err(AgentError::SerdeJsonError(_))
+        ));
+        assert!(matches!(
+            strict.parse("I just answer without json"),
+            Err(AgentError::OtherError(_))
+        ));
+        let valid = "```json\n{\"action\": \"Final Answer\", \"action_input\": \"hi\"}\n```";
+        assert!(matches!(strict.parse(valid), Ok(AgentEvent::Finish(_))));
     }
 
-    // Attempt to parse again.
-    serde_json::from_str(&new_s).ok()
-}
+    #[test]
+    fn test_nested_fences_in_action_input() {
+        let parser = ChatOutputParser::new();
+        let text = "Sure! Here it is:\n```json\n{\"action\": \"Search\", \"action_input\": \
+                    \"find ```json\\n{}\\n``` examples\"}\n```\nHope that helps.";
+        match parser.parse(text).unwrap() {
+            AgentEvent::Action(actions) => {
+                assert_eq!(actions[0].tool_input, "find ```json\n{}\n``` examples")
+            }
+            event => panic!("expected an action, got {:?}", event),
+        }
+
+        let text = "```json\n{\"action\": \"Final Answer\", \"action_input\": \
+                    \"```json\\n{\\\"action\\\": \\\"Final Answer\\\", \
+                    \\\"action_input\\\": \\\"42\\\"}\\n```\"}\n```";
+        match parser.parse(text).unwrap() {
+            AgentEvent::Finish(finish) => assert_eq!(finish.output, "42"),
+            event => panic!("expected a finish, got {:?}", event),
+        }
 
-fn parse_json_markdown(json_markdown: &str) -> Option<Value> {
-    let re = Regex::new(r"```(?:json)?\s*([\s\S]+?)\s*```").unwrap();
-    if let Some(caps) = re.captures(json_markdown) {
-        if let Some(json_str) = caps.get(1) {
-            return parse_partial_json(json_str.as_str(), false);
+        let text = "```json\n{\"action\": \"Final Answer\", \"action_input\": \
+                    \"```python\\nprint(1)\\n```\"}\n```";
+        match parser.parse(text).unwrap() {
+            AgentEvent::Finish(finish) => assert_eq!(finish.output, "```python\nprint(1)\n```"),
+            event => panic!("expected a finish, got {:?}", event),
         }
+
+        let text = "I will search.\n{\"action\": \"Search\", \"action_input\": \"rust\"} ok";
+        assert!(matches!(parser.parse(text).unwrap(), AgentEvent::Action(_)));
+    }
+
+    #[test]
+    fn test_partial_final_answer() {
+        let parser = ChatOutputParser::new();
+
+        let text = "```json\n{\"action\": \"Final Answer\", \"action_input\": \"The ans";
+        assert_eq!(
+            parser.partial_final_answer(text),
+            Some("The ans".to_string())
+        );
+
+        let text = "```json\n{\"action\": \"Search\", \"action_input\": \"rust";
+        assert_eq!(parser.partial_final_answer(text), None);
+
+        let text = "```json\n{\"action\": \"Final Answer\", \"action_input\": {\"a\": 1";
+        assert_eq!(parser.partial_final_answer(text), None);
+
+        let text = "```json\n{\"action\": \"Final Answer\", \"action_input\": \"```json\\n{";
+        assert_eq!(parser.partial_final_answer(text), None);
     }
-    None
 }
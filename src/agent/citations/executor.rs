@@ -0,0 +1,179 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    agent::{Agent, AgentExecutor},
+    chain::{Chain, ChainError},
+    language_models::GenerateResult,
+    prompt::PromptArgs,
+};
+
+use super::CitationStore;
+
+const SNIPPET_LENGTH: usize = 200;
+
+/// A document cited in an answer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    pub id: String,
+    /// The beginning of the document content.
+    pub snippet: String,
+    pub metadata: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CitedAnswer {
+    pub answer: String,
+    pub sources: Vec<Source>,
+}
+
+/// Runs an agent using [`CitingRetrieverTool`](super::CitingRetrieverTool)s and collects
+/// the documents cited in its final answer. `call` sets the [`CitedAnswer`] as the
+/// `parsed` value of the result.
+///
+/// The store is cleared at the beginning of every run, so an executor should not run
+/// several inputs at the same time.
+///
+/// # Example
+/// ```rust,ignore
+/// let store = CitationStore::new();
+/// let search = CitingRetrieverTool::new("docs", "Searches the manual.", retriever, store.clone());
+/// let agent = ConversationalAgentBuilder::new().tools(&[Arc::new(search)]).build(llm)?;
+/// let executor = CitationExecutor::new(AgentExecutor::from_agent(agent), store);
+///
+/// let cited = executor.invoke_with_sources(prompt_args! {"input" => "How do I reset it?"}).await?;
+/// for source in cited.sources {
+///     println!("{}: {}", source.id, source.snippet);
+/// }
+/// ```
+pub struct CitationExecutor<A: Agent> {
+    executor: AgentExecutor<A>,
+    store: CitationStore,
+}
+
+impl<A: Agent> CitationExecutor<A> {
+    pub fn new(executor: AgentExecutor<A>, store: CitationStore) -> Self {
+        Self { executor, store }
+    }
+
+    /// Runs the agent, returning its answer and the sources it cited, in citation order.
+    pub async fn invoke_with_sources(
+        &self,
+        input_variables: PromptArgs,
+    ) -> Result<CitedAnswer, ChainError> {
+        self.store.clear();
+        let answer = self.executor.invoke(input_variables).await?;
+        let sources = self.cited_sources(&answer);
+        Ok(CitedAnswer { answer, sources })
+    }
+
+    fn cited_sources(&self, answer: &str) -> Vec<Source> {
+        let re = Regex::new(r"\[([^\[\]]+)\]").unwrap();
+        let mut seen = HashSet::new();
+        re.captures_iter(answer)
+            .flat_map(|caps| {
+                caps[1]
+                    .split(',')
+                    .map(|id| id.trim().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|id| seen.insert(id.clone()))
+            .filter_map(|id| {
+                self.store.get(&id).map(|document| Source {
+                    snippet: document.page_content.chars().take(SNIPPET_LENGTH).collect(),
+                    metadata: serde_json::to_value(&document.metadata).unwrap_or_default(),
+                    id,
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<A: Agent + Send + Sync> Chain for CitationExecutor<A> {
+    async fn call(&self, input_variables: PromptArgs) -> Result<GenerateResult, ChainError> {
+        let cited = self.invoke_with_sources(input_variables).await?;
+        Ok(GenerateResult {
+            generation: cited.answer.clone(),
+            parsed: Some(serde_json::to_value(&cited)?),
+            ..Default::default()
+        })
+    }
+
+    fn get_input_keys(&self) -> Vec<String> {
+        self.executor.get_input_keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, sync::Arc};
+
+    use serde_json::json;
+
+    use crate::{
+        agent::{CitingRetrieverTool, ConversationalAgentBuilder},
+        llm::fake::FakeLLM,
+        prompt_args,
+        schemas::{Document, Retriever},
+    };
+
+    use super::*;
+
+    struct Manual {}
+
+    #[async_trait]
+    impl Retriever for Manual {
+        async fn get_relevant_documents(
+            &self,
+            _query: &str,
+        ) -> Result<Vec<Document>, Box<dyn Error>> {
+            Ok(vec![
+                Document::new("Hold the button for 10 seconds to reset.").with_metadata(
+                    [("id".to_string(), json!("manual-7"))]
+                        .into_iter()
+                        .collect(),
+                ),
+                Document::new("The warranty lasts two years."),
+            ])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collects_cited_sources() {
+        let llm = FakeLLM::new().with_responses(vec![
+            "```json\n{\"action\": \"manual\", \"action_input\": \"reset\"}\n```",
+            r#"```json
+{"action": "Final Answer", "action_input": "Hold the button for 10 seconds [manual-7]."}
+```"#,
+        ]);
+        let store = CitationStore::new();
+        let tool =
+            CitingRetrieverTool::new("manual", "Searches the manual.", Manual {}, store.clone());
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(tool)])
+            .build(llm.clone())
+            .unwrap();
+        let executor = CitationExecutor::new(AgentExecutor::from_agent(agent), store);
+
+        let cited = executor
+            .invoke_with_sources(prompt_args! {"input" => "How do I reset it?"})
+            .await
+            .unwrap();
+
+        assert_eq!(cited.answer, "Hold the button for 10 seconds [manual-7].");
+        assert_eq!(cited.sources.len(), 1);
+        assert_eq!(cited.sources[0].id, "manual-7");
+        assert_eq!(
+            cited.sources[0].snippet,
+            "Hold the button for 10 seconds to reset."
+        );
+        let observation = &llm.calls()[1].last().unwrap().content;
+        assert!(observation.contains("[manual-7] Hold the button"));
+        assert!(observation.contains("[doc-2] The warranty"));
+    }
+}
@@ -0,0 +1,5 @@
+mod executor;
+mod retriever_tool;
+
+pub use executor::*;
+pub use retriever_tool::*;
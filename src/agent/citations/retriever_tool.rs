@@ -0,0 +1,121 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{
+    schemas::{Document, Retriever},
+    tools::Tool,
+};
+
+/// Documents returned by a [`CitingRetrieverTool`], by id, so the sources cited in an
+/// answer can be looked up. Clones share the same documents.
+#[derive(Clone, Default)]
+pub struct CitationStore {
+    documents: Arc<Mutex<HashMap<String, Document>>>,
+}
+
+impl CitationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Document> {
+        self.lock().get(id).cloned()
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear()
+    }
+
+    /// Stores `document` and returns its id: the `id_key` metadata field when present,
+    /// otherwise a generated `doc-N`.
+    fn insert(&self, document: Document, id_key: &str) -> String {
+        let mut documents = self.lock();
+        let id = match document.metadata.get(id_key) {
+            Some(Value::String(id)) => id.clone(),
+            Some(id) => id.to_string(),
+            None => format!("doc-{}", documents.len() + 1),
+        };
+        documents.insert(id.clone(), document);
+        id
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Document>> {
+        self.documents.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A tool searching a [`Retriever`], whose observations tag every document with its id,
+/// e.g. `[doc-1] ...`. The model is asked to cite those ids, which
+/// [`CitationExecutor`](super::CitationExecutor) turns into sources.
+pub struct CitingRetrieverTool {
+    name: String,
+    description: String,
+    retriever: Box<dyn Retriever>,
+    store: CitationStore,
+    id_key: String,
+}
+
+impl CitingRetrieverTool {
+    pub fn new<S: Into<String>, D: Into<String>, R: Into<Box<dyn Retriever>>>(
+        name: S,
+        description: D,
+        retriever: R,
+        store: CitationStore,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            retriever: retriever.into(),
+            store,
+            id_key: "id".to_string(),
+        }
+    }
+
+    /// The metadata field holding the document id. Defaults to `id`.
+    pub fn with_id_key<S: Into<String>>(mut self, id_key: S) -> Self {
+        self.id_key = id_key.into();
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for CitingRetrieverTool {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "{} Every result starts with its id in brackets, like [doc-1]. Cite the ids of \
+            the results you use in your final answer, in the same format.",
+            self.description
+        )
+    }
+
+    async fn run(&self, input: Value) -> Result<String, Box<dyn Error>> {
+        let query = input.as_str().ok_or("Input should be a string")?;
+        let documents = self.retriever.get_relevant_documents(query).await?;
+        if documents.is_empty() {
+            return Ok("No documents found".to_string());
+        }
+
+        Ok(documents
+            .into_iter()
+            .map(|document| {
+                let content = document.page_content.clone();
+                format!(
+                    "[{}] {}",
+                    self.store.insert(document, &self.id_key),
+                    content
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n"))
+    }
+}
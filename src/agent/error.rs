@@ -16,6 +16,13 @@ pub enum AgentError {
     #[error("Tool error: {0}")]
     ToolError(String),
 
+    #[error("Tools {first} and {second} both have the name {normalized}")]
+    ToolNameCollision {
+        first: String,
+        second: String,
+        normalized: String,
+    },
+
     #[error("Missing Object On Builder: {0}")]
     MissingObject(String),
 
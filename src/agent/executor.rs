@@ -1,23 +1,52 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, pin::Pin, sync::Arc, time::Duration};
 
+use async_stream::stream;
 use async_trait::async_trait;
-use serde_json::json;
+use futures::{Stream, StreamExt};
+use serde_json::{json, Value};
 use tokio::sync::Mutex;
 
 use crate::{
-    chain::{chain_trait::Chain, ChainError},
-    language_models::GenerateResult,
-    memory::SimpleMemory,
+    chain::{chain_trait::Chain, llm_chain::dry_run_result, ChainError},
+    language_models::{context_window_or_default, GenerateResult},
     prompt::PromptArgs,
     schemas::{
-        agent::{AgentAction, AgentEvent},
-        memory::BaseMemory,
+        agent::{AgentAction, AgentEvent, AgentFinish, AgentPlanChunk},
+        memory::{with_memory_timeout, BaseMemory, MEMORY_LOCK_TIMEOUT},
+        Message, StreamData,
     },
-    tools::Tool,
+    tools::{normalize_tool_name, Tool},
 };
 
 use super::{agent::Agent, AgentError};
 
+/// How observations longer than the configured maximum are shortened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ObservationTruncation {
+    /// Keep the beginning of the observation.
+    #[default]
+    End,
+    /// Keep the beginning and the end of the observation, dropping the middle.
+    Middle,
+}
+
+/// The result of a single [`AgentExecutor::step`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepOutcome {
+    /// The agent used tools, or is asked to fix an output that couldn't be parsed. Holds
+    /// the steps so far, including the new ones, to pass to the next step.
+    Continue(Vec<(AgentAction, String)>),
+    /// The agent gave its final answer.
+    Finish(AgentFinish),
+    /// The loop guard stopped the run, with the reason.
+    Stopped(String),
+}
+
+/// Runs an agent, calling the tools it picks until it returns a final answer.
+///
+/// Cancelling a run, by dropping its future or with `Chain::call_with_cancellation`, stops
+/// it at the current LLM or tool call. Tools that already ran are not undone, and the
+/// memory is only written once the final answer is known, so it is left untouched.
 pub struct AgentExecutor<A>
 where
     A: Agent,
@@ -25,6 +54,16 @@ where
     agent: A,
     max_iterations: Option<i32>,
     break_if_error: bool,
+    max_repeated_actions: Option<usize>,
+    max_observation_length: Option<usize>,
+    context_model: Option<String>,
+    observation_truncation: ObservationTruncation,
+    dynamic_context: Option<Arc<dyn Fn() -> PromptArgs + Send + Sync>>,
+    tool_error_template: String,
+    max_format_retries: usize,
+    format_retry_message: String,
+    dry_run: bool,
+    memory_lock_timeout: Duration,
     pub memory: Option<Arc<Mutex<dyn BaseMemory>>>,
 }
 
@@ -37,6 +76,16 @@ where
             agent,
             max_iterations: Some(10),
             break_if_error: false,
+            max_repeated_actions: None,
+            max_observation_length: None,
+            context_model: None,
+            observation_truncation: ObservationTruncation::default(),
+            dynamic_context: None,
+            tool_error_template: DEFAULT_TOOL_ERROR_TEMPLATE.to_string(),
+            max_format_retries: 0,
+            format_retry_message: DEFAULT_FORMAT_RETRY_MESSAGE.to_string(),
+            dry_run: false,
+            memory_lock_timeout: MEMORY_LOCK_TIMEOUT,
             memory: None,
         }
     }
@@ -51,86 +100,566 @@ where
         self
     }
 
+    /// How long to wait for the memory lock, e.g. while another request uses the same
+    /// memory, before failing with a `MemoryError`. Defaults to [`MEMORY_LOCK_TIMEOUT`].
+    pub fn with_memory_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.memory_lock_timeout = timeout;
+        self
+    }
+
+    /// Returns a handle to the executor memory, if any, e.g. to read the conversation so far.
+    pub fn memory(&self) -> Option<Arc<Mutex<dyn BaseMemory>>> {
+        self.memory.clone()
+    }
+
+    /// Clears the memory, if any, so the executor can be reused for a new conversation.
+    pub async fn reset_memory(&self) -> Result<(), ChainError> {
+        if let Some(memory) = &self.memory {
+            with_memory_timeout(memory, self.memory_lock_timeout, |memory| memory.clear()).await?;
+        }
+        Ok(())
+    }
+
     pub fn with_break_if_error(mut self, break_if_error: bool) -> Self {
         self.break_if_error = break_if_error;
         self
     }
 
+    /// Stops the run when the agent requests the same tool with the same input
+    /// more than `max_repeats` times in a row. Whitespace in the input is
+    /// normalized before comparing. Disabled by default.
+    pub fn with_loop_guard(mut self, max_repeats: usize) -> Self {
+        self.max_repeated_actions = Some(max_repeats);
+        self
+    }
+
+    /// Truncates each tool observation to at most `max_length` characters, marking the cut
+    /// with an ellipsis, so large tool outputs don't blow the context window. Disabled by
+    /// default, unless `with_context_model` is set.
+    pub fn with_max_observation_length(mut self, max_length: usize) -> Self {
+        self.max_observation_length = Some(max_length);
+        self
+    }
+
+    /// The model the agent plans with. Unless `with_max_observation_length` is set, each
+    /// observation is truncated to about a quarter of the model context window, as given by
+    /// `context_window_or_default`, counting 4 characters per token.
+    pub fn with_context_model<S: Into<String>>(mut self, model: S) -> Self {
+        self.context_model = Some(model.into());
+        self
+    }
+
+    fn max_observation_length(&self) -> Option<usize> {
+        self.max_observation_length.or_else(|| {
+            self.context_model
+                .as_deref()
+                // A quarter of the window, at 4 characters per token
+                .map(context_window_or_default)
+        })
+    }
+
+    /// Which part of a long observation is dropped. Defaults to [`ObservationTruncation::End`].
+    pub fn with_observation_truncation(mut self, truncation: ObservationTruncation) -> Self {
+        self.observation_truncation = truncation;
+        self
+    }
+
+    /// When enabled, the executor doesn't call the LLM nor any tool: the `generation` of the
+    /// result is the prompt of the first planning step, serialized as JSON. Only supported by
+    /// agents implementing `Agent::render_plan_prompt`.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// The observation given to the agent when a tool fails and `break_if_error` is false.
+    /// `{error}` is replaced with the error message.
+    ///
+    /// # Usage
+    /// ```rust,ignore
+    /// let executor = AgentExecutor::from_agent(agent)
+    ///     .with_tool_error_template("The tool failed with: {error}. Try a different approach.");
+    /// ```
+    pub fn with_tool_error_template<S: Into<String>>(mut self, template: S) -> Self {
+        self.tool_error_template = template.into();
+        self
+    }
+
+    /// When the agent output can't be parsed, e.g. the model answered without the required
+    /// JSON block, asks the agent again up to `max_retries` times instead of returning the
+    /// raw output as the answer. Each retry counts as an iteration. Disabled by default.
+    pub fn with_max_format_retries(mut self, max_retries: usize) -> Self {
+        self.max_format_retries = max_retries;
+        self
+    }
+
+    /// The message sent to the agent after an output that couldn't be parsed.
+    pub fn with_format_retry_message<S: Into<String>>(mut self, message: S) -> Self {
+        self.format_retry_message = message.into();
+        self
+    }
+
+    /// Calls `context` before each planning step and adds the returned values to the agent
+    /// inputs, e.g. the current date for a `templated_prefix` like `Today is {{date}}`.
+    /// Values passed explicitly as inputs take precedence.
+    ///
+    /// # Usage
+    /// ```rust,ignore
+    /// let executor = AgentExecutor::from_agent(agent).with_dynamic_context(|| {
+    ///     prompt_args! {"date" => current_date()}
+    /// });
+    /// ```
+    pub fn with_dynamic_context<F>(mut self, context: F) -> Self
+    where
+        F: Fn() -> PromptArgs + Send + Sync + 'static,
+    {
+        self.dynamic_context = Some(Arc::new(context));
+        self
+    }
+
+    /// Runs a single planning step: asks the agent for its next move and runs the tools it
+    /// picks, returning the steps so far with the new ones, or the final answer. `call`
+    /// loops over this method, so it allows stepping through a run, e.g. in a debugger UI.
+    /// Unlike `call`, it doesn't write the memory nor check `max_iterations`.
+    ///
+    /// # Usage
+    /// ```rust,ignore
+    /// let mut steps = Vec::new();
+    /// loop {
+    ///     match executor.step(steps, inputs.clone()).await? {
+    ///         StepOutcome::Continue(next_steps) => steps = next_steps,
+    ///         StepOutcome::Finish(finish) => break println!("{}", finish.output),
+    ///         StepOutcome::Stopped(reason) => break println!("{}", reason),
+    ///     }
+    /// }
+    /// ```
+    pub async fn step(
+        &self,
+        steps: Vec<(AgentAction, String)>,
+        inputs: PromptArgs,
+    ) -> Result<StepOutcome, ChainError> {
+        let mut inputs = inputs;
+        if self.agent.uses_chat_history() && !inputs.contains_key("chat_history") {
+            inputs.insert(
+                "chat_history".to_string(),
+                json!(self.chat_history().await?),
+            );
+        }
+
+        let plan = self.agent.plan(&steps, self.plan_inputs(&inputs));
+        #[cfg(feature = "tracing")]
+        let plan = tracing::Instrument::instrument(
+            plan,
+            tracing::info_span!("agent.plan", step = steps.len()),
+        );
+        let agent_event = plan
+            .await
+            .map_err(|e| ChainError::AgentError(format!("Error in agent planning: {}", e)))?;
+        self.handle_event(steps, agent_event).await
+    }
+
+    /// Streams the final answer of a run: the steps run silently, like in `call`, and the
+    /// tokens of the planning call that produces the final answer are streamed as they are
+    /// generated. Agents that can't tell a final answer while it's being generated, see
+    /// `Agent::plan_stream`, yield it as a single chunk once known. A run stopped by the
+    /// loop guard or `max_iterations` yields the stop message instead.
+    ///
+    /// # Usage
+    /// ```rust,ignore
+    /// let mut answer = executor.stream_final_answer(prompt_args! {"input" => "Weather in Lima?"});
+    /// while let Some(data) = answer.next().await {
+    ///     print!("{}", data?.content);
+    /// }
+    /// ```
+    pub fn stream_final_answer(
+        &self,
+        input_variables: PromptArgs,
+    ) -> Pin<Box<dyn Stream<Item = Result<StreamData, ChainError>> + Send + '_>> {
+        Box::pin(stream! {
+            let mut input_variables = input_variables;
+            if self.agent.uses_chat_history() {
+                match self.chat_history().await {
+                    Ok(chat_history) => {
+                        input_variables.insert("chat_history".to_string(), json!(chat_history));
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+            }
+
+            let mut steps: Vec<(AgentAction, String)> = Vec::new();
+            loop {
+                let mut plan = match self
+                    .agent
+                    .plan_stream(&steps, self.plan_inputs(&input_variables))
+                    .await
+                {
+                    Ok(plan) => plan,
+                    Err(e) => {
+                        yield Err(ChainError::AgentError(format!(
+                            "Error in agent planning: {}",
+                            e
+                        )));
+                        return;
+                    }
+                };
+                let mut streamed = false;
+                let mut agent_event = None;
+                while let Some(chunk) = plan.next().await {
+                    match chunk {
+                        Ok(AgentPlanChunk::Answer(token)) => {
+                            streamed = true;
+                            yield Ok(StreamData::new(Value::Null, None, token));
+                        }
+                        Ok(AgentPlanChunk::Event(event)) => agent_event = Some(event),
+                        Err(e) => {
+                            yield Err(ChainError::AgentError(format!(
+                                "Error in agent planning: {}",
+                                e
+                            )));
+                            return;
+                        }
+                    }
+                }
+                let agent_event = match agent_event {
+                    Some(agent_event) => agent_event,
+                    None => {
+                        yield Err(ChainError::AgentError(
+                            "The agent plan ended without an event".to_string(),
+                        ));
+                        return;
+                    }
+                };
+
+                match self.handle_event(steps, agent_event).await {
+                    Ok(StepOutcome::Continue(next_steps)) => steps = next_steps,
+                    Ok(StepOutcome::Stopped(reason)) => {
+                        yield Ok(StreamData::new(Value::Null, None, reason));
+                        return;
+                    }
+                    Ok(StepOutcome::Finish(finish)) => {
+                        if !streamed {
+                            yield Ok(StreamData::new(Value::Null, None, finish.output.clone()));
+                        }
+                        if let Err(e) = self.save_turn(&input_variables, &finish.output).await {
+                            yield Err(e);
+                        }
+                        return;
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+
+                if let Some(max_iterations) = self.max_iterations {
+                    if steps.len() >= max_iterations as usize {
+                        yield Ok(StreamData::new(Value::Null, None, "Max iterations reached"));
+                        return;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Applies a planned event: runs the tools of an action, or asks the agent to fix an
+    /// output that couldn't be parsed.
+    async fn handle_event(
+        &self,
+        steps: Vec<(AgentAction, String)>,
+        agent_event: AgentEvent,
+    ) -> Result<StepOutcome, ChainError> {
+        let mut steps = steps;
+        let format_retries = count_format_retries(&steps);
+        match agent_event {
+            AgentEvent::Action(actions) => {
+                let name_to_tools = self.get_name_to_tools();
+                for action in actions {
+                    log::debug!("Action: {}", action.tool_input);
+                    if let Some(max_repeats) = self.max_repeated_actions {
+                        let repeated_actions = count_repeated_actions(&steps, &action) + 1;
+                        if repeated_actions > max_repeats {
+                            log::warn!(
+                                "Tool {} was called {} times in a row with the same input",
+                                action.tool,
+                                repeated_actions
+                            );
+                            return Ok(StepOutcome::Stopped(format!(
+                                "Agent stopped: the tool {} was called {} times in a row with the same input",
+                                action.tool, repeated_actions
+                            )));
+                        }
+                    }
+                    let tool = find_tool(&name_to_tools, &action.tool)?;
+
+                    let observation_future = tool.call_value(&action.tool_input);
+                    #[cfg(feature = "tracing")]
+                    let observation_future = tracing::Instrument::instrument(
+                        observation_future,
+                        tracing::info_span!(
+                            "agent.tool",
+                            tool = %tool.name(),
+                            step = steps.len()
+                        ),
+                    );
+                    let observation_result = observation_future.await;
+
+                    let observation = match observation_result {
+                        Ok(result) => tool.format_observation(&value_to_observation(result)),
+                        Err(err) => {
+                            log::info!("The tool returned the following error: {}", err);
+                            if self.break_if_error {
+                                return Err(ChainError::AgentError(
+                                    AgentError::ToolError(err.to_string()).to_string(),
+                                ));
+                            } else {
+                                self.tool_error_template
+                                    .replace("{error}", &err.to_string())
+                            }
+                        }
+                    };
+
+                    let observation = match self.max_observation_length() {
+                        Some(max_length) => truncate_observation(
+                            &observation,
+                            max_length,
+                            self.observation_truncation,
+                        ),
+                        None => observation,
+                    };
+
+                    steps.push((action, observation));
+                }
+                Ok(StepOutcome::Continue(steps))
+            }
+            AgentEvent::Finish(finish)
+                if finish.parse_fallback && format_retries < self.max_format_retries =>
+            {
+                log::warn!(
+                    "Could not parse the agent output, retrying ({}/{})",
+                    format_retries + 1,
+                    self.max_format_retries
+                );
+                let action = AgentAction {
+                    tool: FORMAT_RETRY_TOOL.to_string(),
+                    tool_input: Value::String(finish.output.clone()),
+                    log: finish.output,
+                };
+                steps.push((action, self.format_retry_message.clone()));
+                Ok(StepOutcome::Continue(steps))
+            }
+            AgentEvent::Finish(finish) => Ok(StepOutcome::Finish(finish)),
+        }
+    }
+
+    /// Writes the input and the final answer of a run to the memory, if any.
+    async fn save_turn(
+        &self,
+        input_variables: &PromptArgs,
+        output: &str,
+    ) -> Result<(), ChainError> {
+        if let Some(memory) = &self.memory {
+            with_memory_timeout(memory, self.memory_lock_timeout, |memory| {
+                memory.add_user_message(&input_variables["input"]);
+                memory.add_ai_message(&output);
+            })
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn chat_history(&self) -> Result<Vec<Message>, ChainError> {
+        match &self.memory {
+            Some(memory) => Ok(
+                with_memory_timeout(memory, self.memory_lock_timeout, |memory| memory.messages())
+                    .await?,
+            ),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn plan_inputs(&self, input_variables: &PromptArgs) -> PromptArgs {
+        let mut inputs = input_variables.clone();
+        if let Some(context) = &self.dynamic_context {
+            for (key, value) in context() {
+                inputs.entry(key).or_insert(value);
+            }
+        }
+        inputs
+    }
+
     fn get_name_to_tools(&self) -> HashMap<String, Arc<dyn Tool>> {
         let mut name_to_tool = HashMap::new();
         for tool in self.agent.get_tools().iter() {
             log::debug!("Loading Tool:{}", tool.name());
-            name_to_tool.insert(tool.name().trim().replace(" ", "_"), tool.clone());
+            name_to_tool.insert(normalize_tool_name(&tool.name()), tool.clone());
         }
         name_to_tool
     }
 }
 
+const DEFAULT_TOOL_ERROR_TEMPLATE: &str = "The tool returned the following error: {error}";
+
+const DEFAULT_FORMAT_RETRY_MESSAGE: &str = "Your last output wasn't valid, it must follow the \
+required response format. Respond again, using the required format.";
+
+/// Tool name of the steps recording an output that couldn't be parsed. It's never called.
+const FORMAT_RETRY_TOOL: &str = "_Exception";
+
+/// Tool names within this edit distance of the requested name are used automatically.
+const MAX_TOOL_NAME_DISTANCE: usize = 2;
+
+/// Looks up the tool requested by the agent. When there is no exact match, the closest
+/// tool name is used if it is within `MAX_TOOL_NAME_DISTANCE` edits, otherwise it is
+/// returned as a suggestion in `ChainError::ToolNotFound`. Ties go to the first name in
+/// alphabetical order.
+fn find_tool(
+    name_to_tools: &HashMap<String, Arc<dyn Tool>>,
+    name: &str,
+) -> Result<Arc<dyn Tool>, ChainError> {
+    let normalized = normalize_tool_name(name);
+    if let Some(tool) = name_to_tools.get(name).or(name_to_tools.get(&normalized)) {
+        return Ok(tool.clone());
+    }
+
+    let closest = name_to_tools
+        .keys()
+        .map(|key| {
+            (
+                key,
+                levenshtein(&key.to_lowercase(), &normalized.to_lowercase()),
+            )
+        })
+        .min_by_key(|(key, distance)| (*distance, *key));
+
+    match closest {
+        Some((key, distance)) if distance <= MAX_TOOL_NAME_DISTANCE => {
+            log::warn!("Tool {} not found, using {} instead", name, key);
+            Ok(name_to_tools[key].clone())
+        }
+        closest => Err(ChainError::ToolNotFound {
+            tool: name.to_string(),
+            suggestion: closest.map(|(key, _)| key.clone()),
+        }),
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Strings are passed through as-is, while structured results are sent to the model as JSON.
+fn value_to_observation(value: Value) -> String {
+    match value {
+        Value::String(observation) => observation,
+        value => value.to_string(),
+    }
+}
+
+fn truncate_observation(
+    observation: &str,
+    max_length: usize,
+    truncation: ObservationTruncation,
+) -> String {
+    let chars: Vec<char> = observation.chars().collect();
+    if chars.len() <= max_length {
+        return observation.to_string();
+    }
+    match truncation {
+        ObservationTruncation::End => {
+            format!("{}...", chars[..max_length].iter().collect::<String>())
+        }
+        ObservationTruncation::Middle => {
+            let head = max_length - max_length / 2;
+            let tail = max_length / 2;
+            format!(
+                "{}\n...\n{}",
+                chars[..head].iter().collect::<String>(),
+                chars[chars.len() - tail..].iter().collect::<String>()
+            )
+        }
+    }
+}
+
+/// Number of steps at the end of `steps`, skipping format retries, calling the same tool
+/// as `action` with the same input, whitespace aside.
+fn count_repeated_actions(steps: &[(AgentAction, String)], action: &AgentAction) -> usize {
+    let tool = normalize_tool_name(&action.tool);
+    let input = normalize_whitespace(&action.tool_input_string());
+    steps
+        .iter()
+        .rev()
+        .filter(|(step, _)| step.tool != FORMAT_RETRY_TOOL)
+        .take_while(|(step, _)| {
+            normalize_tool_name(&step.tool) == tool
+                && normalize_whitespace(&step.tool_input_string()) == input
+        })
+        .count()
+}
+
+fn count_format_retries(steps: &[(AgentAction, String)]) -> usize {
+    steps
+        .iter()
+        .filter(|(step, _)| step.tool == FORMAT_RETRY_TOOL)
+        .count()
+}
+
+fn normalize_whitespace(input: &str) -> String {
+    input.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
 #[async_trait]
 impl<A> Chain for AgentExecutor<A>
 where
     A: Agent + Send + Sync,
 {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "agent_executor.call", skip_all)
+    )]
     async fn call(&self, input_variables: PromptArgs) -> Result<GenerateResult, ChainError> {
         let mut input_variables = input_variables.clone();
-        let name_to_tools = self.get_name_to_tools();
-        let mut steps: Vec<(AgentAction, String)> = Vec::new();
-        log::debug!("steps: {:?}", steps);
-        if let Some(memory) = &self.memory {
-            let memory = memory.lock().await;
-            input_variables.insert("chat_history".to_string(), json!(memory.messages()));
-        } else {
+        if self.agent.uses_chat_history() {
             input_variables.insert(
                 "chat_history".to_string(),
-                json!(SimpleMemory::new().messages()),
+                json!(self.chat_history().await?),
             );
         }
 
-        loop {
-            let agent_event = self
+        let mut steps: Vec<(AgentAction, String)> = Vec::new();
+        if self.dry_run {
+            let messages = self
                 .agent
-                .plan(&steps, input_variables.clone())
-                .await
-                .map_err(|e| ChainError::AgentError(format!("Error in agent planning: {}", e)))?;
-            match agent_event {
-                AgentEvent::Action(actions) => {
-                    for action in actions {
-                        log::debug!("Action: {:?}", action.tool_input);
-                        let tool = name_to_tools
-                            .get(&action.tool)
-                            .ok_or_else(|| {
-                                AgentError::ToolError(format!("Tool {} not found", action.tool))
-                            })
-                            .map_err(|e| ChainError::AgentError(e.to_string()))?;
-
-                        let observation_result = tool.call(&action.tool_input).await;
-
-                        let observation = match observation_result {
-                            Ok(result) => result,
-                            Err(err) => {
-                                log::info!(
-                                    "The tool return the following error: {}",
-                                    err.to_string()
-                                );
-                                if self.break_if_error {
-                                    return Err(ChainError::AgentError(
-                                        AgentError::ToolError(err.to_string()).to_string(),
-                                    ));
-                                } else {
-                                    format!("The tool return the following error: {}", err)
-                                }
-                            }
-                        };
+                .render_plan_prompt(&steps, self.plan_inputs(&input_variables))
+                .map_err(|e| ChainError::AgentError(e.to_string()))?;
+            return dry_run_result(&messages);
+        }
 
-                        steps.push((action, observation));
-                    }
+        loop {
+            match self.step(steps, input_variables.clone()).await? {
+                StepOutcome::Continue(next_steps) => steps = next_steps,
+                StepOutcome::Stopped(reason) => {
+                    return Ok(GenerateResult {
+                        generation: reason,
+                        ..Default::default()
+                    });
                 }
-                AgentEvent::Finish(finish) => {
-                    if let Some(memory) = &self.memory {
-                        let mut memory = memory.lock().await;
-                        memory.add_user_message(&input_variables["input"]);
-                        memory.add_ai_message(&finish.output);
-                    }
+                StepOutcome::Finish(finish) => {
+                    self.save_turn(&input_variables, &finish.output).await?;
                     return Ok(GenerateResult {
                         generation: finish.output,
                         ..Default::default()
@@ -154,3 +683,438 @@ where
         Ok(result.generation)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use crate::{
+        agent::ConversationalAgentBuilder,
+        llm::fake::FakeLLM,
+        memory::SimpleMemory,
+        prompt_args,
+        schemas::{AgentFinish, Message},
+    };
+
+    use super::*;
+
+    struct Echo {}
+
+    #[async_trait]
+    impl Tool for Echo {
+        fn name(&self) -> String {
+            "Echo".to_string()
+        }
+        fn description(&self) -> String {
+            "Returns the input".to_string()
+        }
+        async fn run(&self, input: Value) -> Result<String, Box<dyn Error>> {
+            Ok(input.to_string())
+        }
+    }
+
+    fn action(tool: &str, input: &str) -> String {
+        format!(
+            "```json\n{{\"action\": \"{}\", \"action_input\": \"{}\"}}\n```",
+            tool, input
+        )
+    }
+
+    #[test]
+    fn test_find_tool_suggests_closest_name() {
+        let mut name_to_tools: HashMap<String, Arc<dyn Tool>> = HashMap::new();
+        name_to_tools.insert("Web_Search".to_string(), Arc::new(Echo {}));
+
+        assert!(find_tool(&name_to_tools, "Web Search").is_ok());
+        assert!(find_tool(&name_to_tools, "web_serch").is_ok());
+
+        let err = find_tool(&name_to_tools, "Search").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Tool Search not found, did you mean Web_Search?"
+        );
+        match err {
+            ChainError::ToolNotFound { tool, suggestion } => {
+                assert_eq!(tool, "Search");
+                assert_eq!(suggestion.as_deref(), Some("Web_Search"));
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+
+        // Both names are one edit away, the first in alphabetical order wins
+        name_to_tools.insert("fetch_b".to_string(), Arc::new(Echo {}));
+        name_to_tools.insert("fetch_a".to_string(), Arc::new(Echo {}));
+        let tool = find_tool(&name_to_tools, "fetch_c").unwrap();
+        assert!(Arc::ptr_eq(&tool, &name_to_tools["fetch_a"]));
+    }
+
+    #[test]
+    fn test_truncate_observation() {
+        assert_eq!(
+            truncate_observation("short", 10, ObservationTruncation::End),
+            "short"
+        );
+        assert_eq!(
+            truncate_observation("abcdefghij", 4, ObservationTruncation::End),
+            "abcd..."
+        );
+        assert_eq!(
+            truncate_observation("abcdefghij", 5, ObservationTruncation::Middle),
+            "abc\n...\nij"
+        );
+        assert_eq!(
+            truncate_observation("ñandúñandú", 3, ObservationTruncation::End),
+            "ñan..."
+        );
+    }
+
+    #[test]
+    fn test_max_observation_length_from_context_window() {
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Echo {})])
+            .build(FakeLLM::new())
+            .unwrap();
+        let executor = AgentExecutor::from_agent(agent);
+        assert_eq!(executor.max_observation_length(), None);
+
+        let executor = executor.with_context_model("gpt-4-0613");
+        assert_eq!(executor.max_observation_length(), Some(8_192));
+
+        let executor = executor.with_max_observation_length(100);
+        assert_eq!(executor.max_observation_length(), Some(100));
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_loop_guard_stops_repeated_actions() {
+        let llm = FakeLLM::new().with_responses(vec![
+            action("Echo", "hello  world"),
+            action("Echo", "hello world"),
+            action("Echo", " hello world "),
+        ]);
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Echo {})])
+            .build(llm.clone())
+            .unwrap();
+        let executor = AgentExecutor::from_agent(agent).with_loop_guard(2);
+
+        let result = executor
+            .invoke(prompt_args! {"input" => "say hello"})
+            .await
+            .unwrap();
+
+        assert!(result.starts_with("Agent stopped"));
+        assert!(result.contains("called 3 times in a row"));
+        assert_eq!(llm.calls().len(), 3);
+    }
+
+    struct Table {}
+
+    #[async_trait]
+    impl Tool for Table {
+        fn name(&self) -> String {
+            "Table".to_string()
+        }
+        fn description(&self) -> String {
+            "Returns a table".to_string()
+        }
+        async fn run(&self, _input: Value) -> Result<String, Box<dyn Error>> {
+            Ok("unused".to_string())
+        }
+        async fn run_typed(&self, _input: Value) -> Result<Value, Box<dyn Error>> {
+            Ok(json!({"rows": [[1, "a"], [2, "b"]]}))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_structured_tool_observation_is_sent_as_json() {
+        let llm = FakeLLM::new().with_responses(vec![
+            action("Table", "table"),
+            "```json\n{\"action\": \"Final Answer\", \"action_input\": \"done\"}\n```".to_string(),
+        ]);
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Table {})])
+            .build(llm.clone())
+            .unwrap();
+        let executor = AgentExecutor::from_agent(agent);
+
+        let result = executor
+            .invoke(prompt_args! {"input" => "show the table"})
+            .await
+            .unwrap();
+
+        assert_eq!(result, "done");
+        let last_call = llm.last_call().unwrap();
+        assert!(last_call
+            .iter()
+            .any(|m| m.content.contains(r#"{"rows":[[1,"a"],[2,"b"]]}"#)));
+    }
+
+    struct HistoryProbe {
+        uses_chat_history: bool,
+    }
+
+    #[async_trait]
+    impl Agent for HistoryProbe {
+        async fn plan(
+            &self,
+            _intermediate_steps: &[(AgentAction, String)],
+            inputs: PromptArgs,
+        ) -> Result<AgentEvent, AgentError> {
+            Ok(AgentEvent::Finish(AgentFinish::new(
+                inputs.contains_key("chat_history").to_string(),
+            )))
+        }
+
+        fn get_tools(&self) -> Vec<Arc<dyn Tool>> {
+            vec![]
+        }
+
+        fn uses_chat_history(&self) -> bool {
+            self.uses_chat_history
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chat_history_only_injected_when_used() {
+        for uses_chat_history in [true, false] {
+            let executor = AgentExecutor::from_agent(HistoryProbe { uses_chat_history });
+            let result = executor
+                .invoke(prompt_args! {"input" => "hi"})
+                .await
+                .unwrap();
+            assert_eq!(result, uses_chat_history.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_context_renders_in_prefix() {
+        let llm = FakeLLM::new().with_responses(vec![
+            action("Echo", "hi"),
+            "```json\n{\"action\": \"Final Answer\", \"action_input\": \"done\"}\n```".to_string(),
+        ]);
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Echo {})])
+            .prefix("You are helpful. Today is {{date}}.")
+            .templated_prefix(true)
+            .build(llm.clone())
+            .unwrap();
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        let executor = AgentExecutor::from_agent(agent).with_dynamic_context(move || {
+            let call = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            prompt_args! {"date" => format!("day {}", call)}
+        });
+
+        let result = executor
+            .invoke(prompt_args! {"input" => "hello"})
+            .await
+            .unwrap();
+
+        assert_eq!(result, "done");
+        let calls = llm.calls();
+        assert_eq!(calls[0][0].content, "You are helpful. Today is day 0.");
+        assert_eq!(calls[1][0].content, "You are helpful. Today is day 1.");
+    }
+
+    struct Failing {}
+
+    #[async_trait]
+    impl Tool for Failing {
+        fn name(&self) -> String {
+            "Failing".to_string()
+        }
+        fn description(&self) -> String {
+            "Always fails".to_string()
+        }
+        async fn run(&self, _input: Value) -> Result<String, Box<dyn Error>> {
+            Err("service unavailable".into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_error_template() {
+        for (template, expected) in [
+            (
+                None,
+                "The tool returned the following error: service unavailable",
+            ),
+            (
+                Some("The tool failed with: {error}. Try a different approach."),
+                "The tool failed with: service unavailable. Try a different approach.",
+            ),
+        ] {
+            let llm = FakeLLM::new().with_responses(vec![
+                action("Failing", "hi"),
+                "```json\n{\"action\": \"Final Answer\", \"action_input\": \"done\"}\n```"
+                    .to_string(),
+            ]);
+            let agent = ConversationalAgentBuilder::new()
+                .tools(&[Arc::new(Failing {})])
+                .build(llm.clone())
+                .unwrap();
+            let mut executor = AgentExecutor::from_agent(agent);
+            if let Some(template) = template {
+                executor = executor.with_tool_error_template(template);
+            }
+
+            executor
+                .invoke(prompt_args! {"input" => "hello"})
+                .await
+                .unwrap();
+
+            let last_call = llm.last_call().unwrap();
+            assert!(last_call.iter().any(|m| m.content.contains(expected)));
+        }
+    }
+
+    struct Formatted {}
+
+    #[async_trait]
+    impl Tool for Formatted {
+        fn name(&self) -> String {
+            "Formatted".to_string()
+        }
+        fn description(&self) -> String {
+            "Returns the input".to_string()
+        }
+        async fn run(&self, input: Value) -> Result<String, Box<dyn Error>> {
+            Ok(input.as_str().unwrap_or_default().to_string())
+        }
+        fn format_observation(&self, observation: &str) -> String {
+            format!("<<{}>>", observation)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_formats_observation() {
+        let llm = FakeLLM::new().with_responses(vec![
+            action("Formatted", "hi"),
+            "```json\n{\"action\": \"Final Answer\", \"action_input\": \"done\"}\n```".to_string(),
+        ]);
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Formatted {})])
+            .build(llm.clone())
+            .unwrap();
+        AgentExecutor::from_agent(agent)
+            .invoke(prompt_args! {"input" => "hello"})
+            .await
+            .unwrap();
+
+        let last_call = llm.last_call().unwrap();
+        assert!(last_call.iter().any(|m| m.content.contains("<<hi>>")));
+    }
+
+    #[tokio::test]
+    async fn test_format_retries() {
+        for max_retries in [0, 1] {
+            let llm = FakeLLM::new().with_responses(vec![
+                "I think the answer is 4",
+                "```json\n{\"action\": \"Final Answer\", \"action_input\": \"4\"}\n```",
+            ]);
+            let agent = ConversationalAgentBuilder::new()
+                .tools(&[Arc::new(Echo {})])
+                .build(llm.clone())
+                .unwrap();
+            let executor = AgentExecutor::from_agent(agent).with_max_format_retries(max_retries);
+
+            let result = executor
+                .invoke(prompt_args! {"input" => "2 + 2?"})
+                .await
+                .unwrap();
+
+            if max_retries == 0 {
+                assert_eq!(result, "I think the answer is 4");
+                assert_eq!(llm.calls().len(), 1);
+            } else {
+                assert_eq!(result, "4");
+                let last_call = llm.last_call().unwrap();
+                assert!(last_call
+                    .iter()
+                    .any(|m| m.content.contains("I think the answer is 4")));
+                assert!(last_call
+                    .iter()
+                    .any(|m| m.content.contains(DEFAULT_FORMAT_RETRY_MESSAGE)));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_step() {
+        let llm = FakeLLM::new().with_responses(vec![
+            action("Echo", "hello"),
+            "```json\n{\"action\": \"Final Answer\", \"action_input\": \"done\"}\n```".into(),
+        ]);
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Echo {})])
+            .build(llm.clone())
+            .unwrap();
+        let executor = AgentExecutor::from_agent(agent);
+        let inputs = prompt_args! {"input" => "Say hello"};
+
+        let steps = match executor.step(Vec::new(), inputs.clone()).await.unwrap() {
+            StepOutcome::Continue(steps) => steps,
+            outcome => panic!("unexpected outcome: {:?}", outcome),
+        };
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].0.tool, "Echo");
+        assert_eq!(llm.calls().len(), 1);
+
+        let outcome = executor.step(steps, inputs).await.unwrap();
+        assert!(matches!(outcome, StepOutcome::Finish(ref finish) if finish.output == "done"));
+        assert_eq!(llm.calls().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_final_answer() {
+        let llm = FakeLLM::new().with_responses(vec![
+            action("Echo", "hello"),
+            "```json\n{\"action\": \"Final Answer\", \"action_input\": \"done\"}\n```".into(),
+        ]);
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Echo {})])
+            .build(llm.clone())
+            .unwrap();
+        let memory: Arc<Mutex<dyn BaseMemory>> = SimpleMemory::new().into();
+        let executor = AgentExecutor::from_agent(agent).with_memory(memory.clone());
+
+        let chunks: Vec<String> = executor
+            .stream_final_answer(prompt_args! {"input" => "Say hello"})
+            .map(|data| data.unwrap().content)
+            .collect()
+            .await;
+
+        assert_eq!(chunks, vec!["done".to_string()]);
+        assert_eq!(llm.calls().len(), 2);
+        let messages = memory.lock().await.messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].content, "done");
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_returns_plan_prompt() {
+        let llm = FakeLLM::new();
+        let agent = ConversationalAgentBuilder::new()
+            .tools(&[Arc::new(Echo {})])
+            .build(llm.clone())
+            .unwrap();
+        let executor = AgentExecutor::from_agent(agent).with_dry_run(true);
+
+        let output = executor
+            .invoke(prompt_args! {"input" => "What does the echo say?"})
+            .await
+            .unwrap();
+
+        let messages: Vec<Message> = serde_json::from_str(&output).unwrap();
+        assert!(messages
+            .iter()
+            .any(|m| m.content.contains("What does the echo say?")));
+        assert!(llm.calls().is_empty());
+    }
+}
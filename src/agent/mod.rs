@@ -1,9 +1,15 @@
+#[macro_use]
+mod builder_methods;
+
 mod agent;
 pub use agent::*;
 
 mod executor;
 pub use executor::*;
 
+mod system_segments;
+pub use system_segments::*;
+
 mod chat;
 pub use chat::*;
 
@@ -12,3 +18,15 @@ pub use open_ai_tools::*;
 
 mod error;
 pub use error::*;
+
+mod self_ask;
+pub use self_ask::*;
+
+mod react;
+pub use react::*;
+
+mod citations;
+pub use citations::*;
+
+#[cfg(test)]
+mod test_tools;
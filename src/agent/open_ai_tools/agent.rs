@@ -1,31 +1,46 @@
 use std::sync::Arc;
 
+use async_stream::stream;
 use async_trait::async_trait;
-use serde_json::json;
+use futures::{stream, StreamExt};
+use serde_json::{json, Value};
 
 use crate::{
-    agent::{Agent, AgentError},
-    chain::Chain,
-    fmt_message, fmt_placeholder, fmt_template, message_formatter,
+    agent::{
+        agent::{render_chat_prompt, scratchpad_inputs, system_prefix},
+        Agent, AgentError, AgentPlanStream,
+    },
+    chain::{options::ChainCallOptions, Chain, LLMChain},
+    fmt_placeholder, fmt_template, message_formatter,
     prompt::{HumanMessagePromptTemplate, MessageFormatterStruct, PromptArgs},
     schemas::{
-        agent::{AgentAction, AgentEvent, AgentFinish, LogTools},
+        agent::{AgentAction, AgentEvent, AgentFinish, AgentPlanChunk, LogTools},
         messages::Message,
-        FunctionCallResponse,
+        FunctionCallResponse, ToolCallAccumulator, ToolChoice,
     },
     template_jinja2,
     tools::Tool,
 };
 
 pub struct OpenAiToolAgent {
-    pub(crate) chain: Box<dyn Chain>,
+    pub(crate) chain: LLMChain,
     pub(crate) tools: Vec<Arc<dyn Tool>>,
+    pub(crate) tool_choice: Option<ToolChoice>,
 }
 
 impl OpenAiToolAgent {
     pub fn create_prompt(prefix: &str) -> Result<MessageFormatterStruct, AgentError> {
+        Self::build_prompt(prefix, false)
+    }
+
+    /// Same as `create_prompt`, rendering `prefix` as a template when `templated_prefix`
+    /// is set, see `OpenAiToolAgentBuilder::templated_prefix`.
+    pub(crate) fn build_prompt(
+        prefix: &str,
+        templated_prefix: bool,
+    ) -> Result<MessageFormatterStruct, AgentError> {
         let prompt = message_formatter![
-            fmt_message!(Message::new_system_message(prefix)),
+            system_prefix(prefix, templated_prefix),
             fmt_placeholder!("chat_history"),
             fmt_template!(HumanMessagePromptTemplate::new(template_jinja2!(
                 "{{input}}",
@@ -61,6 +76,25 @@ impl OpenAiToolAgent {
 
         Ok(thoughts)
     }
+
+    fn plan_inputs(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<PromptArgs, AgentError> {
+        let scratchpad = self.construct_scratchpad(intermediate_steps)?;
+        Ok(scratchpad_inputs(inputs, scratchpad))
+    }
+
+    /// Renders the messages `plan` would send to the LLM for the given steps and inputs,
+    /// without calling it. `chat_history` defaults to an empty history.
+    pub fn render_plan_prompt(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<Vec<Message>, AgentError> {
+        render_chat_prompt(&self.chain, self.plan_inputs(intermediate_steps, inputs)?)
+    }
 }
 
 #[async_trait]
@@ -70,33 +104,188 @@ impl Agent for OpenAiToolAgent {
         intermediate_steps: &[(AgentAction, String)],
         inputs: PromptArgs,
     ) -> Result<AgentEvent, AgentError> {
-        let mut inputs = inputs.clone();
-        let scratchpad = self.construct_scratchpad(intermediate_steps)?;
-        inputs.insert("agent_scratchpad".to_string(), json!(scratchpad));
-        let output = self.chain.call(inputs).await?.generation;
-        match serde_json::from_str::<Vec<FunctionCallResponse>>(&output) {
-            Ok(tools) => {
-                let mut actions: Vec<AgentAction> = Vec::new();
-                for tool in tools {
-                    //Log tools will be send as log
-                    let log: LogTools = LogTools {
-                        tool_id: tool.id.clone(),
-                        tools: output.clone(), //We send the complete tools ouput, we will need it in
-                                               //the open ai call
-                    };
-                    actions.push(AgentAction {
-                        tool: tool.function.name.clone(),
-                        tool_input: tool.function.arguments.clone(),
-                        log: serde_json::to_string(&log)?, //We send this as string to minimise changes
-                    });
-                }
-                return Ok(AgentEvent::Action(actions));
+        let inputs = self.plan_inputs(intermediate_steps, inputs)?;
+        let output = match &self.tool_choice {
+            Some(tool_choice) if intermediate_steps.is_empty() => {
+                let options = ChainCallOptions::new().with_tool_choice(tool_choice.clone());
+                self.chain.call_with_options(inputs, options).await?
             }
-            Err(_) => return Ok(AgentEvent::Finish(AgentFinish { output })),
+            _ => self.chain.call_with_callbacks(inputs, &[]).await?,
         }
+        .generation;
+        parse_output(output)
+    }
+
+    /// Models may write some text before their tool calls, so text is only streamed once
+    /// a chunk's finish reason shows no tool calls follow. Outputs starting with `[` may be
+    /// tool calls written as text, so they are only known once complete.
+    async fn plan_stream(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<AgentPlanStream, AgentError> {
+        // A forced tool choice never produces the final answer
+        if self.tool_choice.is_some() && intermediate_steps.is_empty() {
+            let event = self.plan(intermediate_steps, inputs).await?;
+            return Ok(Box::pin(stream::iter(vec![Ok(AgentPlanChunk::Event(
+                event,
+            ))])));
+        }
+        let inputs = self.plan_inputs(intermediate_steps, inputs)?;
+        let mut llm_stream = self.chain.stream(inputs).await?;
+
+        Ok(Box::pin(stream! {
+            let mut output = String::new();
+            let mut tool_calls = ToolCallAccumulator::new();
+            let mut streaming = false;
+            while let Some(data) = llm_stream.next().await {
+                let data = match data {
+                    Ok(data) => data,
+                    Err(e) => {
+                        yield Err(e.into());
+                        return;
+                    }
+                };
+                tool_calls.push_chunk(&data.value);
+                output.push_str(&data.content);
+                let finished = matches!(
+                    data.value.pointer("/choices/0/finish_reason").and_then(Value::as_str),
+                    Some(reason) if reason != "tool_calls"
+                );
+                if streaming {
+                    if !data.content.is_empty() {
+                        yield Ok(AgentPlanChunk::Answer(data.content));
+                    }
+                } else if finished && tool_calls.is_empty() {
+                    let text = output.trim_start();
+                    if !text.is_empty() && !text.starts_with('[') {
+                        streaming = true;
+                        yield Ok(AgentPlanChunk::Answer(output.clone()));
+                    }
+                }
+            }
+
+            if !tool_calls.is_empty() {
+                output = match serde_json::to_string(&tool_calls.finish()) {
+                    Ok(output) => output,
+                    Err(e) => {
+                        yield Err(e.into());
+                        return;
+                    }
+                };
+            }
+            yield parse_output(output).map(AgentPlanChunk::Event);
+        }))
     }
 
     fn get_tools(&self) -> Vec<Arc<dyn Tool>> {
         self.tools.clone()
     }
+
+    fn uses_chat_history(&self) -> bool {
+        self.chain
+            .get_input_keys()
+            .contains(&"chat_history".to_string())
+    }
+
+    fn render_plan_prompt(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<Vec<Message>, AgentError> {
+        OpenAiToolAgent::render_plan_prompt(self, intermediate_steps, inputs)
+    }
+}
+
+/// Reads the tool calls of the output, a JSON list of [`FunctionCallResponse`]. Any other
+/// output is the final answer.
+fn parse_output(output: String) -> Result<AgentEvent, AgentError> {
+    match serde_json::from_str::<Vec<FunctionCallResponse>>(&output) {
+        Ok(tools) => {
+            let mut actions: Vec<AgentAction> = Vec::new();
+            for tool in tools {
+                //Log tools will be send as log
+                let log: LogTools = LogTools {
+                    tool_id: tool.id.clone(),
+                    tools: output.clone(), //We send the complete tools ouput, we will need it in
+                                           //the open ai call
+                };
+                actions.push(AgentAction {
+                    tool: tool.function.name.clone(),
+                    tool_input: serde_json::from_str(&tool.function.arguments)
+                        .unwrap_or_else(|_| Value::String(tool.function.arguments.clone())),
+                    log: serde_json::to_string(&log)?, //We send this as string to minimise changes
+                });
+            }
+            Ok(AgentEvent::Action(actions))
+        }
+        Err(_) => Ok(AgentEvent::Finish(AgentFinish::new(output))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use serde_json::Value;
+
+    use crate::{
+        agent::{executor::AgentExecutor, OpenAiToolAgentBuilder},
+        llm::fake::FakeLLM,
+        prompt_args,
+        schemas::FunctionCallBehavior,
+    };
+
+    use super::*;
+
+    struct Search {}
+
+    #[async_trait]
+    impl Tool for Search {
+        fn name(&self) -> String {
+            "search".to_string()
+        }
+        fn description(&self) -> String {
+            "Searches the web".to_string()
+        }
+        async fn run(&self, _input: Value) -> Result<String, Box<dyn Error>> {
+            Ok("sunny".to_string())
+        }
+    }
+
+    #[test]
+    fn test_build_requires_a_tool() {
+        let result = OpenAiToolAgentBuilder::new().build(FakeLLM::new());
+
+        assert!(matches!(result, Err(AgentError::MissingObject(_))));
+    }
+
+    #[tokio::test]
+    async fn test_tool_choice_only_forces_first_step() {
+        let tool_call = json!([{
+            "id": "call_1",
+            "type": "function",
+            "function": {"name": "search", "arguments": "{\"query\": \"weather\"}"}
+        }]);
+        let llm = FakeLLM::new().with_responses(vec![tool_call.to_string(), "It is sunny".into()]);
+        let agent = OpenAiToolAgentBuilder::new()
+            .tools(&[Arc::new(Search {})])
+            .tool_choice(ToolChoice::Named("search".into()))
+            .build(llm.clone())
+            .unwrap();
+        let executor = AgentExecutor::from_agent(agent);
+
+        let result = executor
+            .invoke(prompt_args! {"input" => "weather?"})
+            .await
+            .unwrap();
+
+        assert_eq!(result, "It is sunny");
+        let options = llm.call_options();
+        assert_eq!(
+            options[0].function_call_behavior,
+            Some(FunctionCallBehavior::Named("search".into()))
+        );
+        assert_eq!(options[1].function_call_behavior, None);
+    }
 }
@@ -1,10 +1,10 @@
 use std::sync::Arc;
 
 use crate::{
-    agent::AgentError,
+    agent::{agent::check_tool_names, AgentError},
     chain::{options::ChainCallOptions, LLMChainBuilder},
     language_models::{llm::LLM, options::CallOptions},
-    schemas::FunctionDefinition,
+    schemas::{FunctionDefinition, ToolChoice},
     tools::Tool,
 };
 
@@ -13,18 +13,29 @@ use super::{prompt::PREFIX, OpenAiToolAgent};
 pub struct OpenAiToolAgentBuilder {
     tools: Option<Vec<Arc<dyn Tool>>>,
     prefix: Option<String>,
+    templated_prefix: bool,
     options: Option<ChainCallOptions>,
+    tool_choice: Option<ToolChoice>,
+    parallel_tool_calls: Option<bool>,
 }
 
+impl_tool_methods!(OpenAiToolAgentBuilder);
+impl_option_methods!(OpenAiToolAgentBuilder);
+
 impl OpenAiToolAgentBuilder {
     pub fn new() -> Self {
         Self {
             tools: None,
             prefix: None,
+            templated_prefix: false,
             options: None,
+            tool_choice: None,
+            parallel_tool_calls: None,
         }
     }
 
+    /// Sets the tools of the agent, replacing any added before. Tools that are expensive to
+    /// build can be wrapped in a `LazyTool`, so they are only built when first called.
     pub fn tools(mut self, tools: &[Arc<dyn Tool>]) -> Self {
         self.tools = Some(tools.to_vec());
         self
@@ -35,31 +46,71 @@ impl OpenAiToolAgentBuilder {
         self
     }
 
+    /// Renders the prefix as a Jinja2 template on every planning call, so a prefix like
+    /// `Today is {{date}}` gets its values from the inputs or from
+    /// `AgentExecutor::with_dynamic_context`. Off by default: the prefix is sent as is.
+    pub fn templated_prefix(mut self, templated_prefix: bool) -> Self {
+        self.templated_prefix = templated_prefix;
+        self
+    }
+
     pub fn options(mut self, options: ChainCallOptions) -> Self {
         self.options = Some(options);
         self
     }
 
-    pub fn build<L: LLM + 'static>(self, llm: L) -> Result<OpenAiToolAgent, AgentError> {
+    /// Forces the tool choice of the first step, e.g. `ToolChoice::Required` or
+    /// `ToolChoice::Named("search".into())`. Later steps use `auto` so the agent can
+    /// still give a final answer.
+    pub fn tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// With `false` the model calls at most one tool per step, so every call can build on
+    /// the result of the previous one. When unset, the API default applies.
+    pub fn parallel_tool_calls(mut self, parallel_tool_calls: bool) -> Self {
+        self.parallel_tool_calls = Some(parallel_tool_calls);
+        self
+    }
+
+    /// Fails with `AgentError::MissingObject` when no tool was set.
+    pub fn build<L: Into<Box<dyn LLM>>>(self, llm: L) -> Result<OpenAiToolAgent, AgentError> {
         let tools = self.tools.unwrap_or_default();
+        // An empty functions array is rejected by some providers, and the agent could
+        // never take an action anyway
+        if tools.is_empty() {
+            return Err(AgentError::MissingObject(
+                "at least one tool required".to_string(),
+            ));
+        }
+        check_tool_names(&tools)?;
         let prefix = self.prefix.unwrap_or_else(|| PREFIX.to_string());
-        let mut llm = llm;
+        let mut llm: Box<dyn LLM> = llm.into();
 
-        let prompt = OpenAiToolAgent::create_prompt(&prefix)?;
-        let default_options = ChainCallOptions::default().with_max_tokens(1000);
+        let prompt = OpenAiToolAgent::build_prompt(&prefix, self.templated_prefix)?;
         let functions = tools
             .iter()
             .map(FunctionDefinition::from_langchain_tool)
             .collect::<Vec<FunctionDefinition>>();
-        llm.add_options(CallOptions::new().with_functions(functions));
-        let chain = Box::new(
-            LLMChainBuilder::new()
-                .prompt(prompt)
-                .llm(llm)
-                .options(self.options.unwrap_or(default_options))
-                .build()?,
-        );
+        let mut call_options = CallOptions::new().with_functions(functions);
+        if let Some(parallel_tool_calls) = self.parallel_tool_calls {
+            call_options = call_options.with_parallel_tool_calls(parallel_tool_calls);
+        }
+        llm.add_options(call_options);
+        let chain = LLMChainBuilder::new()
+            .prompt(prompt)
+            .llm(llm)
+            .options(
+                self.options
+                    .unwrap_or_else(crate::agent::builder_methods::default_options),
+            )
+            .build()?;
 
-        Ok(OpenAiToolAgent { chain, tools })
+        Ok(OpenAiToolAgent {
+            chain,
+            tools,
+            tool_choice: self.tool_choice,
+        })
     }
 }
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use crate::{
+    agent::{agent::check_tool_names, AgentError},
+    chain::{options::ChainCallOptions, LLMChainBuilder},
+    language_models::llm::LLM,
+    tools::Tool,
+};
+
+use super::{
+    output_parser::ReActOutputParser,
+    prompt::{FORMAT_INSTRUCTIONS, OBSERVATION, PREFIX, SUFFIX},
+    ReActAgent,
+};
+
+pub struct ReActAgentBuilder {
+    tools: Option<Vec<Arc<dyn Tool>>>,
+    prefix: Option<String>,
+    suffix: Option<String>,
+    options: Option<ChainCallOptions>,
+}
+
+impl_tool_methods!(ReActAgentBuilder);
+
+impl ReActAgentBuilder {
+    pub fn new() -> Self {
+        Self {
+            tools: None,
+            prefix: None,
+            suffix: None,
+            options: None,
+        }
+    }
+
+    /// Sets the tools of the agent, replacing any added before. Tools that are expensive to
+    /// build can be wrapped in a `LazyTool`, so they are only built when first called.
+    pub fn tools(mut self, tools: &[Arc<dyn Tool>]) -> Self {
+        self.tools = Some(tools.to_vec());
+        self
+    }
+
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn suffix<S: Into<String>>(mut self, suffix: S) -> Self {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    pub fn options(mut self, options: ChainCallOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    pub fn build<L: Into<Box<dyn LLM>>>(self, llm: L) -> Result<ReActAgent, AgentError> {
+        let tools = self.tools.unwrap_or_default();
+        check_tool_names(&tools)?;
+        let prefix = self.prefix.unwrap_or_else(|| PREFIX.to_string());
+        let suffix = self.suffix.unwrap_or_else(|| SUFFIX.to_string());
+
+        let prompt = ReActAgent::create_prompt(&prefix, FORMAT_INSTRUCTIONS, &suffix);
+        let mut options = self
+            .options
+            .unwrap_or_else(|| ChainCallOptions::default().with_max_tokens(1000));
+        // The observation must come from the tool, not from the model.
+        if options.stop_words.is_none() {
+            options = options.with_stop_words(vec![format!("\n{}", OBSERVATION)]);
+        }
+        let chain = LLMChainBuilder::new()
+            .prompt(prompt)
+            .llm(llm)
+            .options(options)
+            .build()?;
+
+        Ok(ReActAgent {
+            chain,
+            tools,
+            output_parser: ReActOutputParser::new(),
+        })
+    }
+}
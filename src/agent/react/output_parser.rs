@@ -0,0 +1,93 @@
+use regex::Regex;
+
+use crate::{
+    agent::AgentError,
+    schemas::agent::{AgentAction, AgentEvent, AgentFinish},
+};
+
+use super::prompt::{FINAL_ANSWER, OBSERVATION};
+
+const ACTION_PATTERN: &str = r"(?s)Action\s*\d*\s*:(.*?)\n\s*Action\s*\d*\s*Input\s*\d*\s*:(.*)";
+
+/// Parses the text ReAct format, `Action:` / `Action Input:` lines become an action and a
+/// `Final Answer:` line finishes the agent.
+pub struct ReActOutputParser {}
+
+impl ReActOutputParser {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn parse(&self, text: &str) -> Result<AgentEvent, AgentError> {
+        log::debug!("Parsing to ReAct Action: {}", text);
+        // Models that ignore the stop sequence keep going and invent the observation.
+        let text = match text.find(OBSERVATION) {
+            Some(index) => text[..index].trim_end(),
+            None => text.trim_end(),
+        };
+
+        if let Some(caps) = Regex::new(ACTION_PATTERN).unwrap().captures(text) {
+            let tool = caps[1].trim();
+            let tool_input = caps[2].trim().trim_matches('"');
+            if !tool.is_empty() {
+                return Ok(AgentEvent::Action(vec![AgentAction {
+                    tool: tool.to_string(),
+                    tool_input: tool_input.into(),
+                    log: text.to_string(),
+                }]));
+            }
+        }
+
+        if let Some(index) = text.find(FINAL_ANSWER) {
+            return Ok(AgentEvent::Finish(AgentFinish::new(
+                text[index + FINAL_ANSWER.len()..].trim(),
+            )));
+        }
+
+        Err(AgentError::OtherError(format!(
+            "Could not parse ReAct output, expected `Action:` and `Action Input:` or `{}`: {}",
+            FINAL_ANSWER, text
+        )))
+    }
+}
+
+impl Default for ReActOutputParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_react_output() {
+        let parser = ReActOutputParser::new();
+
+        let event = parser
+            .parse(" I need to search\nAction: Search\nAction Input: \"rust lang\"\nObservation: 1")
+            .unwrap();
+        match event {
+            AgentEvent::Action(actions) => {
+                assert_eq!(actions[0].tool, "Search");
+                assert_eq!(actions[0].tool_input, "rust lang");
+                assert_eq!(
+                    actions[0].log,
+                    " I need to search\nAction: Search\nAction Input: \"rust lang\""
+                );
+            }
+            AgentEvent::Finish(_) => panic!("Expected an action"),
+        }
+
+        let event = parser
+            .parse(" I now know the final answer\nFinal Answer: 42")
+            .unwrap();
+        match event {
+            AgentEvent::Finish(finish) => assert_eq!(finish.output, "42"),
+            AgentEvent::Action(_) => panic!("Expected a final answer"),
+        }
+
+        assert!(parser.parse(" I should search\nAction: Search").is_err());
+    }
+}
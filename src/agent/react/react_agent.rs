@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::{
+    agent::{Agent, AgentError},
+    chain::{chain_trait::Chain, LLMChain},
+    prompt::{PromptArgs, PromptTemplate},
+    prompt_args,
+    schemas::{
+        agent::{AgentAction, AgentEvent},
+        messages::Message,
+    },
+    template_jinja2,
+    tools::{normalize_tool_name, Tool},
+};
+
+use super::{output_parser::ReActOutputParser, prompt::OBSERVATION};
+
+/// Text based ReAct agent. Unlike `ConversationalAgent` the model answers with
+/// `Thought:` / `Action:` / `Action Input:` lines instead of JSON blobs, a format many
+/// open models follow more reliably.
+pub struct ReActAgent {
+    pub(crate) chain: LLMChain,
+    pub(crate) tools: Vec<Arc<dyn Tool>>,
+    pub(crate) output_parser: ReActOutputParser,
+}
+
+impl ReActAgent {
+    /// Jinja2 template expecting `tools`, `tool_names`, `input` and `agent_scratchpad`.
+    /// The tools are passed as values when planning, see `tool_inputs`, so the prompt is
+    /// rendered once and braces in a tool description are kept as they are.
+    pub fn create_prompt(prefix: &str, format_instructions: &str, suffix: &str) -> PromptTemplate {
+        template_jinja2!(
+            &format!("{}\n\n{}\n\n{}", prefix, format_instructions, suffix),
+            "tools",
+            "tool_names",
+            "input",
+            "agent_scratchpad"
+        )
+    }
+
+    /// Values of the `tools` and `tool_names` variables of the prompt.
+    pub fn tool_inputs(tools: &[Arc<dyn Tool>]) -> PromptArgs {
+        let tool_string = tools
+            .iter()
+            .map(|tool| {
+                format!(
+                    "{}: {}",
+                    normalize_tool_name(&tool.name()),
+                    tool.description()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let tool_names = tools
+            .iter()
+            .map(|tool| normalize_tool_name(&tool.name()))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        prompt_args! {
+            "tools" => tool_string,
+            "tool_names" => tool_names,
+        }
+    }
+
+    fn construct_scratchpad(&self, intermediate_steps: &[(AgentAction, String)]) -> String {
+        intermediate_steps
+            .iter()
+            .map(|(action, observation)| {
+                format!("{}\n{} {}\nThought:", action.log, OBSERVATION, observation)
+            })
+            .collect()
+    }
+
+    fn plan_inputs(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> PromptArgs {
+        let mut inputs = inputs;
+        inputs.extend(Self::tool_inputs(&self.tools));
+        inputs.insert(
+            "agent_scratchpad".to_string(),
+            json!(self.construct_scratchpad(intermediate_steps)),
+        );
+        inputs
+    }
+
+    /// Renders the messages `plan` would send to the LLM for the given steps and inputs,
+    /// without calling it.
+    pub fn render_plan_prompt(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<Vec<Message>, AgentError> {
+        Ok(self
+            .chain
+            .render_prompt(self.plan_inputs(intermediate_steps, inputs))?)
+    }
+}
+
+#[async_trait]
+impl Agent for ReActAgent {
+    async fn plan(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<AgentEvent, AgentError> {
+        let inputs = self.plan_inputs(intermediate_steps, inputs);
+        let output = self
+            .chain
+            .call_with_callbacks(inputs, &[])
+            .await?
+            .generation;
+        self.output_parser.parse(&output)
+    }
+
+    fn get_tools(&self) -> Vec<Arc<dyn Tool>> {
+        self.tools.clone()
+    }
+
+    fn uses_chat_history(&self) -> bool {
+        false
+    }
+
+    fn render_plan_prompt(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<Vec<Message>, AgentError> {
+        ReActAgent::render_plan_prompt(self, intermediate_steps, inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::{executor::AgentExecutor, test_tools::StubTool, ReActAgentBuilder},
+        llm::fake::FakeLLM,
+    };
+
+    fn calculator() -> Arc<dyn Tool> {
+        Arc::new(StubTool::new(
+            "Calculator",
+            "Usefull to make calculations",
+            "25",
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_react_agent() {
+        let llm = FakeLLM::new().with_responses(vec![
+            " I should calculate it\nAction: Calculator\nAction Input: 5 * 5",
+            " I now know the final answer\nFinal Answer: 25",
+        ]);
+        let agent = ReActAgentBuilder::new()
+            .tools(&[calculator()])
+            .build(llm.clone())
+            .unwrap();
+        let executor = AgentExecutor::from_agent(agent);
+
+        let result = executor
+            .invoke(prompt_args! {"input" => "What is 5 * 5?"})
+            .await
+            .unwrap();
+
+        assert_eq!(result, "25");
+        let first_prompt = llm.calls()[0]
+            .iter()
+            .map(|m| m.content.clone())
+            .collect::<String>();
+        assert!(first_prompt.contains("Calculator: Usefull to make calculations"));
+        assert!(first_prompt.contains("should be one of [Calculator]"));
+        let second_prompt = llm.calls()[1]
+            .iter()
+            .map(|m| m.content.clone())
+            .collect::<String>();
+        assert!(second_prompt.ends_with("Action Input: 5 * 5\nObservation: 25\nThought:"));
+    }
+
+    #[tokio::test]
+    async fn test_braces_in_tool_descriptions_are_kept() {
+        let llm = FakeLLM::new().with_responses(vec![" Done\nFinal Answer: ok"]);
+        let template = Arc::new(StubTool::new("Template", "Fills {{name}} placeholders", ""));
+        let agent = ReActAgentBuilder::new()
+            .tools(&[template])
+            .build(llm.clone())
+            .unwrap();
+        let executor = AgentExecutor::from_agent(agent);
+
+        executor
+            .invoke(prompt_args! {"input" => "Fill it"})
+            .await
+            .unwrap();
+
+        let prompt = llm.calls()[0]
+            .iter()
+            .map(|m| m.content.clone())
+            .collect::<String>();
+        assert!(prompt.contains("Template: Fills {{name}} placeholders"));
+        assert!(prompt.contains("Question: Fill it"));
+    }
+
+    #[test]
+    fn test_render_plan_prompt() {
+        let llm = FakeLLM::new();
+        let agent = ReActAgentBuilder::new()
+            .tools(&[calculator()])
+            .build(llm.clone())
+            .unwrap();
+
+        let messages = agent
+            .render_plan_prompt(&[], prompt_args! {"input" => "What is 5 * 5?"})
+            .unwrap();
+
+        let prompt = messages
+            .iter()
+            .map(|m| m.content.clone())
+            .collect::<String>();
+        assert!(prompt.contains("Calculator: Usefull to make calculations"));
+        assert!(prompt.contains("Question: What is 5 * 5?"));
+        assert!(llm.calls().is_empty());
+    }
+}
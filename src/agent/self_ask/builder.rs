@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use crate::{
+    agent::AgentError,
+    chain::{options::ChainCallOptions, LLMChainBuilder},
+    language_models::llm::LLM,
+    tools::{normalize_tool_name, Tool},
+};
+
+use super::{
+    output_parser::SelfAskOutputParser,
+    prompt::{INTERMEDIATE_ANSWER, PREFIX},
+    SelfAskAgent,
+};
+
+pub struct SelfAskAgentBuilder {
+    tool: Option<Arc<dyn Tool>>,
+    prefix: Option<String>,
+    options: Option<ChainCallOptions>,
+}
+
+impl SelfAskAgentBuilder {
+    pub fn new() -> Self {
+        Self {
+            tool: None,
+            prefix: None,
+            options: None,
+        }
+    }
+
+    /// The search tool used to answer every follow up question.
+    pub fn tool(mut self, tool: Arc<dyn Tool>) -> Self {
+        self.tool = Some(tool);
+        self
+    }
+
+    /// Replaces the default few-shot examples placed before the question.
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn options(mut self, options: ChainCallOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    pub fn build<L: Into<Box<dyn LLM>>>(self, llm: L) -> Result<SelfAskAgent, AgentError> {
+        let tool = self
+            .tool
+            .ok_or_else(|| AgentError::MissingObject("tool".into()))?;
+        let prefix = self.prefix.unwrap_or_else(|| PREFIX.to_string());
+
+        let prompt = SelfAskAgent::create_prompt(&prefix);
+        let mut options = self
+            .options
+            .unwrap_or_else(|| ChainCallOptions::default().with_max_tokens(1000));
+        // The intermediate answer must come from the tool, not from the model.
+        if options.stop_words.is_none() {
+            options = options.with_stop_words(vec![format!("\n{}", INTERMEDIATE_ANSWER)]);
+        }
+        let chain = LLMChainBuilder::new()
+            .prompt(prompt)
+            .llm(llm)
+            .options(options)
+            .build()?;
+
+        Ok(SelfAskAgent {
+            chain: Box::new(chain),
+            output_parser: SelfAskOutputParser::new(normalize_tool_name(&tool.name())),
+            tool,
+        })
+    }
+}
@@ -0,0 +1,8 @@
+mod builder;
+mod output_parser;
+mod prompt;
+mod self_ask_agent;
+
+pub use builder::*;
+pub use output_parser::*;
+pub use self_ask_agent::*;
@@ -0,0 +1,87 @@
+use crate::{
+    agent::AgentError,
+    schemas::agent::{AgentAction, AgentEvent, AgentFinish},
+};
+
+use super::prompt::{FINAL_ANSWER, FOLLOW_UP, INTERMEDIATE_ANSWER};
+
+/// Parses the self-ask format: a `Follow up:` line becomes an action on the search tool,
+/// `So the final answer is:` finishes the agent.
+pub struct SelfAskOutputParser {
+    tool_name: String,
+}
+
+impl SelfAskOutputParser {
+    pub fn new<S: Into<String>>(tool_name: S) -> Self {
+        Self {
+            tool_name: tool_name.into(),
+        }
+    }
+
+    pub fn parse(&self, text: &str) -> Result<AgentEvent, AgentError> {
+        log::debug!("Parsing to Self Ask Action: {}", text);
+        // Anything after an intermediate answer was made up by the model, the real one
+        // comes from the tool.
+        let text = match text.find(INTERMEDIATE_ANSWER) {
+            Some(index) => &text[..index],
+            None => text,
+        };
+
+        if let Some(index) = text.rfind(FINAL_ANSWER) {
+            let output = text[index + FINAL_ANSWER.len()..].trim();
+            return Ok(AgentEvent::Finish(AgentFinish::new(output)));
+        }
+
+        let follow_up = text
+            .lines()
+            .rev()
+            .find_map(|line| line.trim().strip_prefix(FOLLOW_UP))
+            .map(|question| question.trim())
+            .filter(|question| !question.is_empty());
+
+        match follow_up {
+            Some(question) => Ok(AgentEvent::Action(vec![AgentAction {
+                tool: self.tool_name.clone(),
+                tool_input: question.into(),
+                log: text.trim_end().to_string(),
+            }])),
+            None => Err(AgentError::OtherError(format!(
+                "Could not parse self ask output: {}",
+                text
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_self_ask_output() {
+        let parser = SelfAskOutputParser::new("Search");
+
+        let event = parser
+            .parse(" Yes.\nFollow up: Who is the director of Jaws?\nIntermediate answer: Spielberg")
+            .unwrap();
+        match event {
+            AgentEvent::Action(actions) => {
+                assert_eq!(actions[0].tool, "Search");
+                assert_eq!(actions[0].tool_input, "Who is the director of Jaws?");
+                assert_eq!(
+                    actions[0].log,
+                    " Yes.\nFollow up: Who is the director of Jaws?"
+                );
+            }
+            AgentEvent::Finish(_) => panic!("Expected an action"),
+        }
+
+        let event = parser.parse("\nSo the final answer is: No").unwrap();
+        match event {
+            AgentEvent::Finish(finish) => assert_eq!(finish.output, "No"),
+            AgentEvent::Action(_) => panic!("Expected a final answer"),
+        }
+
+        assert!(parser.parse("I don't know").is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::{
+    agent::{Agent, AgentError},
+    chain::chain_trait::Chain,
+    prompt::{PromptArgs, PromptTemplate},
+    schemas::agent::{AgentAction, AgentEvent},
+    template_jinja2,
+    tools::Tool,
+};
+
+use super::{
+    output_parser::SelfAskOutputParser,
+    prompt::{INTERMEDIATE_ANSWER, SUFFIX},
+};
+
+/// Agent using the self-ask prompting pattern: the model asks itself follow up
+/// questions, each one answered by a single search tool, until it can give the final
+/// answer.
+pub struct SelfAskAgent {
+    pub(crate) chain: Box<dyn Chain>,
+    pub(crate) tool: Arc<dyn Tool>,
+    pub(crate) output_parser: SelfAskOutputParser,
+}
+
+impl SelfAskAgent {
+    pub fn create_prompt(prefix: &str) -> PromptTemplate {
+        template_jinja2!(
+            &format!("{}{}", prefix, SUFFIX),
+            "input",
+            "agent_scratchpad"
+        )
+    }
+
+    fn construct_scratchpad(&self, intermediate_steps: &[(AgentAction, String)]) -> String {
+        intermediate_steps
+            .iter()
+            .map(|(action, observation)| {
+                format!("{}\n{} {}\n", action.log, INTERMEDIATE_ANSWER, observation)
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Agent for SelfAskAgent {
+    async fn plan(
+        &self,
+        intermediate_steps: &[(AgentAction, String)],
+        inputs: PromptArgs,
+    ) -> Result<AgentEvent, AgentError> {
+        let mut inputs = inputs.clone();
+        inputs.insert(
+            "agent_scratchpad".to_string(),
+            json!(self.construct_scratchpad(intermediate_steps)),
+        );
+        let output = self
+            .chain
+            .call_with_callbacks(inputs, &[])
+            .await?
+            .generation;
+        self.output_parser.parse(&output)
+    }
+
+    fn get_tools(&self) -> Vec<Arc<dyn Tool>> {
+        vec![self.tool.clone()]
+    }
+
+    fn uses_chat_history(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::{executor::AgentExecutor, test_tools::StubTool, SelfAskAgentBuilder},
+        llm::fake::FakeLLM,
+        prompt_args,
+    };
+
+    #[tokio::test]
+    async fn test_self_ask_agent() {
+        let llm = FakeLLM::new().with_responses(vec![
+            " Yes.\nFollow up: Who was the founder of craigslist?",
+            "So the final answer is: Craig Newmark",
+        ]);
+        let agent = SelfAskAgentBuilder::new()
+            .tool(Arc::new(StubTool::new(
+                "Search",
+                "Searches the web",
+                "Craig Newmark",
+            )))
+            .build(llm.clone())
+            .unwrap();
+        let executor = AgentExecutor::from_agent(agent);
+
+        let result = executor
+            .invoke(prompt_args! {"input" => "Who founded craigslist?"})
+            .await
+            .unwrap();
+
+        assert_eq!(result, "Craig Newmark");
+        let prompt = llm.calls()[1]
+            .iter()
+            .map(|m| m.content.clone())
+            .collect::<String>();
+        assert!(prompt.ends_with(
+            "Follow up: Who was the founder of craigslist?\nIntermediate answer: Craig Newmark\n"
+        ));
+    }
+}
@@ -0,0 +1,115 @@
+/// An ordered list of named pieces, like the role, the rules or the output format,
+/// joined with blank lines into an agent system message. Segments can be replaced or
+/// removed by name, so one piece can change while the others keep their defaults.
+///
+/// # Usage
+/// ```rust,ignore
+/// let segments = ConversationalAgent::default_system_segments()
+///     .with_segment("rules", "Never give medical advice.");
+/// let agent = ConversationalAgentBuilder::new()
+///     .tools(&tools)
+///     .system_segments(segments)
+///     .build(llm)?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SystemSegments {
+    segments: Vec<(String, String)>,
+}
+
+impl SystemSegments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the segment called `name`, keeping its position, or appends it.
+    pub fn with_segment<N: Into<String>, T: Into<String>>(mut self, name: N, text: T) -> Self {
+        self.set(name, text);
+        self
+    }
+
+    /// Inserts the segment called `name` before the segment `before`, or appends it when
+    /// there is no such segment. An existing segment called `name` is moved.
+    pub fn with_segment_before<N: Into<String>, T: Into<String>>(
+        mut self,
+        before: &str,
+        name: N,
+        text: T,
+    ) -> Self {
+        let name = name.into();
+        self.segments.retain(|(segment, _)| *segment != name);
+        let index = self
+            .segments
+            .iter()
+            .position(|(segment, _)| segment == before)
+            .unwrap_or(self.segments.len());
+        self.segments.insert(index, (name, text.into()));
+        self
+    }
+
+    pub fn without_segment(mut self, name: &str) -> Self {
+        self.segments.retain(|(segment, _)| segment != name);
+        self
+    }
+
+    pub fn set<N: Into<String>, T: Into<String>>(&mut self, name: N, text: T) {
+        let name = name.into();
+        let text = text.into();
+        match self
+            .segments
+            .iter_mut()
+            .find(|(segment, _)| *segment == name)
+        {
+            Some((_, existing)) => *existing = text,
+            None => self.segments.push((name, text)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.segments
+            .iter()
+            .find(|(segment, _)| segment == name)
+            .map(|(_, text)| text.as_str())
+    }
+
+    /// The segment names, in order.
+    pub fn names(&self) -> Vec<&str> {
+        self.segments
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Joins the non empty segments, in order, separated by a blank line.
+    pub fn join(&self) -> String {
+        self.segments
+            .iter()
+            .map(|(_, text)| text.trim())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_segments() {
+        let segments = SystemSegments::new()
+            .with_segment("role", "You are a travel assistant.")
+            .with_segment("rules", "Answer briefly.")
+            .with_segment("format", "Use markdown.");
+
+        let segments = segments
+            .with_segment("rules", "Answer in Spanish.")
+            .with_segment_before("format", "tone", "Be friendly.")
+            .without_segment("missing");
+        assert_eq!(segments.names(), vec!["role", "rules", "tone", "format"]);
+        assert_eq!(segments.get("rules"), Some("Answer in Spanish."));
+        assert_eq!(
+            segments.without_segment("tone").join(),
+            "You are a travel assistant.\n\nAnswer in Spanish.\n\nUse markdown."
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use std::error::Error;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::tools::Tool;
+
+/// Tool answering every call with the same output, used by the agent tests.
+pub(crate) struct StubTool {
+    name: &'static str,
+    description: &'static str,
+    output: &'static str,
+}
+
+impl StubTool {
+    pub(crate) fn new(name: &'static str, description: &'static str, output: &'static str) -> Self {
+        Self {
+            name,
+            description,
+            output,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for StubTool {
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+    fn description(&self) -> String {
+        self.description.to_string()
+    }
+    async fn run(&self, _input: Value) -> Result<String, Box<dyn Error>> {
+        Ok(self.output.to_string())
+    }
+}
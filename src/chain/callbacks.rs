@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{language_models::GenerateResult, prompt::PromptArgs};
+
+use super::{Chain, ChainError, DEFAULT_OUTPUT_KEY, DEFAULT_RESULT_KEY};
+
+/// Identifies one chain run. `parent_run_id` is the run of the chain (or the
+/// [`with_run_id`] scope) this run is nested in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunInfo {
+    pub run_id: String,
+    pub parent_run_id: Option<String>,
+}
+
+/// Receives the events of the runs started with [`Chain::call_with_callbacks`] and of
+/// the chains they call.
+///
+/// [`Chain::call_with_callbacks`]: super::Chain::call_with_callbacks
+#[async_trait]
+pub trait CallbackHandler: Send + Sync {
+    async fn on_chain_start(&self, _run: &RunInfo, _inputs: &PromptArgs) {}
+
+    async fn on_chain_end(&self, _run: &RunInfo, _output: &GenerateResult) {}
+
+    async fn on_chain_error(&self, _run: &RunInfo, _error: &ChainError) {}
+}
+
+#[derive(Clone)]
+pub(crate) struct RunContext {
+    pub(crate) run: RunInfo,
+    pub(crate) handlers: Vec<Arc<dyn CallbackHandler>>,
+}
+
+tokio::task_local! {
+    static RUN_CONTEXT: RunContext;
+}
+
+impl RunContext {
+    /// Context of a new run nested in the current one, with `callbacks` added to the
+    /// inherited handlers. `None` when there is nothing to notify.
+    pub(crate) fn child(callbacks: &[Arc<dyn CallbackHandler>]) -> Option<Self> {
+        let parent = RUN_CONTEXT.try_with(|context| context.clone()).ok();
+        if parent.is_none() && callbacks.is_empty() {
+            return None;
+        }
+
+        let (parent_run_id, mut handlers) = match parent {
+            Some(parent) => (Some(parent.run.run_id), parent.handlers),
+            None => (None, Vec::new()),
+        };
+        handlers.extend(callbacks.iter().cloned());
+
+        Some(Self {
+            run: RunInfo {
+                run_id: new_run_id(),
+                parent_run_id,
+            },
+            handlers,
+        })
+    }
+
+    pub(crate) async fn scope<F: Future>(self, future: F) -> F::Output {
+        RUN_CONTEXT.scope(self, future).await
+    }
+}
+
+/// Runs `chain.execute` as a run nested in the current one, so the handlers of the
+/// enclosing [`Chain::call_with_callbacks`] are notified whether or not `chain` overrides
+/// `execute`. Chains calling other chains should go through it, as the ones of this
+/// crate do. Outside of a run it is the same as calling `execute`.
+pub async fn execute_nested<C: Chain + ?Sized>(
+    chain: &C,
+    input_variables: PromptArgs,
+) -> Result<HashMap<String, Value>, ChainError> {
+    let context = match RunContext::child(&[]) {
+        Some(context) => context,
+        None => return chain.execute(input_variables).await,
+    };
+    let run = context.run.clone();
+    let handlers = context.handlers.clone();
+
+    for handler in &handlers {
+        handler.on_chain_start(&run, &input_variables).await;
+    }
+    let output = context.scope(chain.execute(input_variables)).await;
+    for handler in &handlers {
+        match &output {
+            Ok(output) => {
+                handler
+                    .on_chain_end(&run, &execute_result(chain, output))
+                    .await
+            }
+            Err(error) => handler.on_chain_error(&run, error).await,
+        }
+    }
+    output
+}
+
+/// The `GenerateResult` returned by `execute`, or one built from its first output.
+fn execute_result<C: Chain + ?Sized>(chain: &C, output: &HashMap<String, Value>) -> GenerateResult {
+    if let Some(result) = output
+        .get(DEFAULT_RESULT_KEY)
+        .and_then(|result| serde_json::from_value(result.clone()).ok())
+    {
+        return result;
+    }
+    let output_key = chain
+        .get_output_keys()
+        .first()
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_OUTPUT_KEY.to_string());
+    let generation = match output.get(&output_key) {
+        Some(Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+        None => String::new(),
+    };
+    GenerateResult {
+        generation,
+        ..Default::default()
+    }
+}
+
+/// Returns the id of the run being executed, if any.
+pub fn current_run_id() -> Option<String> {
+    RUN_CONTEXT
+        .try_with(|context| context.run.run_id.clone())
+        .ok()
+}
+
+/// Runs `future` with `run_id` as the parent of the chain runs started inside it, e.g.
+/// to correlate every chain run with the id of the HTTP request that triggered it.
+///
+/// # Usage
+/// ```rust,ignore
+/// let result = with_run_id(request_id, chain.call_with_callbacks(inputs, &[tracer])).await?;
+/// ```
+pub async fn with_run_id<F: Future>(run_id: impl Into<String>, future: F) -> F::Output {
+    let handlers = RUN_CONTEXT
+        .try_with(|context| context.handlers.clone())
+        .unwrap_or_default();
+    let context = RunContext {
+        run: RunInfo {
+            run_id: run_id.into(),
+            parent_run_id: current_run_id(),
+        },
+        handlers,
+    };
+    context.scope(future).await
+}
+
+fn new_run_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    format!("{:x}-{:x}", nanos, COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::{
+        chain::{Chain, LLMChainBuilder, TransformChain},
+        llm::fake::FakeLLM,
+        prompt_args, sequential_chain, template_fstring,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<(String, RunInfo)>>,
+    }
+
+    #[async_trait]
+    impl CallbackHandler for Recorder {
+        async fn on_chain_start(&self, run: &RunInfo, _inputs: &PromptArgs) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(("start".into(), run.clone()));
+        }
+
+        async fn on_chain_end(&self, run: &RunInfo, _output: &GenerateResult) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(("end".into(), run.clone()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_ids_propagate_to_nested_chains() {
+        let llm = FakeLLM::new().with_responses(vec!["Rusty", "Fast and safe"]);
+        let name = LLMChainBuilder::new()
+            .prompt(template_fstring!("Name for {product}", "product"))
+            .llm(llm.clone())
+            .output_key("name")
+            .build()
+            .unwrap();
+        let slogan = LLMChainBuilder::new()
+            .prompt(template_fstring!("Slogan for {name}", "name"))
+            .llm(llm)
+            .output_key("slogan")
+            .build()
+            .unwrap();
+        let chain = sequential_chain!(name, slogan);
+
+        let recorder = Arc::new(Recorder::default());
+        let handler: Arc<dyn CallbackHandler> = recorder.clone();
+        let result = with_run_id(
+            "request-1",
+            chain.call_with_callbacks(prompt_args! {"product" => "a language"}, &[handler]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.generation, "Fast and safe");
+
+        let events = recorder.events.lock().unwrap();
+        let kinds: Vec<&str> = events.iter().map(|(kind, _)| kind.as_str()).collect();
+        assert_eq!(kinds, vec!["start", "start", "end", "start", "end", "end"]);
+
+        let root = &events[0].1;
+        assert_eq!(root.parent_run_id.as_deref(), Some("request-1"));
+        assert_eq!(events[5].1, *root);
+        assert_eq!(events[1].1.parent_run_id, Some(root.run_id.clone()));
+        assert_eq!(events[3].1.parent_run_id, Some(root.run_id.clone()));
+        assert_ne!(events[1].1.run_id, events[3].1.run_id);
+        assert_eq!(current_run_id(), None);
+    }
+
+    #[tokio::test]
+    async fn test_chains_overriding_execute_are_reported() {
+        let upper = TransformChain::new(vec!["input"], vec!["upper"], |inputs| {
+            let text = inputs["input"].as_str().unwrap_or_default().to_uppercase();
+            Ok(prompt_args! {"upper" => text})
+        });
+        let chain = sequential_chain!(upper);
+
+        let recorder = Arc::new(Recorder::default());
+        let handler: Arc<dyn CallbackHandler> = recorder.clone();
+        let result = chain
+            .call_with_callbacks(prompt_args! {"input" => "dulces"}, &[handler])
+            .await
+            .unwrap();
+        assert_eq!(result.generation, "DULCES");
+
+        let events = recorder.events.lock().unwrap();
+        let kinds: Vec<&str> = events.iter().map(|(kind, _)| kind.as_str()).collect();
+        assert_eq!(kinds, vec!["start", "start", "end", "end"]);
+        assert_eq!(events[1].1.parent_run_id, Some(events[0].1.run_id.clone()));
+    }
+}
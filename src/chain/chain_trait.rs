@@ -1,12 +1,13 @@
-use std::{collections::HashMap, pin::Pin};
+use std::{collections::HashMap, pin::Pin, sync::Arc};
 
 use async_trait::async_trait;
 use futures::Stream;
 use serde_json::{json, Value};
+pub use tokio_util::sync::CancellationToken;
 
 use crate::{language_models::GenerateResult, prompt::PromptArgs, schemas::StreamData};
 
-use super::ChainError;
+use super::{CallbackHandler, ChainError, RunContext};
 
 pub(crate) const DEFAULT_OUTPUT_KEY: &str = "output";
 pub(crate) const DEFAULT_RESULT_KEY: &str = "generate_result";
@@ -42,8 +43,76 @@ pub trait Chain: Sync + Send {
     /// };
     /// # };
     /// ```
+    ///
+    /// # Cancellation
+    ///
+    /// Dropping the returned future, e.g. when a client disconnects or inside `tokio::select!`,
+    /// cancels the call. The chains of this crate only write to their memory once the
+    /// generation is complete, in a single step, so a cancelled call leaves the memory as
+    /// it was. See also [`call_with_cancellation`](Chain::call_with_cancellation).
     async fn call(&self, input_variables: PromptArgs) -> Result<GenerateResult, ChainError>;
 
+    /// Same as `call`, failing with `ChainError::Cancelled` as soon as `token` is cancelled.
+    /// The call is dropped at that point, with the guarantees described in `call`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let token = CancellationToken::new();
+    /// on_disconnect(token.clone());
+    /// let result = chain.call_with_cancellation(input_variables, &token).await;
+    /// ```
+    async fn call_with_cancellation(
+        &self,
+        input_variables: PromptArgs,
+        token: &CancellationToken,
+    ) -> Result<GenerateResult, ChainError> {
+        tokio::select! {
+            biased;
+            _ = token.cancelled() => Err(ChainError::Cancelled),
+            result = self.call(input_variables) => result,
+        }
+    }
+
+    /// Same as `call`, notifying `callbacks` of this run. The run gets its own run id and,
+    /// when it happens inside another run (or [`with_run_id`](super::with_run_id)), that
+    /// run's id as parent id. Chains run through [`execute_nested`](super::execute_nested)
+    /// during this run inherit the handlers, so the chains nested in the chains of this
+    /// crate are reported too.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let tracer: Arc<dyn CallbackHandler> = Arc::new(MyTracer::new());
+    /// let result = chain
+    ///     .call_with_callbacks(prompt_args! {"input" => "Im from Peru"}, &[tracer])
+    ///     .await?;
+    /// ```
+    async fn call_with_callbacks(
+        &self,
+        input_variables: PromptArgs,
+        callbacks: &[Arc<dyn CallbackHandler>],
+    ) -> Result<GenerateResult, ChainError> {
+        let context = match RunContext::child(callbacks) {
+            Some(context) => context,
+            None => return self.call(input_variables).await,
+        };
+        let run = context.run.clone();
+        let handlers = context.handlers.clone();
+
+        for handler in &handlers {
+            handler.on_chain_start(&run, &input_variables).await;
+        }
+        let result = context.scope(self.call(input_variables)).await;
+        for handler in &handlers {
+            match &result {
+                Ok(output) => handler.on_chain_end(&run, output).await,
+                Err(error) => handler.on_chain_error(&run, error).await,
+            }
+        }
+        result
+    }
+
     /// Invoke the `Chain` and receive just the generation result as a String.
     /// The input is a set of variables passed as a `PromptArgs` hashmap.
     ///
@@ -115,7 +184,7 @@ pub trait Chain: Sync + Send {
         input_variables: PromptArgs,
     ) -> Result<HashMap<String, Value>, ChainError> {
         log::info!("Using default implementation");
-        let result = self.call(input_variables.clone()).await?;
+        let result = self.call(input_variables).await?;
         let mut output = HashMap::new();
         let output_key = self
             .get_output_keys()
@@ -126,6 +195,27 @@ pub trait Chain: Sync + Send {
         output.insert(DEFAULT_RESULT_KEY.to_string(), json!(result));
         Ok(output)
     }
+    /// Call the `Chain` and receive its outputs by name, so chains producing several values,
+    /// or structured ones, can pass all of them forward. Unlike `execute`, the complete
+    /// `GenerateResult` is not included. The default implementation is based on `execute`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let outputs = extraction_chain
+    ///     .call_map(prompt_args! {"input" => "Luis is 30 years old"})
+    ///     .await?;
+    /// assert_eq!(outputs["output"]["age"], json!(30));
+    /// ```
+    async fn call_map(
+        &self,
+        input_variables: PromptArgs,
+    ) -> Result<HashMap<String, Value>, ChainError> {
+        let mut output = self.execute(input_variables).await?;
+        output.remove(DEFAULT_RESULT_KEY);
+        Ok(output)
+    }
+
     /// Stream the `Chain` and get an asynchronous stream of chain generations.
     /// The input is a set of variables passed as a `PromptArgs` hashmap.
     /// If the chain have memroy, the tream method will not be able to automaticaly
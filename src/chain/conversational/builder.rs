@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use tokio::sync::Mutex;
 
@@ -10,11 +10,14 @@ use crate::{
     memory::SimpleMemory,
     output_parsers::OutputParser,
     prompt::{FormatPrompter, HumanMessagePromptTemplate},
-    schemas::memory::BaseMemory,
+    schemas::memory::{BaseMemory, MEMORY_LOCK_TIMEOUT},
     template_fstring,
 };
 
-use super::{prompt::DEFAULT_TEMPLATE, ConversationalChain, DEFAULT_INPUT_VARIABLE};
+use super::{
+    prompt::{DEFAULT_AI_PREFIX, DEFAULT_HUMAN_PREFIX, DEFAULT_TEMPLATE},
+    ConversationalChain, DEFAULT_INPUT_VARIABLE,
+};
 
 pub struct ConversationalChainBuilder {
     llm: Option<Box<dyn LLM>>,
@@ -24,6 +27,9 @@ pub struct ConversationalChainBuilder {
     output_parser: Option<Box<dyn OutputParser>>,
     input_key: Option<String>,
     prompt: Option<Box<dyn FormatPrompter>>,
+    human_prefix: Option<String>,
+    ai_prefix: Option<String>,
+    memory_lock_timeout: Option<Duration>,
 }
 
 impl ConversationalChainBuilder {
@@ -36,6 +42,9 @@ impl ConversationalChainBuilder {
             output_parser: None,
             input_key: None,
             prompt: None,
+            human_prefix: None,
+            ai_prefix: None,
+            memory_lock_timeout: None,
         }
     }
 
@@ -75,14 +84,46 @@ impl ConversationalChainBuilder {
         self
     }
 
+    /// Prefix of the human messages in `{history}` and in the default prompt.
+    /// Default: `human` in `{history}` and `Human` in the prompt
+    pub fn with_human_prefix<S: Into<String>>(mut self, human_prefix: S) -> Self {
+        self.human_prefix = Some(human_prefix.into());
+        self
+    }
+
+    /// Prefix of the AI messages in `{history}` and in the default prompt.
+    /// Default: `ai` in `{history}` and `AI` in the prompt
+    pub fn with_ai_prefix<S: Into<String>>(mut self, ai_prefix: S) -> Self {
+        self.ai_prefix = Some(ai_prefix.into());
+        self
+    }
+
+    /// How long to wait for the memory lock before failing with a `MemoryError`.
+    /// Default: [`MEMORY_LOCK_TIMEOUT`]
+    pub fn memory_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.memory_lock_timeout = Some(timeout);
+        self
+    }
+
     pub fn build(self) -> Result<ConversationalChain, ChainError> {
         let llm = self
             .llm
             .ok_or_else(|| ChainError::MissingObject("LLM must be set".into()))?;
+        let human_prefix = self
+            .human_prefix
+            .clone()
+            .unwrap_or_else(|| DEFAULT_HUMAN_PREFIX.to_string());
+        let ai_prefix = self
+            .ai_prefix
+            .clone()
+            .unwrap_or_else(|| DEFAULT_AI_PREFIX.to_string());
         let prompt = match self.prompt {
             Some(prompt) => prompt,
             None => Box::new(HumanMessagePromptTemplate::new(template_fstring!(
-                DEFAULT_TEMPLATE,
+                DEFAULT_TEMPLATE.replace(
+                    "\nHuman: {input}\nAI:",
+                    &format!("\n{}: {{input}}\n{}:", human_prefix, ai_prefix)
+                ),
                 "history",
                 "input"
             ))),
@@ -114,6 +155,9 @@ impl ConversationalChainBuilder {
             input_key: self
                 .input_key
                 .unwrap_or_else(|| DEFAULT_INPUT_VARIABLE.to_string()),
+            human_prefix: self.human_prefix,
+            ai_prefix: self.ai_prefix,
+            memory_lock_timeout: self.memory_lock_timeout.unwrap_or(MEMORY_LOCK_TIMEOUT),
         })
     }
 }
@@ -1,4 +1,4 @@
-use std::{pin::Pin, sync::Arc};
+use std::{pin::Pin, sync::Arc, time::Duration};
 
 use async_stream::stream;
 use async_trait::async_trait;
@@ -10,7 +10,11 @@ use crate::{
     language_models::GenerateResult,
     prompt::PromptArgs,
     prompt_args,
-    schemas::{memory::BaseMemory, messages::Message, StreamData},
+    schemas::{
+        memory::{with_memory_timeout, BaseMemory},
+        messages::Message,
+        StreamData,
+    },
 };
 
 const DEFAULT_INPUT_VARIABLE: &str = "input";
@@ -47,6 +51,10 @@ impl ConversationalChainPromptBuilder {
 pub struct ConversationalChain {
     llm: LLMChain,
     input_key: String,
+    /// Prefixes of `{history}`. When unset, the message type names are used.
+    human_prefix: Option<String>,
+    ai_prefix: Option<String>,
+    memory_lock_timeout: Duration,
     pub memory: Arc<Mutex<dyn BaseMemory>>,
 }
 
@@ -55,6 +63,20 @@ impl ConversationalChain {
     pub fn prompt_builder(&self) -> ConversationalChainPromptBuilder {
         ConversationalChainPromptBuilder::new()
     }
+
+    /// Returns a handle to the chain memory, e.g. to read the conversation so far.
+    pub fn memory(&self) -> Arc<Mutex<dyn BaseMemory>> {
+        self.memory.clone()
+    }
+
+    /// Clears the memory, so the chain can be reused for a new conversation.
+    pub async fn reset_memory(&self) -> Result<(), ChainError> {
+        with_memory_timeout(&self.memory, self.memory_lock_timeout, |memory| {
+            memory.clear()
+        })
+        .await?;
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -65,20 +87,32 @@ impl Chain for ConversationalChain {
             .ok_or(ChainError::MissingInputVariable(self.input_key.clone()))?;
         let human_message = Message::new_human_message(input_variable);
 
-        let history = {
-            let memory = self.memory.lock().await;
-            memory.to_string()
-        };
+        let history = with_memory_timeout(&self.memory, self.memory_lock_timeout, |memory| {
+            memory.buffer_as_string(
+                self.human_prefix.as_deref().unwrap_or("human"),
+                self.ai_prefix.as_deref().unwrap_or("ai"),
+            )
+        })
+        .await?;
         let mut input_variables = input_variables;
         input_variables.insert("history".to_string(), history.into());
         let result = self.llm.call(input_variables.clone()).await?;
+        // A dry run returns the prompt, which isn't part of the conversation.
+        if self.llm.is_dry_run() {
+            return Ok(result);
+        }
 
-        let mut memory = self.memory.lock().await;
-        memory.add_message(human_message);
-        memory.add_message(Message::new_ai_message(&result.generation));
+        with_memory_timeout(&self.memory, self.memory_lock_timeout, |memory| {
+            memory.add_message(human_message);
+            memory.add_message(Message::new_ai_message(&result.generation));
+        })
+        .await?;
         Ok(result)
     }
 
+    /// Streams the AI answer as it is generated. The human and AI messages are written to
+    /// memory together, once the stream has completed successfully: if the LLM fails
+    /// midway, or the stream is dropped before its end, the memory is left untouched.
     async fn stream(
         &self,
         input_variables: PromptArgs,
@@ -89,40 +123,52 @@ impl Chain for ConversationalChain {
             .ok_or(ChainError::MissingInputVariable(self.input_key.clone()))?;
         let human_message = Message::new_human_message(input_variable);
 
-        let history = {
-            let memory = self.memory.lock().await;
-            memory.to_string()
-        };
+        let history = with_memory_timeout(&self.memory, self.memory_lock_timeout, |memory| {
+            memory.buffer_as_string(
+                self.human_prefix.as_deref().unwrap_or("human"),
+                self.ai_prefix.as_deref().unwrap_or("ai"),
+            )
+        })
+        .await?;
 
         let mut input_variables = input_variables;
         input_variables.insert("history".to_string(), history.into());
 
-        let complete_ai_message = Arc::new(Mutex::new(String::new()));
-        let complete_ai_message_clone = complete_ai_message.clone();
-
         let memory = self.memory.clone();
+        let memory_lock_timeout = self.memory_lock_timeout;
+        let dry_run = self.llm.is_dry_run();
 
         let stream = self.llm.stream(input_variables).await?;
         let output_stream = stream! {
             pin_mut!(stream);
+            let mut complete_ai_message = String::new();
             while let Some(result) = stream.next().await {
                 match result {
                     Ok(data) => {
-                        let mut complete_ai_message_clone =
-                            complete_ai_message_clone.lock().await;
-                        complete_ai_message_clone.push_str(&data.content);
-
+                        complete_ai_message.push_str(&data.content);
                         yield Ok(data);
                     },
                     Err(e) => {
+                        // Nothing was written to memory yet, stopping here keeps a
+                        // partial answer out of the history.
                         yield Err(e);
+                        return;
                     }
                 }
             }
+            if dry_run {
+                return;
+            }
 
-            let mut memory = memory.lock().await;
-            memory.add_message(human_message);
-            memory.add_message(Message::new_ai_message(&complete_ai_message.lock().await));
+            let ai_message = Message::new_ai_message(&complete_ai_message);
+            if let Err(e) = with_memory_timeout(&memory, memory_lock_timeout, |memory| {
+                memory.add_message(human_message);
+                memory.add_message(ai_message);
+            })
+            .await
+            {
+                yield Err(e.into());
+            }
         };
 
         Ok(Box::pin(output_stream))
@@ -135,9 +181,18 @@ impl Chain for ConversationalChain {
 
 #[cfg(test)]
 mod tests {
+    use serde_json::json;
+
     use crate::{
-        chain::conversational::builder::ConversationalChainBuilder,
-        llm::openai::{OpenAI, OpenAIModel},
+        chain::{
+            conversational::builder::ConversationalChainBuilder, options::ChainCallOptions,
+            CancellationToken,
+        },
+        language_models::{llm::LLM, LLMError},
+        llm::{
+            fake::FakeLLM,
+            openai::{OpenAI, OpenAIModel},
+        },
         prompt_args,
     };
 
@@ -184,4 +239,193 @@ mod tests {
             println!("Result: {:?}", result);
         }
     }
+
+    #[tokio::test]
+    async fn test_dry_run_leaves_memory_untouched() {
+        let llm = FakeLLM::new();
+        let chain = ConversationalChainBuilder::new()
+            .llm(llm.clone())
+            .options(ChainCallOptions::new().with_dry_run(true))
+            .build()
+            .expect("Error building ConversationalChain");
+
+        let prompt = chain
+            .invoke(prompt_args! {"input" => "Soy de peru"})
+            .await
+            .unwrap();
+
+        assert!(prompt.contains("Soy de peru"));
+        assert!(llm.calls().is_empty());
+        assert!(chain.memory().lock().await.messages().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_custom_prefixes() {
+        let llm = FakeLLM::new().with_responses(vec!["Hola", "Lomo saltado"]);
+        let chain = ConversationalChainBuilder::new()
+            .llm(llm.clone())
+            .with_human_prefix("User")
+            .with_ai_prefix("Assistant")
+            .build()
+            .expect("Error building ConversationalChain");
+
+        chain
+            .invoke(prompt_args! {"input" => "Soy de peru"})
+            .await
+            .unwrap();
+        chain
+            .invoke(prompt_args! {"input" => "Un plato tipico?"})
+            .await
+            .unwrap();
+
+        let prompt = &llm.calls()[1][0].content;
+        assert!(prompt.contains("User: Soy de peru\nAssistant: Hola"));
+        assert!(prompt.contains("User: Un plato tipico?\nAssistant:"));
+        assert!(!prompt.contains("Human:"));
+    }
+
+    #[tokio::test]
+    async fn test_default_history_prefixes() {
+        let llm = FakeLLM::new().with_responses(vec!["Hola", "Lomo saltado"]);
+        let chain = ConversationalChainBuilder::new()
+            .llm(llm.clone())
+            .build()
+            .expect("Error building ConversationalChain");
+
+        chain
+            .invoke(prompt_args! {"input" => "Soy de peru"})
+            .await
+            .unwrap();
+        chain
+            .invoke(prompt_args! {"input" => "Un plato tipico?"})
+            .await
+            .unwrap();
+
+        let prompt = &llm.calls()[1][0].content;
+        assert!(prompt.contains("human: Soy de peru\nai: Hola"));
+        assert!(prompt.contains("Human: Un plato tipico?\nAI:"));
+    }
+
+    #[tokio::test]
+    async fn test_reset_memory() {
+        let llm = FakeLLM::new().with_responses(vec!["Hola", "Hola de nuevo"]);
+        let chain = ConversationalChainBuilder::new()
+            .llm(llm.clone())
+            .build()
+            .expect("Error building ConversationalChain");
+
+        chain
+            .invoke(prompt_args! {"input" => "Soy de peru"})
+            .await
+            .unwrap();
+        assert_eq!(chain.memory().lock().await.messages().len(), 2);
+
+        chain.reset_memory().await.unwrap();
+        assert!(chain.memory().lock().await.messages().is_empty());
+
+        chain
+            .invoke(prompt_args! {"input" => "Hola"})
+            .await
+            .unwrap();
+        assert!(!llm.calls()[1][0].content.contains("Soy de peru"));
+    }
+
+    #[derive(Clone)]
+    struct ChunkedLLM {
+        fail_midway: bool,
+    }
+
+    #[async_trait]
+    impl LLM for ChunkedLLM {
+        async fn generate(&self, _messages: &[Message]) -> Result<GenerateResult, LLMError> {
+            Err(LLMError::OtherError("only streaming is supported".into()))
+        }
+
+        async fn stream(
+            &self,
+            _messages: &[Message],
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>>, LLMError>
+        {
+            let mut chunks = vec![
+                Ok(StreamData::new(json!({}), None, "Hola")),
+                Ok(StreamData::new(json!({}), None, " mundo")),
+            ];
+            if self.fail_midway {
+                chunks.push(Err(LLMError::OtherError("connection reset".into())));
+            }
+            Ok(Box::pin(futures::stream::iter(chunks)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_writes_memory_only_on_completion() {
+        for fail_midway in [false, true] {
+            let chain = ConversationalChainBuilder::new()
+                .llm(ChunkedLLM { fail_midway })
+                .build()
+                .expect("Error building ConversationalChain");
+
+            let stream = chain
+                .stream(prompt_args! {"input" => "Saluda"})
+                .await
+                .unwrap();
+            let results: Vec<_> = stream.collect().await;
+            let contents: Vec<String> = results
+                .iter()
+                .filter_map(|result| result.as_ref().ok())
+                .map(|data| data.content.clone())
+                .collect();
+            assert_eq!(contents, vec!["Hola", " mundo"]);
+
+            let messages = chain.memory.lock().await.messages();
+            if fail_midway {
+                assert!(results.last().unwrap().is_err());
+                assert!(messages.is_empty());
+            } else {
+                assert!(results.iter().all(|result| result.is_ok()));
+                assert_eq!(messages.len(), 2);
+                assert_eq!(messages[0].content, "Saluda");
+                assert_eq!(messages[1].content, "Hola mundo");
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    struct StalledLLM {}
+
+    #[async_trait]
+    impl LLM for StalledLLM {
+        async fn generate(&self, _messages: &[Message]) -> Result<GenerateResult, LLMError> {
+            futures::future::pending().await
+        }
+
+        async fn stream(
+            &self,
+            _messages: &[Message],
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>>, LLMError>
+        {
+            futures::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_call_leaves_memory_untouched() {
+        let chain = ConversationalChainBuilder::new()
+            .llm(StalledLLM {})
+            .build()
+            .expect("Error building ConversationalChain");
+
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::task::yield_now().await;
+            canceller.cancel();
+        });
+
+        let result = chain
+            .call_with_cancellation(prompt_args! {"input" => "Hola"}, &token)
+            .await;
+        assert!(matches!(result, Err(ChainError::Cancelled)));
+        assert!(chain.memory.lock().await.messages().is_empty());
+    }
 }
@@ -1,3 +1,6 @@
+pub const DEFAULT_HUMAN_PREFIX: &str = "Human";
+pub const DEFAULT_AI_PREFIX: &str = "AI";
+
 pub const DEFAULT_TEMPLATE: &str = r#"The following is a friendly conversation between a human and an AI. The AI is talkative and provides lots of specific details from its context. If the AI does not know the answer to a question, it truthfully says it does not know.
 
 Current conversation:
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::sync::Mutex;
 
 use crate::{
@@ -8,7 +8,7 @@ use crate::{
     language_models::llm::LLM,
     memory::SimpleMemory,
     prompt::FormatPrompter,
-    schemas::{BaseMemory, Retriever},
+    schemas::{BaseMemory, Retriever, MEMORY_LOCK_TIMEOUT},
 };
 
 use super::ConversationalRetrieverChain;
@@ -43,7 +43,6 @@ const CONVERSATIONAL_RETRIEVAL_QA_DEFAULT_INPUT_KEY: &str = "question";
 ///     .build()
 ///     .expect("Error building ConversationalChain");
 /// ```
-///
 pub struct ConversationalRetrieverChainBuilder {
     llm: Option<Box<dyn LLM>>,
     retriever: Option<Box<dyn Retriever>>,
@@ -55,7 +54,12 @@ pub struct ConversationalRetrieverChainBuilder {
     return_source_documents: bool,
     input_key: String,
     output_key: String,
+    memory_lock_timeout: Duration,
 }
+
+/// Builder for [`super::ConversationalRetrievalChain`].
+pub type ConversationalRetrievalChainBuilder = ConversationalRetrieverChainBuilder;
+
 impl ConversationalRetrieverChainBuilder {
     pub fn new() -> Self {
         ConversationalRetrieverChainBuilder {
@@ -69,6 +73,7 @@ impl ConversationalRetrieverChainBuilder {
             return_source_documents: true,
             input_key: CONVERSATIONAL_RETRIEVAL_QA_DEFAULT_INPUT_KEY.to_string(),
             output_key: DEFAULT_OUTPUT_KEY.to_string(),
+            memory_lock_timeout: MEMORY_LOCK_TIMEOUT,
         }
     }
 
@@ -93,6 +98,13 @@ impl ConversationalRetrieverChainBuilder {
         self
     }
 
+    /// How long to wait for the memory lock before failing with a `MemoryError`. Defaults
+    /// to [`MEMORY_LOCK_TIMEOUT`].
+    pub fn memory_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.memory_lock_timeout = timeout;
+        self
+    }
+
     pub fn llm<L: Into<Box<dyn LLM>>>(mut self, llm: L) -> Self {
         self.llm = Some(llm.into());
         self
@@ -167,6 +179,7 @@ impl ConversationalRetrieverChainBuilder {
             return_source_documents: self.return_source_documents,
             input_key: self.input_key,
             output_key: self.output_key,
+            memory_lock_timeout: self.memory_lock_timeout,
         })
     }
 }
@@ -1,6 +1,6 @@
 use futures::Stream;
 use futures_util::{pin_mut, StreamExt};
-use std::{collections::HashMap, pin::Pin, sync::Arc};
+use std::{collections::HashMap, pin::Pin, sync::Arc, time::Duration};
 
 use async_stream::stream;
 use async_trait::async_trait;
@@ -13,7 +13,7 @@ use crate::{
     },
     language_models::{GenerateResult, TokenUsage},
     prompt::PromptArgs,
-    schemas::{BaseMemory, Message, Retriever, StreamData},
+    schemas::{with_memory_timeout, BaseMemory, Message, Retriever, StreamData},
 };
 // _conversationalRetrievalQADefaultInputKey             = "question"
 // _conversationalRetrievalQADefaultSourceDocumentKey    = "source_documents"
@@ -32,8 +32,26 @@ pub struct ConversationalRetrieverChain {
     pub(crate) return_source_documents: bool,
     pub(crate) input_key: String,  //Default is `question`
     pub(crate) output_key: String, //default is output
+    pub(crate) memory_lock_timeout: Duration,
 }
+/// Alias following the naming used by other LangChain implementations.
+pub type ConversationalRetrievalChain = ConversationalRetrieverChain;
+
 impl ConversationalRetrieverChain {
+    /// Returns a handle to the chain memory, e.g. to read the conversation so far.
+    pub fn memory(&self) -> Arc<Mutex<dyn BaseMemory>> {
+        self.memory.clone()
+    }
+
+    /// Clears the memory, so the chain can be reused for a new conversation.
+    pub async fn reset_memory(&self) -> Result<(), ChainError> {
+        with_memory_timeout(&self.memory, self.memory_lock_timeout, |memory| {
+            memory.clear()
+        })
+        .await?;
+        Ok(())
+    }
+
     async fn get_question(
         &self,
         history: &[Message],
@@ -84,10 +102,10 @@ impl Chain for ConversationalRetrieverChain {
             .ok_or(ChainError::MissingInputVariable(self.input_key.clone()))?;
 
         let human_message = Message::new_human_message(input_variable);
-        let history = {
-            let memory = self.memory.lock().await;
+        let history = with_memory_timeout(&self.memory, self.memory_lock_timeout, |memory| {
             memory.messages()
-        };
+        })
+        .await?;
 
         let (question, token) = self.get_question(&history, &human_message.content).await?;
         if let Some(token) = token {
@@ -120,11 +138,11 @@ impl Chain for ConversationalRetrieverChain {
             None => {}
         }
 
-        {
-            let mut memory = self.memory.lock().await;
+        with_memory_timeout(&self.memory, self.memory_lock_timeout, |memory| {
             memory.add_message(human_message);
             memory.add_message(Message::new_ai_message(&output.generation));
-        }
+        })
+        .await?;
 
         let mut result = HashMap::new();
         result.insert(self.output_key.clone(), json!(output.generation));
@@ -158,10 +176,10 @@ impl Chain for ConversationalRetrieverChain {
             .ok_or(ChainError::MissingInputVariable(self.input_key.clone()))?;
 
         let human_message = Message::new_human_message(input_variable);
-        let history = {
-            let memory = self.memory.lock().await;
+        let history = with_memory_timeout(&self.memory, self.memory_lock_timeout, |memory| {
             memory.messages()
-        };
+        })
+        .await?;
 
         let (question, _) = self.get_question(&history, &human_message.content).await?;
 
@@ -182,6 +200,7 @@ impl Chain for ConversationalRetrieverChain {
             .await?;
 
         let memory = self.memory.clone();
+        let memory_lock_timeout = self.memory_lock_timeout;
         let complete_ai_message = Arc::new(Mutex::new(String::new()));
         let complete_ai_message_clone = complete_ai_message.clone();
         let output_stream = stream! {
@@ -201,9 +220,15 @@ impl Chain for ConversationalRetrieverChain {
                 }
             }
 
-            let mut memory = memory.lock().await;
-            memory.add_message(human_message);
-            memory.add_message(Message::new_ai_message(&complete_ai_message.lock().await));
+            let ai_message = Message::new_ai_message(&complete_ai_message.lock().await);
+            if let Err(e) = with_memory_timeout(&memory, memory_lock_timeout, |memory| {
+                memory.add_message(human_message);
+                memory.add_message(ai_message);
+            })
+            .await
+            {
+                yield Err(e.into());
+            }
         };
 
         Ok(Box::pin(output_stream))
@@ -236,7 +261,10 @@ mod tests {
 
     use crate::{
         chain::ConversationalRetrieverChainBuilder,
-        llm::openai::{OpenAI, OpenAIModel},
+        llm::{
+            fake::FakeLLM,
+            openai::{OpenAI, OpenAIModel},
+        },
         memory::SimpleMemory,
         prompt_args,
         schemas::Document,
@@ -314,4 +342,47 @@ mod tests {
             println!("Result: {:?}", result);
         }
     }
+
+    #[tokio::test]
+    async fn test_condenses_question_and_stores_turns() {
+        let llm = FakeLLM::new().with_responses(vec![
+            "Nvim",
+            "What is the favorite food of luis?",
+            "Pan con chicharron",
+        ]);
+        let memory: Arc<Mutex<dyn BaseMemory>> = SimpleMemory::new().into();
+        let chain = ConversationalRetrieverChainBuilder::new()
+            .llm(llm.clone())
+            .retriever(RetrieverTest {})
+            .memory(memory.clone())
+            .return_source_documents(true)
+            .build()
+            .expect("Error building ConversationalChain");
+
+        let answer = chain
+            .invoke(prompt_args! {"question" => "Which is the favorite text editor of luis?"})
+            .await
+            .unwrap();
+        assert_eq!(answer, "Nvim");
+
+        let output = chain
+            .execute(prompt_args! {"question" => "And his favorite food?"})
+            .await
+            .unwrap();
+        assert_eq!(output["output"], json!("Pan con chicharron"));
+        assert_eq!(
+            output[CONVERSATIONAL_RETRIEVAL_QA_DEFAULT_GENERATED_QUESTION_KEY],
+            json!("What is the favorite food of luis?")
+        );
+        assert_eq!(
+            output[CONVERSATIONAL_RETRIEVAL_QA_DEFAULT_SOURCE_DOCUMENT_KEY]
+                .as_array()
+                .unwrap()
+                .len(),
+            4
+        );
+
+        assert_eq!(llm.calls().len(), 3);
+        assert_eq!(memory.lock().await.messages().len(), 4);
+    }
 }
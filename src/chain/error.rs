@@ -1,6 +1,9 @@
 use thiserror::Error;
 
-use crate::{language_models::LLMError, output_parsers::OutputParserError, prompt::PromptError};
+use crate::{
+    language_models::LLMError, output_parsers::OutputParserError, prompt::PromptError,
+    schemas::MemoryError,
+};
 
 #[derive(Error, Debug)]
 pub enum ChainError {
@@ -19,6 +22,31 @@ pub enum ChainError {
     #[error("Missing Object On Builder: {0}")]
     MissingObject(String),
 
+    #[error("Invalid chain: {0}")]
+    InvalidChain(String),
+
+    #[error("LLM output was cut off by the token limit: {0}")]
+    TruncatedOutput(String),
+
+    #[error("Content flagged by moderation in {target}: {}", .categories.join(", "))]
+    ContentFlagged {
+        /// `input` or `output`.
+        target: String,
+        categories: Vec<String>,
+    },
+
+    #[error("Output rejected by the {validator} guardrail: {reason}")]
+    GuardrailViolation { validator: String, reason: String },
+
+    #[error("The call was cancelled")]
+    Cancelled,
+
+    #[error("The call timed out after {0:?}")]
+    Timeout(std::time::Duration),
+
+    #[error("Moderation error: {0}")]
+    ModerationError(String),
+
     #[error("Missing input variable: {0}")]
     MissingInputVariable(String),
 
@@ -39,4 +67,21 @@ pub enum ChainError {
 
     #[error("Agent error: {0}")]
     AgentError(String),
+
+    #[error("Tool {tool} not found{}", did_you_mean(.suggestion))]
+    ToolNotFound {
+        tool: String,
+        /// The closest tool name, when none was close enough to be used instead.
+        suggestion: Option<String>,
+    },
+
+    #[error("Memory error: {0}")]
+    MemoryError(#[from] MemoryError),
+}
+
+fn did_you_mean(suggestion: &Option<String>) -> String {
+    match suggestion {
+        Some(suggestion) => format!(", did you mean {}?", suggestion),
+        None => String::new(),
+    }
 }
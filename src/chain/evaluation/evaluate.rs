@@ -0,0 +1,147 @@
+use futures::{stream, StreamExt};
+
+use crate::{chain::Chain, prompt::PromptArgs};
+
+use super::Scorer;
+
+const DEFAULT_EVAL_CONCURRENCY: usize = 5;
+
+/// The result of running a chain over a single example of the dataset.
+#[derive(Debug, Clone)]
+pub struct ExampleScore {
+    pub input: PromptArgs,
+    pub expected: String,
+    /// Empty when the chain failed.
+    pub output: String,
+    /// 0.0 when the chain or the scorer failed.
+    pub score: f64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EvalResult {
+    /// One entry per example, in the same order as the dataset.
+    pub examples: Vec<ExampleScore>,
+    /// Mean score over every example, failed ones count as 0.0.
+    pub mean_score: f64,
+}
+
+impl EvalResult {
+    /// Number of examples that failed to run or to be scored.
+    pub fn errors(&self) -> usize {
+        self.examples.iter().filter(|e| e.error.is_some()).count()
+    }
+}
+
+/// Runs `chain` over every `(input, expected)` pair of `dataset` and scores each output,
+/// running up to 5 examples at the same time. A failing example gets a 0.0 score and its
+/// error is recorded instead of aborting the evaluation.
+///
+/// # Example
+/// ```rust,ignore
+/// let dataset = vec![
+///     (prompt_args! {"input" => "2 + 2"}, "4".to_string()),
+///     (prompt_args! {"input" => "3 * 3"}, "9".to_string()),
+/// ];
+/// let result = evaluate(&chain, dataset, &exact_match).await;
+/// println!("Accuracy: {}", result.mean_score);
+/// ```
+pub async fn evaluate<C, S>(chain: &C, dataset: Vec<(PromptArgs, String)>, scorer: &S) -> EvalResult
+where
+    C: Chain + ?Sized,
+    S: Scorer + ?Sized,
+{
+    evaluate_with_concurrency(chain, dataset, scorer, DEFAULT_EVAL_CONCURRENCY).await
+}
+
+/// Like [`evaluate`], running up to `max_concurrency` examples at the same time.
+pub async fn evaluate_with_concurrency<C, S>(
+    chain: &C,
+    dataset: Vec<(PromptArgs, String)>,
+    scorer: &S,
+    max_concurrency: usize,
+) -> EvalResult
+where
+    C: Chain + ?Sized,
+    S: Scorer + ?Sized,
+{
+    let examples: Vec<ExampleScore> = stream::iter(dataset)
+        .map(|(input, expected)| async move {
+            let scored = match chain.invoke(input.clone()).await {
+                Ok(output) => {
+                    let score = scorer.score(&input, &output, &expected).await;
+                    (output, score)
+                }
+                Err(err) => (String::new(), Err(err)),
+            };
+            let (output, score, error) = match scored {
+                (output, Ok(score)) => (output, score, None),
+                (output, Err(err)) => (output, 0.0, Some(err.to_string())),
+            };
+            ExampleScore {
+                input,
+                expected,
+                output,
+                score,
+                error,
+            }
+        })
+        .buffered(max_concurrency.max(1))
+        .collect()
+        .await;
+
+    let mean_score = if examples.is_empty() {
+        0.0
+    } else {
+        examples.iter().map(|e| e.score).sum::<f64>() / examples.len() as f64
+    };
+
+    EvalResult {
+        examples,
+        mean_score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chain::{contains_match, exact_match, LLMChainBuilder},
+        llm::fake::FakeLLM,
+        prompt_args, template_jinja2,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_evaluate() {
+        let llm = FakeLLM::new().with_responses(vec!["4", "The answer is 9", "Lima"]);
+        let chain = LLMChainBuilder::new()
+            .prompt(template_jinja2!("{{input}}", "input"))
+            .llm(llm)
+            .build()
+            .unwrap();
+        let dataset = vec![
+            (prompt_args! {"input" => "2 + 2"}, "4".to_string()),
+            (prompt_args! {"input" => "3 * 3"}, "9".to_string()),
+            (
+                prompt_args! {"input" => "Capital of Peru"},
+                "Lima".to_string(),
+            ),
+            (
+                prompt_args! {"input" => "Capital of Chile"},
+                "Santiago".to_string(),
+            ),
+        ];
+
+        let result = evaluate_with_concurrency(&chain, dataset, &exact_match, 1).await;
+
+        let scores: Vec<f64> = result.examples.iter().map(|e| e.score).collect();
+        assert_eq!(scores, vec![1.0, 0.0, 1.0, 0.0]);
+        assert_eq!(result.mean_score, 0.5);
+        // The LLM has no response left for the last example.
+        assert_eq!(result.errors(), 1);
+        assert!(result.examples[3].error.is_some());
+
+        assert_eq!(contains_match("The answer is 9", "9"), 1.0);
+    }
+}
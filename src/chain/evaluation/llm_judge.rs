@@ -0,0 +1,184 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{
+    chain::ChainError,
+    language_models::llm::LLM,
+    output_parsers::{parse_json_output, OutputParserError},
+    prompt::PromptArgs,
+    schemas::Message,
+};
+
+use super::Scorer;
+
+const DEFAULT_RUBRIC: &str = r#"Grade the candidate answer for correctness against the reference answer.
+The candidate doesn't need the same wording, only the same meaning. Give 1 when it is fully
+correct, 0 when it is wrong or contradicts the reference, and a value in between when it is
+partially correct or incomplete."#;
+
+/// The grade given by an [`LlmJudge`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct JudgeResult {
+    /// Between 0.0 and 1.0.
+    pub score: f64,
+    pub rationale: String,
+}
+
+/// Uses an LLM to grade open-ended answers against a reference answer, returning a score
+/// between 0 and 1 together with the reasoning behind it.
+///
+/// As a [`Scorer`], the question is read from the `input` key of the example inputs.
+///
+/// # Usage
+/// ```rust,ignore
+/// let judge = LlmJudge::new(OpenAI::default())
+///     .with_rubric("Give 1 only if the answer mentions the exact date, otherwise 0.");
+/// let grade = judge
+///     .grade("When did Peru become independent?", "July 28, 1821", "In 1821")
+///     .await?;
+/// println!("{}: {}", grade.score, grade.rationale);
+///
+/// let result = evaluate(&chain, dataset, &judge).await;
+/// ```
+pub struct LlmJudge {
+    llm: Box<dyn LLM>,
+    rubric: String,
+    question_key: String,
+}
+
+impl LlmJudge {
+    pub fn new<L: Into<Box<dyn LLM>>>(llm: L) -> Self {
+        Self {
+            llm: llm.into(),
+            rubric: DEFAULT_RUBRIC.to_string(),
+            question_key: "input".to_string(),
+        }
+    }
+
+    /// Replaces the grading instructions. The judge always asks for the score and the
+    /// rationale in the same JSON format, so only the criteria need to be described.
+    pub fn with_rubric<S: Into<String>>(mut self, rubric: S) -> Self {
+        self.rubric = rubric.into();
+        self
+    }
+
+    /// The input key holding the question when used as a [`Scorer`].
+    /// Default: `input`
+    pub fn with_question_key<S: Into<String>>(mut self, question_key: S) -> Self {
+        self.question_key = question_key.into();
+        self
+    }
+
+    pub async fn grade(
+        &self,
+        question: &str,
+        reference: &str,
+        candidate: &str,
+    ) -> Result<JudgeResult, ChainError> {
+        let prompt = format!(
+            "You are grading the answer to a question.\n\n{}\n\n\
+             Question: {}\nReference answer: {}\nCandidate answer: {}\n\n{}",
+            self.rubric, question, reference, candidate, FORMAT_INSTRUCTIONS
+        );
+        let output = self
+            .llm
+            .generate(&[Message::new_human_message(prompt)])
+            .await?
+            .generation;
+        Ok(parse_judge_output(&output)?)
+    }
+}
+
+#[async_trait]
+impl Scorer for LlmJudge {
+    async fn score(
+        &self,
+        input: &PromptArgs,
+        output: &str,
+        expected: &str,
+    ) -> Result<f64, ChainError> {
+        let question = match input.get(&self.question_key) {
+            Some(Value::String(question)) => question.clone(),
+            Some(question) => question.to_string(),
+            None => return Err(ChainError::MissingInputVariable(self.question_key.clone())),
+        };
+        Ok(self.grade(&question, expected, output).await?.score)
+    }
+}
+
+const FORMAT_INSTRUCTIONS: &str = r#"Respond only with a JSON object like {"rationale": "<short explanation>", "score": <number between 0 and 1>}. "score" can also be "pass" or "fail"."#;
+
+fn parse_judge_output(output: &str) -> Result<JudgeResult, OutputParserError> {
+    let value = parse_json_output(output)
+        .ok_or_else(|| OutputParserError::InvalidJson(output.to_string()))?;
+
+    let score = match &value["score"] {
+        Value::Number(score) => score.as_f64(),
+        Value::Bool(pass) => Some(if *pass { 1.0 } else { 0.0 }),
+        Value::String(score) => match score.trim().to_lowercase().as_str() {
+            "pass" => Some(1.0),
+            "fail" => Some(0.0),
+            score => score.parse::<f64>().ok(),
+        },
+        _ => None,
+    }
+    .ok_or_else(|| OutputParserError::MissingField {
+        field: "score".to_string(),
+        output: output.to_string(),
+    })?;
+
+    Ok(JudgeResult {
+        score: score.clamp(0.0, 1.0),
+        rationale: value["rationale"].as_str().unwrap_or_default().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{llm::fake::FakeLLM, prompt_args};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_judge_output() {
+        let result = parse_judge_output(
+            "```json\n{\"rationale\": \"Missing the day\", \"score\": 0.5}\n```",
+        )
+        .unwrap();
+        assert_eq!(result.score, 0.5);
+        assert_eq!(result.rationale, "Missing the day");
+
+        let result = parse_judge_output(r#"{"rationale": "Wrong", "score": "fail"}"#).unwrap();
+        assert_eq!(result.score, 0.0);
+
+        assert!(matches!(
+            parse_judge_output("Looks good to me"),
+            Err(OutputParserError::InvalidJson(_))
+        ));
+        assert!(matches!(
+            parse_judge_output(r#"{"rationale": "Fine"}"#),
+            Err(OutputParserError::MissingField { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_llm_judge_as_scorer() {
+        let llm = FakeLLM::new().with_responses(vec![r#"{"rationale": "Same", "score": 1}"#]);
+        let judge = LlmJudge::new(llm.clone()).with_rubric("Be strict.");
+
+        let score = judge
+            .score(
+                &prompt_args! {"input" => "Capital of Peru?"},
+                "Lima",
+                "Lima, Peru",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(score, 1.0);
+        let prompt = &llm.last_call().unwrap()[0].content;
+        assert!(prompt.contains("Be strict."));
+        assert!(prompt.contains("Question: Capital of Peru?"));
+        assert!(prompt.contains("Reference answer: Lima, Peru\nCandidate answer: Lima"));
+    }
+}
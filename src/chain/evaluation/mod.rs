@@ -0,0 +1,8 @@
+mod scorer;
+pub use scorer::*;
+
+mod evaluate;
+pub use evaluate::*;
+
+mod llm_judge;
+pub use llm_judge::*;
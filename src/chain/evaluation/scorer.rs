@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+
+use crate::{chain::ChainError, prompt::PromptArgs};
+
+/// Scores the output of a chain against the expected answer, usually between 0 and 1.
+///
+/// Any `Fn(&str, &str) -> f64` taking the output and the expected answer is a scorer, so
+/// simple metrics can be plain functions like [`exact_match`] or [`contains_match`].
+#[async_trait]
+pub trait Scorer: Send + Sync {
+    async fn score(
+        &self,
+        input: &PromptArgs,
+        output: &str,
+        expected: &str,
+    ) -> Result<f64, ChainError>;
+}
+
+#[async_trait]
+impl<F> Scorer for F
+where
+    F: Fn(&str, &str) -> f64 + Send + Sync,
+{
+    async fn score(
+        &self,
+        _input: &PromptArgs,
+        output: &str,
+        expected: &str,
+    ) -> Result<f64, ChainError> {
+        Ok(self(output, expected))
+    }
+}
+
+/// 1.0 when the output equals the expected answer, ignoring surrounding whitespace.
+pub fn exact_match(output: &str, expected: &str) -> f64 {
+    if output.trim() == expected.trim() {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// 1.0 when the output contains the expected answer, ignoring case.
+pub fn contains_match(output: &str, expected: &str) -> f64 {
+    if output
+        .to_lowercase()
+        .contains(&expected.trim().to_lowercase())
+    {
+        1.0
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,114 @@
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    chain::{llm_chain::LLMChainBuilder, options::ChainCallOptions, ChainError},
+    language_models::{llm::LLM, options::CallOptions},
+    prompt::HumanMessagePromptTemplate,
+    schemas::{FunctionDefinition, ToolChoice},
+    template_jinja2,
+};
+
+use super::{
+    chain::ExtractionChain,
+    prompt::{DEFAULT_EXTRACTION_FUNCTION_DESCRIPTION, DEFAULT_EXTRACTION_TEMPLATE},
+    EXTRACTION_DEFAULT_FUNCTION_NAME, EXTRACTION_DEFAULT_INPUT_KEY,
+};
+
+pub struct ExtractionChainBuilder<T> {
+    llm: Option<Box<dyn LLM>>,
+    schema: Option<Value>,
+    function_name: Option<String>,
+    options: Option<ChainCallOptions>,
+    output_key: Option<String>,
+    _output: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> ExtractionChainBuilder<T> {
+    pub fn new() -> Self {
+        Self {
+            llm: None,
+            schema: None,
+            function_name: None,
+            options: None,
+            output_key: None,
+            _output: PhantomData,
+        }
+    }
+
+    pub fn llm<L: Into<Box<dyn LLM>>>(mut self, llm: L) -> Self {
+        self.llm = Some(llm.into());
+        self
+    }
+
+    /// JSON schema of `T`, sent to the model as the function parameters and included in
+    /// the prompt for models without function calling.
+    pub fn schema(mut self, schema: Value) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Name of the function the model is asked to call. Default: `information_extraction`
+    pub fn function_name<S: Into<String>>(mut self, function_name: S) -> Self {
+        self.function_name = Some(function_name.into());
+        self
+    }
+
+    pub fn options(mut self, options: ChainCallOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    pub fn output_key<S: Into<String>>(mut self, output_key: S) -> Self {
+        self.output_key = Some(output_key.into());
+        self
+    }
+
+    pub fn build(self) -> Result<ExtractionChain<T>, ChainError> {
+        let mut llm = self
+            .llm
+            .ok_or_else(|| ChainError::MissingObject("LLM must be set".into()))?;
+        let schema = self
+            .schema
+            .ok_or_else(|| ChainError::MissingObject("Schema must be set".into()))?;
+        let function = FunctionDefinition::new(
+            self.function_name
+                .as_deref()
+                .unwrap_or(EXTRACTION_DEFAULT_FUNCTION_NAME),
+            DEFAULT_EXTRACTION_FUNCTION_DESCRIPTION,
+            schema.clone(),
+        );
+        let function_name = function.name.clone();
+
+        // Backends with function calling answer with a call to this function, the others
+        // ignore these options and follow the prompt instead.
+        llm.add_options(
+            CallOptions::new()
+                .with_functions(vec![function])
+                .with_tool_choice(ToolChoice::Named(function_name.clone())),
+        );
+
+        let template = DEFAULT_EXTRACTION_TEMPLATE
+            .replace("{{schema}}", &serde_json::to_string_pretty(&schema)?);
+        let prompt = HumanMessagePromptTemplate::new(template_jinja2!(
+            template,
+            EXTRACTION_DEFAULT_INPUT_KEY
+        ));
+
+        let mut builder = LLMChainBuilder::new().prompt(prompt).llm(llm);
+        if let Some(options) = self.options {
+            builder = builder.options(options);
+        }
+        if let Some(output_key) = self.output_key {
+            builder = builder.output_key(output_key);
+        }
+
+        Ok(ExtractionChain {
+            llmchain: builder.build()?,
+            function_name,
+            _output: PhantomData,
+        })
+    }
+}
@@ -0,0 +1,214 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::{
+    chain::{
+        chain_trait::Chain, llm_chain::LLMChain, ChainError, DEFAULT_OUTPUT_KEY, DEFAULT_RESULT_KEY,
+    },
+    language_models::GenerateResult,
+    output_parsers::{extract_partial_json_block, parse_partial_json, OutputParserError},
+    prompt::PromptArgs,
+    prompt_args,
+    schemas::FunctionCallResponse,
+};
+
+use super::EXTRACTION_DEFAULT_INPUT_KEY;
+
+/// Extracts structured data from a passage into a `T`.
+///
+/// Models with function calling are forced to call a function whose parameters are the
+/// schema, other models are asked to answer with a JSON block, which is read leniently.
+/// The input variable name is `input`.
+///
+/// # Example
+/// ```rust,ignore
+/// #[derive(Deserialize)]
+/// struct Person {
+///     name: String,
+///     age: Option<u32>,
+/// }
+///
+/// let chain = ExtractionChainBuilder::<Person>::new()
+///     .llm(OpenAI::default())
+///     .schema(json!({
+///         "type": "object",
+///         "properties": {
+///             "name": {"type": "string"},
+///             "age": {"type": "integer"}
+///         },
+///         "required": ["name"]
+///     }))
+///     .build()?;
+///
+/// let person: Person = chain.extract("Luis is 30 years old").await?;
+/// ```
+pub struct ExtractionChain<T> {
+    pub(crate) llmchain: LLMChain,
+    pub(crate) function_name: String,
+    pub(crate) _output: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> ExtractionChain<T> {
+    /// Extracts a `T` from `input`.
+    pub async fn extract<S: Into<String>>(&self, input: S) -> Result<T, ChainError> {
+        self.call_typed(prompt_args! {
+            EXTRACTION_DEFAULT_INPUT_KEY => input.into(),
+        })
+        .await
+    }
+
+    /// Same as `call`, but returns the deserialized `T`.
+    pub async fn call_typed(&self, input_variables: PromptArgs) -> Result<T, ChainError> {
+        let output = self.llmchain.call(input_variables).await?;
+        let value = self.parse_generation(&output.generation)?;
+        serde_json::from_value(value)
+            .map_err(|e| OutputParserError::SchemaMismatch(e.to_string()).into())
+    }
+
+    /// Reads the arguments of the function call, or the JSON in the text when the model
+    /// answered without calling the function.
+    fn parse_generation(&self, generation: &str) -> Result<Value, ChainError> {
+        if let Ok(calls) = serde_json::from_str::<Vec<FunctionCallResponse>>(generation) {
+            if let Some(call) = calls
+                .into_iter()
+                .find(|call| call.function.name == self.function_name)
+            {
+                return parse_partial_json(&call.function.arguments, false).ok_or_else(|| {
+                    OutputParserError::InvalidJson(format!(
+                        "arguments of {}: {}",
+                        self.function_name, call.function.arguments
+                    ))
+                    .into()
+                });
+            }
+        }
+
+        extract_partial_json_block(generation)
+            .and_then(|json| parse_partial_json(json, false))
+            .ok_or_else(|| OutputParserError::InvalidJson(generation.to_string()).into())
+    }
+}
+
+#[async_trait]
+impl<T: DeserializeOwned> Chain for ExtractionChain<T> {
+    fn get_input_keys(&self) -> Vec<String> {
+        self.llmchain.get_input_keys()
+    }
+
+    fn get_output_keys(&self) -> Vec<String> {
+        self.llmchain.get_output_keys()
+    }
+
+    /// The generation is the extracted JSON, also set as `parsed` once it has been
+    /// checked to deserialize into `T`.
+    async fn call(&self, input_variables: PromptArgs) -> Result<GenerateResult, ChainError> {
+        let output = self.llmchain.call(input_variables).await?;
+        let value = self.parse_generation(&output.generation)?;
+        serde_json::from_value::<T>(value.clone())?;
+
+        Ok(GenerateResult {
+            generation: value.to_string(),
+            parsed: Some(value),
+            ..output
+        })
+    }
+
+    /// The extracted JSON is set as a value under the output key, so chains after this one
+    /// in a `SequentialChain` can read its fields.
+    async fn execute(
+        &self,
+        input_variables: PromptArgs,
+    ) -> Result<HashMap<String, Value>, ChainError> {
+        let result = self.call(input_variables).await?;
+        let output_key = self
+            .get_output_keys()
+            .first()
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_OUTPUT_KEY.to_string());
+        Ok(HashMap::from([
+            (output_key, result.parsed.clone().unwrap_or_default()),
+            (DEFAULT_RESULT_KEY.to_string(), json!(result)),
+        ]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::{chain::ExtractionChainBuilder, llm::fake::FakeLLM, schemas::FunctionCallBehavior};
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Person {
+        name: String,
+        age: Option<u32>,
+    }
+
+    fn chain(llm: FakeLLM) -> ExtractionChain<Person> {
+        ExtractionChainBuilder::<Person>::new()
+            .llm(llm)
+            .schema(json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string"},
+                    "age": {"type": "integer"}
+                },
+                "required": ["name"]
+            }))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_extracts_from_function_call() {
+        let tool_call = json!([{
+            "id": "call_1",
+            "type": "function",
+            "function": {
+                "name": "information_extraction",
+                "arguments": "{\"name\": \"Luis\", \"age\": 30}"
+            }
+        }]);
+        let llm = FakeLLM::new().with_responses(vec![tool_call.to_string()]);
+        let chain = chain(llm.clone());
+
+        let person = chain.extract("Luis is 30 years old").await.unwrap();
+
+        assert_eq!(
+            person,
+            Person {
+                name: "Luis".into(),
+                age: Some(30)
+            }
+        );
+        let options = llm.call_options();
+        assert_eq!(options[0].functions.as_ref().map(|f| f.len()), Some(1));
+        assert_eq!(
+            options[0].function_call_behavior,
+            Some(FunctionCallBehavior::Named("information_extraction".into()))
+        );
+        assert!(llm.calls()[0][0].content.contains("\"required\""));
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_json_in_text() {
+        let llm = FakeLLM::new().with_responses(vec![
+            "Here it is:\n```json\n{\"name\": \"Ana\"\n```",
+            "I could not find anyone",
+        ]);
+        let chain = chain(llm);
+
+        let result = chain
+            .call(prompt_args! {"input" => "Ana lives in Lima"})
+            .await
+            .unwrap();
+        assert_eq!(result.parsed, Some(json!({"name": "Ana"})));
+
+        assert!(chain.extract("Nobody here").await.is_err());
+    }
+}
@@ -0,0 +1,10 @@
+mod builder;
+mod chain;
+mod prompt;
+
+pub use builder::*;
+pub use chain::*;
+pub use prompt::*;
+
+const EXTRACTION_DEFAULT_INPUT_KEY: &str = "input";
+const EXTRACTION_DEFAULT_FUNCTION_NAME: &str = "information_extraction";
@@ -0,0 +1,11 @@
+pub const DEFAULT_EXTRACTION_TEMPLATE: &str = r#"Extract the relevant information from the passage below, following this JSON schema:
+
+{{schema}}
+
+Leave out the properties that are not mentioned in the passage. Respond only with a JSON object matching the schema inside a ```json code block.
+
+Passage:
+{{input}}"#;
+
+pub const DEFAULT_EXTRACTION_FUNCTION_DESCRIPTION: &str =
+    "Extracts the relevant information from the passage.";
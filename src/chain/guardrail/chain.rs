@@ -0,0 +1,238 @@
+use std::{collections::HashMap, pin::Pin};
+
+use async_trait::async_trait;
+use futures::Stream;
+use serde_json::{json, Value};
+
+use crate::{
+    chain::{execute_nested, Chain, ChainError, DEFAULT_OUTPUT_KEY, DEFAULT_RESULT_KEY},
+    language_models::GenerateResult,
+    prompt::PromptArgs,
+    schemas::StreamData,
+};
+
+use super::{OutputValidator, Validation};
+
+/// Wraps a chain, running its generation through a list of [`OutputValidator`]s in order.
+/// Redacted outputs are passed on to the next validator; a rejected output calls the
+/// wrapped chain again up to `max_retries` times, then fails with
+/// `ChainError::GuardrailViolation` naming the validator. When the output is redacted, the
+/// `parsed` and `raw` fields of the result are cleared, since they hold the original text.
+///
+/// The validators run on the output of the wrapped chain, once it has returned. A chain
+/// with memory, e.g. `ConversationalChain`, has already stored that output by then,
+/// including rejected or unredacted ones, so wrap it only when its memory may hold them.
+///
+/// # Example
+/// ```rust,ignore
+/// let chain = GuardrailChain::new(
+///     llm_chain,
+///     vec![Box::new(MaxLength::new(500)), Box::new(PiiDetector::new().with_redaction("***"))],
+/// )
+/// .with_max_retries(2);
+/// ```
+pub struct GuardrailChain {
+    chain: Box<dyn Chain>,
+    validators: Vec<Box<dyn OutputValidator>>,
+    max_retries: usize,
+}
+
+impl GuardrailChain {
+    pub fn new<C: Into<Box<dyn Chain>>>(
+        chain: C,
+        validators: Vec<Box<dyn OutputValidator>>,
+    ) -> Self {
+        Self {
+            chain: chain.into(),
+            validators,
+            max_retries: 0,
+        }
+    }
+
+    pub fn with_validator<V: OutputValidator + 'static>(mut self, validator: V) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    /// How many times the wrapped chain is called again after a rejected output.
+    /// Defaults to 0.
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    async fn validate(&self, output: &str) -> Result<String, ChainError> {
+        let mut output = output.to_string();
+        for validator in &self.validators {
+            match validator.validate(&output).await {
+                Validation::Valid => {}
+                Validation::Redacted(redacted) => output = redacted,
+                Validation::Invalid(reason) => {
+                    return Err(ChainError::GuardrailViolation {
+                        validator: validator.name(),
+                        reason,
+                    })
+                }
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Replaces the generation of `result` with the validated `output`, dropping the fields
+/// that would still carry the text before redaction.
+fn redact_result(result: &mut GenerateResult, output: String) {
+    if result.generation != output {
+        result.parsed = None;
+        result.raw = None;
+    }
+    result.generation = output;
+}
+
+#[async_trait]
+impl Chain for GuardrailChain {
+    async fn call(&self, input_variables: PromptArgs) -> Result<GenerateResult, ChainError> {
+        let mut attempt = 0;
+        loop {
+            let mut result = self.chain.call(input_variables.clone()).await?;
+            match self.validate(&result.generation).await {
+                Ok(output) => {
+                    redact_result(&mut result, output);
+                    return Ok(result);
+                }
+                Err(err) if attempt < self.max_retries => {
+                    log::warn!("Retrying after guardrail rejection: {}", err);
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    async fn execute(
+        &self,
+        input_variables: PromptArgs,
+    ) -> Result<HashMap<String, Value>, ChainError> {
+        let output_key = self
+            .get_output_keys()
+            .first()
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_OUTPUT_KEY.to_string());
+        let mut attempt = 0;
+        loop {
+            let mut output = execute_nested(self.chain.as_ref(), input_variables.clone()).await?;
+            let text = match output.get(&output_key).and_then(Value::as_str) {
+                Some(text) => text.to_string(),
+                None => return Ok(output),
+            };
+            match self.validate(&text).await {
+                Ok(validated) => {
+                    if validated != text {
+                        let result = output
+                            .remove(DEFAULT_RESULT_KEY)
+                            .and_then(|result| serde_json::from_value(result).ok());
+                        if let Some(mut result) = result {
+                            redact_result(&mut result, validated.clone());
+                            output.insert(DEFAULT_RESULT_KEY.to_string(), json!(result));
+                        }
+                    }
+                    output.insert(output_key, Value::from(validated));
+                    return Ok(output);
+                }
+                Err(err) if attempt < self.max_retries => {
+                    log::warn!("Retrying after guardrail rejection: {}", err);
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Streams the wrapped chain unvalidated, since the output is only complete at the end.
+    async fn stream(
+        &self,
+        input_variables: PromptArgs,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, ChainError>> + Send>>, ChainError>
+    {
+        self.chain.stream(input_variables).await
+    }
+
+    fn get_input_keys(&self) -> Vec<String> {
+        self.chain.get_input_keys()
+    }
+
+    fn get_output_keys(&self) -> Vec<String> {
+        self.chain.get_output_keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chain::{LLMChainBuilder, MaxLength, PiiDetector},
+        llm::fake::FakeLLM,
+        prompt_args, template_fstring,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_guardrail_chain() {
+        let llm = FakeLLM::new().with_responses(vec![
+            "this answer is much too long for the limit",
+            "mail me at luis@example.com",
+            "still far too long",
+        ]);
+        let chain = LLMChainBuilder::new()
+            .prompt(template_fstring!("{input}", "input"))
+            .llm(llm.clone())
+            .build()
+            .unwrap();
+        let chain = GuardrailChain::new(chain, vec![Box::new(MaxLength::new(30))])
+            .with_validator(PiiDetector::new().with_redaction("<email>"))
+            .with_max_retries(1);
+
+        let result = chain.invoke(prompt_args! {"input" => "hi"}).await.unwrap();
+        assert_eq!(result, "mail me at <email>");
+        assert_eq!(llm.calls().len(), 2);
+
+        let chain = GuardrailChain::new(
+            LLMChainBuilder::new()
+                .prompt(template_fstring!("{input}", "input"))
+                .llm(llm.clone())
+                .build()
+                .unwrap(),
+            vec![Box::new(MaxLength::new(10))],
+        );
+        let err = chain
+            .invoke(prompt_args! {"input" => "hi"})
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ChainError::GuardrailViolation { ref validator, .. } if validator == "max_length"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_redaction_clears_parsed_output() {
+        let llm = FakeLLM::new().with_responses(vec![r#"{"email": "luis@example.com"}"#]);
+        let chain = LLMChainBuilder::new()
+            .prompt(template_fstring!("{input}", "input"))
+            .llm(llm)
+            .parse_json(true)
+            .build()
+            .unwrap();
+        let chain = GuardrailChain::new(chain, vec![])
+            .with_validator(PiiDetector::new().with_redaction("<email>"));
+
+        let output = chain.execute(prompt_args! {"input" => "hi"}).await.unwrap();
+
+        assert_eq!(output[DEFAULT_OUTPUT_KEY], r#"{"email": "<email>"}"#);
+        let result: GenerateResult =
+            serde_json::from_value(output[DEFAULT_RESULT_KEY].clone()).unwrap();
+        assert_eq!(result.generation, r#"{"email": "<email>"}"#);
+        assert!(result.parsed.is_none());
+        assert!(!output[DEFAULT_RESULT_KEY].to_string().contains("luis@"));
+    }
+}
@@ -0,0 +1,8 @@
+mod validator;
+pub use validator::*;
+
+mod validators;
+pub use validators::*;
+
+mod chain;
+pub use chain::*;
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+
+/// The outcome of an [`OutputValidator`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Validation {
+    Valid,
+    /// The output is rejected for the given reason.
+    Invalid(String),
+    /// The output is accepted after replacing it, e.g. with sensitive data masked.
+    Redacted(String),
+}
+
+/// Checks the output of a chain wrapped in a `GuardrailChain`.
+#[async_trait]
+pub trait OutputValidator: Send + Sync {
+    /// Name reported in `ChainError::GuardrailViolation` when the output is rejected.
+    fn name(&self) -> String;
+
+    async fn validate(&self, output: &str) -> Validation;
+}
@@ -0,0 +1,279 @@
+use async_trait::async_trait;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::output_parsers::extract_partial_json_block;
+
+use super::{OutputValidator, Validation};
+
+/// Rejects outputs matching any of the given patterns, or masks the matches when a
+/// redaction is set.
+pub struct RegexDenylist {
+    patterns: Vec<Regex>,
+    redaction: Option<String>,
+}
+
+impl RegexDenylist {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self, regex::Error> {
+        Ok(Self {
+            patterns: patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern.as_ref()))
+                .collect::<Result<_, _>>()?,
+            redaction: None,
+        })
+    }
+
+    /// Replace the matches with `redaction` instead of rejecting the output.
+    pub fn with_redaction<S: Into<String>>(mut self, redaction: S) -> Self {
+        self.redaction = Some(redaction.into());
+        self
+    }
+}
+
+#[async_trait]
+impl OutputValidator for RegexDenylist {
+    fn name(&self) -> String {
+        "regex_denylist".to_string()
+    }
+
+    async fn validate(&self, output: &str) -> Validation {
+        redact_or_reject(&self.patterns, self.redaction.as_deref(), output)
+    }
+}
+
+/// Rejects outputs longer than the given number of characters.
+pub struct MaxLength {
+    max_chars: usize,
+}
+
+impl MaxLength {
+    pub fn new(max_chars: usize) -> Self {
+        Self { max_chars }
+    }
+}
+
+#[async_trait]
+impl OutputValidator for MaxLength {
+    fn name(&self) -> String {
+        "max_length".to_string()
+    }
+
+    async fn validate(&self, output: &str) -> Validation {
+        let chars = output.chars().count();
+        if chars > self.max_chars {
+            return Validation::Invalid(format!(
+                "output has {} characters, the maximum is {}",
+                chars, self.max_chars
+            ));
+        }
+        Validation::Valid
+    }
+}
+
+/// Rejects outputs that are not JSON matching a JSON schema, also inside a markdown code
+/// block. Supports the `type`, `properties`, `required`, `items` and `enum` keywords.
+pub struct JsonSchemaValidator {
+    schema: Value,
+}
+
+impl JsonSchemaValidator {
+    pub fn new(schema: Value) -> Self {
+        Self { schema }
+    }
+}
+
+#[async_trait]
+impl OutputValidator for JsonSchemaValidator {
+    fn name(&self) -> String {
+        "json_schema".to_string()
+    }
+
+    async fn validate(&self, output: &str) -> Validation {
+        let json = extract_partial_json_block(output).unwrap_or(output).trim();
+        let value = match serde_json::from_str::<Value>(json) {
+            Ok(value) => value,
+            Err(e) => return Validation::Invalid(format!("output is not valid JSON: {}", e)),
+        };
+        match check_schema(&value, &self.schema, "") {
+            Ok(()) => Validation::Valid,
+            Err(reason) => Validation::Invalid(reason),
+        }
+    }
+}
+
+fn check_schema(value: &Value, schema: &Value, path: &str) -> Result<(), String> {
+    let location = if path.is_empty() { "/" } else { path };
+    if let Some(expected) = schema["type"].as_str() {
+        let matches = match expected {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => true,
+        };
+        if !matches {
+            return Err(format!("{} is not of type {}", location, expected));
+        }
+    }
+    if let Some(options) = schema["enum"].as_array() {
+        if !options.contains(value) {
+            return Err(format!("{} is not one of the allowed values", location));
+        }
+    }
+    if let Some(required) = schema["required"].as_array() {
+        for key in required.iter().filter_map(Value::as_str) {
+            if value.get(key).is_none() {
+                return Err(format!(
+                    "{} is missing the required property {}",
+                    location, key
+                ));
+            }
+        }
+    }
+    if let (Some(properties), Some(object)) = (schema["properties"].as_object(), value.as_object())
+    {
+        for (key, property_schema) in properties {
+            if let Some(property) = object.get(key) {
+                check_schema(property, property_schema, &format!("{}/{}", path, key))?;
+            }
+        }
+    }
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        for (index, item) in array.iter().enumerate() {
+            check_schema(item, items, &format!("{}/{}", path, index))?;
+        }
+    }
+    Ok(())
+}
+
+/// Detects email addresses, phone numbers, credit card numbers and US social security
+/// numbers. Rejects the output by default, or masks them when a redaction is set.
+///
+/// Phone numbers are either international, starting with `+`, or North American like
+/// `(555) 123-4567`, so ids and amounts made only of digits aren't taken for one.
+pub struct PiiDetector {
+    patterns: Vec<Regex>,
+    redaction: Option<String>,
+}
+
+impl PiiDetector {
+    pub fn new() -> Self {
+        let patterns = [
+            r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}",
+            r"\b\d{3}-\d{2}-\d{4}\b",
+            r"\b(?:\d[ -]?){13,16}\b",
+            r"\+\d{1,3}(?:[ .-]?\(?\d{1,4}\)?){1,2}(?:[ .-]\d{2,4}){2,4}\b",
+            r"(?:\(\d{3}\) ?|\b\d{3}[ .-])\d{3}[ .-]\d{4}\b",
+        ];
+        Self {
+            patterns: patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern).unwrap())
+                .collect(),
+            redaction: None,
+        }
+    }
+
+    /// Replace the detected data with `redaction` instead of rejecting the output.
+    pub fn with_redaction<S: Into<String>>(mut self, redaction: S) -> Self {
+        self.redaction = Some(redaction.into());
+        self
+    }
+}
+
+impl Default for PiiDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl OutputValidator for PiiDetector {
+    fn name(&self) -> String {
+        "pii_detector".to_string()
+    }
+
+    async fn validate(&self, output: &str) -> Validation {
+        redact_or_reject(&self.patterns, self.redaction.as_deref(), output)
+    }
+}
+
+fn redact_or_reject(patterns: &[Regex], redaction: Option<&str>, output: &str) -> Validation {
+    match redaction {
+        Some(redaction) => {
+            let mut redacted = output.to_string();
+            for pattern in patterns {
+                redacted = pattern.replace_all(&redacted, redaction).into_owned();
+            }
+            if redacted == output {
+                Validation::Valid
+            } else {
+                Validation::Redacted(redacted)
+            }
+        }
+        None => match patterns.iter().find(|pattern| pattern.is_match(output)) {
+            Some(pattern) => {
+                Validation::Invalid(format!("output matches the pattern {}", pattern.as_str()))
+            }
+            None => Validation::Valid,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_validators() {
+        let denylist = RegexDenylist::new(&["(?i)password"]).unwrap();
+        assert_eq!(denylist.validate("all good").await, Validation::Valid);
+        assert!(matches!(
+            denylist.validate("the Password is 1234").await,
+            Validation::Invalid(_)
+        ));
+
+        assert!(matches!(
+            MaxLength::new(3).validate("four").await,
+            Validation::Invalid(_)
+        ));
+
+        let schema = JsonSchemaValidator::new(json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {"name": {"type": "string"}, "age": {"type": "integer"}}
+        }));
+        assert_eq!(
+            schema
+                .validate("```json\n{\"name\": \"Luis\", \"age\": 30}\n```")
+                .await,
+            Validation::Valid
+        );
+        assert_eq!(
+            schema.validate(r#"{"name": "Luis", "age": "30"}"#).await,
+            Validation::Invalid("/age is not of type integer".to_string())
+        );
+
+        let pii = PiiDetector::new().with_redaction("[REDACTED]");
+        assert_eq!(
+            pii.validate("Write to luis@example.com or call 555-12-3456")
+                .await,
+            Validation::Redacted("Write to [REDACTED] or call [REDACTED]".to_string())
+        );
+        assert_eq!(
+            pii.validate("Call (555) 123-4567 or +44 20 7946 0958")
+                .await,
+            Validation::Redacted("Call [REDACTED] or [REDACTED]".to_string())
+        );
+        assert_eq!(
+            pii.validate("Order 1234567890 shipped on 2024-01-15").await,
+            Validation::Valid
+        );
+    }
+}
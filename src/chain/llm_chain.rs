@@ -1,17 +1,24 @@
-use std::pin::Pin;
+use std::{collections::HashMap, pin::Pin, sync::Arc};
 
 use async_trait::async_trait;
-use futures::Stream;
+use futures::{
+    future::{join_all, try_join_all},
+    stream, Stream, StreamExt,
+};
 use futures_util::TryStreamExt;
+use serde_json::Value;
+use tokio::sync::Semaphore;
 
 use crate::{
-    language_models::{llm::LLM, GenerateResult},
-    output_parsers::{OutputParser, SimpleParser},
+    language_models::{llm::LLM, FinishReason, GenerateResult},
+    output_parsers::{OutputParser, OutputParserError, SimpleParser},
     prompt::{FormatPrompter, PromptArgs},
-    schemas::StreamData,
+    schemas::{messages::Message, StreamData},
 };
 
-use super::{chain_trait::Chain, options::ChainCallOptions, ChainError};
+use super::{chain_trait::Chain, options::ChainCallOptions, ChainError, DEFAULT_OUTPUT_KEY};
+
+const DEFAULT_CONCURRENCY: usize = 5;
 
 pub struct LLMChainBuilder {
     prompt: Option<Box<dyn FormatPrompter>>,
@@ -19,6 +26,9 @@ pub struct LLMChainBuilder {
     output_key: Option<String>,
     options: Option<ChainCallOptions>,
     output_parser: Option<Box<dyn OutputParser>>,
+    concurrency: Option<usize>,
+    continue_on_error: bool,
+    parse_json: bool,
 }
 
 impl LLMChainBuilder {
@@ -29,6 +39,9 @@ impl LLMChainBuilder {
             options: None,
             output_key: None,
             output_parser: None,
+            concurrency: None,
+            continue_on_error: false,
+            parse_json: false,
         }
     }
     pub fn options(mut self, options: ChainCallOptions) -> Self {
@@ -46,6 +59,8 @@ impl LLMChainBuilder {
         self
     }
 
+    /// Key of the generation in the output of `execute`. Surrounding whitespace is trimmed,
+    /// and `build` fails if nothing is left. Default: "output"
     pub fn output_key<S: Into<String>>(mut self, output_key: S) -> Self {
         self.output_key = Some(output_key.into());
         self
@@ -56,7 +71,39 @@ impl LLMChainBuilder {
         self
     }
 
+    /// Deserializes the output of the parser as JSON into `GenerateResult::parsed`, failing
+    /// with `OutputParserError::InvalidJson` when it isn't. `generation` keeps the text.
+    pub fn parse_json(mut self, parse_json: bool) -> Self {
+        self.parse_json = parse_json;
+        self
+    }
+
+    /// Maximum number of inputs processed at the same time by [`LLMChain::apply`].
+    /// Default: 5
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency.max(1));
+        self
+    }
+
+    /// Skip inputs whose call fails in [`LLMChain::apply`] instead of failing the whole batch.
+    /// Default: false
+    pub fn with_continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
     pub fn build(self) -> Result<LLMChain, ChainError> {
+        let output_key = match self.output_key {
+            Some(output_key) if output_key.trim().is_empty() => {
+                return Err(ChainError::InvalidChain(format!(
+                    "output key must not be empty, got {:?}",
+                    output_key
+                )));
+            }
+            Some(output_key) => output_key.trim().to_string(),
+            None => DEFAULT_OUTPUT_KEY.to_string(),
+        };
+
         let prompt = self
             .prompt
             .ok_or_else(|| ChainError::MissingObject("Prompt must be set".into()))?;
@@ -65,7 +112,11 @@ impl LLMChainBuilder {
             .llm
             .ok_or_else(|| ChainError::MissingObject("LLM must be set".into()))?;
 
+        let mut error_on_truncation = false;
+        let mut dry_run = false;
         if let Some(options) = self.options {
+            error_on_truncation = options.error_on_truncation.unwrap_or_default();
+            dry_run = options.dry_run.unwrap_or_default();
             let llm_options = ChainCallOptions::to_llm_options(options);
             llm.add_options(llm_options);
         }
@@ -73,10 +124,17 @@ impl LLMChainBuilder {
         let chain = LLMChain {
             prompt,
             llm,
-            output_key: self.output_key.unwrap_or("output".to_string()),
+            output_key,
             output_parser: self
                 .output_parser
                 .unwrap_or_else(|| Box::new(SimpleParser::default())),
+            semaphore: Arc::new(Semaphore::new(
+                self.concurrency.unwrap_or(DEFAULT_CONCURRENCY),
+            )),
+            continue_on_error: self.continue_on_error,
+            error_on_truncation,
+            dry_run,
+            parse_json: self.parse_json,
         };
 
         Ok(chain)
@@ -88,6 +146,144 @@ pub struct LLMChain {
     llm: Box<dyn LLM>,
     output_key: String,
     output_parser: Box<dyn OutputParser>,
+    semaphore: Arc<Semaphore>,
+    continue_on_error: bool,
+    error_on_truncation: bool,
+    dry_run: bool,
+    parse_json: bool,
+}
+
+impl LLMChain {
+    /// Whether the chain returns the rendered prompt instead of calling the LLM, see
+    /// `ChainCallOptions::with_dry_run`.
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Calls the chain once per input, running up to `concurrency` calls at the same time.
+    /// The limit is shared by every call to `apply`. Results are returned in the same order
+    /// as the inputs.
+    ///
+    /// Fails with the first error found, dropping the calls still running. With
+    /// `continue_on_error`, failing inputs are skipped instead, so results no longer line up
+    /// with the inputs; the batch only fails if every input fails. Use
+    /// [`LLMChain::apply_each`] to keep one result per input.
+    ///
+    /// # Usage
+    /// ```rust,ignore
+    /// let results = chain
+    ///     .apply(vec![prompt_args! {"nombre" => "luis"}, prompt_args! {"nombre" => "ana"}])
+    ///     .await?;
+    /// ```
+    pub async fn apply(&self, inputs: Vec<PromptArgs>) -> Result<Vec<GenerateResult>, ChainError> {
+        if !self.continue_on_error {
+            return try_join_all(inputs.into_iter().map(|input| self.call_limited(input))).await;
+        }
+
+        let mut results = Vec::new();
+        let mut first_error = None;
+        for result in self.apply_each(inputs).await {
+            match result {
+                Ok(result) => results.push(result),
+                Err(err) => {
+                    log::warn!("Skipping input, call failed: {}", err);
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+        match first_error {
+            Some(err) if results.is_empty() => Err(err),
+            _ => Ok(results),
+        }
+    }
+
+    /// Like [`LLMChain::apply`], but every input gets its own result, so a failing input
+    /// doesn't discard the outputs of the others.
+    pub async fn apply_each(
+        &self,
+        inputs: Vec<PromptArgs>,
+    ) -> Vec<Result<GenerateResult, ChainError>> {
+        join_all(inputs.into_iter().map(|input| self.call_limited(input))).await
+    }
+
+    async fn call_limited(&self, input: PromptArgs) -> Result<GenerateResult, ChainError> {
+        let _permit = self
+            .semaphore
+            .acquire()
+            .await
+            .map_err(|e| ChainError::OtherError(e.to_string()))?;
+        self.call(input).await
+    }
+
+    /// Calls the chain with `options` merged into the LLM options for this call only,
+    /// e.g. to route simple inputs to a cheaper model. The chain's own options are not
+    /// modified.
+    ///
+    /// # Usage
+    /// ```rust,ignore
+    /// let options = ChainCallOptions::new().with_model("gpt-4o-mini").with_max_tokens(100);
+    /// let result = chain.call_with_options(prompt_args! {"input" => "Hi"}, options).await?;
+    /// ```
+    pub async fn call_with_options(
+        &self,
+        input_variables: PromptArgs,
+        options: ChainCallOptions,
+    ) -> Result<GenerateResult, ChainError> {
+        let error_on_truncation = options
+            .error_on_truncation
+            .unwrap_or(self.error_on_truncation);
+        let dry_run = options.dry_run.unwrap_or(self.dry_run);
+        let mut llm = self.llm.clone_box();
+        llm.add_options(ChainCallOptions::to_llm_options(options));
+        self.generate(llm.as_ref(), input_variables, error_on_truncation, dry_run)
+            .await
+    }
+
+    /// Renders the messages `call` would send to the LLM for these inputs, without calling it.
+    pub fn render_prompt(&self, input_variables: PromptArgs) -> Result<Vec<Message>, ChainError> {
+        Ok(self
+            .prompt
+            .format_prompt(input_variables)?
+            .to_chat_messages())
+    }
+
+    async fn generate(
+        &self,
+        llm: &dyn LLM,
+        input_variables: PromptArgs,
+        error_on_truncation: bool,
+        dry_run: bool,
+    ) -> Result<GenerateResult, ChainError> {
+        let prompt = self.prompt.format_prompt(input_variables)?;
+        log::debug!("Prompt: {:?}", prompt);
+        if dry_run {
+            return dry_run_result(&prompt.to_chat_messages());
+        }
+        let mut output = llm.generate(&prompt.to_chat_messages()).await?;
+        if output.finish_reason == Some(FinishReason::Length) {
+            if error_on_truncation {
+                return Err(ChainError::TruncatedOutput(output.generation));
+            }
+            log::warn!("LLM output was cut off by the token limit");
+        }
+        output.generation = self.output_parser.parse(&output.generation).await?;
+        if self.parse_json {
+            let parsed = serde_json::from_str(&output.generation)
+                .map_err(|e| OutputParserError::InvalidJson(e.to_string()))?;
+            output.parsed = Some(parsed);
+        }
+
+        Ok(output)
+    }
+}
+
+/// The result returned instead of calling the LLM in dry run mode, see
+/// `ChainCallOptions::with_dry_run`.
+pub(crate) fn dry_run_result(messages: &[Message]) -> Result<GenerateResult, ChainError> {
+    Ok(GenerateResult {
+        generation: serde_json::to_string_pretty(messages)?,
+        ..Default::default()
+    })
 }
 
 #[async_trait]
@@ -100,18 +296,42 @@ impl Chain for LLMChain {
         vec![self.output_key.clone()]
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "chain.call",
+            skip_all,
+            fields(chain = "llm_chain", output_key = %self.output_key)
+        )
+    )]
     async fn call(&self, input_variables: PromptArgs) -> Result<GenerateResult, ChainError> {
-        let prompt = self.prompt.format_prompt(input_variables.clone())?;
-        log::debug!("Prompt: {:?}", prompt);
-        let mut output = self.llm.generate(&prompt.to_chat_messages()).await?;
-        output.generation = self.output_parser.parse(&output.generation).await?;
+        self.generate(
+            self.llm.as_ref(),
+            input_variables,
+            self.error_on_truncation,
+            self.dry_run,
+        )
+        .await
+    }
 
-        Ok(output)
+    /// The output is the `parsed` value when `parse_json` is set, the generation otherwise.
+    async fn call_map(
+        &self,
+        input_variables: PromptArgs,
+    ) -> Result<HashMap<String, Value>, ChainError> {
+        let result = self.call(input_variables).await?;
+        let output = result
+            .parsed
+            .unwrap_or_else(|| Value::String(result.generation));
+        Ok(HashMap::from([(self.output_key.clone(), output)]))
     }
 
     async fn invoke(&self, input_variables: PromptArgs) -> Result<String, ChainError> {
         let prompt = self.prompt.format_prompt(input_variables.clone())?;
         log::debug!("Prompt: {:?}", prompt);
+        if self.dry_run {
+            return Ok(dry_run_result(&prompt.to_chat_messages())?.generation);
+        }
         let output = self
             .llm
             .generate(&prompt.to_chat_messages())
@@ -127,6 +347,11 @@ impl Chain for LLMChain {
     {
         let prompt = self.prompt.format_prompt(input_variables.clone())?;
         log::debug!("Prompt: {:?}", prompt);
+        if self.dry_run {
+            let generation = dry_run_result(&prompt.to_chat_messages())?.generation;
+            let data = StreamData::new(Value::String(generation.clone()), None, generation);
+            return Ok(Box::pin(stream::iter(vec![Ok(data)])));
+        }
         let llm_stream = self.llm.stream(&prompt.to_chat_messages()).await?;
 
         // Map the errors from LLMError to ChainError
@@ -140,12 +365,18 @@ impl Chain for LLMChain {
 mod tests {
     use crate::{
         chain::options::ChainCallOptions,
-        llm::openai::{OpenAI, OpenAIModel},
+        llm::{
+            fake::FakeLLM,
+            openai::{OpenAI, OpenAIModel},
+        },
         message_formatter,
+        output_parsers::MarkdownParser,
         prompt::{HumanMessagePromptTemplate, MessageOrTemplate},
         prompt_args, template_fstring,
     };
 
+    use serde_json::json;
+
     use super::*;
 
     #[tokio::test]
@@ -182,4 +413,225 @@ mod tests {
             result.err()
         )
     }
+
+    #[tokio::test]
+    async fn test_call_chain_with_fake_llm() {
+        let llm = FakeLLM::new().with_responses(vec!["Hola luis"]);
+        let chain = LLMChainBuilder::new()
+            .prompt(HumanMessagePromptTemplate::new(template_fstring!(
+                "Mi nombre es: {nombre}",
+                "nombre",
+            )))
+            .llm(llm.clone())
+            .build()
+            .expect("Failed to build LLMChain");
+
+        let result = chain
+            .invoke(prompt_args! {"nombre" => "luis"})
+            .await
+            .unwrap();
+
+        assert_eq!(result, "Hola luis");
+        assert_eq!(llm.calls()[0][0].content, "Mi nombre es: luis");
+    }
+
+    #[tokio::test]
+    async fn test_call_applies_output_parser() {
+        let raw = "Here it is:\n```json\n{\"name\": \"luis\"}\n```";
+        let chain = LLMChainBuilder::new()
+            .prompt(HumanMessagePromptTemplate::new(template_fstring!(
+                "Mi nombre es: {nombre}",
+                "nombre",
+            )))
+            .llm(FakeLLM::new().with_responses(vec![raw, "no code block"]))
+            .output_parser(MarkdownParser::new())
+            .parse_json(true)
+            .build()
+            .expect("Failed to build LLMChain");
+
+        let result = chain.call(prompt_args! {"nombre" => "luis"}).await.unwrap();
+        assert_eq!(result.generation, "{\"name\": \"luis\"}");
+        assert_eq!(result.parsed, Some(json!({"name": "luis"})));
+
+        let result = chain.call(prompt_args! {"nombre" => "luis"}).await;
+        assert!(matches!(result, Err(ChainError::OutputParser(_))));
+    }
+
+    #[tokio::test]
+    async fn test_call_map_returns_parsed_output() {
+        let chain = LLMChainBuilder::new()
+            .prompt(HumanMessagePromptTemplate::new(template_fstring!(
+                "Mi nombre es: {nombre}",
+                "nombre",
+            )))
+            .llm(FakeLLM::new().with_responses(vec!["{\"name\": \"luis\"}"]))
+            .output_key("person")
+            .parse_json(true)
+            .build()
+            .expect("Failed to build LLMChain");
+
+        let outputs = chain
+            .call_map(prompt_args! {"nombre" => "luis"})
+            .await
+            .unwrap();
+        assert_eq!(outputs["person"], json!({"name": "luis"}));
+    }
+
+    #[tokio::test]
+    async fn test_apply_preserves_order_and_collects_errors() {
+        let llm = FakeLLM::new().with_responses(vec!["uno", "dos"]);
+        let chain = LLMChainBuilder::new()
+            .prompt(HumanMessagePromptTemplate::new(template_fstring!(
+                "Numero: {numero}",
+                "numero",
+            )))
+            .llm(llm)
+            .with_concurrency(1)
+            .build()
+            .expect("Failed to build LLMChain");
+
+        let results = chain
+            .apply_each(vec![
+                prompt_args! {"numero" => "1"},
+                prompt_args! {"numero" => "2"},
+                prompt_args! {"numero" => "3"},
+            ])
+            .await;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().generation, "uno");
+        assert_eq!(results[1].as_ref().unwrap().generation, "dos");
+        assert!(results[2].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_apply_continue_on_error_skips_failed_inputs() {
+        for continue_on_error in [false, true] {
+            let chain = LLMChainBuilder::new()
+                .prompt(HumanMessagePromptTemplate::new(template_fstring!(
+                    "Numero: {numero}",
+                    "numero",
+                )))
+                .llm(FakeLLM::new().with_responses(vec!["uno"]))
+                .with_concurrency(1)
+                .with_continue_on_error(continue_on_error)
+                .build()
+                .expect("Failed to build LLMChain");
+
+            let result = chain
+                .apply(vec![
+                    prompt_args! {"numero" => "1"},
+                    prompt_args! {"numero" => "2"},
+                ])
+                .await;
+
+            if continue_on_error {
+                let results = result.unwrap();
+                assert_eq!(results.len(), 1);
+                assert_eq!(results[0].generation, "uno");
+            } else {
+                assert!(result.is_err());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_with_options_overrides_only_that_call() {
+        let llm = FakeLLM::new().with_responses(vec!["uno", "dos", "tres"]);
+        let chain = LLMChainBuilder::new()
+            .prompt(HumanMessagePromptTemplate::new(template_fstring!(
+                "Numero: {numero}",
+                "numero",
+            )))
+            .llm(llm.clone())
+            .options(
+                ChainCallOptions::new()
+                    .with_model("big-model")
+                    .with_temperature(0.7),
+            )
+            .build()
+            .expect("Failed to build LLMChain");
+
+        chain.call(prompt_args! {"numero" => "1"}).await.unwrap();
+        let result = chain
+            .call_with_options(
+                prompt_args! {"numero" => "2"},
+                ChainCallOptions::new()
+                    .with_model("small-model")
+                    .with_max_tokens(10),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.generation, "dos");
+        chain.call(prompt_args! {"numero" => "3"}).await.unwrap();
+
+        let options = llm.call_options();
+        assert_eq!(options[0].model.as_deref(), Some("big-model"));
+        assert_eq!(options[1].model.as_deref(), Some("small-model"));
+        assert_eq!(options[1].max_tokens, Some(10));
+        assert_eq!(options[1].temperature, Some(0.7));
+        assert_eq!(options[2].model.as_deref(), Some("big-model"));
+        assert_eq!(options[2].max_tokens, None);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_output() {
+        let llm = FakeLLM::new()
+            .with_responses(vec!["{\"name\": \"lu", "{\"name\": \"lu"])
+            .with_finish_reason(FinishReason::Length);
+        let prompt =
+            HumanMessagePromptTemplate::new(template_fstring!("Mi nombre es: {nombre}", "nombre",));
+        let chain = LLMChainBuilder::new()
+            .prompt(prompt.clone())
+            .llm(llm.clone())
+            .build()
+            .expect("Failed to build LLMChain");
+        let result = chain.call(prompt_args! {"nombre" => "luis"}).await.unwrap();
+        assert_eq!(result.finish_reason, Some(FinishReason::Length));
+
+        let chain = LLMChainBuilder::new()
+            .prompt(prompt)
+            .llm(llm)
+            .options(ChainCallOptions::new().with_error_on_truncation(true))
+            .build()
+            .expect("Failed to build LLMChain");
+        let result = chain.call(prompt_args! {"nombre" => "luis"}).await;
+        assert!(matches!(result, Err(ChainError::TruncatedOutput(_))));
+    }
+
+    #[test]
+    fn test_output_key_is_validated() {
+        let builder = || {
+            LLMChainBuilder::new()
+                .prompt(template_fstring!("{input}", "input"))
+                .llm(FakeLLM::new())
+        };
+
+        let chain = builder().output_key(" answer ").build().unwrap();
+        assert_eq!(chain.get_output_keys(), vec!["answer".to_string()]);
+
+        let err = builder().output_key("  ").build().err().unwrap();
+        assert!(matches!(err, ChainError::InvalidChain(_)));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_returns_prompt() {
+        let llm = FakeLLM::new();
+        let chain = LLMChainBuilder::new()
+            .prompt(HumanMessagePromptTemplate::new(template_fstring!(
+                "Mi nombre es: {nombre}",
+                "nombre",
+            )))
+            .llm(llm.clone())
+            .options(ChainCallOptions::new().with_dry_run(true))
+            .build()
+            .expect("Failed to build LLMChain");
+
+        let result = chain.call(prompt_args! {"nombre" => "luis"}).await.unwrap();
+
+        let messages: Vec<Message> = serde_json::from_str(&result.generation).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "Mi nombre es: luis");
+        assert!(llm.calls().is_empty());
+    }
 }
@@ -0,0 +1,110 @@
+use crate::{
+    chain::{options::ChainCallOptions, ChainError, LLMChainBuilder, StuffDocumentBuilder},
+    language_models::llm::LLM,
+    prompt::FormatPrompter,
+    template_jinja2,
+};
+
+use super::MapReduceDocuments;
+
+pub struct MapReduceDocumentsBuilder {
+    llm: Option<Box<dyn LLM>>,
+    options: Option<ChainCallOptions>,
+    map_prompt: Option<Box<dyn FormatPrompter>>,
+    reduce_prompt: Option<Box<dyn FormatPrompter>>,
+    concurrency: Option<usize>,
+    continue_on_error: bool,
+}
+
+impl MapReduceDocumentsBuilder {
+    pub fn new() -> Self {
+        Self {
+            llm: None,
+            options: None,
+            map_prompt: None,
+            reduce_prompt: None,
+            concurrency: None,
+            continue_on_error: false,
+        }
+    }
+
+    pub fn llm<L: Into<Box<dyn LLM>>>(mut self, llm: L) -> Self {
+        self.llm = Some(llm.into());
+        self
+    }
+
+    pub fn options(mut self, options: ChainCallOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Prompt called once per document. The document is passed as `context`.
+    pub fn map_prompt<P: Into<Box<dyn FormatPrompter>>>(mut self, prompt: P) -> Self {
+        self.map_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Prompt that combines the map results. They are joined and passed as `context`.
+    pub fn reduce_prompt<P: Into<Box<dyn FormatPrompter>>>(mut self, prompt: P) -> Self {
+        self.reduce_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Maximum number of map calls running at the same time.
+    /// Default: 5
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Skip documents whose map call fails instead of failing the whole chain.
+    /// Default: false
+    pub fn with_continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    pub fn build(self) -> Result<MapReduceDocuments, ChainError> {
+        let mut llm = self
+            .llm
+            .ok_or_else(|| ChainError::MissingObject("LLM must be set".into()))?;
+        if let Some(options) = self.options {
+            llm.add_options(ChainCallOptions::to_llm_options(options));
+        }
+
+        let map_prompt = match self.map_prompt {
+            Some(prompt) => prompt,
+            None => Box::new(template_jinja2!(
+                DEFAULT_MAP_TEMPLATE,
+                "context",
+                "question"
+            )),
+        };
+        let map_chain = LLMChainBuilder::new()
+            .prompt(map_prompt)
+            .llm(llm.clone_box())
+            .build()?;
+
+        let reduce_chain = {
+            let mut builder = StuffDocumentBuilder::new().llm(llm);
+            if let Some(prompt) = self.reduce_prompt {
+                builder = builder.prompt(prompt);
+            }
+            builder.build()?
+        };
+
+        let mut chain = MapReduceDocuments::new(map_chain, reduce_chain)
+            .with_continue_on_error(self.continue_on_error);
+        if let Some(concurrency) = self.concurrency {
+            chain = chain.with_concurrency(concurrency);
+        }
+        Ok(chain)
+    }
+}
+
+const DEFAULT_MAP_TEMPLATE: &str = r#"Use the following portion of a long document to see if any of the text is relevant to answer the question. Return any relevant text verbatim.
+
+{{context}}
+
+Question: {{question}}
+Relevant text, if any:"#;
@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use serde_json::{json, Value};
+use tokio::sync::Semaphore;
+
+use crate::{
+    chain::{Chain, ChainError, LLMChain, StuffDocument},
+    language_models::GenerateResult,
+    prompt::PromptArgs,
+    schemas::Document,
+};
+
+const MAP_REDUCE_DEFAULT_INPUT_KEY: &str = "input_documents";
+const MAP_REDUCE_DEFAULT_DOCUMENT_VARIABLE_NAME: &str = "context";
+const MAP_REDUCE_DEFAULT_CONCURRENCY: usize = 5;
+
+/// Calls `map_chain` once per document, then combines the results with `reduce_chain`.
+/// Useful when the documents don't fit in a single prompt.
+///
+/// Map calls run concurrently, at most `concurrency` at a time. The limit is shared by
+/// every call to the chain, so running the chain for several inputs at once doesn't
+/// multiply the load on the provider. The reduce step starts once every map call is done.
+///
+/// # Example
+/// ```rust,ignore
+/// let chain = MapReduceDocumentsBuilder::new()
+///     .llm(OpenAI::default())
+///     .with_concurrency(3)
+///     .build()?;
+///
+/// let output = chain
+///     .invoke(prompt_args! {
+///         "input_documents" => documents,
+///         "question" => "What is the refund policy?",
+///     })
+///     .await?;
+/// ```
+pub struct MapReduceDocuments {
+    map_chain: LLMChain,
+    reduce_chain: StuffDocument,
+    input_key: String,
+    document_variable_name: String,
+    semaphore: Arc<Semaphore>,
+    continue_on_error: bool,
+}
+
+impl MapReduceDocuments {
+    pub fn new(map_chain: LLMChain, reduce_chain: StuffDocument) -> Self {
+        Self {
+            map_chain,
+            reduce_chain,
+            input_key: MAP_REDUCE_DEFAULT_INPUT_KEY.to_string(),
+            document_variable_name: MAP_REDUCE_DEFAULT_DOCUMENT_VARIABLE_NAME.to_string(),
+            semaphore: Arc::new(Semaphore::new(MAP_REDUCE_DEFAULT_CONCURRENCY)),
+            continue_on_error: false,
+        }
+    }
+
+    /// Maximum number of map calls running at the same time.
+    /// Default: 5
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+        self
+    }
+
+    /// Skip documents whose map call fails instead of failing the whole chain. The chain
+    /// still fails if every map call fails.
+    /// Default: false
+    pub fn with_continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    /// Runs the map step only, returning one result per document in the same order.
+    /// A failing document doesn't discard the results of the others.
+    pub async fn map(
+        &self,
+        documents: &[Document],
+        input_variables: &PromptArgs,
+    ) -> Vec<Result<GenerateResult, ChainError>> {
+        join_all(documents.iter().map(|doc| async move {
+            let _permit = self
+                .semaphore
+                .acquire()
+                .await
+                .map_err(|e| ChainError::OtherError(e.to_string()))?;
+            let mut inputs = input_variables.clone();
+            inputs.insert(
+                self.document_variable_name.clone(),
+                Value::String(doc.page_content.clone()),
+            );
+            self.map_chain.call(inputs).await
+        }))
+        .await
+    }
+}
+
+#[async_trait]
+impl Chain for MapReduceDocuments {
+    async fn call(&self, input_variables: PromptArgs) -> Result<GenerateResult, ChainError> {
+        let docs = input_variables
+            .get(&self.input_key)
+            .ok_or_else(|| ChainError::MissingInputVariable(self.input_key.clone()))?;
+
+        let documents: Vec<Document> = serde_json::from_value(docs.clone()).map_err(|e| {
+            ChainError::IncorrectInputVariable {
+                source: e,
+                expected_type: "Vec<Document>".to_string(),
+            }
+        })?;
+
+        let results = self.map(&documents, &input_variables).await;
+
+        let mut mapped = Vec::with_capacity(results.len());
+        let mut first_error = None;
+        for (doc, result) in documents.into_iter().zip(results) {
+            match result {
+                Ok(result) => mapped.push(Document {
+                    page_content: result.generation,
+                    ..doc
+                }),
+                Err(err) if self.continue_on_error => {
+                    log::warn!("Skipping document, map step failed: {}", err);
+                    first_error.get_or_insert(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        if mapped.is_empty() {
+            if let Some(err) = first_error {
+                return Err(err);
+            }
+        }
+
+        let mut reduce_inputs = input_variables;
+        reduce_inputs.insert(self.input_key.clone(), json!(mapped));
+        self.reduce_chain.call(reduce_inputs).await
+    }
+
+    fn get_input_keys(&self) -> Vec<String> {
+        vec![self.input_key.clone()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chain::{LLMChainBuilder, StuffDocumentBuilder},
+        llm::fake::FakeLLM,
+        prompt_args, template_jinja2,
+    };
+
+    use super::*;
+
+    fn map_reduce(map_llm: FakeLLM, reduce_llm: FakeLLM) -> MapReduceDocuments {
+        let map_chain = LLMChainBuilder::new()
+            .prompt(template_jinja2!("Summarize: {{context}}", "context"))
+            .llm(map_llm)
+            .build()
+            .unwrap();
+        let reduce_chain = StuffDocumentBuilder::new()
+            .llm(reduce_llm)
+            .prompt(template_jinja2!("Combine: {{context}}", "context"))
+            .build()
+            .unwrap();
+        MapReduceDocuments::new(map_chain, reduce_chain).with_concurrency(1)
+    }
+
+    fn inputs() -> PromptArgs {
+        let documents = vec![Document::new("a"), Document::new("b"), Document::new("c")];
+        prompt_args! {"input_documents" => documents}
+    }
+
+    #[tokio::test]
+    async fn test_map_reduce_combines_map_results() {
+        let map_llm = FakeLLM::new().with_responses(vec!["sum a", "sum b", "sum c"]);
+        let reduce_llm = FakeLLM::new().with_responses(vec!["all"]);
+        let chain = map_reduce(map_llm.clone(), reduce_llm.clone());
+
+        let output = chain.invoke(inputs()).await.unwrap();
+
+        assert_eq!(output, "all");
+        assert_eq!(map_llm.calls().len(), 3);
+        assert_eq!(
+            reduce_llm.last_call().unwrap()[0].content,
+            "Combine: sum a\n\nsum b\n\nsum c"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_map_reduce_continue_on_error() {
+        // The map LLM runs out of responses on the third document.
+        let map_llm = FakeLLM::new().with_responses(vec!["sum a", "sum b"]);
+        let reduce_llm = FakeLLM::new().with_responses(vec!["all"]);
+        let chain = map_reduce(map_llm, reduce_llm.clone());
+        assert!(chain.invoke(inputs()).await.is_err());
+
+        let map_llm = FakeLLM::new().with_responses(vec!["sum a", "sum b"]);
+        let chain = map_reduce(map_llm, reduce_llm.clone()).with_continue_on_error(true);
+        let output = chain.invoke(inputs()).await.unwrap();
+
+        assert_eq!(output, "all");
+        assert_eq!(
+            reduce_llm.last_call().unwrap()[0].content,
+            "Combine: sum a\n\nsum b"
+        );
+    }
+}
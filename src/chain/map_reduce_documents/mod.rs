@@ -0,0 +1,5 @@
+mod chain;
+pub use chain::*;
+
+mod builder;
+pub use builder::*;
@@ -16,13 +16,40 @@ pub use sql_datbase::*;
 mod stuff_documents;
 pub use stuff_documents::*;
 
+mod map_reduce_documents;
+pub use map_reduce_documents::*;
+
+mod summarize;
+pub use summarize::*;
+
 mod question_answering;
 pub use question_answering::*;
 
 mod conversational_retrieval_qa;
 pub use conversational_retrieval_qa::*;
 
+mod extraction;
+pub use extraction::*;
+
+mod transform;
+pub use transform::*;
+
+mod moderation;
+pub use moderation::*;
+
+mod guardrail;
+pub use guardrail::*;
+
+mod timeout;
+pub use timeout::*;
+
+mod evaluation;
+pub use evaluation::*;
+
 mod error;
 pub use error::*;
 
+mod callbacks;
+pub use callbacks::*;
+
 pub mod options;
@@ -0,0 +1,199 @@
+use std::{collections::HashMap, pin::Pin, sync::Arc};
+
+use async_trait::async_trait;
+use futures::Stream;
+use serde_json::Value;
+
+use crate::{
+    chain::{execute_nested, Chain, ChainError, DEFAULT_OUTPUT_KEY},
+    language_models::GenerateResult,
+    prompt::PromptArgs,
+    schemas::StreamData,
+};
+
+use super::Moderator;
+
+/// Wraps a chain, or an `AgentExecutor`, checking its inputs with a [`Moderator`] before
+/// calling it. Flagged content fails with `ChainError::ContentFlagged` and the wrapped
+/// chain is never called.
+///
+/// # Example
+/// ```rust,ignore
+/// let chain = ModeratedChain::new(executor, Arc::new(OpenAIModeration::default()))
+///     .with_input_keys(vec!["input"])
+///     .with_output_moderation(true);
+/// let answer = chain.invoke(prompt_args! {"input" => user_input}).await?;
+/// ```
+pub struct ModeratedChain {
+    chain: Box<dyn Chain>,
+    moderator: Arc<dyn Moderator>,
+    input_keys: Option<Vec<String>>,
+    moderate_output: bool,
+    block_flagged: bool,
+}
+
+impl ModeratedChain {
+    pub fn new<C: Into<Box<dyn Chain>>>(chain: C, moderator: Arc<dyn Moderator>) -> Self {
+        Self {
+            chain: chain.into(),
+            moderator,
+            input_keys: None,
+            moderate_output: false,
+            block_flagged: true,
+        }
+    }
+
+    /// The inputs to check. By default every string input is checked.
+    pub fn with_input_keys<S: Into<String>>(mut self, input_keys: Vec<S>) -> Self {
+        self.input_keys = Some(input_keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Also checks the generation of the wrapped chain. Not applied to `stream`.
+    pub fn with_output_moderation(mut self, moderate_output: bool) -> Self {
+        self.moderate_output = moderate_output;
+        self
+    }
+
+    /// When `false`, flagged content is only logged as a warning. Defaults to `true`.
+    pub fn with_block_flagged(mut self, block_flagged: bool) -> Self {
+        self.block_flagged = block_flagged;
+        self
+    }
+
+    async fn check(&self, target: &str, text: &str) -> Result<(), ChainError> {
+        let result = self.moderator.moderate(text).await?;
+        if !result.flagged {
+            return Ok(());
+        }
+        if !self.block_flagged {
+            log::warn!(
+                "Moderation flagged the {} for: {}",
+                target,
+                result.categories.join(", ")
+            );
+            return Ok(());
+        }
+        Err(ChainError::ContentFlagged {
+            target: target.to_string(),
+            categories: result.categories,
+        })
+    }
+
+    async fn check_inputs(&self, input_variables: &PromptArgs) -> Result<(), ChainError> {
+        let texts: Vec<&str> = match &self.input_keys {
+            Some(keys) => keys
+                .iter()
+                .filter_map(|key| input_variables.get(key).and_then(Value::as_str))
+                .collect(),
+            None => input_variables.values().filter_map(Value::as_str).collect(),
+        };
+        for text in texts {
+            self.check("input", text).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Chain for ModeratedChain {
+    async fn call(&self, input_variables: PromptArgs) -> Result<GenerateResult, ChainError> {
+        self.check_inputs(&input_variables).await?;
+        let result = self.chain.call(input_variables).await?;
+        if self.moderate_output {
+            self.check("output", &result.generation).await?;
+        }
+        Ok(result)
+    }
+
+    async fn execute(
+        &self,
+        input_variables: PromptArgs,
+    ) -> Result<HashMap<String, Value>, ChainError> {
+        self.check_inputs(&input_variables).await?;
+        let output = execute_nested(self.chain.as_ref(), input_variables).await?;
+        if self.moderate_output {
+            let output_key = self
+                .get_output_keys()
+                .first()
+                .cloned()
+                .unwrap_or_else(|| DEFAULT_OUTPUT_KEY.to_string());
+            if let Some(text) = output.get(&output_key).and_then(Value::as_str) {
+                self.check("output", text).await?;
+            }
+        }
+        Ok(output)
+    }
+
+    async fn stream(
+        &self,
+        input_variables: PromptArgs,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, ChainError>> + Send>>, ChainError>
+    {
+        self.check_inputs(&input_variables).await?;
+        self.chain.stream(input_variables).await
+    }
+
+    fn get_input_keys(&self) -> Vec<String> {
+        self.chain.get_input_keys()
+    }
+
+    fn get_output_keys(&self) -> Vec<String> {
+        self.chain.get_output_keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chain::{LLMChainBuilder, ModerationResult},
+        llm::fake::FakeLLM,
+        prompt_args, template_fstring,
+    };
+
+    use super::*;
+
+    struct BlockList {}
+
+    #[async_trait]
+    impl Moderator for BlockList {
+        async fn moderate(&self, text: &str) -> Result<ModerationResult, ChainError> {
+            let flagged = text.contains("forbidden");
+            Ok(ModerationResult {
+                flagged,
+                categories: if flagged {
+                    vec!["block_list".into()]
+                } else {
+                    vec![]
+                },
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_moderated_chain() {
+        let llm = FakeLLM::new().with_responses(vec!["fine", "a forbidden answer"]);
+        let chain = LLMChainBuilder::new()
+            .prompt(template_fstring!("{input}", "input"))
+            .llm(llm.clone())
+            .build()
+            .unwrap();
+        let chain = ModeratedChain::new(chain, Arc::new(BlockList {})).with_output_moderation(true);
+
+        let err = chain
+            .invoke(prompt_args! {"input" => "say something forbidden"})
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ChainError::ContentFlagged { ref target, .. } if target == "input"));
+        assert!(llm.calls().is_empty());
+
+        let result = chain.invoke(prompt_args! {"input" => "hi"}).await.unwrap();
+        assert_eq!(result, "fine");
+
+        let err = chain
+            .invoke(prompt_args! {"input" => "hi again"})
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ChainError::ContentFlagged { ref target, .. } if target == "output"));
+    }
+}
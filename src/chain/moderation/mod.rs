@@ -0,0 +1,7 @@
+mod chain;
+mod moderator;
+mod openai;
+
+pub use chain::*;
+pub use moderator::*;
+pub use openai::*;
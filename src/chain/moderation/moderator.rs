@@ -0,0 +1,19 @@
+use async_trait::async_trait;
+
+use crate::chain::ChainError;
+
+/// Result of checking a text against a moderation policy.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModerationResult {
+    pub flagged: bool,
+    /// The categories the text was flagged for, e.g. `harassment`.
+    pub categories: Vec<String>,
+}
+
+/// Checks texts against a moderation policy. Implemented by
+/// [`OpenAIModeration`](super::OpenAIModeration), implement it to use your own classifier
+/// or block list.
+#[async_trait]
+pub trait Moderator: Send + Sync {
+    async fn moderate(&self, text: &str) -> Result<ModerationResult, ChainError>;
+}
@@ -0,0 +1,121 @@
+use async_openai::config::{Config, OpenAIConfig};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::chain::ChainError;
+
+use super::{ModerationResult, Moderator};
+
+/// [`Moderator`] backed by the OpenAI `/v1/moderations` endpoint.
+///
+/// # Example
+/// ```rust,ignore
+/// let moderation = OpenAIModeration::default().with_model("omni-moderation-latest");
+/// let result = moderation.moderate("some user input").await?;
+/// ```
+pub struct OpenAIModeration<C: Config> {
+    config: C,
+    model: Option<String>,
+    http_client: reqwest::Client,
+}
+
+impl<C: Config> OpenAIModeration<C> {
+    pub fn new(config: C) -> Self {
+        Self {
+            config,
+            model: None,
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// Defaults to the model chosen by the API.
+    pub fn with_model<S: Into<String>>(mut self, model: S) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn with_http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+}
+
+impl Default for OpenAIModeration<OpenAIConfig> {
+    fn default() -> Self {
+        Self::new(OpenAIConfig::default())
+    }
+}
+
+fn parse_moderation_response(response: &Value) -> Result<ModerationResult, ChainError> {
+    let result = response["results"].get(0).ok_or_else(|| {
+        ChainError::ModerationError(format!("Unexpected moderation response: {}", response))
+    })?;
+    let categories = result["categories"]
+        .as_object()
+        .map(|categories| {
+            categories
+                .iter()
+                .filter(|(_, flagged)| flagged.as_bool().unwrap_or_default())
+                .map(|(category, _)| category.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(ModerationResult {
+        flagged: result["flagged"].as_bool().unwrap_or_default(),
+        categories,
+    })
+}
+
+#[async_trait]
+impl<C: Config + Send + Sync> Moderator for OpenAIModeration<C> {
+    async fn moderate(&self, text: &str) -> Result<ModerationResult, ChainError> {
+        let mut body = json!({ "input": text });
+        if let Some(model) = &self.model {
+            body["model"] = json!(model);
+        }
+
+        let response = self
+            .http_client
+            .post(self.config.url("/moderations"))
+            .headers(self.config.headers())
+            .query(&self.config.query())
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ChainError::ModerationError(e.to_string()))?;
+        let status = response.status();
+        let response: Value = response
+            .json()
+            .await
+            .map_err(|e| ChainError::ModerationError(e.to_string()))?;
+        if !status.is_success() {
+            return Err(ChainError::ModerationError(format!(
+                "Moderation request failed with status {}: {}",
+                status, response
+            )));
+        }
+        parse_moderation_response(&response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_moderation_response() {
+        let response = json!({
+            "id": "modr-1",
+            "model": "omni-moderation-latest",
+            "results": [{
+                "flagged": true,
+                "categories": {"harassment": true, "violence": false},
+                "category_scores": {"harassment": 0.9, "violence": 0.01}
+            }]
+        });
+
+        let result = parse_moderation_response(&response).unwrap();
+        assert!(result.flagged);
+        assert_eq!(result.categories, vec!["harassment".to_string()]);
+    }
+}
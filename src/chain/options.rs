@@ -1,9 +1,10 @@
 use futures::Future;
 use std::pin::Pin;
 
-use crate::language_models::options::CallOptions;
+use crate::{language_models::options::CallOptions, schemas::ToolChoice};
 
 pub struct ChainCallOptions {
+    pub model: Option<String>,
     pub max_tokens: Option<u32>,
     pub temperature: Option<f32>,
     pub stop_words: Option<Vec<String>>,
@@ -16,6 +17,11 @@ pub struct ChainCallOptions {
     pub min_length: Option<usize>,
     pub max_length: Option<usize>,
     pub repetition_penalty: Option<f32>,
+    pub tool_choice: Option<ToolChoice>,
+    /// Fail instead of logging a warning when the LLM stops because of the token limit.
+    pub error_on_truncation: Option<bool>,
+    /// Return the rendered prompt instead of calling the LLM.
+    pub dry_run: Option<bool>,
 }
 
 impl Default for ChainCallOptions {
@@ -27,6 +33,7 @@ impl Default for ChainCallOptions {
 impl ChainCallOptions {
     pub fn new() -> Self {
         Self {
+            model: None,
             max_tokens: None,
             temperature: None,
             stop_words: None,
@@ -37,11 +44,17 @@ impl ChainCallOptions {
             min_length: None,
             max_length: None,
             repetition_penalty: None,
+            tool_choice: None,
+            error_on_truncation: None,
+            dry_run: None,
         }
     }
 
     pub fn to_llm_options(options: ChainCallOptions) -> CallOptions {
         let mut llm_option = CallOptions::new();
+        if let Some(model) = options.model {
+            llm_option = llm_option.with_model(model);
+        }
         if let Some(max_tokens) = options.max_tokens {
             llm_option = llm_option.with_max_tokens(max_tokens);
         }
@@ -69,6 +82,9 @@ impl ChainCallOptions {
         if let Some(repetition_penalty) = options.repetition_penalty {
             llm_option = llm_option.with_repetition_penalty(repetition_penalty);
         }
+        if let Some(tool_choice) = options.tool_choice {
+            llm_option = llm_option.with_tool_choice(tool_choice);
+        }
 
         if let Some(streaming_func) = options.streaming_func {
             llm_option = llm_option.with_streaming_func(streaming_func)
@@ -76,6 +92,12 @@ impl ChainCallOptions {
         llm_option
     }
 
+    /// Overrides the model of the LLM, for backends that support it.
+    pub fn with_model<S: Into<String>>(mut self, model: S) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
     pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
         self.max_tokens = Some(max_tokens);
         self
@@ -130,4 +152,24 @@ impl ChainCallOptions {
         self.repetition_penalty = Some(repetition_penalty);
         self
     }
+
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// When enabled, a generation cut off by the token limit (`FinishReason::Length`) is
+    /// returned as `ChainError::TruncatedOutput` instead of only being logged.
+    pub fn with_error_on_truncation(mut self, error_on_truncation: bool) -> Self {
+        self.error_on_truncation = Some(error_on_truncation);
+        self
+    }
+
+    /// When enabled, the chain doesn't call the LLM: the `generation` of the result is the
+    /// prompt messages that would have been sent, serialized as JSON. Useful to inspect
+    /// prompts without spending tokens.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = Some(dry_run);
+        self
+    }
 }
@@ -1,16 +1,20 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use crate::chain::Chain;
+use crate::chain::{Chain, ChainError, DEFAULT_OUTPUT_KEY, DEFAULT_RESULT_KEY};
 
 use super::SequentialChain;
 
 pub struct SequentialChainBuilder {
     chains: Vec<Box<dyn Chain>>,
+    input_keys: Option<Vec<String>>,
 }
 
 impl SequentialChainBuilder {
     pub fn new() -> Self {
-        Self { chains: Vec::new() }
+        Self {
+            chains: Vec::new(),
+            input_keys: None,
+        }
     }
 
     pub fn add_chain<C: Chain + 'static>(mut self, chain: C) -> Self {
@@ -18,7 +22,71 @@ impl SequentialChainBuilder {
         self
     }
 
+    /// Declares the keys provided by the caller. When set, `try_build` checks that every
+    /// key read by a chain is either one of these or produced by a previous chain.
+    pub fn input_keys<S: Into<String>>(mut self, input_keys: Vec<S>) -> Self {
+        self.input_keys = Some(input_keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Checks that no two chains write the same output key and, if `input_keys` was
+    /// declared, that every chain input is available when the chain runs.
+    pub fn validate(&self) -> Result<(), ChainError> {
+        let mut available: HashSet<String> = self.input_keys.iter().flatten().cloned().collect();
+        let mut writers: HashMap<String, usize> = HashMap::new();
+
+        for (index, chain) in self.chains.iter().enumerate() {
+            if self.input_keys.is_some() {
+                if let Some(key) = chain
+                    .get_input_keys()
+                    .into_iter()
+                    .find(|key| !available.contains(key))
+                {
+                    return Err(ChainError::InvalidChain(format!(
+                        "chain {} reads the key '{}', which is not an input nor produced by a previous chain",
+                        index, key
+                    )));
+                }
+            }
+
+            let mut output_keys: Vec<String> = chain
+                .get_output_keys()
+                .into_iter()
+                .filter(|key| key != DEFAULT_RESULT_KEY)
+                .collect();
+            if output_keys.is_empty() {
+                output_keys.push(DEFAULT_OUTPUT_KEY.to_string());
+            }
+            for output_key in output_keys {
+                if let Some(previous) = writers.insert(output_key.clone(), index) {
+                    return Err(ChainError::InvalidChain(format!(
+                        "chains {} and {} both write the output key '{}'",
+                        previous, index, output_key
+                    )));
+                }
+                available.insert(output_key);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the chain, failing if [`SequentialChainBuilder::validate`] finds a conflict.
+    pub fn try_build(self) -> Result<SequentialChain, ChainError> {
+        self.validate()?;
+        Ok(self.build_unchecked())
+    }
+
+    /// Builds the chain. Conflicts found by [`SequentialChainBuilder::validate`] are only
+    /// logged, use `try_build` to get them as an error.
     pub fn build(self) -> SequentialChain {
+        if let Err(e) = self.validate() {
+            log::warn!("Invalid SequentialChain: {}", e);
+        }
+        self.build_unchecked()
+    }
+
+    fn build_unchecked(self) -> SequentialChain {
         let outputs: HashSet<String> = self
             .chains
             .iter()
@@ -51,3 +119,58 @@ macro_rules! sequential_chain {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chain::{LLMChain, LLMChainBuilder},
+        llm::fake::FakeLLM,
+        template_fstring,
+    };
+
+    use super::*;
+
+    fn chain(template: &str, input: &str, output_key: &str) -> LLMChain {
+        LLMChainBuilder::new()
+            .prompt(template_fstring!(template, input))
+            .llm(FakeLLM::new())
+            .output_key(output_key)
+            .build()
+            .expect("Failed to build LLMChain")
+    }
+
+    #[test]
+    fn test_detects_clobbered_output_key() {
+        let result = SequentialChainBuilder::new()
+            .add_chain(chain("name for {input}", "input", "name"))
+            .add_chain(chain("another name for {name}", "name", "name"))
+            .try_build();
+
+        let err = result.err().expect("expected a conflict");
+        assert!(matches!(err, ChainError::InvalidChain(_)));
+        assert!(err.to_string().contains("chains 0 and 1"));
+        assert!(err.to_string().contains("'name'"));
+    }
+
+    #[test]
+    fn test_detects_missing_input_key() {
+        let builder = SequentialChainBuilder::new()
+            .add_chain(chain("name for {input}", "input", "name"))
+            .add_chain(chain("slogan for {nombre}", "nombre", "slogan"));
+        assert!(builder.validate().is_ok());
+
+        let err = builder
+            .input_keys(vec!["input"])
+            .try_build()
+            .err()
+            .expect("expected a missing key");
+        assert!(err.to_string().contains("chain 1 reads the key 'nombre'"));
+
+        assert!(SequentialChainBuilder::new()
+            .add_chain(chain("name for {input}", "input", "name"))
+            .add_chain(chain("slogan for {name}", "name", "slogan"))
+            .input_keys(vec!["input"])
+            .try_build()
+            .is_ok());
+    }
+}
@@ -4,7 +4,7 @@ use async_trait::async_trait;
 use serde_json::{json, Value};
 
 use crate::{
-    chain::{Chain, ChainError, DEFAULT_OUTPUT_KEY, DEFAULT_RESULT_KEY},
+    chain::{execute_nested, Chain, ChainError, DEFAULT_OUTPUT_KEY, DEFAULT_RESULT_KEY},
     language_models::{GenerateResult, TokenUsage},
     prompt::PromptArgs,
 };
@@ -45,7 +45,7 @@ impl Chain for SequentialChain {
         let mut output_result = HashMap::new();
         let mut final_result = GenerateResult::default();
         for chain in self.chains.iter() {
-            let output = chain.execute(input_variables.clone()).await?;
+            let output = execute_nested(chain.as_ref(), input_variables.clone()).await?;
             //Get the oput key for the chain result
             let output_key = chain
                 .get_output_keys()
@@ -60,8 +60,20 @@ impl Chain for SequentialChain {
             let result: GenerateResult = serde_json::from_value(result)?;
             log::debug!("{}", result.generation);
             //Insert the output chain to the final output
-            output_result.insert(output_key.clone(), json!(result.generation.clone()));
-            input_variables.insert(output_key, json!(result.generation.clone()));
+            //Structured outputs are passed as they are, otherwise the generation is used
+            let value = output
+                .get(&output_key)
+                .cloned()
+                .unwrap_or_else(|| json!(result.generation.clone()));
+            output_result.insert(output_key.clone(), value.clone());
+            input_variables.insert(output_key, value);
+            //Chains with several outputs, like TransformChain, pass the other ones along too
+            for key in chain.get_output_keys().into_iter().skip(1) {
+                if let Some(value) = output.get(&key).filter(|_| key != DEFAULT_RESULT_KEY) {
+                    output_result.insert(key.clone(), value.clone());
+                    input_variables.insert(key, value.clone());
+                }
+            }
 
             //add the generation to keep track of the final generation
             final_result.generation = result.generation;
@@ -88,11 +100,13 @@ impl Chain for SequentialChain {
 #[cfg(test)]
 mod tests {
     use crate::{
-        chain::{Chain, LLMChainBuilder},
-        llm::openai::OpenAI,
+        chain::{Chain, ExtractionChainBuilder, LLMChainBuilder, TransformChain},
+        llm::{fake::FakeLLM, openai::OpenAI},
         prompt_args, sequential_chain, template_fstring,
     };
 
+    use super::*;
+
     #[tokio::test]
     #[ignore]
     async fn test_sequential() {
@@ -132,4 +146,32 @@ mod tests {
             println!("{:?}", output);
         }
     }
+
+    #[tokio::test]
+    async fn test_structured_outputs_are_passed_forward() {
+        let llm = FakeLLM::new().with_responses(vec![r#"{"name": "Luis", "age": 30}"#]);
+        let extraction = ExtractionChainBuilder::<Value>::new()
+            .llm(llm)
+            .schema(json!({"type": "object", "properties": {"name": {"type": "string"}}}))
+            .output_key("person")
+            .build()
+            .unwrap();
+        let greeting = TransformChain::new(vec!["person"], vec!["greeting"], |inputs| {
+            let person = &inputs["person"];
+            let name = person["name"].as_str().unwrap_or_default();
+            Ok(prompt_args! {
+                "greeting" => format!("Hola {}, tienes {}", name, person["age"]),
+            })
+        });
+
+        let chain = sequential_chain!(extraction, greeting);
+        let outputs = chain
+            .call_map(prompt_args! {"input" => "Luis tiene 30"})
+            .await
+            .unwrap();
+
+        assert_eq!(outputs["person"], json!({"name": "Luis", "age": 30}));
+        assert_eq!(outputs["greeting"], json!("Hola Luis, tienes 30"));
+        assert!(!outputs.contains_key(DEFAULT_RESULT_KEY));
+    }
 }
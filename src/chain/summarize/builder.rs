@@ -0,0 +1,121 @@
+use crate::{
+    chain::{llm_chain::LLMChainBuilder, options::ChainCallOptions, ChainError},
+    language_models::llm::LLM,
+    prompt::FormatPrompter,
+    template_jinja2,
+    text_splitter::TextSplitter,
+};
+
+use super::{
+    prompt::{DEFAULT_REFINE_TEMPLATE, DEFAULT_SUMMARIZE_TEMPLATE},
+    SummarizeChain, SummarizeStrategy,
+};
+
+pub struct SummarizeChainBuilder {
+    llm: Option<Box<dyn LLM>>,
+    options: Option<ChainCallOptions>,
+    strategy: SummarizeStrategy,
+    prompt: Option<Box<dyn FormatPrompter>>,
+    refine_prompt: Option<Box<dyn FormatPrompter>>,
+    text_splitter: Option<Box<dyn TextSplitter>>,
+}
+
+impl SummarizeChainBuilder {
+    pub fn new() -> Self {
+        Self {
+            llm: None,
+            options: None,
+            strategy: SummarizeStrategy::Stuff,
+            prompt: None,
+            refine_prompt: None,
+            text_splitter: None,
+        }
+    }
+
+    pub fn llm<L: Into<Box<dyn LLM>>>(mut self, llm: L) -> Self {
+        self.llm = Some(llm.into());
+        self
+    }
+
+    pub fn options(mut self, options: ChainCallOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Default: `SummarizeStrategy::Stuff`
+    pub fn strategy(mut self, strategy: SummarizeStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Prompt summarizing the whole text with `Stuff`, or the first chunk with `Refine`.
+    /// The text is passed as `text`.
+    pub fn prompt<P: Into<Box<dyn FormatPrompter>>>(mut self, prompt: P) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+
+    /// Prompt used by `Refine` for every chunk after the first one. The summary so far is
+    /// passed as `existing_answer` and the chunk as `text`. `build` fails if it's set with
+    /// the `Stuff` strategy.
+    pub fn refine_prompt<P: Into<Box<dyn FormatPrompter>>>(mut self, prompt: P) -> Self {
+        self.refine_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Splits the input into chunks before summarizing. Mostly useful with `Refine`, to
+    /// turn a long text into chunks that fit in the context window.
+    pub fn text_splitter<T: TextSplitter + 'static>(mut self, text_splitter: T) -> Self {
+        self.text_splitter = Some(Box::new(text_splitter));
+        self
+    }
+
+    pub fn build(self) -> Result<SummarizeChain, ChainError> {
+        let mut llm = self
+            .llm
+            .ok_or_else(|| ChainError::MissingObject("LLM must be set".into()))?;
+        if let Some(options) = self.options {
+            llm.add_options(ChainCallOptions::to_llm_options(options));
+        }
+
+        let prompt = match self.prompt {
+            Some(prompt) => prompt,
+            None => Box::new(template_jinja2!(DEFAULT_SUMMARIZE_TEMPLATE, "text")),
+        };
+        let summarize_chain = LLMChainBuilder::new()
+            .prompt(prompt)
+            .llm(llm.clone_box())
+            .build()?;
+
+        let refine_chain = match self.strategy {
+            SummarizeStrategy::Stuff if self.refine_prompt.is_some() => {
+                return Err(ChainError::InvalidChain(
+                    "refine_prompt is only used with SummarizeStrategy::Refine".to_string(),
+                ));
+            }
+            SummarizeStrategy::Stuff => None,
+            SummarizeStrategy::Refine => {
+                let refine_prompt = match self.refine_prompt {
+                    Some(prompt) => prompt,
+                    None => Box::new(template_jinja2!(
+                        DEFAULT_REFINE_TEMPLATE,
+                        "existing_answer",
+                        "text"
+                    )),
+                };
+                Some(
+                    LLMChainBuilder::new()
+                        .prompt(refine_prompt)
+                        .llm(llm)
+                        .build()?,
+                )
+            }
+        };
+
+        Ok(SummarizeChain {
+            summarize_chain,
+            refine_chain,
+            text_splitter: self.text_splitter,
+        })
+    }
+}
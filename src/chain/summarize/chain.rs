@@ -0,0 +1,206 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{
+    chain::{Chain, ChainError, LLMChain},
+    language_models::{GenerateResult, TokenUsage},
+    prompt::PromptArgs,
+    prompt_args,
+    schemas::Document,
+    text_splitter::TextSplitter,
+};
+
+pub(crate) const SUMMARIZE_DOCUMENTS_INPUT_KEY: &str = "input_documents";
+pub(crate) const SUMMARIZE_TEXT_INPUT_KEY: &str = "text";
+const SUMMARIZE_DOCUMENTS_SEPARATOR: &str = "\n\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummarizeStrategy {
+    /// Summarizes all the text in a single prompt.
+    Stuff,
+    /// Summarizes the first chunk, then updates the running summary with each of the
+    /// following chunks, one LLM call per chunk.
+    Refine,
+}
+
+/// Summarizes a list of documents, passed as `input_documents`, or a long string, passed
+/// as `text`.
+///
+/// # Example
+/// ```rust,ignore
+/// let chain = SummarizeChainBuilder::new()
+///     .llm(OpenAI::default())
+///     .strategy(SummarizeStrategy::Refine)
+///     .text_splitter(TokenSplitter::default())
+///     .build()?;
+///
+/// let summary = chain.invoke(prompt_args! {"text" => long_text}).await?;
+/// ```
+pub struct SummarizeChain {
+    pub(crate) summarize_chain: LLMChain,
+    pub(crate) refine_chain: Option<LLMChain>,
+    pub(crate) text_splitter: Option<Box<dyn TextSplitter>>,
+}
+
+impl SummarizeChain {
+    async fn documents(&self, input_variables: &PromptArgs) -> Result<Vec<Document>, ChainError> {
+        let documents = match (
+            input_variables.get(SUMMARIZE_DOCUMENTS_INPUT_KEY),
+            input_variables.get(SUMMARIZE_TEXT_INPUT_KEY),
+        ) {
+            (Some(docs), _) => {
+                serde_json::from_value::<Vec<Document>>(docs.clone()).map_err(|e| {
+                    ChainError::IncorrectInputVariable {
+                        source: e,
+                        expected_type: "Vec<Document>".to_string(),
+                    }
+                })?
+            }
+            (None, Some(Value::String(text))) => vec![Document::new(text.clone())],
+            (None, Some(text)) => vec![Document::new(text.to_string())],
+            (None, None) => {
+                return Err(ChainError::MissingInputVariable(format!(
+                    "{} or {}",
+                    SUMMARIZE_DOCUMENTS_INPUT_KEY, SUMMARIZE_TEXT_INPUT_KEY
+                )))
+            }
+        };
+
+        match &self.text_splitter {
+            Some(text_splitter) => text_splitter
+                .split_documents(&documents)
+                .await
+                .map_err(|e| ChainError::OtherError(e.to_string())),
+            None => Ok(documents),
+        }
+    }
+}
+
+#[async_trait]
+impl Chain for SummarizeChain {
+    async fn call(&self, input_variables: PromptArgs) -> Result<GenerateResult, ChainError> {
+        let documents = self.documents(&input_variables).await?;
+
+        let refine_chain = match &self.refine_chain {
+            Some(refine_chain) => refine_chain,
+            None => {
+                let text = documents
+                    .iter()
+                    .map(|doc| doc.page_content.as_str())
+                    .collect::<Vec<_>>()
+                    .join(SUMMARIZE_DOCUMENTS_SEPARATOR);
+                return self
+                    .summarize_chain
+                    .call(prompt_args! {"text" => text})
+                    .await;
+            }
+        };
+
+        let mut documents = documents.into_iter();
+        let first = documents
+            .next()
+            .ok_or_else(|| ChainError::OtherError("Nothing to summarize".to_string()))?;
+        let mut result = self
+            .summarize_chain
+            .call(prompt_args! {"text" => first.page_content})
+            .await?;
+        let mut tokens: Option<TokenUsage> = result.tokens.clone();
+
+        for doc in documents {
+            result = refine_chain
+                .call(prompt_args! {
+                    "existing_answer" => result.generation,
+                    "text" => doc.page_content,
+                })
+                .await?;
+            if let Some(new_tokens) = &result.tokens {
+                tokens = Some(match tokens {
+                    Some(tokens) => tokens.sum(new_tokens),
+                    None => new_tokens.clone(),
+                });
+            }
+        }
+
+        result.tokens = tokens;
+        Ok(result)
+    }
+
+    /// Either of the keys is enough; `input_documents` wins when both are given.
+    fn get_input_keys(&self) -> Vec<String> {
+        vec![
+            SUMMARIZE_DOCUMENTS_INPUT_KEY.to_string(),
+            SUMMARIZE_TEXT_INPUT_KEY.to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chain::SummarizeChainBuilder, llm::fake::FakeLLM, template_jinja2};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_summarize_stuff() {
+        let llm = FakeLLM::new().with_responses(vec!["Short summary"]);
+        let chain = SummarizeChainBuilder::new()
+            .llm(llm.clone())
+            .prompt(template_jinja2!("Summarize: {{text}}", "text"))
+            .build()
+            .unwrap();
+
+        let documents = vec![Document::new("First part."), Document::new("Second part.")];
+        let summary = chain
+            .invoke(prompt_args! {"input_documents" => documents})
+            .await
+            .unwrap();
+
+        assert_eq!(summary, "Short summary");
+        assert_eq!(
+            llm.last_call().unwrap()[0].content,
+            "Summarize: First part.\n\nSecond part."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_summarize_refine() {
+        let llm = FakeLLM::new().with_responses(vec!["S1", "S2", "S3"]);
+        let chain = SummarizeChainBuilder::new()
+            .llm(llm.clone())
+            .strategy(SummarizeStrategy::Refine)
+            .prompt(template_jinja2!("Summarize: {{text}}", "text"))
+            .refine_prompt(template_jinja2!(
+                "Refine {{existing_answer}} with: {{text}}",
+                "existing_answer",
+                "text"
+            ))
+            .build()
+            .unwrap();
+
+        let documents = vec![Document::new("a"), Document::new("b"), Document::new("c")];
+        let summary = chain
+            .invoke(prompt_args! {"input_documents" => documents})
+            .await
+            .unwrap();
+
+        assert_eq!(summary, "S3");
+        let calls = llm.calls();
+        assert_eq!(calls[0][0].content, "Summarize: a");
+        assert_eq!(calls[1][0].content, "Refine S1 with: b");
+        assert_eq!(calls[2][0].content, "Refine S2 with: c");
+    }
+
+    #[test]
+    fn test_refine_prompt_requires_refine_strategy() {
+        let result = SummarizeChainBuilder::new()
+            .llm(FakeLLM::new())
+            .refine_prompt(template_jinja2!(
+                "Refine {{existing_answer}} with: {{text}}",
+                "existing_answer",
+                "text"
+            ))
+            .build();
+
+        assert!(matches!(result, Err(ChainError::InvalidChain(_))));
+    }
+}
@@ -0,0 +1,7 @@
+mod chain;
+pub use chain::*;
+
+mod builder;
+pub use builder::*;
+
+mod prompt;
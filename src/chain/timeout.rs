@@ -0,0 +1,129 @@
+use std::{collections::HashMap, pin::Pin, time::Duration};
+
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use tokio::time::{timeout, timeout_at, Instant};
+
+use crate::{
+    chain::{execute_nested, Chain, ChainError},
+    language_models::GenerateResult,
+    prompt::PromptArgs,
+    schemas::StreamData,
+};
+
+/// Wraps a chain, or an `AgentExecutor`, bounding the whole run to `duration`. When the
+/// deadline passes the run fails with `ChainError::Timeout` and the inner call is dropped,
+/// which cancels it as described in `Chain::call`.
+///
+/// # Example
+/// ```rust,ignore
+/// let chain = TimeoutChain::new(executor, Duration::from_secs(30));
+/// let answer = chain.invoke(prompt_args! {"input" => question}).await?;
+/// ```
+pub struct TimeoutChain {
+    chain: Box<dyn Chain>,
+    duration: Duration,
+}
+
+impl TimeoutChain {
+    pub fn new<C: Into<Box<dyn Chain>>>(chain: C, duration: Duration) -> Self {
+        Self {
+            chain: chain.into(),
+            duration,
+        }
+    }
+}
+
+#[async_trait]
+impl Chain for TimeoutChain {
+    async fn call(&self, input_variables: PromptArgs) -> Result<GenerateResult, ChainError> {
+        timeout(self.duration, self.chain.call(input_variables))
+            .await
+            .map_err(|_| ChainError::Timeout(self.duration))?
+    }
+
+    async fn execute(
+        &self,
+        input_variables: PromptArgs,
+    ) -> Result<HashMap<String, Value>, ChainError> {
+        timeout(
+            self.duration,
+            execute_nested(self.chain.as_ref(), input_variables),
+        )
+        .await
+        .map_err(|_| ChainError::Timeout(self.duration))?
+    }
+
+    /// The deadline covers the whole stream: once it passes, the stream yields a
+    /// `ChainError::Timeout` and ends.
+    async fn stream(
+        &self,
+        input_variables: PromptArgs,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, ChainError>> + Send>>, ChainError>
+    {
+        let duration = self.duration;
+        let deadline = Instant::now() + duration;
+        let mut inner = timeout_at(deadline, self.chain.stream(input_variables))
+            .await
+            .map_err(|_| ChainError::Timeout(duration))??;
+
+        Ok(Box::pin(stream! {
+            loop {
+                match timeout_at(deadline, inner.next()).await {
+                    Ok(Some(item)) => yield item,
+                    Ok(None) => break,
+                    Err(_) => {
+                        yield Err(ChainError::Timeout(duration));
+                        break;
+                    }
+                }
+            }
+        }))
+    }
+
+    fn get_input_keys(&self) -> Vec<String> {
+        self.chain.get_input_keys()
+    }
+
+    fn get_output_keys(&self) -> Vec<String> {
+        self.chain.get_output_keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chain::LLMChainBuilder, llm::fake::FakeLLM, prompt_args, template_fstring};
+
+    use super::*;
+
+    struct Stalled {}
+
+    #[async_trait]
+    impl Chain for Stalled {
+        async fn call(&self, _input_variables: PromptArgs) -> Result<GenerateResult, ChainError> {
+            futures::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timeout_chain() {
+        let chain = TimeoutChain::new(Stalled {}, Duration::from_millis(20));
+        let err = chain
+            .invoke(prompt_args! {"input" => "hi"})
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ChainError::Timeout(duration) if duration.as_millis() == 20));
+
+        let llm = FakeLLM::new().with_responses(vec!["fast"]);
+        let chain = LLMChainBuilder::new()
+            .prompt(template_fstring!("{input}", "input"))
+            .llm(llm)
+            .build()
+            .unwrap();
+        let chain = TimeoutChain::new(chain, Duration::from_secs(5));
+        let result = chain.invoke(prompt_args! {"input" => "hi"}).await.unwrap();
+        assert_eq!(result, "fast");
+    }
+}
@@ -0,0 +1,210 @@
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::{
+    chain::{Chain, ChainError, DEFAULT_OUTPUT_KEY, DEFAULT_RESULT_KEY},
+    language_models::GenerateResult,
+    prompt::PromptArgs,
+};
+
+type TransformFuture = Pin<Box<dyn Future<Output = Result<PromptArgs, ChainError>> + Send>>;
+type TransformFn = dyn Fn(PromptArgs) -> TransformFuture + Send + Sync;
+
+/// A chain that runs a function over its inputs instead of calling an LLM, e.g. to
+/// reformat or filter the output of a chain before the next one in a `SequentialChain`.
+///
+/// The declared input keys must be present when the chain is called, and the function
+/// must return every declared output key. The first output key is used as the generation.
+///
+/// # Example
+/// ```rust,ignore
+/// let upper = TransformChain::new(vec!["text"], vec!["upper"], |inputs| {
+///     let text = inputs["text"].as_str().unwrap_or_default().to_uppercase();
+///     Ok(prompt_args! {"upper" => text})
+/// });
+/// let chain = sequential_chain!(name_chain, upper, slogan_chain);
+/// ```
+pub struct TransformChain {
+    input_keys: Vec<String>,
+    output_keys: Vec<String>,
+    transform: Arc<TransformFn>,
+}
+
+impl TransformChain {
+    pub fn new<S, F>(input_keys: Vec<S>, output_keys: Vec<S>, transform: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(PromptArgs) -> Result<PromptArgs, ChainError> + Send + Sync + 'static,
+    {
+        let transform = Arc::new(transform);
+        Self::new_async(input_keys, output_keys, move |inputs| {
+            let transform = transform.clone();
+            async move { transform(inputs) }
+        })
+    }
+
+    /// Same as `new`, for transformations that need to await, e.g. a lookup in a database.
+    pub fn new_async<S, F, Fut>(input_keys: Vec<S>, output_keys: Vec<S>, transform: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(PromptArgs) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<PromptArgs, ChainError>> + Send + 'static,
+    {
+        Self {
+            input_keys: input_keys.into_iter().map(Into::into).collect(),
+            output_keys: output_keys.into_iter().map(Into::into).collect(),
+            transform: Arc::new(move |inputs| Box::pin(transform(inputs))),
+        }
+    }
+
+    async fn transform(&self, input_variables: PromptArgs) -> Result<PromptArgs, ChainError> {
+        if let Some(key) = self
+            .input_keys
+            .iter()
+            .find(|key| !input_variables.contains_key(*key))
+        {
+            return Err(ChainError::MissingInputVariable(key.clone()));
+        }
+
+        let mut outputs = (self.transform)(input_variables).await?;
+        let mut declared = HashMap::new();
+        for key in &self.output_keys {
+            let value = outputs.remove(key).ok_or_else(|| {
+                ChainError::OtherError(format!("Transform did not return the key '{}'", key))
+            })?;
+            declared.insert(key.clone(), value);
+        }
+        Ok(declared)
+    }
+}
+
+fn value_to_generation(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[async_trait]
+impl Chain for TransformChain {
+    async fn call(&self, input_variables: PromptArgs) -> Result<GenerateResult, ChainError> {
+        let outputs = self.transform(input_variables).await?;
+        let generation = self
+            .output_keys
+            .first()
+            .and_then(|key| outputs.get(key))
+            .map(value_to_generation)
+            .unwrap_or_default();
+        Ok(GenerateResult {
+            generation,
+            ..Default::default()
+        })
+    }
+
+    async fn execute(
+        &self,
+        input_variables: PromptArgs,
+    ) -> Result<HashMap<String, Value>, ChainError> {
+        let mut outputs = self.transform(input_variables).await?;
+        let generation = self
+            .output_keys
+            .first()
+            .and_then(|key| outputs.get(key))
+            .map(value_to_generation)
+            .unwrap_or_default();
+        if self.output_keys.is_empty() {
+            outputs.insert(DEFAULT_OUTPUT_KEY.to_string(), json!(generation));
+        }
+        let result = GenerateResult {
+            generation,
+            ..Default::default()
+        };
+        outputs.insert(DEFAULT_RESULT_KEY.to_string(), json!(result));
+        Ok(outputs)
+    }
+
+    fn get_input_keys(&self) -> Vec<String> {
+        self.input_keys.clone()
+    }
+
+    fn get_output_keys(&self) -> Vec<String> {
+        self.output_keys.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chain::{LLMChainBuilder, SequentialChainBuilder},
+        llm::fake::FakeLLM,
+        prompt_args, template_fstring,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_transform_chain_in_sequence() {
+        let name_llm = FakeLLM::new().with_responses(vec!["  la tiendita  "]);
+        let slogan_llm = FakeLLM::new().with_responses(vec!["Compra en LA TIENDITA"]);
+        let name_chain = LLMChainBuilder::new()
+            .prompt(template_fstring!(
+                "nombre para una tienda de {input}",
+                "input"
+            ))
+            .llm(name_llm)
+            .output_key("name")
+            .build()
+            .unwrap();
+        let clean = TransformChain::new(vec!["name"], vec!["clean_name", "length"], |inputs| {
+            let name = inputs["name"]
+                .as_str()
+                .unwrap_or_default()
+                .trim()
+                .to_uppercase();
+            Ok(prompt_args! {"length" => name.len(), "clean_name" => name})
+        });
+        let slogan_chain = LLMChainBuilder::new()
+            .prompt(template_fstring!(
+                "slogan para {clean_name} ({length} letras)",
+                "clean_name",
+                "length"
+            ))
+            .llm(slogan_llm.clone())
+            .output_key("slogan")
+            .build()
+            .unwrap();
+
+        let chain = SequentialChainBuilder::new()
+            .add_chain(name_chain)
+            .add_chain(clean)
+            .add_chain(slogan_chain)
+            .input_keys(vec!["input"])
+            .try_build()
+            .unwrap();
+        let output = chain
+            .execute(prompt_args! {"input" => "dulces"})
+            .await
+            .unwrap();
+
+        assert_eq!(output["clean_name"], json!("LA TIENDITA"));
+        assert_eq!(output["length"], json!(11));
+        assert_eq!(output["slogan"], json!("Compra en LA TIENDITA"));
+        assert_eq!(
+            slogan_llm.last_call().unwrap()[0].content,
+            "slogan para LA TIENDITA (11 letras)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transform_chain_missing_keys() {
+        let chain = TransformChain::new(vec!["text"], vec!["upper"], |_| Ok(PromptArgs::new()));
+
+        let err = chain.call(prompt_args! {"other" => 1}).await.unwrap_err();
+        assert!(matches!(err, ChainError::MissingInputVariable(key) if key == "text"));
+
+        let err = chain.call(prompt_args! {"text" => "a"}).await.unwrap_err();
+        assert!(err.to_string().contains("'upper'"));
+    }
+}
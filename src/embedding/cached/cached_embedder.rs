@@ -0,0 +1,253 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use async_trait::async_trait;
+
+use crate::embedding::{embedder_trait::Embedder, EmbedderError};
+
+/// Storage used by [`CachedEmbedder`]. Keys are stable hashes of the namespace and
+/// the text, so a persistent store can be shared between runs.
+#[async_trait]
+pub trait EmbeddingCache: Send + Sync {
+    async fn get(&self, key: &str) -> Option<Vec<f64>>;
+    async fn set(&self, key: String, embedding: Vec<f64>);
+}
+
+/// Keeps the embeddings in a map for the lifetime of the process.
+#[derive(Default)]
+pub struct InMemoryEmbeddingCache {
+    embeddings: RwLock<HashMap<String, Vec<f64>>>,
+}
+
+impl InMemoryEmbeddingCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.embeddings.read().map(|e| e.len()).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl EmbeddingCache for InMemoryEmbeddingCache {
+    async fn get(&self, key: &str) -> Option<Vec<f64>> {
+        self.embeddings.read().ok()?.get(key).cloned()
+    }
+
+    async fn set(&self, key: String, embedding: Vec<f64>) {
+        if let Ok(mut embeddings) = self.embeddings.write() {
+            embeddings.insert(key, embedding);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+/// Wraps an [`Embedder`] and only calls it for texts that are not cached yet.
+///
+/// The namespace should identify the model, so embeddings from different models
+/// sharing the same store never get mixed.
+///
+/// # Example
+/// ```rust,ignore
+/// let embedder = CachedEmbedder::new(OpenAiEmbedder::default(), "text-embedding-3-small");
+/// let vectors = embedder.embed_documents(&texts).await?;
+/// println!("{:?}", embedder.stats());
+/// ```
+pub struct CachedEmbedder {
+    embedder: Arc<dyn Embedder>,
+    cache: Arc<dyn EmbeddingCache>,
+    namespace: String,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl CachedEmbedder {
+    pub fn new<E: Embedder + 'static, S: Into<String>>(embedder: E, namespace: S) -> Self {
+        Self {
+            embedder: Arc::new(embedder),
+            cache: Arc::new(InMemoryEmbeddingCache::new()),
+            namespace: namespace.into(),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Replaces the default in-memory cache.
+    pub fn with_cache<C: EmbeddingCache + 'static>(mut self, cache: C) -> Self {
+        self.cache = Arc::new(cache);
+        self
+    }
+
+    pub fn with_shared_cache(mut self, cache: Arc<dyn EmbeddingCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    fn key(&self, kind: &str, text: &str) -> String {
+        format!("{:032x}", fnv1a_128(&[&self.namespace, kind, text]))
+    }
+}
+
+impl From<CachedEmbedder> for Box<dyn Embedder> {
+    fn from(embedder: CachedEmbedder) -> Self {
+        Box::new(embedder)
+    }
+}
+
+#[async_trait]
+impl Embedder for CachedEmbedder {
+    async fn embed_documents(&self, documents: &[String]) -> Result<Vec<Vec<f64>>, EmbedderError> {
+        let mut embeddings: Vec<Option<Vec<f64>>> = vec![None; documents.len()];
+        // Texts to embed, each with the positions it fills, so duplicates are sent once.
+        let mut missing: Vec<(String, Vec<usize>)> = Vec::new();
+        let mut missing_by_key: HashMap<String, usize> = HashMap::new();
+
+        for (index, document) in documents.iter().enumerate() {
+            let key = self.key("document", document);
+            if let Some(position) = missing_by_key.get(&key) {
+                missing[*position].1.push(index);
+                continue;
+            }
+            match self.cache.get(&key).await {
+                Some(embedding) => {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    embeddings[index] = Some(embedding);
+                }
+                None => {
+                    self.misses.fetch_add(1, Ordering::Relaxed);
+                    missing_by_key.insert(key, missing.len());
+                    missing.push((document.clone(), vec![index]));
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            let texts: Vec<String> = missing.iter().map(|(text, _)| text.clone()).collect();
+            let computed = self.embedder.embed_documents(&texts).await?;
+            if computed.len() != texts.len() {
+                return Err(EmbedderError::EmbeddingCountMismatch {
+                    expected: texts.len(),
+                    actual: computed.len(),
+                });
+            }
+            for ((text, indexes), embedding) in missing.into_iter().zip(computed) {
+                for index in indexes {
+                    embeddings[index] = Some(embedding.clone());
+                }
+                self.cache.set(self.key("document", &text), embedding).await;
+            }
+        }
+
+        Ok(embeddings.into_iter().flatten().collect())
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f64>, EmbedderError> {
+        let key = self.key("query", text);
+        if let Some(embedding) = self.cache.get(&key).await {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(embedding);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let embedding = self.embedder.embed_query(text).await?;
+        self.cache.set(key, embedding.clone()).await;
+        Ok(embedding)
+    }
+}
+
+/// FNV-1a, used because its output does not change between Rust releases.
+fn fnv1a_128(parts: &[&str]) -> u128 {
+    const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    let mut hash = OFFSET;
+    for part in parts {
+        for byte in part.bytes().chain(std::iter::once(0)) {
+            hash ^= byte as u128;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct CountingEmbedder {
+        calls: Arc<Mutex<Vec<Vec<String>>>>,
+    }
+
+    #[async_trait]
+    impl Embedder for CountingEmbedder {
+        async fn embed_documents(
+            &self,
+            documents: &[String],
+        ) -> Result<Vec<Vec<f64>>, EmbedderError> {
+            self.calls.lock().unwrap().push(documents.to_vec());
+            Ok(documents.iter().map(|d| vec![d.len() as f64]).collect())
+        }
+
+        async fn embed_query(&self, text: &str) -> Result<Vec<f64>, EmbedderError> {
+            self.calls.lock().unwrap().push(vec![text.to_string()]);
+            Ok(vec![text.len() as f64])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_embeds_cache_misses() {
+        let inner = CountingEmbedder::default();
+        let calls = inner.calls.clone();
+        let embedder = CachedEmbedder::new(inner, "test-model");
+
+        let first = embedder
+            .embed_documents(&["a".to_string(), "bb".to_string(), "a".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(first, vec![vec![1.0], vec![2.0], vec![1.0]]);
+
+        let second = embedder
+            .embed_documents(&["ccc".to_string(), "bb".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(second, vec![vec![3.0], vec![2.0]]);
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                vec!["a".to_string(), "bb".to_string()],
+                vec!["ccc".to_string()]
+            ]
+        );
+        assert_eq!(embedder.stats(), CacheStats { hits: 1, misses: 3 });
+
+        embedder.embed_query("bb").await.unwrap();
+        embedder.embed_query("bb").await.unwrap();
+        assert_eq!(calls.lock().unwrap().len(), 3);
+        assert_eq!(embedder.stats(), CacheStats { hits: 2, misses: 4 });
+    }
+}
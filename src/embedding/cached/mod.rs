@@ -0,0 +1,2 @@
+mod cached_embedder;
+pub use cached_embedder::*;
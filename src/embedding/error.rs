@@ -23,9 +23,21 @@ pub enum EmbedderError {
         error_message: String,
     },
 
+    #[error("Embedding batch {index} failed: {source}")]
+    BatchError {
+        index: usize,
+        source: Box<EmbedderError>,
+    },
+
+    #[error("Embedder returned {actual} embeddings for {expected} texts")]
+    EmbeddingCountMismatch { expected: usize, actual: usize },
+
     #[error("FastEmbed error: {0}")]
     FastEmbedError(String),
 
+    #[error("Local embedder error: {0}")]
+    LocalEmbedderError(String),
+
     #[cfg(feature = "ollama")]
     #[error("Ollama error: {0}")]
     OllamaError(#[from] OllamaError),
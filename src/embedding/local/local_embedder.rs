@@ -0,0 +1,219 @@
+use std::{fmt::Display, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use tokenizers::{Encoding, PaddingParams, Tokenizer, TruncationParams};
+
+use crate::embedding::{Embedder, EmbedderError};
+
+/// How the token embeddings of a text are combined into a single vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pooling {
+    /// Average of the token embeddings, used by MiniLM and most sentence-transformers.
+    #[default]
+    Mean,
+    /// Embedding of the `[CLS]` token, used by the BGE models.
+    Cls,
+}
+
+/// Computes embeddings locally with a BERT based sentence-transformer model, like
+/// `BAAI/bge-small-en-v1.5` or `sentence-transformers/all-MiniLM-L6-v2`, using candle.
+///
+/// The model directory must contain the `config.json`, `tokenizer.json` and
+/// `model.safetensors` files of the model, as downloaded from the Hugging Face hub.
+/// The model runs on the CPU, or on the first CUDA device with the `candle-cuda` feature,
+/// in a blocking task so it doesn't stall the async runtime.
+///
+/// # Usage
+/// ```rust,ignore
+/// let embedder = LocalEmbedder::try_new("models/bge-small-en-v1.5")?
+///     .with_pooling(Pooling::Cls)
+///     .with_batch_size(16);
+/// let embedding = embedder.embed_query("What is the capital of France?").await?;
+/// ```
+pub struct LocalEmbedder {
+    inference: Inference,
+    batch_size: usize,
+}
+
+/// The model and the settings needed to run it, cloned into the blocking task.
+#[derive(Clone)]
+struct Inference {
+    model: Arc<BertModel>,
+    tokenizer: Arc<Tokenizer>,
+    device: Device,
+    pooling: Pooling,
+    normalize: bool,
+}
+
+impl LocalEmbedder {
+    pub fn try_new<P: AsRef<Path>>(model_dir: P) -> Result<Self, EmbedderError> {
+        Self::try_new_on(model_dir, default_device())
+    }
+
+    /// Loads the model on the given device.
+    pub fn try_new_on<P: AsRef<Path>>(model_dir: P, device: Device) -> Result<Self, EmbedderError> {
+        let model_dir = model_dir.as_ref();
+        let config = std::fs::read_to_string(model_dir.join("config.json")).map_err(local_error)?;
+        let config: Config = serde_json::from_str(&config).map_err(local_error)?;
+
+        let mut tokenizer =
+            Tokenizer::from_file(model_dir.join("tokenizer.json")).map_err(local_error)?;
+        tokenizer
+            .with_padding(Some(PaddingParams::default()))
+            .with_truncation(Some(TruncationParams {
+                max_length: config.max_position_embeddings,
+                ..Default::default()
+            }))
+            .map_err(local_error)?;
+
+        // SAFETY: the weights file must not be modified while the model is loaded.
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(
+                &[model_dir.join("model.safetensors")],
+                DTYPE,
+                &device,
+            )
+            .map_err(local_error)?
+        };
+        let model = BertModel::load(vb, &config).map_err(local_error)?;
+
+        Ok(Self {
+            inference: Inference {
+                model: Arc::new(model),
+                tokenizer: Arc::new(tokenizer),
+                device,
+                pooling: Pooling::default(),
+                normalize: true,
+            },
+            batch_size: 32,
+        })
+    }
+
+    pub fn with_pooling(mut self, pooling: Pooling) -> Self {
+        self.inference.pooling = pooling;
+        self
+    }
+
+    /// Whether embeddings are scaled to unit length, so the dot product equals the cosine
+    /// similarity. Defaults to `true`.
+    pub fn with_normalize(mut self, normalize: bool) -> Self {
+        self.inference.normalize = normalize;
+        self
+    }
+
+    /// Number of texts run through the model at once. Defaults to 32.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+}
+
+impl Inference {
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f64>>, EmbedderError> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(local_error)?;
+        self.forward(&encodings).map_err(local_error)
+    }
+
+    fn forward(&self, encodings: &[Encoding]) -> candle_core::Result<Vec<Vec<f64>>> {
+        let token_ids = encodings
+            .iter()
+            .map(|encoding| Tensor::new(encoding.get_ids(), &self.device))
+            .collect::<Result<Vec<_>, _>>()?;
+        let attention_mask = encodings
+            .iter()
+            .map(|encoding| Tensor::new(encoding.get_attention_mask(), &self.device))
+            .collect::<Result<Vec<_>, _>>()?;
+        let token_ids = Tensor::stack(&token_ids, 0)?;
+        let attention_mask = Tensor::stack(&attention_mask, 0)?;
+        let token_type_ids = token_ids.zeros_like()?;
+        let output = self
+            .model
+            .forward(&token_ids, &token_type_ids, Some(&attention_mask))?;
+
+        let pooled = match self.pooling {
+            Pooling::Cls => output.narrow(1, 0, 1)?.squeeze(1)?,
+            Pooling::Mean => {
+                let mask = attention_mask.to_dtype(DTYPE)?.unsqueeze(2)?;
+                let sum = output.broadcast_mul(&mask)?.sum(1)?;
+                sum.broadcast_div(&mask.sum(1)?)?
+            }
+        };
+        let pooled = if self.normalize {
+            pooled.broadcast_div(&pooled.sqr()?.sum_keepdim(1)?.sqrt()?)?
+        } else {
+            pooled
+        };
+        pooled.to_dtype(DType::F64)?.to_vec2::<f64>()
+    }
+}
+
+fn default_device() -> Device {
+    #[cfg(feature = "candle-cuda")]
+    {
+        match Device::cuda_if_available(0) {
+            Ok(device) => return device,
+            Err(e) => log::warn!("Could not use the CUDA device, using the CPU: {}", e),
+        }
+    }
+    Device::Cpu
+}
+
+fn local_error<E: Display>(e: E) -> EmbedderError {
+    EmbedderError::LocalEmbedderError(e.to_string())
+}
+
+#[async_trait]
+impl Embedder for LocalEmbedder {
+    async fn embed_documents(&self, documents: &[String]) -> Result<Vec<Vec<f64>>, EmbedderError> {
+        let inference = self.inference.clone();
+        let documents = documents.to_vec();
+        let batch_size = self.batch_size;
+        tokio::task::spawn_blocking(move || {
+            let mut embeddings = Vec::with_capacity(documents.len());
+            for batch in documents.chunks(batch_size) {
+                embeddings.extend(inference.embed_batch(batch)?);
+            }
+            Ok(embeddings)
+        })
+        .await
+        .map_err(local_error)?
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f64>, EmbedderError> {
+        self.embed_documents(&[text.to_string()])
+            .await?
+            .pop()
+            .ok_or(EmbedderError::EmbeddingCountMismatch {
+                expected: 1,
+                actual: 0,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_local_embedder() {
+        // Requires a model downloaded to `models/all-MiniLM-L6-v2`.
+        let embedder = LocalEmbedder::try_new("models/all-MiniLM-L6-v2")
+            .unwrap()
+            .with_batch_size(1);
+        let embeddings = embedder
+            .embed_documents(&["hello world".to_string(), "foo bar".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(embeddings.len(), 2);
+
+        let norm: f64 = embeddings[0].iter().map(|x| x * x).sum::<f64>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,4 @@
+mod local_embedder;
+pub use local_embedder::*;
+
+pub use candle_core::Device;
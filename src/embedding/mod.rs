@@ -9,13 +9,24 @@ pub mod ollama;
 pub use ollama::*;
 
 pub mod openai;
+
+pub mod cached;
+pub use cached::*;
+
+mod rate_limited;
 pub use error::*;
+pub use rate_limited::*;
 
 #[cfg(feature = "fastembed")]
 mod fastembed;
 #[cfg(feature = "fastembed")]
 pub use fastembed::*;
 
+#[cfg(feature = "candle")]
+mod local;
+#[cfg(feature = "candle")]
+pub use local::*;
+
 #[cfg(feature = "mistralai")]
 pub mod mistralai;
 #[cfg(feature = "mistralai")]
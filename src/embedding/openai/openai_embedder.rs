@@ -7,11 +7,15 @@ use async_openai::{
     Client,
 };
 use async_trait::async_trait;
+use futures::{stream, StreamExt, TryStreamExt};
 
 #[derive(Debug)]
 pub struct OpenAiEmbedder<C: Config> {
     config: C,
     model: String,
+    batch_size: Option<usize>,
+    max_concurrency: usize,
+    batch_retries: usize,
 }
 
 impl<C: Config + Send + Sync + 'static> Into<Box<dyn Embedder>> for OpenAiEmbedder<C> {
@@ -25,6 +29,9 @@ impl<C: Config> OpenAiEmbedder<C> {
         OpenAiEmbedder {
             config,
             model: String::from("text-embedding-ada-002"),
+            batch_size: None,
+            max_concurrency: 1,
+            batch_retries: 0,
         }
     }
 
@@ -37,19 +44,32 @@ impl<C: Config> OpenAiEmbedder<C> {
         self.config = config;
         self
     }
-}
 
-impl Default for OpenAiEmbedder<OpenAIConfig> {
-    fn default() -> Self {
-        OpenAiEmbedder::new(OpenAIConfig::default())
+    /// Splits `embed_documents` into requests of at most `batch_size` documents.
+    /// By default all documents are sent in a single request.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size.max(1));
+        self
     }
-}
 
-#[async_trait]
-impl<C: Config + Send + Sync> Embedder for OpenAiEmbedder<C> {
-    async fn embed_documents(&self, documents: &[String]) -> Result<Vec<Vec<f64>>, EmbedderError> {
-        let client = Client::with_config(self.config.clone());
+    /// Maximum number of batch requests in flight at the same time. Defaults to 1.
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Number of times a failed batch is retried before `embed_documents` returns
+    /// `EmbedderError::BatchError`. Defaults to 0.
+    pub fn with_batch_retries(mut self, batch_retries: usize) -> Self {
+        self.batch_retries = batch_retries;
+        self
+    }
 
+    async fn embed_batch(
+        &self,
+        client: &Client<C>,
+        documents: &[String],
+    ) -> Result<Vec<Vec<f64>>, EmbedderError> {
         let request = CreateEmbeddingRequestArgs::default()
             .model(&self.model)
             .input(EmbeddingInput::StringArray(documents.into()))
@@ -72,6 +92,62 @@ impl<C: Config + Send + Sync> Embedder for OpenAiEmbedder<C> {
         Ok(embeddings)
     }
 
+    async fn embed_batch_with_retries(
+        &self,
+        client: &Client<C>,
+        index: usize,
+        documents: &[String],
+    ) -> Result<Vec<Vec<f64>>, EmbedderError> {
+        let mut attempt = 0;
+        loop {
+            match self.embed_batch(client, documents).await {
+                Ok(embeddings) => return Ok(embeddings),
+                Err(err) if attempt < self.batch_retries => {
+                    attempt += 1;
+                    log::warn!(
+                        "Embedding batch {} failed (attempt {}): {}, retrying",
+                        index,
+                        attempt,
+                        err
+                    );
+                }
+                Err(err) => {
+                    return Err(EmbedderError::BatchError {
+                        index,
+                        source: Box::new(err),
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl Default for OpenAiEmbedder<OpenAIConfig> {
+    fn default() -> Self {
+        OpenAiEmbedder::new(OpenAIConfig::default())
+    }
+}
+
+#[async_trait]
+impl<C: Config + Send + Sync> Embedder for OpenAiEmbedder<C> {
+    async fn embed_documents(&self, documents: &[String]) -> Result<Vec<Vec<f64>>, EmbedderError> {
+        let client = Client::with_config(self.config.clone());
+
+        let batch_size = match self.batch_size {
+            Some(batch_size) => batch_size,
+            None => return self.embed_batch(&client, documents).await,
+        };
+
+        let client = &client;
+        let batches = stream::iter(documents.chunks(batch_size).enumerate())
+            .map(|(index, batch)| self.embed_batch_with_retries(client, index, batch))
+            .buffered(self.max_concurrency)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        Ok(batches.into_iter().flatten().collect())
+    }
+
     async fn embed_query(&self, text: &str) -> Result<Vec<f64>, EmbedderError> {
         let client = Client::with_config(self.config.clone());
 
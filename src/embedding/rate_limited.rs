@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+
+use crate::language_models::rate_limit::RateLimiter;
+
+use super::{Embedder, EmbedderError};
+
+/// Wraps an `Embedder` so every call waits for the [`RateLimiter`], counting the tokens
+/// of all the embedded texts. A call counts as a single request, even when the wrapped
+/// embedder splits it in batches.
+///
+/// # Usage
+/// ```rust,ignore
+/// let limiter = RateLimiter::new()
+///     .with_requests_per_minute(3000)
+///     .with_tokens_per_minute(1_000_000);
+/// let embedder = RateLimitedEmbedder::new(OpenAiEmbedder::default(), limiter);
+/// ```
+pub struct RateLimitedEmbedder {
+    embedder: Box<dyn Embedder>,
+    limiter: RateLimiter,
+}
+
+impl RateLimitedEmbedder {
+    pub fn new<E: Embedder + 'static>(embedder: E, limiter: RateLimiter) -> Self {
+        Self {
+            embedder: Box::new(embedder),
+            limiter,
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for RateLimitedEmbedder {
+    async fn embed_documents(&self, documents: &[String]) -> Result<Vec<Vec<f64>>, EmbedderError> {
+        let tokens = documents
+            .iter()
+            .map(|document| self.limiter.count_tokens(document))
+            .sum();
+        self.limiter.acquire(tokens).await;
+        self.embedder.embed_documents(documents).await
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f64>, EmbedderError> {
+        self.limiter.acquire(self.limiter.count_tokens(text)).await;
+        self.embedder.embed_query(text).await
+    }
+}
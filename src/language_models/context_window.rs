@@ -0,0 +1,101 @@
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
+
+/// Context window assumed by [`context_window_or_default`] for unknown models.
+pub const DEFAULT_CONTEXT_WINDOW: usize = 4_096;
+
+/// Context windows of known models, in tokens. Names are matched by prefix, the longest
+/// one winning, so dated snapshots like `gpt-4o-2024-08-06` resolve to their family.
+const KNOWN_CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    // OpenAI
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-1106", 128_000),
+    ("gpt-4-0125", 128_000),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo-instruct", 4_096),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1-mini", 128_000),
+    ("o1", 200_000),
+    // Anthropic
+    ("claude-3", 200_000),
+    ("claude-2.1", 200_000),
+    ("claude-2", 100_000),
+    // Mistral
+    ("mistral-large", 128_000),
+    ("mistral-small", 32_000),
+    ("open-mistral-nemo", 128_000),
+    ("ministral", 128_000),
+    ("codestral", 256_000),
+    // Ollama
+    ("llama3.1", 128_000),
+    ("llama3.2", 128_000),
+    ("llama3", 8_192),
+    ("mistral", 32_768),
+];
+
+fn overrides() -> &'static RwLock<HashMap<String, usize>> {
+    static OVERRIDES: OnceLock<RwLock<HashMap<String, usize>>> = OnceLock::new();
+    OVERRIDES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Returns the context window of `model` in tokens, or `None` for unknown models.
+/// Windows registered with [`register_context_window`] take precedence. The default limits
+/// of `ConversationSummaryMemory` and `AgentExecutor` are sized with it when they are given
+/// a model with `with_context_model`.
+///
+/// # Usage
+/// ```rust,ignore
+/// assert_eq!(context_window("gpt-4o-2024-08-06"), Some(128_000));
+/// ```
+pub fn context_window(model: &str) -> Option<usize> {
+    let registered = overrides()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(model)
+        .copied();
+    registered.or_else(|| {
+        KNOWN_CONTEXT_WINDOWS
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, tokens)| *tokens)
+    })
+}
+
+/// Same as [`context_window`], falling back to [`DEFAULT_CONTEXT_WINDOW`].
+pub fn context_window_or_default(model: &str) -> usize {
+    context_window(model).unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
+/// Sets the context window of a model, e.g. a fine-tuned or self-hosted one, replacing
+/// the known value if any. The name must match exactly.
+pub fn register_context_window<S: Into<String>>(model: S, tokens: usize) {
+    overrides()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(model.into(), tokens);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_window() {
+        assert_eq!(context_window("gpt-4o-mini"), Some(128_000));
+        assert_eq!(context_window("gpt-4-0613"), Some(8_192));
+        assert_eq!(context_window("claude-3-5-sonnet-20240620"), Some(200_000));
+        assert_eq!(context_window("context-window-test-model"), None);
+        assert_eq!(
+            context_window_or_default("context-window-test-model"),
+            DEFAULT_CONTEXT_WINDOW
+        );
+
+        register_context_window("context-window-test-model", 32_000);
+        assert_eq!(context_window("context-window-test-model"), Some(32_000));
+    }
+}
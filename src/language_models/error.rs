@@ -6,7 +6,7 @@ use serde_json::Error as SerdeJsonError;
 use thiserror::Error;
 use tokio::time::error::Elapsed;
 
-use crate::llm::AnthropicError;
+use crate::llm::{AnthropicError, MistralError};
 
 #[derive(Error, Debug)]
 pub enum LLMError {
@@ -16,6 +16,9 @@ pub enum LLMError {
     #[error("Anthropic error: {0}")]
     AnthropicError(#[from] AnthropicError),
 
+    #[error("Mistral error: {0}")]
+    MistralError(#[from] MistralError),
+
     #[cfg(feature = "ollama")]
     #[error("Ollama error: {0}")]
     OllamaError(#[from] OllamaError),
@@ -38,6 +41,12 @@ pub enum LLMError {
     #[error("Content not found in response: Expected at {0}")]
     ContentNotFound(String),
 
+    #[error("Option not supported by this model: {0}")]
+    UnsupportedOption(String),
+
+    #[error("Invalid option: {0}")]
+    InvalidOption(String),
+
     #[error("Error: {0}")]
     OtherError(String),
 }
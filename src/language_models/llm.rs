@@ -1,14 +1,17 @@
-use std::pin::Pin;
+use std::{pin::Pin, sync::Arc};
 
 use async_trait::async_trait;
-use futures::Stream;
+use futures::{stream, Stream, StreamExt};
+use tokio::sync::mpsc;
 
-use crate::schemas::{Message, StreamData};
+use crate::schemas::{Message, StreamData, StreamDelta};
 
-use super::{options::CallOptions, GenerateResult, LLMError};
+use super::{options::CallOptions, stream_to_channel, GenerateResult, LLMError};
 
 #[async_trait]
 pub trait LLM: Sync + Send + LLMClone {
+    /// Generates a response to `messages`. Dropping the returned future cancels the
+    /// request; implementations keep no state across calls, so the LLM remains usable.
     async fn generate(&self, messages: &[Message]) -> Result<GenerateResult, LLMError>;
     async fn invoke(&self, prompt: &str) -> Result<String, LLMError> {
         self.generate(&[Message::new_human_message(prompt)])
@@ -20,6 +23,40 @@ pub trait LLM: Sync + Send + LLMClone {
         _messages: &[Message],
     ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>>, LLMError>;
 
+    /// Same as `stream`, delivering the chunks through a channel bounded to `capacity`,
+    /// see [`stream_to_channel`]. The provider read pauses while the channel is full, so a
+    /// slow consumer doesn't make the response pile up in memory.
+    async fn stream_channel(
+        &self,
+        messages: &[Message],
+        capacity: usize,
+    ) -> Result<mpsc::Receiver<Result<StreamData, LLMError>>, LLMError> {
+        let stream = self.stream(messages).await?;
+        Ok(stream_to_channel(stream, capacity))
+    }
+
+    /// Same as `stream`, yielding text and tool call fragments as they arrive, e.g. for an
+    /// agent that starts running a tool before the model is done with its other calls.
+    /// See [`crate::schemas::ToolCallDelta`] for the assembly contract. The default reads
+    /// tool calls in the OpenAI chunk format, used by OpenAI and Mistral; other providers
+    /// only yield text.
+    async fn stream_deltas(
+        &self,
+        messages: &[Message],
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamDelta, LLMError>> + Send>>, LLMError> {
+        let stream = self.stream(messages).await?;
+        Ok(Box::pin(stream.flat_map(|data| {
+            let deltas: Vec<Result<StreamDelta, LLMError>> = match data {
+                Ok(data) => StreamDelta::from_stream_data(&data)
+                    .into_iter()
+                    .map(Ok)
+                    .collect(),
+                Err(e) => vec![Err(e)],
+            };
+            stream::iter(deltas)
+        })))
+    }
+
     /// This is usefull when you want to create a chain and override
     /// LLM options
     fn add_options(&mut self, _options: CallOptions) {
@@ -56,3 +93,117 @@ where
         Box::new(llm)
     }
 }
+
+/// Lets several chains share one `LLM`, e.g. to reuse a client and its connection pool:
+/// `Arc<dyn LLM>` can be passed wherever a `Box<dyn LLM>` is expected.
+///
+/// Options added through `add_options` (for instance by a chain builder) are kept per
+/// `SharedLLM` and layered on top of the shared LLM's own options for each call, so
+/// chains never see each other's options.
+///
+/// # Usage
+/// ```rust,ignore
+/// let llm: Arc<dyn LLM> = Arc::new(OpenAI::default());
+/// let chain_a = LLMChainBuilder::new().prompt(prompt_a).llm(llm.clone()).build()?;
+/// let chain_b = LLMChainBuilder::new().prompt(prompt_b).llm(llm.clone()).build()?;
+/// ```
+#[derive(Clone)]
+pub struct SharedLLM {
+    llm: Arc<dyn LLM>,
+    options: Option<CallOptions>,
+}
+
+impl SharedLLM {
+    pub fn new(llm: Arc<dyn LLM>) -> Self {
+        Self { llm, options: None }
+    }
+
+    /// The shared LLM with this instance's options applied, when there are any.
+    fn configured(&self) -> Option<Box<dyn LLM>> {
+        self.options.as_ref().map(|options| {
+            let mut llm = self.llm.clone_box();
+            llm.add_options(options.clone());
+            llm
+        })
+    }
+}
+
+#[async_trait]
+impl LLM for SharedLLM {
+    async fn generate(&self, messages: &[Message]) -> Result<GenerateResult, LLMError> {
+        match self.configured() {
+            Some(llm) => llm.generate(messages).await,
+            None => self.llm.generate(messages).await,
+        }
+    }
+
+    async fn stream(
+        &self,
+        messages: &[Message],
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>>, LLMError> {
+        match self.configured() {
+            Some(llm) => llm.stream(messages).await,
+            None => self.llm.stream(messages).await,
+        }
+    }
+
+    fn add_options(&mut self, options: CallOptions) {
+        match &mut self.options {
+            Some(existing) => existing.merge_options(options),
+            None => self.options = Some(options),
+        }
+    }
+
+    fn messages_to_string(&self, messages: &[Message]) -> String {
+        self.llm.messages_to_string(messages)
+    }
+}
+
+impl From<Arc<dyn LLM>> for Box<dyn LLM> {
+    fn from(llm: Arc<dyn LLM>) -> Self {
+        Box::new(SharedLLM::new(llm))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::fake::FakeLLM;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shared_llm_layers_options_per_owner() {
+        let fake = FakeLLM::new().with_responses(vec!["a", "b", "c"]);
+        let shared: Arc<dyn LLM> = Arc::new(fake.clone());
+
+        let mut first: Box<dyn LLM> = shared.clone().into();
+        first.add_options(CallOptions::new().with_max_tokens(10));
+        let mut second: Box<dyn LLM> = shared.clone().into();
+        second.add_options(CallOptions::new().with_temperature(0.2));
+
+        first.invoke("hi").await.unwrap();
+        second.invoke("hi").await.unwrap();
+        shared.invoke("hi").await.unwrap();
+
+        let options = fake.call_options();
+        assert_eq!(options[0].max_tokens, Some(10));
+        assert_eq!(options[0].temperature, None);
+        assert_eq!(options[1].max_tokens, None);
+        assert_eq!(options[1].temperature, Some(0.2));
+        assert_eq!(options[2].max_tokens, None);
+        assert_eq!(options[2].temperature, None);
+    }
+
+    #[tokio::test]
+    async fn test_stream_deltas_yields_text() {
+        let llm = FakeLLM::new().with_responses(vec!["hello"]);
+        let deltas: Vec<StreamDelta> = llm
+            .stream_deltas(&[Message::new_human_message("hi")])
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(deltas, vec![StreamDelta::Text("hello".to_string())]);
+    }
+}
@@ -1,19 +1,66 @@
 use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 pub mod llm;
 pub mod options;
+pub mod rate_limit;
 
 mod error;
 pub use error::*;
 
+mod context_window;
+pub use context_window::*;
+
+mod stop_words;
+pub use stop_words::*;
+
+mod stream_channel;
+pub use stream_channel::*;
+
 //TODO: check if its this should have a data:serde::Value to save all other things, like OpenAI
 //function responses
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct GenerateResult {
     pub tokens: Option<TokenUsage>,
     pub generation: String,
+    /// Structured output, e.g. set by `LLMChain` when built with `parse_json` or by the
+    /// extraction chain. `None` for plain text generations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parsed: Option<Value>,
+    /// Response of the provider as returned by its API, for fields like `finish_reason`
+    /// that are not mapped. Only set by backends that support it, and not when streaming.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Value>,
+    /// Why the provider stopped generating, if it reports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<FinishReason>,
+}
+
+/// Why the model stopped generating. `Length` means the output was cut off by the token
+/// limit, which usually leaves an incomplete answer.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishReason {
+    Stop,
+    Length,
+    ToolCalls,
+    ContentFilter,
+    Other(String),
+}
+
+impl FinishReason {
+    /// Maps the reasons used by the OpenAI, Anthropic and Mistral APIs.
+    pub fn from_provider(reason: &str) -> Self {
+        match reason {
+            "stop" | "end_turn" | "stop_sequence" => FinishReason::Stop,
+            "length" | "max_tokens" | "model_length" => FinishReason::Length,
+            "tool_calls" | "function_call" | "tool_use" => FinishReason::ToolCalls,
+            "content_filter" => FinishReason::ContentFilter,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
 }
 
 impl GenerateResult {
@@ -34,6 +81,12 @@ impl GenerateResult {
                 tokens.completion_tokens.to_string(),
             );
             map.insert("total_tokens".to_string(), tokens.total_tokens.to_string());
+            if let Some(cached_tokens) = tokens.cached_tokens {
+                map.insert("cached_tokens".to_string(), cached_tokens.to_string());
+            }
+            if let Some(reasoning_tokens) = tokens.reasoning_tokens {
+                map.insert("reasoning_tokens".to_string(), reasoning_tokens.to_string());
+            }
         }
 
         map
@@ -45,30 +98,55 @@ pub struct TokenUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Prompt tokens read from the provider's prompt cache, when reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_tokens: Option<u32>,
+    /// Completion tokens spent on reasoning by reasoning models, when reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_tokens: Option<u32>,
 }
 
 impl TokenUsage {
     pub fn sum(&self, other: &TokenUsage) -> TokenUsage {
-        TokenUsage {
-            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
-            completion_tokens: self.completion_tokens + other.completion_tokens,
-            total_tokens: self.total_tokens + other.total_tokens,
-        }
+        let mut sum = self.clone();
+        sum.add(other);
+        sum
     }
 
     pub fn add(&mut self, other: &TokenUsage) {
         self.prompt_tokens += other.prompt_tokens;
         self.completion_tokens += other.completion_tokens;
         self.total_tokens += other.total_tokens;
+        self.cached_tokens = add_optional(self.cached_tokens, other.cached_tokens);
+        self.reasoning_tokens = add_optional(self.reasoning_tokens, other.reasoning_tokens);
+    }
+}
+
+fn add_optional(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or_default() + b.unwrap_or_default()),
     }
 }
 
 impl TokenUsage {
+    /// Records the token counts on the current span, which must declare the
+    /// `prompt_tokens`, `completion_tokens` and `total_tokens` fields.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn record_in_current_span(&self) {
+        let span = tracing::Span::current();
+        span.record("prompt_tokens", self.prompt_tokens);
+        span.record("completion_tokens", self.completion_tokens);
+        span.record("total_tokens", self.total_tokens);
+    }
+
     pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
         Self {
             prompt_tokens,
             completion_tokens,
             total_tokens: prompt_tokens + completion_tokens,
+            cached_tokens: None,
+            reasoning_tokens: None,
         }
     }
 }
@@ -1,11 +1,13 @@
 use futures::Future;
-use std::{pin::Pin, sync::Arc};
+use std::{collections::HashMap, pin::Pin, sync::Arc};
 use tokio::sync::Mutex;
 
-use crate::schemas::{FunctionCallBehavior, FunctionDefinition};
+use crate::schemas::{FunctionCallBehavior, FunctionDefinition, ToolChoice};
 
 #[derive(Clone)]
 pub struct CallOptions {
+    /// Overrides the model configured on the LLM.
+    pub model: Option<String>,
     pub candidate_count: Option<usize>,
     pub max_tokens: Option<u32>,
     pub temperature: Option<f32>,
@@ -24,9 +26,13 @@ pub struct CallOptions {
     pub repetition_penalty: Option<f32>,
     pub frequency_penalty: Option<f32>,
     pub presence_penalty: Option<f32>,
+    pub logit_bias: Option<HashMap<u32, f32>>,
     pub functions: Option<Vec<FunctionDefinition>>,
     pub function_call_behavior: Option<FunctionCallBehavior>,
+    /// Whether the model may call several tools in a single turn.
+    pub parallel_tool_calls: Option<bool>,
     pub stream_usage: Option<bool>,
+    pub prefill: Option<String>,
 }
 
 impl Default for CallOptions {
@@ -37,6 +43,7 @@ impl Default for CallOptions {
 impl CallOptions {
     pub fn new() -> Self {
         CallOptions {
+            model: None,
             candidate_count: None,
             max_tokens: None,
             temperature: None,
@@ -51,13 +58,21 @@ impl CallOptions {
             repetition_penalty: None,
             frequency_penalty: None,
             presence_penalty: None,
+            logit_bias: None,
             functions: None,
             function_call_behavior: None,
+            parallel_tool_calls: None,
             stream_usage: None,
+            prefill: None,
         }
     }
 
     // Refactored "with" functions as methods of CallOptions
+    pub fn with_model<S: Into<String>>(mut self, model: S) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
     pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
         self.max_tokens = Some(max_tokens);
         self
@@ -73,11 +88,25 @@ impl CallOptions {
         self
     }
 
+    /// Stop sequences sent to the provider. When streaming, the OpenAI, Claude, Mistral and
+    /// Ollama clients also end the streamed text as soon as one is generated, see
+    /// `truncate_stream_at_stop_words`.
     pub fn with_stop_words(mut self, stop_words: Vec<String>) -> Self {
         self.stop_words = Some(stop_words);
         self
     }
 
+    /// Appends a single stop sequence, ignoring it if it is already present.
+    /// Note that some providers limit the number of stop sequences (OpenAI accepts 4).
+    pub fn add_stop_word<S: Into<String>>(mut self, stop_word: S) -> Self {
+        let stop_word = stop_word.into();
+        let stop_words = self.stop_words.get_or_insert_with(Vec::new);
+        if !stop_words.contains(&stop_word) {
+            stop_words.push(stop_word);
+        }
+        self
+    }
+
     //TODO:Check if this should be a &str instead of a String
     pub fn with_streaming_func<F, Fut>(mut self, mut func: F) -> Self
     where
@@ -139,6 +168,12 @@ impl CallOptions {
         self
     }
 
+    /// Sets the bias applied to the given token ids, from -100 (ban) to 100 (exclusive selection).
+    pub fn with_logit_bias(mut self, logit_bias: HashMap<u32, f32>) -> Self {
+        self.logit_bias = Some(logit_bias);
+        self
+    }
+
     pub fn with_functions(mut self, functions: Vec<FunctionDefinition>) -> Self {
         self.functions = Some(functions);
         self
@@ -149,13 +184,35 @@ impl CallOptions {
         self
     }
 
+    /// Same as `with_function_call_behavior`. When unset, the model chooses (`auto`).
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.function_call_behavior = Some(tool_choice);
+        self
+    }
+
+    /// With `false` the model calls at most one tool per turn. When unset, the API
+    /// default applies (parallel calls allowed). Only sent when functions are present.
+    pub fn with_parallel_tool_calls(mut self, parallel_tool_calls: bool) -> Self {
+        self.parallel_tool_calls = Some(parallel_tool_calls);
+        self
+    }
+
     pub fn with_stream_usage(mut self, stream_usage: bool) -> Self {
         self.stream_usage = Some(stream_usage);
         self
     }
 
+    /// Starts the assistant response with the given text, e.g. `{` to force JSON output.
+    /// The prefill is included at the beginning of the generation. Only supported by
+    /// backends that accept a trailing assistant message (Claude); OpenAI returns an error.
+    pub fn with_prefill<S: Into<String>>(mut self, prefill: S) -> Self {
+        self.prefill = Some(prefill.into());
+        self
+    }
+
     pub fn merge_options(&mut self, incoming_options: CallOptions) {
         // For simple scalar types wrapped in Option, prefer incoming option if it is Some
+        self.model = incoming_options.model.or_else(|| self.model.clone());
         self.candidate_count = incoming_options.candidate_count.or(self.candidate_count);
         self.max_tokens = incoming_options.max_tokens.or(self.max_tokens);
         self.temperature = incoming_options.temperature.or(self.temperature);
@@ -172,10 +229,17 @@ impl CallOptions {
             .frequency_penalty
             .or(self.frequency_penalty);
         self.presence_penalty = incoming_options.presence_penalty.or(self.presence_penalty);
+        self.logit_bias = incoming_options
+            .logit_bias
+            .or_else(|| self.logit_bias.clone());
         self.function_call_behavior = incoming_options
             .function_call_behavior
             .or(self.function_call_behavior.clone());
+        self.parallel_tool_calls = incoming_options
+            .parallel_tool_calls
+            .or(self.parallel_tool_calls);
         self.stream_usage = incoming_options.stream_usage.or(self.stream_usage);
+        self.prefill = incoming_options.prefill.or_else(|| self.prefill.clone());
 
         // For `Vec<String>`, merge if both are Some; prefer incoming if only incoming is Some
         if let Some(mut new_stop_words) = incoming_options.stop_words {
@@ -0,0 +1,298 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures::Stream;
+use tiktoken_rs::{get_bpe_from_tokenizer, tokenizer::Tokenizer, CoreBPE};
+
+use crate::schemas::{Message, StreamData};
+
+use super::{llm::LLM, options::CallOptions, GenerateResult, LLMError};
+
+/// Token bucket holding up to `capacity` units, refilled continuously over a minute.
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    refill_per_second: f64,
+}
+
+impl Bucket {
+    fn per_minute(limit: u32) -> Self {
+        let capacity = f64::from(limit.max(1));
+        Self {
+            capacity,
+            available: capacity,
+            refill_per_second: capacity / 60.0,
+        }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        self.available =
+            (self.available + elapsed.as_secs_f64() * self.refill_per_second).min(self.capacity);
+    }
+
+    /// How long until `amount` can be taken. Amounts over the capacity only wait for a
+    /// full bucket, leaving it in debt.
+    fn wait_time(&self, amount: f64) -> Duration {
+        let missing = amount.min(self.capacity) - self.available;
+        if missing <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing / self.refill_per_second)
+        }
+    }
+}
+
+struct LimiterState {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+    last_refill: Instant,
+}
+
+impl LimiterState {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now - self.last_refill;
+        self.last_refill = now;
+        for bucket in self.requests.iter_mut().chain(self.tokens.iter_mut()) {
+            bucket.refill(elapsed);
+        }
+    }
+}
+
+/// Limits the requests and tokens sent per minute, waiting until there is capacity instead
+/// of failing. Clones share their limits, so one limiter can be used by several LLMs or
+/// embedders using the same API key.
+///
+/// Tokens are counted with the given tokenizer, or estimated as 4 characters per token.
+///
+/// # Usage
+/// ```rust,ignore
+/// let limiter = RateLimiter::new()
+///     .with_requests_per_minute(500)
+///     .with_tokens_per_minute(30_000)
+///     .with_tokenizer(Tokenizer::Cl100kBase);
+/// let llm = RateLimitedLLM::new(OpenAI::default(), limiter.clone());
+/// let embedder = RateLimitedEmbedder::new(OpenAiEmbedder::default(), limiter);
+/// ```
+#[derive(Clone)]
+pub struct RateLimiter {
+    state: Arc<Mutex<LimiterState>>,
+    tokenizer: Option<Arc<CoreBPE>>,
+}
+
+impl RateLimiter {
+    /// A limiter without limits, set them with `with_requests_per_minute` and
+    /// `with_tokens_per_minute`.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(LimiterState {
+                requests: None,
+                tokens: None,
+                last_refill: Instant::now(),
+            })),
+            tokenizer: None,
+        }
+    }
+
+    pub fn with_requests_per_minute(self, requests_per_minute: u32) -> Self {
+        self.update(|state| state.requests = Some(Bucket::per_minute(requests_per_minute)))
+    }
+
+    pub fn with_tokens_per_minute(self, tokens_per_minute: u32) -> Self {
+        self.update(|state| state.tokens = Some(Bucket::per_minute(tokens_per_minute)))
+    }
+
+    /// Counts tokens with `tokenizer` instead of estimating them.
+    pub fn with_tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        match get_bpe_from_tokenizer(tokenizer) {
+            Ok(bpe) => self.tokenizer = Some(Arc::new(bpe)),
+            Err(e) => log::warn!("Could not load tokenizer, estimating tokens instead: {}", e),
+        }
+        self
+    }
+
+    fn update(self, f: impl FnOnce(&mut LimiterState)) -> Self {
+        f(&mut self.state());
+        self
+    }
+
+    /// The state is never locked across an await, so waiting for it is short, even for
+    /// a limiter shared with running requests.
+    fn state(&self) -> MutexGuard<'_, LimiterState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn count_tokens(&self, text: &str) -> usize {
+        match &self.tokenizer {
+            Some(bpe) => bpe.encode_ordinary(text).len(),
+            None => text.chars().count().div_ceil(4),
+        }
+    }
+
+    /// Waits until a request using `tokens` tokens is allowed, then takes its capacity.
+    pub async fn acquire(&self, tokens: usize) {
+        loop {
+            let wait = {
+                let mut state = self.state();
+                state.refill();
+                let wait = state
+                    .requests
+                    .as_ref()
+                    .map(|bucket| bucket.wait_time(1.0))
+                    .max(
+                        state
+                            .tokens
+                            .as_ref()
+                            .map(|bucket| bucket.wait_time(tokens as f64)),
+                    )
+                    .unwrap_or_default();
+                if wait.is_zero() {
+                    if let Some(bucket) = &mut state.requests {
+                        bucket.available -= 1.0;
+                    }
+                    if let Some(bucket) = &mut state.tokens {
+                        bucket.available -= tokens as f64;
+                    }
+                    return;
+                }
+                wait
+            };
+            log::debug!("Rate limit reached, waiting {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Takes `tokens` tokens without waiting, e.g. the completion tokens reported once a
+    /// request is done. Later requests wait until the debt is paid off.
+    pub async fn consume(&self, tokens: usize) {
+        let mut state = self.state();
+        state.refill();
+        if let Some(bucket) = &mut state.tokens {
+            bucket.available -= tokens as f64;
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wraps an `LLM` so every call waits for the [`RateLimiter`]. Prompt tokens are taken
+/// before the call and the completion tokens reported by the provider afterwards.
+pub struct RateLimitedLLM {
+    llm: Box<dyn LLM>,
+    limiter: RateLimiter,
+}
+
+impl RateLimitedLLM {
+    pub fn new<L: Into<Box<dyn LLM>>>(llm: L, limiter: RateLimiter) -> Self {
+        Self {
+            llm: llm.into(),
+            limiter,
+        }
+    }
+
+    fn prompt_tokens(&self, messages: &[Message]) -> usize {
+        messages
+            .iter()
+            .map(|message| self.limiter.count_tokens(&message.content))
+            .sum()
+    }
+}
+
+impl Clone for RateLimitedLLM {
+    fn clone(&self) -> Self {
+        Self {
+            llm: self.llm.clone_box(),
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl LLM for RateLimitedLLM {
+    async fn generate(&self, messages: &[Message]) -> Result<GenerateResult, LLMError> {
+        self.limiter.acquire(self.prompt_tokens(messages)).await;
+        let result = self.llm.generate(messages).await?;
+        if let Some(tokens) = &result.tokens {
+            self.limiter
+                .consume(tokens.completion_tokens as usize)
+                .await;
+        }
+        Ok(result)
+    }
+
+    async fn stream(
+        &self,
+        messages: &[Message],
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>>, LLMError> {
+        self.limiter.acquire(self.prompt_tokens(messages)).await;
+        self.llm.stream(messages).await
+    }
+
+    fn add_options(&mut self, options: CallOptions) {
+        self.llm.add_options(options)
+    }
+
+    fn messages_to_string(&self, messages: &[Message]) -> String {
+        self.llm.messages_to_string(messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::fake::FakeLLM;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limiter_waits_for_capacity() {
+        // 1200 requests per minute refill one request every 50ms.
+        let limiter = RateLimiter::new().with_requests_per_minute(1200);
+        for _ in 0..1200 {
+            limiter.acquire(0).await;
+        }
+
+        let start = Instant::now();
+        limiter.acquire(0).await;
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[test]
+    fn test_limits_change_while_in_use() {
+        let limiter = RateLimiter::new();
+        let shared = limiter.clone();
+        let guard = limiter.state();
+        let update = std::thread::spawn(move || {
+            shared.with_requests_per_minute(1);
+        });
+        std::thread::sleep(Duration::from_millis(20));
+        drop(guard);
+        update.join().unwrap();
+
+        assert!(limiter.state().requests.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_llm_counts_tokens() {
+        let limiter = RateLimiter::new().with_tokens_per_minute(6000);
+        let llm = RateLimitedLLM::new(FakeLLM::new().with_responses(vec!["ok"]), limiter.clone());
+
+        let start = Instant::now();
+        llm.invoke(&"a".repeat(24_000)).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(40));
+
+        // The bucket is now empty, the next request waits for 100 tokens (1 second).
+        let start = Instant::now();
+        tokio::time::timeout(Duration::from_millis(200), limiter.acquire(100))
+            .await
+            .expect_err("should wait for tokens");
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,162 @@
+use std::pin::Pin;
+
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+
+use crate::schemas::StreamData;
+
+use super::LLMError;
+
+type LLMStream = Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>>;
+
+/// Ends the text of `stream` as soon as one of `stop_words` is generated, dropping the stop
+/// word and everything after it. Providers don't always honor stop words, and a stop word
+/// can be split across chunks, so text that could be the start of one is held back until
+/// the next chunk tells whether it matches. The rest of the stream is still read, so the
+/// chunks carrying token usage, usually the last ones, are forwarded without their text.
+///
+/// Returns the stream unchanged when there are no stop words.
+pub fn truncate_stream_at_stop_words(stream: LLMStream, stop_words: &[String]) -> LLMStream {
+    let stop_words: Vec<String> = stop_words
+        .iter()
+        .filter(|word| !word.is_empty())
+        .cloned()
+        .collect();
+    if stop_words.is_empty() {
+        return stream;
+    }
+
+    Box::pin(stream! {
+        let mut stream = stream;
+        let mut pending = String::new();
+        let mut stopped = false;
+        while let Some(item) = stream.next().await {
+            let data = match item {
+                Ok(data) => data,
+                Err(e) => {
+                    yield Err(e);
+                    continue;
+                }
+            };
+            if stopped {
+                if data.tokens.is_some() {
+                    yield Ok(StreamData::new(data.value, data.tokens, ""));
+                }
+                continue;
+            }
+            if data.content.is_empty() {
+                yield Ok(data);
+                continue;
+            }
+
+            pending.push_str(&data.content);
+            if let Some(index) = find_stop_word(&pending, &stop_words) {
+                pending.truncate(index);
+                let content = std::mem::take(&mut pending);
+                if !content.is_empty() || data.tokens.is_some() {
+                    yield Ok(StreamData::new(data.value, data.tokens, content));
+                }
+                stopped = true;
+                continue;
+            }
+
+            let ready = pending.len() - partial_stop_word_len(&pending, &stop_words);
+            let content: String = pending.drain(..ready).collect();
+            if !content.is_empty() {
+                yield Ok(StreamData::new(data.value, data.tokens, content));
+            }
+        }
+        if !pending.is_empty() {
+            yield Ok(StreamData::new(Value::Null, None, pending));
+        }
+    })
+}
+
+fn find_stop_word(text: &str, stop_words: &[String]) -> Option<usize> {
+    stop_words
+        .iter()
+        .filter_map(|word| text.find(word.as_str()))
+        .min()
+}
+
+/// Length of the longest end of `text` that is the beginning of a stop word.
+fn partial_stop_word_len(text: &str, stop_words: &[String]) -> usize {
+    stop_words
+        .iter()
+        .filter_map(|word| {
+            (1..word.len())
+                .rev()
+                .find(|&len| word.is_char_boundary(len) && text.ends_with(&word[..len]))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+    use serde_json::json;
+
+    use crate::language_models::TokenUsage;
+
+    use super::*;
+
+    async fn collect(chunks: Vec<&str>, stop_words: &[&str]) -> Vec<String> {
+        let chunks: Vec<Result<StreamData, LLMError>> = chunks
+            .into_iter()
+            .map(|chunk| Ok(StreamData::new(json!({}), None, chunk)))
+            .collect();
+        let stop_words: Vec<String> = stop_words.iter().map(|w| w.to_string()).collect();
+        truncate_stream_at_stop_words(Box::pin(stream::iter(chunks)), &stop_words)
+            .map(|data| data.unwrap().content)
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_truncate_stream_at_stop_words() {
+        let chunks = vec!["Thought: search", "\nObserv", "ation: 42", "more"];
+        assert_eq!(
+            collect(chunks, &["\nObservation:"]).await,
+            vec!["Thought: search"]
+        );
+
+        let chunks = vec!["a\nOb", "ject", " done"];
+        assert_eq!(
+            collect(chunks, &["\nObservation:"]).await,
+            vec!["a", "\nObject", " done"]
+        );
+
+        let chunks = vec!["```json\n{}\n``` ```json"];
+        assert_eq!(collect(chunks, &["``` ```"]).await, vec!["```json\n{}\n"]);
+
+        assert_eq!(collect(vec!["one", " two"], &[]).await, vec!["one", " two"]);
+    }
+
+    #[tokio::test]
+    async fn test_usage_after_stop_word_is_forwarded() {
+        let chunks: Vec<Result<StreamData, LLMError>> = vec![
+            Ok(StreamData::new(
+                json!({}),
+                None,
+                "answer
+Observation:",
+            )),
+            Ok(StreamData::new(json!({}), None, " ignored")),
+            Ok(StreamData::new(json!({}), Some(TokenUsage::new(10, 5)), "")),
+        ];
+        let stop_words = vec!["\nObservation:".to_string()];
+
+        let output: Vec<StreamData> =
+            truncate_stream_at_stop_words(Box::pin(stream::iter(chunks)), &stop_words)
+                .map(|data| data.unwrap())
+                .collect()
+                .await;
+
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0].content, "answer");
+        assert_eq!(output[1].content, "");
+        assert_eq!(output[1].tokens.as_ref().unwrap().total_tokens, 15);
+    }
+}
@@ -0,0 +1,67 @@
+use std::pin::Pin;
+
+use futures::{Stream, StreamExt};
+use tokio::sync::mpsc;
+
+/// Forwards `stream` to a bounded channel from a spawned task, e.g. to hand the tokens of
+/// an LLM or chain stream to a websocket writer. Once `capacity` items are waiting, the
+/// task stops polling `stream` until the consumer catches up, so a slow consumer pauses
+/// the provider read instead of buffering the whole generation. Dropping the receiver
+/// drops `stream`, which closes the request.
+///
+/// # Usage
+/// ```rust,ignore
+/// let mut tokens = stream_to_channel(chain.stream(input).await?, 16);
+/// while let Some(data) = tokens.recv().await {
+///     socket.send(data?.content).await?;
+/// }
+/// ```
+pub fn stream_to_channel<T: Send + 'static>(
+    stream: Pin<Box<dyn Stream<Item = T> + Send>>,
+    capacity: usize,
+) -> mpsc::Receiver<T> {
+    let (sender, receiver) = mpsc::channel(capacity.max(1));
+    tokio::spawn(async move {
+        let mut stream = stream;
+        while let Some(item) = stream.next().await {
+            if sender.send(item).await.is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_slow_consumer_pauses_stream() {
+        let produced = Arc::new(AtomicUsize::new(0));
+        let counter = produced.clone();
+        let stream = futures::stream::iter(0..100).map(move |i| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            i
+        });
+
+        let mut receiver = stream_to_channel(Box::pin(stream), 4);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // The channel holds 4 items and the task waits to send the 5th
+        assert!(produced.load(Ordering::SeqCst) <= 5);
+
+        let mut received = Vec::new();
+        while let Some(i) = receiver.recv().await {
+            received.push(i);
+        }
+        assert_eq!(received, (0..100).collect::<Vec<_>>());
+    }
+}
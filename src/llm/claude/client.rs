@@ -1,5 +1,8 @@
 use crate::{
-    language_models::{llm::LLM, options::CallOptions, GenerateResult, LLMError, TokenUsage},
+    language_models::{
+        llm::LLM, options::CallOptions, truncate_stream_at_stop_words, FinishReason,
+        GenerateResult, LLMError, TokenUsage,
+    },
     llm::AnthropicError,
     schemas::{Message, MessageType, StreamData},
 };
@@ -102,22 +105,34 @@ impl Claude {
             503 => Err(LLMError::AnthropicError(AnthropicError::OverloadedError(
                 "Service Unavailable".to_string(),
             ))),
-            _ => Ok(res.json::<ApiResponse>().await?),
+            _ => Ok(res.json::<Value>().await?),
         }?;
+        let raw = res.clone();
+        let res: ApiResponse = serde_json::from_value(res)?;
 
-        let generation = res
+        let text = res
             .content
             .first()
             .map(|c| c.text.clone())
             .unwrap_or_default();
+        let generation = format!(
+            "{}{}",
+            self.options.prefill.as_deref().unwrap_or_default(),
+            text
+        );
 
-        let tokens = Some(TokenUsage {
-            prompt_tokens: res.usage.input_tokens,
-            completion_tokens: res.usage.output_tokens,
-            total_tokens: res.usage.input_tokens + res.usage.output_tokens,
-        });
+        let tokens = Some(TokenUsage::new(
+            res.usage.input_tokens,
+            res.usage.output_tokens,
+        ));
 
-        Ok(GenerateResult { tokens, generation })
+        Ok(GenerateResult {
+            tokens,
+            generation,
+            raw: Some(raw),
+            finish_reason: res.stop_reason.as_deref().map(FinishReason::from_provider),
+            ..Default::default()
+        })
     }
 
     fn build_payload(&self, messages: &[Message], stream: bool) -> Payload {
@@ -130,6 +145,10 @@ impl Claude {
             messages: other_messages
                 .into_iter()
                 .map(ClaudeMessage::from_message)
+                .chain(self.options.prefill.as_ref().map(|prefill| {
+                    // Anthropic rejects a final assistant message ending with whitespace
+                    ClaudeMessage::new("assistant", prefill.trim_end())
+                }))
                 .collect::<Vec<_>>(),
             max_tokens: self.options.max_tokens.unwrap_or(1024),
             stream: None,
@@ -206,10 +225,25 @@ impl LLM for Claude {
             }
         });
 
-        Ok(Box::pin(processed_stream))
+        let stream: Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>> =
+            match &self.options.prefill {
+                Some(prefill) => {
+                    let prefill = StreamData::new(Value::Null, None, prefill);
+                    Box::pin(
+                        futures::stream::once(async { Ok::<_, LLMError>(prefill) })
+                            .chain(processed_stream),
+                    )
+                }
+                None => Box::pin(processed_stream),
+            };
+        let stop_words = self.options.stop_words.clone().unwrap_or_default();
+        Ok(truncate_stream_at_stop_words(stream, &stop_words))
     }
 
     fn add_options(&mut self, options: CallOptions) {
+        if let Some(model) = &options.model {
+            self.model = model.clone();
+        }
         self.options.merge_options(options)
     }
 }
@@ -259,6 +293,18 @@ mod tests {
     use super::*;
     use tokio::test;
 
+    #[test]
+    async fn test_prefill_is_sent_as_last_assistant_message() {
+        let claude = Claude::new().with_options(CallOptions::new().with_prefill("{ "));
+        let payload = claude.build_payload(&[Message::new_human_message("Give me JSON")], false);
+        let payload = serde_json::to_value(payload).unwrap();
+
+        let messages = payload["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1]["role"], "assistant");
+        assert_eq!(messages[1]["content"], "{");
+    }
+
     #[test]
     #[ignore]
     async fn test_cloudia_generate() {
@@ -0,0 +1,171 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use futures::{stream, Stream};
+use serde_json::json;
+
+use crate::{
+    language_models::{llm::LLM, options::CallOptions, FinishReason, GenerateResult, LLMError},
+    schemas::{Message, StreamData},
+};
+
+/// `FakeLLM` is an offline `LLM` that answers with a pre-scripted queue of responses.
+/// Every call pops the next response and records the messages it was called with,
+/// which makes it possible to test chains and agents deterministically.
+///
+/// # Usage
+/// ```rust,ignore
+/// let llm = FakeLLM::new().with_responses(vec!["Hello".to_string()]);
+/// let chain = LLMChainBuilder::new().prompt(prompt).llm(llm.clone()).build()?;
+/// chain.invoke(prompt_args! {"input" => "Hi"}).await?;
+/// assert_eq!(llm.calls()[0][0].content, "Hi");
+/// ```
+#[derive(Clone, Default)]
+pub struct FakeLLM {
+    responses: Arc<Mutex<VecDeque<String>>>,
+    calls: Arc<Mutex<Vec<Vec<Message>>>>,
+    call_options: Arc<Mutex<Vec<CallOptions>>>,
+    options: CallOptions,
+    finish_reason: Option<FinishReason>,
+}
+
+impl FakeLLM {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the given responses to the queue. They are returned in order, one per call.
+    pub fn with_responses<S: Into<String>>(self, responses: Vec<S>) -> Self {
+        self.push_responses(responses);
+        self
+    }
+
+    /// Sets the finish reason reported with every generation.
+    pub fn with_finish_reason(mut self, finish_reason: FinishReason) -> Self {
+        self.finish_reason = Some(finish_reason);
+        self
+    }
+
+    /// Appends responses to the queue of an already shared `FakeLLM`.
+    pub fn push_responses<S: Into<String>>(&self, responses: Vec<S>) {
+        let mut queue = self.responses.lock().unwrap_or_else(|e| e.into_inner());
+        queue.extend(responses.into_iter().map(Into::into));
+    }
+
+    /// Returns the messages of every call made so far, in call order.
+    pub fn calls(&self) -> Vec<Vec<Message>> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Returns the options in effect for every call made so far, in call order.
+    pub fn call_options(&self) -> Vec<CallOptions> {
+        self.call_options
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Returns the messages of the last call, if any.
+    pub fn last_call(&self) -> Option<Vec<Message>> {
+        self.calls().pop()
+    }
+
+    /// Returns the number of responses still queued.
+    pub fn remaining_responses(&self) -> usize {
+        self.responses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    /// Returns the options that were added through `add_options`.
+    pub fn options(&self) -> &CallOptions {
+        &self.options
+    }
+
+    fn next_response(&self, messages: &[Message]) -> Result<String, LLMError> {
+        self.calls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(messages.to_vec());
+        self.call_options
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(self.options.clone());
+        self.responses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop_front()
+            .ok_or_else(|| LLMError::OtherError("FakeLLM has no responses left".to_string()))
+    }
+}
+
+#[async_trait]
+impl LLM for FakeLLM {
+    async fn generate(&self, messages: &[Message]) -> Result<GenerateResult, LLMError> {
+        let generation = self.next_response(messages)?;
+        if let Some(func) = &self.options.streaming_func {
+            let mut func = func.lock().await;
+            let _ = func(generation.clone()).await;
+        }
+        Ok(GenerateResult {
+            generation,
+            finish_reason: self.finish_reason.clone(),
+            ..Default::default()
+        })
+    }
+
+    async fn stream(
+        &self,
+        messages: &[Message],
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>>, LLMError> {
+        let generation = self.next_response(messages)?;
+        let data = StreamData::new(json!({ "content": generation }), None, generation);
+        Ok(Box::pin(stream::iter(vec![Ok::<_, LLMError>(data)])))
+    }
+
+    fn add_options(&mut self, options: CallOptions) {
+        self.options.merge_options(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fake_llm_returns_responses_in_order() {
+        let llm = FakeLLM::new().with_responses(vec!["first", "second"]);
+
+        assert_eq!(llm.invoke("one").await.unwrap(), "first");
+        assert_eq!(llm.invoke("two").await.unwrap(), "second");
+        assert!(llm.invoke("three").await.is_err());
+
+        let calls = llm.calls();
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0][0].content, "one");
+        assert_eq!(calls[1][0].content, "two");
+    }
+
+    #[tokio::test]
+    async fn test_fake_llm_clones_share_state() {
+        let llm = FakeLLM::new().with_responses(vec!["hello"]);
+        let boxed: Box<dyn LLM> = llm.clone().into();
+
+        let mut stream = boxed
+            .stream(&[Message::new_human_message("hi")])
+            .await
+            .unwrap();
+        let data = stream.next().await.unwrap().unwrap();
+
+        assert_eq!(data.content, "hello");
+        assert_eq!(llm.remaining_responses(), 0);
+        assert_eq!(llm.last_call().unwrap()[0].content, "hi");
+    }
+}
@@ -0,0 +1,2 @@
+mod fake_llm;
+pub use fake_llm::*;
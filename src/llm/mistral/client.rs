@@ -0,0 +1,463 @@
+use crate::{
+    language_models::{
+        llm::LLM, options::CallOptions, truncate_stream_at_stop_words, FinishReason,
+        GenerateResult, LLMError, TokenUsage,
+    },
+    llm::MistralError,
+    schemas::{
+        FunctionCallBehavior, FunctionCallResponse, Message, StreamData, ToolCallAccumulator,
+    },
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::pin::Pin;
+
+use super::models::{content_text, ApiResponse, MistralMessage, Payload};
+
+pub enum MistralModel {
+    MistralLarge,
+    MistralSmall,
+    Codestral,
+    OpenMistralNemo,
+    Ministral8b,
+}
+
+impl ToString for MistralModel {
+    fn to_string(&self) -> String {
+        match self {
+            MistralModel::MistralLarge => "mistral-large-latest".to_string(),
+            MistralModel::MistralSmall => "mistral-small-latest".to_string(),
+            MistralModel::Codestral => "codestral-latest".to_string(),
+            MistralModel::OpenMistralNemo => "open-mistral-nemo".to_string(),
+            MistralModel::Ministral8b => "ministral-8b-latest".to_string(),
+        }
+    }
+}
+
+/// Mistral's chat completions API (La Plateforme). The API is close to OpenAI's, but
+/// messages have no `name`, the seed is `random_seed`, forcing a tool call is done with
+/// `"any"` and tool call arguments may come back as JSON objects: tool calls are
+/// returned as a JSON list of [`FunctionCallResponse`], like the OpenAI client does, so
+/// they work with the OpenAI tools agent.
+///
+/// # Usage
+/// ```rust,ignore
+/// let mistral = Mistral::new()
+///     .with_model(MistralModel::MistralSmall.to_string())
+///     .with_api_key("...");
+/// let answer = mistral.invoke("Hi!").await?;
+/// ```
+#[derive(Clone)]
+pub struct Mistral {
+    model: String,
+    options: CallOptions,
+    api_key: String,
+    base_url: String,
+    safe_prompt: Option<bool>,
+}
+
+impl Default for Mistral {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mistral {
+    pub fn new() -> Self {
+        Self {
+            model: MistralModel::MistralLarge.to_string(),
+            options: CallOptions::default(),
+            api_key: std::env::var("MISTRAL_API_KEY").unwrap_or_default(),
+            base_url: "https://api.mistral.ai/v1".to_string(),
+            safe_prompt: None,
+        }
+    }
+
+    pub fn with_model<S: Into<String>>(mut self, model: S) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn with_options(mut self, options: CallOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_api_key<S: Into<String>>(mut self, api_key: S) -> Self {
+        self.api_key = api_key.into();
+        self
+    }
+
+    pub fn with_base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Asks Mistral to prepend its safety prompt to the conversation.
+    pub fn with_safe_prompt(mut self, safe_prompt: bool) -> Self {
+        self.safe_prompt = Some(safe_prompt);
+        self
+    }
+
+    async fn generate(&self, messages: &[Message]) -> Result<GenerateResult, LLMError> {
+        let payload = self.build_payload(messages, false)?;
+        let raw: Value = self.send(&payload).await?.json().await?;
+        let response: ApiResponse = serde_json::from_value(raw.clone())?;
+        generate_result(response, raw)
+    }
+
+    async fn send(&self, payload: &Payload) -> Result<reqwest::Response, LLMError> {
+        let res = Client::new()
+            .post(format!("{}/chat/completions", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(payload)
+            .send()
+            .await?;
+        let status = res.status();
+        if status.is_success() {
+            return Ok(res);
+        }
+        let message = res.text().await.unwrap_or_default();
+        let error = match status.as_u16() {
+            400 | 422 => MistralError::InvalidRequestError(message),
+            401 | 403 => MistralError::AuthenticationError(message),
+            429 => MistralError::RateLimitError(message),
+            _ => MistralError::ApiError(format!("{}: {}", status, message)),
+        };
+        Err(error.into())
+    }
+
+    fn build_payload(&self, messages: &[Message], stream: bool) -> Result<Payload, LLMError> {
+        if self.options.prefill.is_some() {
+            return Err(LLMError::UnsupportedOption("prefill".to_string()));
+        }
+        let tools = self.options.functions.as_ref().map(|functions| {
+            functions
+                .iter()
+                .map(|function| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": function.name,
+                            "description": function.description,
+                            "parameters": function.parameters,
+                        }
+                    })
+                })
+                .collect()
+        });
+        let tool_choice =
+            self.options
+                .function_call_behavior
+                .as_ref()
+                .map(|behavior| match behavior {
+                    FunctionCallBehavior::None => json!("none"),
+                    FunctionCallBehavior::Auto => json!("auto"),
+                    FunctionCallBehavior::Required => json!("any"),
+                    FunctionCallBehavior::Named(name) => {
+                        json!({"type": "function", "function": {"name": name}})
+                    }
+                });
+
+        Ok(Payload {
+            model: self.model.clone(),
+            messages: messages
+                .iter()
+                .map(MistralMessage::from_message)
+                .collect::<Result<Vec<_>, _>>()?,
+            stream: stream.then_some(true),
+            max_tokens: self.options.max_tokens,
+            temperature: self.options.temperature,
+            top_p: self.options.top_p,
+            stop: self.options.stop_words.clone(),
+            random_seed: self.options.seed,
+            presence_penalty: self.options.presence_penalty,
+            frequency_penalty: self.options.frequency_penalty,
+            n: self.options.n,
+            tools,
+            tool_choice,
+            parallel_tool_calls: self.options.parallel_tool_calls,
+            safe_prompt: self.safe_prompt,
+        })
+    }
+}
+
+#[async_trait]
+impl LLM for Mistral {
+    async fn generate(&self, messages: &[Message]) -> Result<GenerateResult, LLMError> {
+        match &self.options.streaming_func {
+            Some(func) => {
+                let mut generate_result = GenerateResult::default();
+                let mut tool_calls = ToolCallAccumulator::new();
+                let mut stream = self.stream(messages).await?;
+                while let Some(data) = stream.next().await {
+                    let data = data?;
+                    tool_calls.push_chunk(&data.value);
+                    if let Some(reason) = data.value["choices"][0]["finish_reason"].as_str() {
+                        generate_result.finish_reason = Some(FinishReason::from_provider(reason));
+                    }
+                    if data.tokens.is_some() {
+                        generate_result.tokens = data.tokens;
+                    }
+                    generate_result.generation.push_str(&data.content);
+                    let mut func = func.lock().await;
+                    let _ = func(data.content).await;
+                }
+                if !tool_calls.is_empty() {
+                    generate_result.generation = serde_json::to_string(&tool_calls.finish())?;
+                }
+                Ok(generate_result)
+            }
+            None => self.generate(messages).await,
+        }
+    }
+
+    async fn stream(
+        &self,
+        messages: &[Message],
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>>, LLMError> {
+        let payload = self.build_payload(messages, true)?;
+        let mut bytes = self.send(&payload).await?.bytes_stream();
+
+        // Server-sent events may be split across chunks, so lines are buffered
+        let stream = stream! {
+            let mut buffer: Vec<u8> = Vec::new();
+            let mut tool_calls = 0;
+            while let Some(chunk) = bytes.next().await {
+                match chunk {
+                    Ok(chunk) => buffer.extend_from_slice(&chunk),
+                    Err(e) => {
+                        yield Err(LLMError::RequestError(e));
+                        break;
+                    }
+                }
+                while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    let line = String::from_utf8_lossy(&line);
+                    if let Some(data) = parse_stream_line(&line, &mut tool_calls) {
+                        yield data;
+                    }
+                }
+            }
+        };
+
+        let stop_words = self.options.stop_words.clone().unwrap_or_default();
+        Ok(truncate_stream_at_stop_words(Box::pin(stream), &stop_words))
+    }
+
+    fn add_options(&mut self, options: CallOptions) {
+        if let Some(model) = &options.model {
+            self.model = model.clone();
+        }
+        self.options.merge_options(options)
+    }
+}
+
+fn generate_result(response: ApiResponse, raw: Value) -> Result<GenerateResult, LLMError> {
+    let choice = response
+        .choices
+        .into_iter()
+        .next()
+        .ok_or(LLMError::ContentNotFound("/choices/0".to_string()))?;
+
+    let generation = match choice.message.tool_calls {
+        Some(tool_calls) if !tool_calls.is_empty() => {
+            let tool_calls: Vec<FunctionCallResponse> =
+                tool_calls.into_iter().map(Into::into).collect();
+            serde_json::to_string(&tool_calls)?
+        }
+        _ => content_text(&choice.message.content),
+    };
+
+    Ok(GenerateResult {
+        tokens: response
+            .usage
+            .map(|usage| TokenUsage::new(usage.prompt_tokens, usage.completion_tokens)),
+        generation,
+        raw: Some(raw),
+        finish_reason: choice
+            .finish_reason
+            .as_deref()
+            .map(FinishReason::from_provider),
+        ..Default::default()
+    })
+}
+
+/// Parses a `data: {...}` line of the stream, skipping blank lines and `[DONE]`.
+/// `tool_calls` counts the tool calls of the stream so far, to index the ones of the line.
+fn parse_stream_line(line: &str, tool_calls: &mut u64) -> Option<Result<StreamData, LLMError>> {
+    let data = line.trim().strip_prefix("data:")?.trim();
+    if data.is_empty() || data == "[DONE]" {
+        return None;
+    }
+    let mut value: Value = match serde_json::from_str(data) {
+        Ok(value) => value,
+        Err(e) => return Some(Err(e.into())),
+    };
+
+    // Mistral sends each tool call whole, possibly in separate chunks, without the `index`
+    // and with arguments that may be objects, which `ToolCallAccumulator` would merge into
+    // a single call
+    if let Some(Value::Array(calls)) = value.pointer_mut("/choices/0/delta/tool_calls") {
+        for tool_call in calls.iter_mut() {
+            match tool_call["index"].as_u64() {
+                Some(index) => *tool_calls = (*tool_calls).max(index + 1),
+                None => {
+                    tool_call["index"] = json!(*tool_calls);
+                    *tool_calls += 1;
+                }
+            }
+            let arguments = &mut tool_call["function"]["arguments"];
+            if !arguments.is_string() {
+                *arguments = Value::String(arguments.to_string());
+            }
+        }
+    }
+
+    let tokens = match (
+        value
+            .pointer("/usage/prompt_tokens")
+            .and_then(Value::as_u64),
+        value
+            .pointer("/usage/completion_tokens")
+            .and_then(Value::as_u64),
+    ) {
+        (Some(prompt), Some(completion)) => Some(TokenUsage::new(prompt as u32, completion as u32)),
+        _ => None,
+    };
+    let content = value
+        .pointer("/choices/0/delta/content")
+        .map(content_text)
+        .unwrap_or_default();
+    Some(Ok(StreamData::new(value, tokens, content)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schemas::{FunctionDefinition, MessageType};
+    use tokio::test;
+
+    #[test]
+    async fn test_build_payload() {
+        let mistral = Mistral::new().with_options(
+            CallOptions::new()
+                .with_seed(42)
+                .with_functions(vec![FunctionDefinition::new(
+                    "search",
+                    "Searches the web",
+                    json!({"type": "object", "properties": {"q": {"type": "string"}}}),
+                )])
+                .with_tool_choice(FunctionCallBehavior::Required),
+        );
+        let messages = vec![Message::builder()
+            .role(MessageType::HumanMessage)
+            .content("Search rust")
+            .name("alice")
+            .build()];
+
+        let payload = mistral.build_payload(&messages, false).unwrap();
+        let payload = serde_json::to_value(payload).unwrap();
+
+        assert_eq!(payload["random_seed"], json!(42));
+        assert_eq!(payload["tool_choice"], json!("any"));
+        assert_eq!(payload["tools"][0]["function"]["name"], json!("search"));
+        assert!(payload["messages"][0].get("name").is_none());
+        assert!(payload.get("stream").is_none());
+    }
+
+    #[test]
+    async fn test_tool_calls_are_returned_as_function_calls() {
+        let raw = json!({
+            "id": "cmpl-1",
+            "model": "mistral-large-latest",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{
+                        "id": "D681PevKs",
+                        "function": {"name": "search", "arguments": {"q": "rust"}}
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        });
+        let response: ApiResponse = serde_json::from_value(raw.clone()).unwrap();
+
+        let result = generate_result(response, raw).unwrap();
+
+        let calls: Vec<FunctionCallResponse> = serde_json::from_str(&result.generation).unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].type_field, "function");
+        assert_eq!(calls[0].function.arguments, r#"{"q":"rust"}"#);
+        assert_eq!(result.tokens.unwrap().total_tokens, 15);
+        assert_eq!(result.finish_reason, Some(FinishReason::ToolCalls));
+    }
+
+    #[test]
+    async fn test_stream_tool_calls_are_indexed() {
+        let line = r#"data: {"choices": [{"delta": {"tool_calls": [
+            {"id": "a", "function": {"name": "search", "arguments": "{}"}},
+            {"id": "b", "function": {"name": "weather", "arguments": {"city": "Lima"}}}
+        ]}}]}"#
+            .replace('\n', "");
+        let mut tool_calls = 0;
+        let data = parse_stream_line(&line, &mut tool_calls).unwrap().unwrap();
+
+        let mut accumulator = ToolCallAccumulator::new();
+        accumulator.push_chunk(&data.value);
+        let calls = accumulator.finish();
+
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[1].function.name, "weather");
+        assert_eq!(calls[1].function.arguments, r#"{"city":"Lima"}"#);
+        assert!(parse_stream_line("data: [DONE]", &mut tool_calls).is_none());
+    }
+
+    #[test]
+    async fn test_stream_tool_calls_in_separate_chunks() {
+        let lines = [
+            r#"data: {"choices": [{"delta": {"tool_calls": [
+                {"id": "a", "function": {"name": "search", "arguments": {"q": "rust"}}}
+            ]}}]}"#,
+            r#"data: {"choices": [{"delta": {"tool_calls": [
+                {"id": "b", "function": {"name": "search", "arguments": {"q": "go"}}}
+            ]}}]}"#,
+        ];
+
+        let mut tool_calls = 0;
+        let mut accumulator = ToolCallAccumulator::new();
+        for line in lines {
+            let line = line.replace('\n', "");
+            let data = parse_stream_line(&line, &mut tool_calls).unwrap().unwrap();
+            accumulator.push_chunk(&data.value);
+        }
+        let calls = accumulator.finish();
+
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].id, "a");
+        assert_eq!(calls[0].function.name, "search");
+        assert_eq!(calls[0].function.arguments, r#"{"q":"rust"}"#);
+        assert_eq!(calls[1].id, "b");
+        assert_eq!(calls[1].function.arguments, r#"{"q":"go"}"#);
+    }
+
+    #[test]
+    #[ignore]
+    async fn test_mistral_generate() {
+        let mistral = Mistral::new();
+
+        let res = mistral
+            .generate(&[Message::new_human_message("Hi, how are you doing")])
+            .await
+            .unwrap();
+
+        println!("{:?}", res)
+    }
+}
@@ -0,0 +1,16 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MistralError {
+    #[error("Mistral API error: Invalid request - {0}")]
+    InvalidRequestError(String),
+
+    #[error("Mistral API error: Authentication failed - {0}")]
+    AuthenticationError(String),
+
+    #[error("Mistral API error: Rate limit exceeded - {0}")]
+    RateLimitError(String),
+
+    #[error("Mistral API error: Internal error - {0}")]
+    ApiError(String),
+}
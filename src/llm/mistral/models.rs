@@ -0,0 +1,191 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+
+use crate::schemas::{FunctionCallResponse, FunctionDetail, Message, MessageType};
+
+/// A chat message as accepted by Mistral. Unlike OpenAI it has no `name` field, which
+/// Mistral rejects, so the name of the participant is dropped.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct MistralMessage {
+    pub role: String,
+    pub content: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<MistralToolCall>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl MistralMessage {
+    pub fn new<S: Into<String>>(role: S, content: Value) -> Self {
+        Self {
+            role: role.into(),
+            content,
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    pub fn from_message(message: &Message) -> Result<Self, serde_json::Error> {
+        let content = Value::String(message.content.clone());
+        let message = match message.message_type {
+            MessageType::SystemMessage => Self::new("system", content),
+            MessageType::HumanMessage => match &message.images {
+                Some(images) => {
+                    let mut parts = Vec::with_capacity(images.len() + 1);
+                    if !message.content.is_empty() {
+                        parts.push(json!({"type": "text", "text": message.content}));
+                    }
+                    for image in images {
+                        parts.push(json!({"type": "image_url", "image_url": image.image_url}));
+                    }
+                    Self::new("user", Value::Array(parts))
+                }
+                None => Self::new("user", content),
+            },
+            MessageType::AIMessage => {
+                let mut ai_message = Self::new("assistant", content);
+                if let Some(tool_calls) = &message.tool_calls {
+                    let tool_calls: Vec<FunctionCallResponse> =
+                        serde_json::from_value(tool_calls.clone())?;
+                    ai_message.tool_calls =
+                        Some(tool_calls.into_iter().map(MistralToolCall::from).collect());
+                }
+                ai_message
+            }
+            MessageType::ToolMessage => {
+                let mut tool_message = Self::new("tool", content);
+                tool_message.tool_call_id = message.id.clone();
+                tool_message
+            }
+        };
+        Ok(message)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct MistralToolCall {
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "type", default = "function_type")]
+    pub type_field: String,
+    pub function: MistralFunctionCall,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct MistralFunctionCall {
+    pub name: String,
+    /// Mistral may return the arguments as a JSON object instead of a string.
+    #[serde(deserialize_with = "arguments_as_string")]
+    pub arguments: String,
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
+
+fn arguments_as_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(arguments) => arguments,
+        Value::Null => "{}".to_string(),
+        arguments => arguments.to_string(),
+    })
+}
+
+impl From<FunctionCallResponse> for MistralToolCall {
+    fn from(call: FunctionCallResponse) -> Self {
+        Self {
+            id: call.id,
+            type_field: call.type_field,
+            function: MistralFunctionCall {
+                name: call.function.name,
+                arguments: call.function.arguments,
+            },
+        }
+    }
+}
+
+impl From<MistralToolCall> for FunctionCallResponse {
+    fn from(call: MistralToolCall) -> Self {
+        Self {
+            id: call.id,
+            type_field: call.type_field,
+            function: FunctionDetail {
+                name: call.function.name,
+                arguments: call.function.arguments,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Payload {
+    pub model: String,
+    pub messages: Vec<MistralMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub random_seed: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safe_prompt: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct ApiResponse {
+    pub id: String,
+    pub model: String,
+    pub choices: Vec<Choice>,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Choice {
+    pub message: ResponseMessage,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct ResponseMessage {
+    #[serde(default)]
+    pub content: Value,
+    pub tool_calls: Option<Vec<MistralToolCall>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+/// Returns the text of a message content, which is either a string or, for reasoning
+/// models, a list of chunks of which only the `text` ones are kept.
+pub(crate) fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(chunks) => chunks
+            .iter()
+            .filter(|chunk| chunk["type"] == "text")
+            .filter_map(|chunk| chunk["text"].as_str())
+            .collect(),
+        _ => String::new(),
+    }
+}
@@ -4,5 +4,11 @@ pub use openai::*;
 pub mod claude;
 pub use claude::*;
 
+pub mod mistral;
+pub use mistral::*;
+
 pub mod ollama;
 pub use ollama::*;
+
+pub mod fake;
+pub use fake::*;
@@ -1,10 +1,13 @@
 use crate::{
-    language_models::{llm::LLM, GenerateResult, LLMError, TokenUsage},
+    language_models::{
+        llm::LLM, options::CallOptions, truncate_stream_at_stop_words, GenerateResult, LLMError,
+        TokenUsage,
+    },
     schemas::{Message, MessageType, StreamData},
 };
 use async_trait::async_trait;
 use futures::Stream;
-use ollama_rs::generation::images::Image;
+use ollama_rs::generation::{chat::ChatMessageFinalResponseData, images::Image};
 pub use ollama_rs::{
     error::OllamaError,
     generation::{
@@ -22,6 +25,8 @@ pub struct Ollama {
     pub(crate) client: Arc<OllamaClient>,
     pub(crate) model: String,
     pub(crate) options: Option<GenerationOptions>,
+    pub(crate) prefill: Option<String>,
+    pub(crate) stop_words: Option<Vec<String>>,
 }
 
 /// [llama3.2](https://ollama.com/library/llama3.2) is a 3B parameters, 2.0GB model.
@@ -37,6 +42,8 @@ impl Ollama {
             client,
             model: model.into(),
             options,
+            prefill: None,
+            stop_words: None,
         }
     }
 
@@ -50,9 +57,16 @@ impl Ollama {
         self
     }
 
-    fn generate_request(&self, messages: &[Message]) -> ChatMessageRequest {
+    fn generate_request(&self, messages: &[Message]) -> Result<ChatMessageRequest, LLMError> {
+        if self.prefill.is_some() {
+            return Err(LLMError::UnsupportedOption("prefill".to_string()));
+        }
         let mapped_messages = messages.iter().map(|message| message.into()).collect();
-        ChatMessageRequest::new(self.model.clone(), mapped_messages)
+        let mut request = ChatMessageRequest::new(self.model.clone(), mapped_messages);
+        if let Some(options) = &self.options {
+            request = request.options(options.clone());
+        }
+        Ok(request)
     }
 }
 
@@ -87,6 +101,13 @@ impl From<MessageType> for MessageRole {
     }
 }
 
+fn token_usage(final_data: &ChatMessageFinalResponseData) -> TokenUsage {
+    TokenUsage::new(
+        final_data.prompt_eval_count as u32,
+        final_data.eval_count as u32,
+    )
+}
+
 impl Default for Ollama {
     fn default() -> Self {
         let client = Arc::new(OllamaClient::default());
@@ -97,39 +118,37 @@ impl Default for Ollama {
 #[async_trait]
 impl LLM for Ollama {
     async fn generate(&self, messages: &[Message]) -> Result<GenerateResult, LLMError> {
-        let request = self.generate_request(messages);
+        let request = self.generate_request(messages)?;
         let result = self.client.send_chat_messages(request).await?;
+        let raw = serde_json::to_value(&result).ok();
 
         let generation = match result.message {
             Some(message) => message.content,
             None => return Err(OllamaError::from("No message in response".to_string()).into()),
         };
 
-        let tokens = result.final_data.map(|final_data| {
-            let prompt_tokens = final_data.prompt_eval_count as u32;
-            let completion_tokens = final_data.eval_count as u32;
-            TokenUsage {
-                prompt_tokens,
-                completion_tokens,
-                total_tokens: prompt_tokens + completion_tokens,
-            }
-        });
+        let tokens = result.final_data.as_ref().map(token_usage);
 
-        Ok(GenerateResult { tokens, generation })
+        Ok(GenerateResult {
+            tokens,
+            generation,
+            raw,
+            ..Default::default()
+        })
     }
 
     async fn stream(
         &self,
         messages: &[Message],
     ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>>, LLMError> {
-        let request = self.generate_request(messages);
+        let request = self.generate_request(messages)?;
         let result = self.client.send_chat_messages_stream(request).await?;
 
         let stream = result.map(|data| match data {
             Ok(data) => match data.message.clone() {
                 Some(message) => Ok(StreamData::new(
-                    serde_json::to_value(data).unwrap_or_default(),
-                    None,
+                    serde_json::to_value(&data).unwrap_or_default(),
+                    data.final_data.as_ref().map(token_usage),
                     message.content,
                 )),
                 // TODO: no need to return error, see https://github.com/Abraxas-365/langchain-rust/issues/140
@@ -140,7 +159,45 @@ impl LLM for Ollama {
             Err(_) => Err(OllamaError::from("Stream error".to_string()).into()),
         });
 
-        Ok(Box::pin(stream))
+        let stop_words = self.stop_words.clone().unwrap_or_default();
+        Ok(truncate_stream_at_stop_words(Box::pin(stream), &stop_words))
+    }
+
+    /// Maps the model, sampling parameters, stop words, `max_tokens` and `seed` onto the
+    /// `GenerationOptions` set through `with_options`. The stop words are also applied to
+    /// the streamed text, see `truncate_stream_at_stop_words`. Ollama has no assistant
+    /// prefill, so a `prefill` makes every request fail with [`LLMError::UnsupportedOption`].
+    fn add_options(&mut self, options: CallOptions) {
+        if let Some(model) = options.model {
+            self.model = model;
+        }
+        let mut generation_options = self.options.take().unwrap_or_default();
+        if let Some(temperature) = options.temperature {
+            generation_options = generation_options.temperature(temperature);
+        }
+        if let Some(top_p) = options.top_p {
+            generation_options = generation_options.top_p(top_p);
+        }
+        if let Some(top_k) = options.top_k {
+            generation_options = generation_options.top_k(top_k as u32);
+        }
+        if let Some(repetition_penalty) = options.repetition_penalty {
+            generation_options = generation_options.repeat_penalty(repetition_penalty);
+        }
+        if let Some(stop_words) = options.stop_words {
+            generation_options = generation_options.stop(stop_words.clone());
+            self.stop_words = Some(stop_words);
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            generation_options = generation_options.num_predict(max_tokens as i32);
+        }
+        if let Some(seed) = options.seed {
+            generation_options = generation_options.seed(seed as i32);
+        }
+        self.options = Some(generation_options);
+        if options.prefill.is_some() {
+            self.prefill = options.prefill;
+        }
     }
 }
 
@@ -173,4 +230,32 @@ mod tests {
         stdout.write(b"\n").await.unwrap();
         stdout.flush().await.unwrap();
     }
+
+    #[test]
+    fn test_add_options_maps_generation_options() {
+        let mut ollama = Ollama::default();
+        ollama.add_options(
+            CallOptions::new()
+                .with_model("mistral")
+                .with_temperature(0.2)
+                .with_max_tokens(64)
+                .with_stop_words(vec!["\n".to_string()]),
+        );
+        assert_eq!(ollama.model, "mistral");
+        let options = serde_json::to_value(ollama.options.unwrap()).unwrap();
+        assert_eq!(options["temperature"], serde_json::json!(0.2f32));
+        assert_eq!(options["num_predict"], 64);
+        assert_eq!(options["stop"], serde_json::json!(["\n"]));
+    }
+
+    #[tokio::test]
+    async fn test_prefill_is_unsupported() {
+        let mut ollama = Ollama::default();
+        ollama.add_options(CallOptions::new().with_prefill("{"));
+        let err = ollama
+            .generate(&[Message::new_human_message("hi")])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LLMError::UnsupportedOption(option) if option == "prefill"));
+    }
 }
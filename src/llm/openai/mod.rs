@@ -1,54 +1,144 @@
-use std::pin::Pin;
+use std::{collections::HashMap, fmt, pin::Pin};
 
 pub use async_openai::config::{AzureConfig, Config, OpenAIConfig};
 use async_openai::{
     error::OpenAIError,
     types::{
-        ChatChoiceStream, ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessageArgs,
-        ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPartImageArgs,
-        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestToolMessageArgs,
-        ChatCompletionRequestUserMessageArgs, ChatCompletionRequestUserMessageContent,
-        ChatCompletionRequestUserMessageContentPart, ChatCompletionStreamOptions,
-        ChatCompletionToolArgs, ChatCompletionToolType, CreateChatCompletionRequest,
-        CreateChatCompletionRequestArgs, FunctionObjectArgs,
+        ChatChoiceStream, ChatCompletionMessageToolCall, ChatCompletionNamedToolChoice,
+        ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage,
+        ChatCompletionRequestMessageContentPartImageArgs,
+        ChatCompletionRequestMessageContentPartTextArgs, ChatCompletionRequestSystemMessageArgs,
+        ChatCompletionRequestToolMessageArgs, ChatCompletionRequestUserMessageArgs,
+        ChatCompletionRequestUserMessageContent, ChatCompletionRequestUserMessageContentPart,
+        ChatCompletionStreamOptions, ChatCompletionToolArgs, ChatCompletionToolChoiceOption,
+        ChatCompletionToolType, CreateChatCompletionRequest, CreateChatCompletionRequestArgs,
+        CreateChatCompletionResponse, FinishReason as OpenAIFinishReason, FunctionName,
+        FunctionObjectArgs, ImageDetail, ImageUrlArgs,
     },
     Client,
 };
 use async_trait::async_trait;
 use futures::{Stream, StreamExt};
+use serde_json::Value;
 
 use crate::{
-    language_models::{llm::LLM, options::CallOptions, GenerateResult, LLMError, TokenUsage},
+    language_models::{
+        llm::LLM, options::CallOptions, truncate_stream_at_stop_words, FinishReason,
+        GenerateResult, LLMError, TokenUsage,
+    },
     schemas::{
         messages::{Message, MessageType},
         FunctionCallBehavior, StreamData,
     },
 };
 
-#[derive(Clone)]
+/// Maximum number of stop sequences accepted by the chat completions API.
+const MAX_STOP_SEQUENCES: usize = 4;
+
+fn finish_reason(reason: &OpenAIFinishReason) -> FinishReason {
+    match reason {
+        OpenAIFinishReason::Stop => FinishReason::Stop,
+        OpenAIFinishReason::Length => FinishReason::Length,
+        OpenAIFinishReason::ToolCalls | OpenAIFinishReason::FunctionCall => FinishReason::ToolCalls,
+        OpenAIFinishReason::ContentFilter => FinishReason::ContentFilter,
+    }
+}
+
+/// Reads the token counts of an OpenAI usage object, with the cached and reasoning tokens
+/// of `prompt_tokens_details` and `completion_tokens_details` when they are reported.
+fn token_usage<U: serde::Serialize>(usage: &U) -> TokenUsage {
+    let usage = serde_json::to_value(usage).unwrap_or_default();
+    let count = |pointer: &str| {
+        usage
+            .pointer(pointer)
+            .and_then(Value::as_u64)
+            .map(|n| n as u32)
+    };
+    TokenUsage {
+        prompt_tokens: count("/prompt_tokens").unwrap_or_default(),
+        completion_tokens: count("/completion_tokens").unwrap_or_default(),
+        total_tokens: count("/total_tokens").unwrap_or_default(),
+        cached_tokens: count("/prompt_tokens_details/cached_tokens"),
+        reasoning_tokens: count("/completion_tokens_details/reasoning_tokens"),
+    }
+}
+
+fn generate_result(response: CreateChatCompletionResponse) -> GenerateResult {
+    let mut generate_result = GenerateResult {
+        tokens: response.usage.as_ref().map(token_usage),
+        raw: serde_json::to_value(&response).ok(),
+        ..Default::default()
+    };
+
+    if let Some(choice) = &response.choices.first() {
+        generate_result.finish_reason = choice.finish_reason.as_ref().map(finish_reason);
+        generate_result.generation = choice.message.content.clone().unwrap_or_default();
+        if let Some(function) = &choice.message.tool_calls {
+            generate_result.generation = serde_json::to_string(&function).unwrap_or_default();
+        }
+    }
+
+    generate_result
+}
+
+fn image_detail(detail: &str) -> Option<ImageDetail> {
+    match detail {
+        "low" => Some(ImageDetail::Low),
+        "high" => Some(ImageDetail::High),
+        "auto" => Some(ImageDetail::Auto),
+        _ => None,
+    }
+}
+
+/// Convenience names for common models. Any other model id, like a newer model or a
+/// fine-tuned one, can be used through `Custom` or by passing the id as a string to
+/// `OpenAI::with_model`.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum OpenAIModel {
     Gpt35,
     Gpt4,
     Gpt4Turbo,
     Gpt4o,
     Gpt4oMini,
+    Custom(String),
+}
+
+impl fmt::Display for OpenAIModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let model = match self {
+            OpenAIModel::Gpt35 => "gpt-3.5-turbo",
+            OpenAIModel::Gpt4 => "gpt-4",
+            OpenAIModel::Gpt4Turbo => "gpt-4-turbo-preview",
+            OpenAIModel::Gpt4o => "gpt-4o",
+            OpenAIModel::Gpt4oMini => "gpt-4o-mini",
+            OpenAIModel::Custom(model) => model,
+        };
+        f.write_str(model)
+    }
 }
 
-impl ToString for OpenAIModel {
-    fn to_string(&self) -> String {
-        match self {
-            OpenAIModel::Gpt35 => "gpt-3.5-turbo".to_string(),
-            OpenAIModel::Gpt4 => "gpt-4".to_string(),
-            OpenAIModel::Gpt4Turbo => "gpt-4-turbo-preview".to_string(),
-            OpenAIModel::Gpt4o => "gpt-4o".to_string(),
-            OpenAIModel::Gpt4oMini => "gpt-4o-mini".to_string(),
+impl From<&str> for OpenAIModel {
+    fn from(model: &str) -> Self {
+        match model {
+            "gpt-3.5-turbo" => OpenAIModel::Gpt35,
+            "gpt-4" => OpenAIModel::Gpt4,
+            "gpt-4-turbo-preview" => OpenAIModel::Gpt4Turbo,
+            "gpt-4o" => OpenAIModel::Gpt4o,
+            "gpt-4o-mini" => OpenAIModel::Gpt4oMini,
+            other => OpenAIModel::Custom(other.to_string()),
         }
     }
 }
 
-impl Into<String> for OpenAIModel {
-    fn into(self) -> String {
-        self.to_string()
+impl From<String> for OpenAIModel {
+    fn from(model: String) -> Self {
+        OpenAIModel::from(model.as_str())
+    }
+}
+
+impl From<OpenAIModel> for String {
+    fn from(model: OpenAIModel) -> Self {
+        model.to_string()
     }
 }
 
@@ -57,6 +147,7 @@ pub struct OpenAI<C: Config> {
     config: C,
     options: CallOptions,
     model: String,
+    http_client: Option<reqwest::Client>,
 }
 
 impl<C: Config> OpenAI<C> {
@@ -65,6 +156,7 @@ impl<C: Config> OpenAI<C> {
             config,
             options: CallOptions::default(),
             model: OpenAIModel::Gpt4oMini.to_string(),
+            http_client: None,
         }
     }
 
@@ -82,6 +174,57 @@ impl<C: Config> OpenAI<C> {
         self.options = options;
         self
     }
+
+    /// Uses the given `reqwest::Client` for every request instead of a default one.
+    /// This is the place to configure proxies, TLS or default headers.
+    ///
+    /// # Usage
+    /// ```rust,ignore
+    /// let http_client = reqwest::Client::builder()
+    ///     .proxy(reqwest::Proxy::all("http://proxy.internal:8080")?)
+    ///     .build()?;
+    /// let open_ai = OpenAI::default().with_http_client(http_client);
+    /// ```
+    pub fn with_http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    fn client(&self) -> Client<C> {
+        let client = Client::with_config(self.config.clone());
+        match &self.http_client {
+            Some(http_client) => client.with_http_client(http_client.clone()),
+            None => client,
+        }
+    }
+}
+
+/// Any server implementing the OpenAI chat completions API (vLLM, LM Studio, Ollama,
+/// Together, Groq, ...) can be used by pointing the client at its base URL.
+///
+/// Compatible servers usually implement only part of the API. Depending on the server,
+/// `logit_bias`, `stream_options` (`CallOptions::with_stream_usage`), tool calling
+/// (`CallOptions::with_functions`), `tool_choice`, `presence_penalty`/`frequency_penalty` and
+/// image content parts may be rejected or silently ignored, so leave them unset unless the
+/// server documents them.
+///
+/// # Usage
+/// ```rust,ignore
+/// let llm = OpenAI::default()
+///     .with_api_base("http://localhost:8000/v1")
+///     .with_api_key("EMPTY")
+///     .with_model("meta-llama/Meta-Llama-3-8B-Instruct");
+/// ```
+impl OpenAI<OpenAIConfig> {
+    pub fn with_api_base<S: Into<String>>(mut self, api_base: S) -> Self {
+        self.config = self.config.with_api_base(api_base);
+        self
+    }
+
+    pub fn with_api_key<S: Into<String>>(mut self, api_key: S) -> Self {
+        self.config = self.config.with_api_key(api_key);
+        self
+    }
 }
 
 impl Default for OpenAI<OpenAIConfig> {
@@ -92,22 +235,31 @@ impl Default for OpenAI<OpenAIConfig> {
 
 #[async_trait]
 impl<C: Config + Send + Sync + 'static> LLM for OpenAI<C> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "llm.generate",
+            skip_all,
+            fields(
+                model = %self.model,
+                prompt_tokens = tracing::field::Empty,
+                completion_tokens = tracing::field::Empty,
+                total_tokens = tracing::field::Empty,
+            )
+        )
+    )]
     async fn generate(&self, prompt: &[Message]) -> Result<GenerateResult, LLMError> {
-        let client = Client::with_config(self.config.clone());
         let request = self.generate_request(prompt, self.options.streaming_func.is_some())?;
-        match &self.options.streaming_func {
+        #[cfg_attr(not(feature = "tracing"), allow(clippy::let_and_return))]
+        let result = match &self.options.streaming_func {
             Some(func) => {
-                let mut stream = client.chat().create_stream(request).await?;
+                let mut stream = self.client().chat().create_stream(request).await?;
                 let mut generate_result = GenerateResult::default();
                 while let Some(result) = stream.next().await {
                     match result {
                         Ok(response) => {
-                            if let Some(usage) = response.usage {
-                                generate_result.tokens = Some(TokenUsage {
-                                    prompt_tokens: usage.prompt_tokens,
-                                    completion_tokens: usage.completion_tokens,
-                                    total_tokens: usage.total_tokens,
-                                });
+                            if let Some(usage) = &response.usage {
+                                generate_result.tokens = Some(token_usage(usage));
                             }
                             for chat_choice in response.choices.iter() {
                                 let chat_choice: ChatChoiceStream = chat_choice.clone();
@@ -121,6 +273,9 @@ impl<C: Config + Send + Sync + 'static> LLM for OpenAI<C> {
                                 if let Some(content) = chat_choice.delta.content {
                                     generate_result.generation.push_str(&content);
                                 }
+                                if let Some(reason) = chat_choice.finish_reason {
+                                    generate_result.finish_reason = Some(finish_reason(&reason));
+                                }
                             }
                         }
                         Err(err) => {
@@ -130,31 +285,19 @@ impl<C: Config + Send + Sync + 'static> LLM for OpenAI<C> {
                 }
                 Ok(generate_result)
             }
-            None => {
-                let response = client.chat().create(request).await?;
-                let mut generate_result = GenerateResult::default();
-
-                if let Some(usage) = response.usage {
-                    generate_result.tokens = Some(TokenUsage {
-                        prompt_tokens: usage.prompt_tokens,
-                        completion_tokens: usage.completion_tokens,
-                        total_tokens: usage.total_tokens,
-                    });
-                }
-
-                if let Some(choice) = &response.choices.first() {
-                    generate_result.generation = choice.message.content.clone().unwrap_or_default();
-                    if let Some(function) = &choice.message.tool_calls {
-                        generate_result.generation =
-                            serde_json::to_string(&function).unwrap_or_default();
-                    }
-                } else {
-                    generate_result.generation = "".to_string();
-                }
+            None => Ok(generate_result(self.client().chat().create(request).await?)),
+        };
 
-                Ok(generate_result)
-            }
+        #[cfg(feature = "tracing")]
+        if let Ok(GenerateResult {
+            tokens: Some(tokens),
+            ..
+        }) = &result
+        {
+            tokens.record_in_current_span();
         }
+
+        result
     }
 
     async fn invoke(&self, prompt: &str) -> Result<String, LLMError> {
@@ -167,7 +310,7 @@ impl<C: Config + Send + Sync + 'static> LLM for OpenAI<C> {
         &self,
         messages: &[Message],
     ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamData, LLMError>> + Send>>, LLMError> {
-        let client = Client::with_config(self.config.clone());
+        let client = self.client();
         let request = self.generate_request(messages, true)?;
 
         let original_stream = client.chat().create_stream(request).await?;
@@ -177,8 +320,7 @@ impl<C: Config + Send + Sync + 'static> LLM for OpenAI<C> {
                 let value_completion = serde_json::to_value(completion).map_err(LLMError::from)?;
                 let usage = value_completion.pointer("/usage");
                 if usage.is_some() && !usage.unwrap().is_null() {
-                    let usage = serde_json::from_value::<TokenUsage>(usage.unwrap().clone())
-                        .map_err(LLMError::from)?;
+                    let usage = token_usage(usage.unwrap());
                     return Ok(StreamData::new(value_completion, Some(usage), ""));
                 }
                 let content = value_completion
@@ -197,10 +339,17 @@ impl<C: Config + Send + Sync + 'static> LLM for OpenAI<C> {
             Err(e) => Err(LLMError::from(e)),
         });
 
-        Ok(Box::pin(new_stream))
+        let stop_words = self.options.stop_words.clone().unwrap_or_default();
+        Ok(truncate_stream_at_stop_words(
+            Box::pin(new_stream),
+            &stop_words,
+        ))
     }
 
     fn add_options(&mut self, options: CallOptions) {
+        if let Some(model) = &options.model {
+            self.model = model.clone();
+        }
         self.options.merge_options(options)
     }
 }
@@ -213,55 +362,67 @@ impl<C: Config> OpenAI<C> {
         let mut openai_messages: Vec<ChatCompletionRequestMessage> = Vec::new();
         for m in messages {
             match m.message_type {
-                MessageType::AIMessage => openai_messages.push(match &m.tool_calls {
-                    Some(value) => {
+                MessageType::AIMessage => {
+                    let mut message = ChatCompletionRequestAssistantMessageArgs::default();
+                    message.content(m.content.clone());
+                    if let Some(value) = &m.tool_calls {
                         let function: Vec<ChatCompletionMessageToolCall> =
                             serde_json::from_value(value.clone())?;
-                        ChatCompletionRequestAssistantMessageArgs::default()
-                            .tool_calls(function)
-                            .content(m.content.clone())
-                            .build()?
-                            .into()
+                        message.tool_calls(function);
                     }
-                    None => ChatCompletionRequestAssistantMessageArgs::default()
-                        .content(m.content.clone())
-                        .build()?
-                        .into(),
-                }),
+                    if let Some(name) = &m.name {
+                        message.name(name.clone());
+                    }
+                    openai_messages.push(message.build()?.into())
+                }
                 MessageType::HumanMessage => {
                     let content: ChatCompletionRequestUserMessageContent = match m.images.clone() {
                         Some(images) => {
-                            let content: Result<
-                                Vec<ChatCompletionRequestUserMessageContentPart>,
-                                OpenAIError,
-                            > = images
-                                .into_iter()
-                                .map(|image| {
-                                    Ok(ChatCompletionRequestMessageContentPartImageArgs::default()
-                                        .image_url(image.image_url)
+                            let mut parts: Vec<ChatCompletionRequestUserMessageContentPart> =
+                                Vec::with_capacity(images.len() + 1);
+                            if !m.content.is_empty() {
+                                parts.push(
+                                    ChatCompletionRequestMessageContentPartTextArgs::default()
+                                        .text(m.content.clone())
                                         .build()?
-                                        .into())
-                                })
-                                .collect();
+                                        .into(),
+                                );
+                            }
+                            for image in images {
+                                let mut image_url = ImageUrlArgs::default();
+                                image_url.url(image.image_url);
+                                if let Some(detail) = image.detail.as_deref().and_then(image_detail)
+                                {
+                                    image_url.detail(detail);
+                                }
+                                parts.push(
+                                    ChatCompletionRequestMessageContentPartImageArgs::default()
+                                        .image_url(image_url.build()?)
+                                        .build()?
+                                        .into(),
+                                );
+                            }
 
-                            content?.into()
+                            parts.into()
                         }
                         None => m.content.clone().into(),
                     };
 
-                    openai_messages.push(
-                        ChatCompletionRequestUserMessageArgs::default()
-                            .content(content)
-                            .build()?
-                            .into(),
-                    )
+                    let mut message = ChatCompletionRequestUserMessageArgs::default();
+                    message.content(content);
+                    if let Some(name) = &m.name {
+                        message.name(name.clone());
+                    }
+                    openai_messages.push(message.build()?.into())
+                }
+                MessageType::SystemMessage => {
+                    let mut message = ChatCompletionRequestSystemMessageArgs::default();
+                    message.content(m.content.clone());
+                    if let Some(name) = &m.name {
+                        message.name(name.clone());
+                    }
+                    openai_messages.push(message.build()?.into())
                 }
-                MessageType::SystemMessage => openai_messages.push(
-                    ChatCompletionRequestSystemMessageArgs::default()
-                        .content(m.content.clone())
-                        .build()?
-                        .into(),
-                ),
                 MessageType::ToolMessage => {
                     openai_messages.push(
                         ChatCompletionRequestToolMessageArgs::default()
@@ -281,6 +442,9 @@ impl<C: Config> OpenAI<C> {
         messages: &[Message],
         stream: bool,
     ) -> Result<CreateChatCompletionRequest, LLMError> {
+        if self.options.prefill.is_some() {
+            return Err(LLMError::UnsupportedOption("prefill".to_string()));
+        }
         let messages: Vec<ChatCompletionRequestMessage> = self.to_openai_messages(messages)?;
         let mut request_builder = CreateChatCompletionRequestArgs::default();
         if let Some(temperature) = self.options.temperature {
@@ -289,6 +453,19 @@ impl<C: Config> OpenAI<C> {
         if let Some(max_tokens) = self.options.max_tokens {
             request_builder.max_tokens(max_tokens);
         }
+        if let Some(presence_penalty) = self.options.presence_penalty {
+            request_builder.presence_penalty(presence_penalty);
+        }
+        if let Some(frequency_penalty) = self.options.frequency_penalty {
+            request_builder.frequency_penalty(frequency_penalty);
+        }
+        if let Some(logit_bias) = &self.options.logit_bias {
+            let logit_bias: HashMap<String, Value> = logit_bias
+                .iter()
+                .map(|(token, bias)| (token.to_string(), Value::from(*bias)))
+                .collect();
+            request_builder.logit_bias(logit_bias);
+        }
         if stream {
             if let Some(include_usage) = self.options.stream_usage {
                 request_builder.stream_options(ChatCompletionStreamOptions { include_usage });
@@ -296,7 +473,20 @@ impl<C: Config> OpenAI<C> {
         }
         request_builder.model(self.model.to_string());
         if let Some(stop_words) = &self.options.stop_words {
-            request_builder.stop(stop_words);
+            let mut unique_stop_words: Vec<String> = Vec::new();
+            for stop_word in stop_words {
+                if !unique_stop_words.contains(stop_word) {
+                    unique_stop_words.push(stop_word.clone());
+                }
+            }
+            if unique_stop_words.len() > MAX_STOP_SEQUENCES {
+                return Err(LLMError::InvalidOption(format!(
+                    "OpenAI accepts at most {} stop sequences, got {}",
+                    MAX_STOP_SEQUENCES,
+                    unique_stop_words.len()
+                )));
+            }
+            request_builder.stop(unique_stop_words);
         }
 
         if let Some(behavior) = &self.options.functions {
@@ -315,14 +505,23 @@ impl<C: Config> OpenAI<C> {
                 )
             }
             request_builder.tools(functions);
+            if let Some(parallel_tool_calls) = self.options.parallel_tool_calls {
+                request_builder.parallel_tool_calls(parallel_tool_calls);
+            }
         }
 
         if let Some(behavior) = &self.options.function_call_behavior {
-            match behavior {
-                FunctionCallBehavior::Auto => request_builder.tool_choice("auto"),
-                FunctionCallBehavior::None => request_builder.tool_choice("none"),
-                FunctionCallBehavior::Named(name) => request_builder.tool_choice(name.as_str()),
-            };
+            request_builder.tool_choice(match behavior {
+                FunctionCallBehavior::Auto => ChatCompletionToolChoiceOption::Auto,
+                FunctionCallBehavior::None => ChatCompletionToolChoiceOption::None,
+                FunctionCallBehavior::Required => ChatCompletionToolChoiceOption::Required,
+                FunctionCallBehavior::Named(name) => {
+                    ChatCompletionToolChoiceOption::Named(ChatCompletionNamedToolChoice {
+                        r#type: ChatCompletionToolType::Function,
+                        function: FunctionName { name: name.clone() },
+                    })
+                }
+            });
         }
         request_builder.messages(messages);
         Ok(request_builder.build()?)
@@ -331,7 +530,7 @@ impl<C: Config> OpenAI<C> {
 #[cfg(test)]
 mod tests {
 
-    use crate::schemas::FunctionDefinition;
+    use crate::schemas::{FunctionDefinition, ImageSource};
 
     use super::*;
 
@@ -341,6 +540,242 @@ mod tests {
     use tokio::sync::Mutex;
     use tokio::test;
 
+    #[test]
+    async fn test_token_usage_details() {
+        // The typed usage of streamed chunks
+        let usage: async_openai::types::CompletionUsage = serde_json::from_value(json!({
+            "prompt_tokens": 1200,
+            "completion_tokens": 300,
+            "total_tokens": 1500,
+            "prompt_tokens_details": {"cached_tokens": 1024},
+            "completion_tokens_details": {"reasoning_tokens": 256}
+        }))
+        .unwrap();
+        let usage = token_usage(&usage);
+        assert_eq!(usage.total_tokens, 1500);
+        assert_eq!(usage.cached_tokens, Some(1024));
+        assert_eq!(usage.reasoning_tokens, Some(256));
+
+        let usage = token_usage(&json!({
+            "prompt_tokens": 10,
+            "completion_tokens": 5,
+            "total_tokens": 15
+        }));
+        assert_eq!(usage.cached_tokens, None);
+        assert_eq!(usage.sum(&TokenUsage::new(1, 1)).reasoning_tokens, None);
+    }
+
+    #[test]
+    async fn test_generate_result_token_details() {
+        let body = json!({
+            "id": "chatcmpl-AFQH3Vf6pcqLkn4XSQhzQ4VFxKzVc",
+            "object": "chat.completion",
+            "created": 1728316265,
+            "model": "o1-mini-2024-09-12",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "Paris",
+                    "refusal": null
+                },
+                "logprobs": null,
+                "finish_reason": "stop"
+            }],
+            "usage": {
+                "prompt_tokens": 1200,
+                "completion_tokens": 300,
+                "total_tokens": 1500,
+                "prompt_tokens_details": {"cached_tokens": 1024, "audio_tokens": 0},
+                "completion_tokens_details": {"reasoning_tokens": 256, "audio_tokens": 0}
+            },
+            "system_fingerprint": "fp_f7eab99a33"
+        });
+
+        let result = generate_result(serde_json::from_value(body).unwrap());
+        assert_eq!(result.generation, "Paris");
+        assert_eq!(result.finish_reason, Some(FinishReason::Stop));
+        let tokens = result.tokens.unwrap();
+        assert_eq!(tokens.total_tokens, 1500);
+        assert_eq!(tokens.cached_tokens, Some(1024));
+        assert_eq!(tokens.reasoning_tokens, Some(256));
+        assert_eq!(
+            result.raw.unwrap()["usage"]["prompt_tokens_details"]["cached_tokens"],
+            1024
+        );
+    }
+
+    #[test]
+    async fn test_openai_compatible_endpoint() {
+        let open_ai = OpenAI::default()
+            .with_api_base("http://localhost:8000/v1")
+            .with_api_key("EMPTY");
+        assert_eq!(open_ai.config.api_base(), "http://localhost:8000/v1");
+        assert_eq!(
+            open_ai.config.url("/chat/completions"),
+            "http://localhost:8000/v1/chat/completions"
+        );
+    }
+
+    #[test]
+    async fn test_generate_request_validates_stop_words() {
+        let messages = vec![Message::new_human_message("Hello")];
+
+        let options = CallOptions::new()
+            .with_stop_words(vec!["a".into(), "b".into()])
+            .add_stop_word("b")
+            .add_stop_word("c");
+        let request = OpenAI::default()
+            .with_options(options.clone().add_stop_word("d"))
+            .generate_request(&messages, false)
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(request).unwrap()["stop"],
+            json!(["a", "b", "c", "d"])
+        );
+
+        let result = OpenAI::default()
+            .with_options(options.add_stop_word("d").add_stop_word("e"))
+            .generate_request(&messages, false);
+        assert!(matches!(result, Err(LLMError::InvalidOption(_))));
+    }
+
+    #[test]
+    async fn test_generate_request_penalties_and_logit_bias() {
+        let messages = vec![Message::new_human_message("Hello")];
+
+        let open_ai = OpenAI::default();
+        let request =
+            serde_json::to_value(open_ai.generate_request(&messages, false).unwrap()).unwrap();
+        assert!(request.get("presence_penalty").is_none());
+        assert!(request.get("frequency_penalty").is_none());
+        assert!(request.get("logit_bias").is_none());
+
+        let open_ai = OpenAI::default().with_options(
+            CallOptions::new()
+                .with_presence_penalty(0.5)
+                .with_frequency_penalty(0.25)
+                .with_logit_bias(HashMap::from([(50256, -100.0)])),
+        );
+        let request =
+            serde_json::to_value(open_ai.generate_request(&messages, false).unwrap()).unwrap();
+        assert_eq!(request["presence_penalty"], json!(0.5));
+        assert_eq!(request["frequency_penalty"], json!(0.25));
+        assert_eq!(request["logit_bias"], json!({"50256": -100.0}));
+    }
+
+    #[test]
+    async fn test_custom_model() {
+        let model = OpenAIModel::Custom("ft:gpt-4o-mini:acme::abc123".into());
+        assert_eq!(model.to_string(), "ft:gpt-4o-mini:acme::abc123");
+        assert_eq!(OpenAIModel::from("gpt-4o"), OpenAIModel::Gpt4o);
+        assert_eq!(
+            OpenAIModel::from("gpt-4.1"),
+            OpenAIModel::Custom("gpt-4.1".into())
+        );
+
+        let open_ai = OpenAI::default().with_model(model);
+        let request = open_ai
+            .generate_request(&[Message::new_human_message("Hello")], false)
+            .unwrap();
+        assert_eq!(request.model, "ft:gpt-4o-mini:acme::abc123");
+    }
+
+    #[test]
+    async fn test_generate_request_tool_choice() {
+        let messages = vec![Message::new_human_message("Hello")];
+
+        let open_ai = OpenAI::default()
+            .with_options(CallOptions::new().with_tool_choice(FunctionCallBehavior::Required));
+        let request =
+            serde_json::to_value(open_ai.generate_request(&messages, false).unwrap()).unwrap();
+        assert_eq!(request["tool_choice"], json!("required"));
+
+        let open_ai = OpenAI::default().with_options(
+            CallOptions::new().with_tool_choice(FunctionCallBehavior::Named("search".into())),
+        );
+        let request =
+            serde_json::to_value(open_ai.generate_request(&messages, false).unwrap()).unwrap();
+        assert_eq!(
+            request["tool_choice"],
+            json!({"type": "function", "function": {"name": "search"}})
+        );
+    }
+
+    #[test]
+    async fn test_generate_request_parallel_tool_calls() {
+        let messages = vec![Message::new_human_message("Hello")];
+
+        let request = serde_json::to_value(
+            OpenAI::default()
+                .with_options(CallOptions::new().with_parallel_tool_calls(false))
+                .generate_request(&messages, false)
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(request["parallel_tool_calls"].is_null());
+
+        let function = FunctionDefinition::new("search", "Search the web", json!({}));
+        let request = serde_json::to_value(
+            OpenAI::default()
+                .with_options(
+                    CallOptions::new()
+                        .with_functions(vec![function])
+                        .with_parallel_tool_calls(false),
+                )
+                .generate_request(&messages, false)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(request["parallel_tool_calls"], json!(false));
+    }
+
+    #[test]
+    async fn test_generate_request_with_image_parts() {
+        let messages = vec![Message::new_human_message_with_images(
+            "What is in this image?",
+            vec![
+                ImageSource::url("https://example.com/cat.png"),
+                ImageSource::base64("image/png", "aGVsbG8="),
+            ],
+        )];
+
+        let open_ai = OpenAI::default();
+        let request =
+            serde_json::to_value(open_ai.generate_request(&messages, false).unwrap()).unwrap();
+        let content = &request["messages"][0]["content"];
+        assert_eq!(content[0]["type"], json!("text"));
+        assert_eq!(content[0]["text"], json!("What is in this image?"));
+        assert_eq!(content[1]["type"], json!("image_url"));
+        assert_eq!(
+            content[1]["image_url"]["url"],
+            json!("https://example.com/cat.png")
+        );
+        assert_eq!(
+            content[2]["image_url"]["url"],
+            json!("data:image/png;base64,aGVsbG8=")
+        );
+    }
+
+    #[test]
+    async fn test_generate_request_with_message_names() {
+        let messages = vec![
+            Message::new_system_message("Moderate the debate").with_name("moderator"),
+            Message::new_human_message("I think so")
+                .with_name("alice")
+                .with_metadata(json!({"trace_id": "1"})),
+            Message::new_ai_message("I don't"),
+        ];
+
+        let open_ai = OpenAI::default();
+        let request =
+            serde_json::to_value(open_ai.generate_request(&messages, false).unwrap()).unwrap();
+        assert_eq!(request["messages"][0]["name"], json!("moderator"));
+        assert_eq!(request["messages"][1]["name"], json!("alice"));
+        assert!(request["messages"][1].get("metadata").is_none());
+        assert!(request["messages"][2].get("name").is_none());
+    }
+
     #[test]
     #[ignore]
     async fn test_invoke() {
@@ -497,11 +932,10 @@ mod tests {
         let image_base64 = BASE64_STANDARD.encode(image);
 
         // Define a set of messages to send to the generate function
-        let image_urls = vec![format!("data:image/jpeg;base64,{image_base64}")];
-        let messages = vec![
-            Message::new_human_message("Describe this image"),
-            Message::new_human_message_with_images(image_urls),
-        ];
+        let messages = vec![Message::new_human_message_with_images(
+            "Describe this image",
+            vec![ImageSource::base64("image/jpeg", image_base64)],
+        )];
 
         // Call the generate function
         let response = open_ai.generate(&messages).await.unwrap();
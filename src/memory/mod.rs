@@ -1,7 +1,14 @@
 mod dummy_memory;
 mod simple_memory;
+mod summary_memory;
 mod window_buffer;
 
 pub use dummy_memory::*;
 pub use simple_memory::*;
+pub use summary_memory::*;
 pub use window_buffer::*;
+
+#[cfg(feature = "sqlite")]
+mod sqlite_memory;
+#[cfg(feature = "sqlite")]
+pub use sqlite_memory::*;
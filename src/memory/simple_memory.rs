@@ -6,14 +6,36 @@ use crate::schemas::{memory::BaseMemory, messages::Message};
 
 pub struct SimpleMemory {
     messages: Vec<Message>,
+    dedup: bool,
 }
 
 impl SimpleMemory {
     pub fn new() -> Self {
         Self {
             messages: Vec::new(),
+            dedup: false,
         }
     }
+
+    /// When `true`, a message identical to the one just before it, e.g. a user message
+    /// added again by a retry, is dropped. Older messages are not compared. Defaults to
+    /// `false`. The other memories don't deduplicate messages.
+    pub fn with_dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+}
+
+/// Whether two messages have the same role, content, name and tool calls. Ids are not
+/// compared, as a retried message may get a new one. Messages with images are never
+/// considered identical.
+fn is_same_message(a: &Message, b: &Message) -> bool {
+    a.message_type == b.message_type
+        && a.content == b.content
+        && a.name == b.name
+        && a.tool_calls == b.tool_calls
+        && a.images.is_none()
+        && b.images.is_none()
 }
 
 impl Into<Arc<dyn BaseMemory>> for SimpleMemory {
@@ -33,9 +55,53 @@ impl BaseMemory for SimpleMemory {
         self.messages.clone()
     }
     fn add_message(&mut self, message: Message) {
+        if self.dedup
+            && self
+                .messages
+                .last()
+                .is_some_and(|last| is_same_message(last, &message))
+        {
+            return;
+        }
         self.messages.push(message);
     }
     fn clear(&mut self) {
         self.messages.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::schemas::messages::MessageType;
+
+    use super::*;
+
+    #[test]
+    fn test_dedup_drops_consecutive_duplicates() {
+        let mut memory = SimpleMemory::new().with_dedup(true);
+        memory.add_user_message(&"Hi");
+        let mut retried = Message::new_human_message("Hi");
+        retried.id = Some("retry".to_string());
+        memory.add_message(retried);
+        memory.add_ai_message(&"Hi");
+        memory.add_user_message(&"Hi");
+        let types: Vec<_> = memory
+            .messages()
+            .iter()
+            .map(|m| m.message_type.clone())
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                MessageType::HumanMessage,
+                MessageType::AIMessage,
+                MessageType::HumanMessage
+            ]
+        );
+
+        let mut memory = SimpleMemory::new();
+        memory.add_user_message(&"Hi");
+        memory.add_user_message(&"Hi");
+        assert_eq!(memory.messages().len(), 2);
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    future::Future,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    Pool, Row, Sqlite,
+};
+use tokio::{runtime::Handle, sync::Mutex};
+
+use crate::schemas::{memory::BaseMemory, messages::Message};
+
+const TABLE: &str = "chat_messages";
+
+/// Conversation memory persisted to a local SQLite database, keeping the messages of each
+/// session id with the time they were added. The schema is created on first use.
+///
+/// Every call reads or writes the database through the pool, so a message is stored when
+/// `add_message` returns and the messages added by other memories of the same session are
+/// seen. As `BaseMemory` is synchronous, the calls block the current thread with
+/// `tokio::task::block_in_place`, which needs the multi-thread tokio runtime.
+///
+/// # Usage
+/// ```rust,ignore
+/// let memory = SqliteMemory::try_new("sqlite://chat.db", "user-42").await?;
+/// let chain = ConversationalChainBuilder::new()
+///     .llm(llm)
+///     .memory(memory.into())
+///     .build()?;
+/// ```
+pub struct SqliteMemory {
+    pool: Pool<Sqlite>,
+    session_id: String,
+}
+
+impl SqliteMemory {
+    /// Opens the database at `connection_url`, like `sqlite://chat.db`, creating the file
+    /// if needed.
+    pub async fn try_new<S: Into<String>>(
+        connection_url: &str,
+        session_id: S,
+    ) -> Result<Self, sqlx::Error> {
+        let pool = SqlitePoolOptions::new()
+            .connect_with(
+                SqliteConnectOptions::from_str(connection_url)?
+                    .create_if_missing(true)
+                    .journal_mode(SqliteJournalMode::Wal)
+                    .busy_timeout(Duration::from_secs(5)),
+            )
+            .await?;
+        Self::from_pool(pool, session_id).await
+    }
+
+    /// Same as `try_new`, sharing an existing pool, e.g. between the memories of several
+    /// sessions.
+    pub async fn from_pool<S: Into<String>>(
+        pool: Pool<Sqlite>,
+        session_id: S,
+    ) -> Result<Self, sqlx::Error> {
+        create_schema(&pool).await?;
+        Ok(Self {
+            pool,
+            session_id: session_id.into(),
+        })
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Returns the ids of the sessions with stored messages, the most recent first.
+    pub async fn list_sessions(&self) -> Result<Vec<String>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT session_id FROM {} GROUP BY session_id ORDER BY MAX(created_at) DESC",
+            TABLE
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().map(|row| row.get("session_id")).collect())
+    }
+
+    /// Deletes the stored messages of a session.
+    pub async fn delete_session(&self, session_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(&format!("DELETE FROM {} WHERE session_id = ?", TABLE))
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the stored messages of the session, in the order they were added.
+    pub async fn load_messages(&self) -> Result<Vec<Message>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT message FROM {} WHERE session_id = ? ORDER BY id",
+            TABLE
+        ))
+        .bind(&self.session_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let message: String = row.get("message");
+                serde_json::from_str(&message)
+                    .map_err(|e| log::warn!("Skipping an unreadable stored message: {}", e))
+                    .ok()
+            })
+            .collect())
+    }
+
+    async fn insert_message(&self, message: &Message) -> Result<(), Box<dyn std::error::Error>> {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as i64)
+            .unwrap_or_default();
+        sqlx::query(&format!(
+            "INSERT INTO {} (session_id, message, created_at) VALUES (?, ?, ?)",
+            TABLE
+        ))
+        .bind(&self.session_id)
+        .bind(serde_json::to_string(message)?)
+        .bind(created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Runs `future` to completion from the synchronous `BaseMemory` methods.
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| Handle::current().block_on(future))
+}
+
+async fn create_schema(pool: &Pool<Sqlite>) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id TEXT NOT NULL,
+            message TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        table = TABLE
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::query(&format!(
+        "CREATE INDEX IF NOT EXISTS {table}_session_id ON {table} (session_id, id)",
+        table = TABLE
+    ))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+impl Into<Arc<dyn BaseMemory>> for SqliteMemory {
+    fn into(self) -> Arc<dyn BaseMemory> {
+        Arc::new(self)
+    }
+}
+
+impl Into<Arc<Mutex<dyn BaseMemory>>> for SqliteMemory {
+    fn into(self) -> Arc<Mutex<dyn BaseMemory>> {
+        Arc::new(Mutex::new(self))
+    }
+}
+
+impl BaseMemory for SqliteMemory {
+    fn messages(&self) -> Vec<Message> {
+        block_on(self.load_messages()).unwrap_or_else(|e| {
+            log::error!(
+                "Could not read the chat history of session {}: {}",
+                self.session_id,
+                e
+            );
+            Vec::new()
+        })
+    }
+
+    fn add_message(&mut self, message: Message) {
+        if let Err(e) = block_on(self.insert_message(&message)) {
+            log::error!(
+                "Could not persist the chat history of session {}: {}",
+                self.session_id,
+                e
+            );
+        }
+    }
+
+    fn clear(&mut self) {
+        if let Err(e) = block_on(self.delete_session(&self.session_id)) {
+            log::error!(
+                "Could not clear the chat history of session {}: {}",
+                self.session_id,
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sqlite_memory_persists_sessions() {
+        // A single connection, as each connection to `:memory:` opens its own database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        let mut memory = SqliteMemory::from_pool(pool.clone(), "alice")
+            .await
+            .unwrap();
+        memory.add_user_message(&"Hi");
+        let mut other = SqliteMemory::from_pool(pool.clone(), "bob").await.unwrap();
+        other.add_user_message(&"Hey");
+
+        let mut shared = SqliteMemory::from_pool(pool.clone(), "alice")
+            .await
+            .unwrap();
+        shared.add_ai_message(&"Hello!");
+        assert_eq!(memory.messages().len(), 2);
+        assert_eq!(memory.messages()[1].content, "Hello!");
+        let mut sessions = memory.list_sessions().await.unwrap();
+        sessions.sort();
+        assert_eq!(sessions, vec!["alice".to_string(), "bob".to_string()]);
+
+        memory.delete_session("bob").await.unwrap();
+        assert!(other.messages().is_empty());
+        shared.clear();
+        assert!(memory.messages().is_empty());
+        assert!(memory.list_sessions().await.unwrap().is_empty());
+    }
+}
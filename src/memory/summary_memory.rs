@@ -0,0 +1,328 @@
+use std::sync::{Arc, Mutex as StdMutex};
+
+use tiktoken_rs::{get_bpe_from_tokenizer, tokenizer::Tokenizer, CoreBPE};
+use tokio::sync::Mutex;
+
+use crate::{
+    language_models::{context_window_or_default, llm::LLM},
+    schemas::{memory::BaseMemory, messages::Message},
+};
+
+const DEFAULT_SUMMARY_PROMPT: &str = "Progressively summarize the lines of conversation \
+provided, adding onto the previous summary and returning a new summary. Keep names, facts \
+and decisions that may be needed later.
+
+Current summary:
+{summary}
+
+New lines of conversation:
+{new_lines}
+
+New summary:";
+
+#[derive(Default)]
+struct SummaryState {
+    summary: String,
+    /// Messages being summarized, still returned verbatim until the summary is ready.
+    summarizing: Vec<Message>,
+    messages: Vec<Message>,
+    /// Incremented by `clear`, so a summarization started before it doesn't write back.
+    epoch: u64,
+}
+
+/// Memory that keeps the conversation under `max_token_limit` tokens by summarizing the
+/// oldest messages with an LLM, while the last `keep_recent` messages are always kept
+/// verbatim. The summary is returned as a system message before the remaining messages.
+///
+/// Summarization runs in a background task when a message pushes the memory over the
+/// limit, as `BaseMemory` is synchronous. The task is spawned on the tokio runtime of the
+/// caller of `add_message`; without one, or if summarizing fails, the messages are kept as
+/// they are.
+///
+/// # Usage
+/// ```rust,ignore
+/// let memory = ConversationSummaryMemory::new(OpenAI::default())
+///     .with_max_token_limit(1000)
+///     .with_keep_recent(4)
+///     .with_tokenizer(Tokenizer::Cl100kBase);
+/// ```
+pub struct ConversationSummaryMemory {
+    llm: Arc<dyn LLM>,
+    tokenizer: Option<Arc<CoreBPE>>,
+    max_token_limit: Option<usize>,
+    context_model: Option<String>,
+    keep_recent: usize,
+    prompt: String,
+    state: Arc<StdMutex<SummaryState>>,
+}
+
+impl ConversationSummaryMemory {
+    pub fn new<L: Into<Box<dyn LLM>>>(llm: L) -> Self {
+        Self {
+            llm: Arc::from(llm.into()),
+            tokenizer: None,
+            max_token_limit: None,
+            context_model: None,
+            keep_recent: 4,
+            prompt: DEFAULT_SUMMARY_PROMPT.to_string(),
+            state: Arc::new(StdMutex::new(SummaryState::default())),
+        }
+    }
+
+    /// Tokens of the raw messages above which older messages are summarized.
+    /// Defaults to a quarter of the context window set with `with_context_model`, or 2000.
+    pub fn with_max_token_limit(mut self, max_token_limit: usize) -> Self {
+        self.max_token_limit = Some(max_token_limit);
+        self
+    }
+
+    /// The model the conversation is sent to. Unless `with_max_token_limit` is set, the
+    /// memory is summarized once it fills a quarter of the model context window, as given
+    /// by `context_window_or_default`.
+    pub fn with_context_model<S: Into<String>>(mut self, model: S) -> Self {
+        self.context_model = Some(model.into());
+        self
+    }
+
+    /// Number of recent messages never summarized. Defaults to 4.
+    pub fn with_keep_recent(mut self, keep_recent: usize) -> Self {
+        self.keep_recent = keep_recent;
+        self
+    }
+
+    /// Counts tokens with `tokenizer` instead of estimating 4 characters per token.
+    pub fn with_tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        match get_bpe_from_tokenizer(tokenizer) {
+            Ok(bpe) => self.tokenizer = Some(Arc::new(bpe)),
+            Err(e) => log::warn!("Could not load tokenizer, estimating tokens instead: {}", e),
+        }
+        self
+    }
+
+    /// Replaces the summarization prompt, which receives `{summary}` and `{new_lines}`.
+    pub fn with_prompt<S: Into<String>>(mut self, prompt: S) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+
+    /// The summary of the messages no longer kept verbatim.
+    pub fn summary(&self) -> String {
+        self.state.lock().unwrap().summary.clone()
+    }
+
+    /// Whether a summarization is in progress.
+    pub fn is_summarizing(&self) -> bool {
+        !self.state.lock().unwrap().summarizing.is_empty()
+    }
+
+    fn max_token_limit(&self) -> usize {
+        self.max_token_limit
+            .unwrap_or_else(|| match &self.context_model {
+                Some(model) => context_window_or_default(model) / 4,
+                None => 2000,
+            })
+    }
+
+    fn count_tokens(&self, messages: &[Message]) -> usize {
+        messages
+            .iter()
+            .map(|message| match &self.tokenizer {
+                Some(bpe) => bpe.encode_ordinary(&message.content).len(),
+                None => message.content.chars().count().div_ceil(4),
+            })
+            .sum()
+    }
+
+    fn maybe_summarize(&self) {
+        let (summary, to_summarize, epoch) = {
+            let mut state = self.state.lock().unwrap();
+            if !state.summarizing.is_empty()
+                || state.messages.len() <= self.keep_recent
+                || self.count_tokens(&state.messages) <= self.max_token_limit()
+            {
+                return;
+            }
+            let split = state.messages.len() - self.keep_recent;
+            let to_summarize: Vec<Message> = state.messages.drain(..split).collect();
+            state.summarizing = to_summarize.clone();
+            (state.summary.clone(), to_summarize, state.epoch)
+        };
+
+        let handle = match tokio::runtime::Handle::try_current() {
+            Ok(handle) => handle,
+            Err(_) => {
+                log::warn!("No tokio runtime to summarize the memory, keeping raw messages");
+                restore(&self.state, epoch);
+                return;
+            }
+        };
+        let new_lines = to_summarize
+            .iter()
+            .map(|message| format!("{}: {}", message.message_type.to_string(), message.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = self
+            .prompt
+            .replace("{summary}", &summary)
+            .replace("{new_lines}", &new_lines);
+        let llm = self.llm.clone();
+        let state = self.state.clone();
+        handle.spawn(async move {
+            match llm.invoke(&prompt).await {
+                Ok(summary) => {
+                    let mut state = state.lock().unwrap();
+                    if state.epoch != epoch {
+                        return;
+                    }
+                    state.summary = summary.trim().to_string();
+                    state.summarizing.clear();
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Could not summarize the memory, keeping raw messages: {}",
+                        e
+                    );
+                    restore(&state, epoch);
+                }
+            }
+        });
+    }
+}
+
+/// Puts the messages being summarized back in front of the raw messages, unless the memory
+/// was cleared since the summarization started.
+fn restore(state: &StdMutex<SummaryState>, epoch: u64) {
+    let mut state = state.lock().unwrap();
+    if state.epoch != epoch {
+        return;
+    }
+    let mut messages = std::mem::take(&mut state.summarizing);
+    messages.append(&mut state.messages);
+    state.messages = messages;
+}
+
+impl Into<Arc<dyn BaseMemory>> for ConversationSummaryMemory {
+    fn into(self) -> Arc<dyn BaseMemory> {
+        Arc::new(self)
+    }
+}
+
+impl Into<Arc<Mutex<dyn BaseMemory>>> for ConversationSummaryMemory {
+    fn into(self) -> Arc<Mutex<dyn BaseMemory>> {
+        Arc::new(Mutex::new(self))
+    }
+}
+
+impl BaseMemory for ConversationSummaryMemory {
+    fn messages(&self) -> Vec<Message> {
+        let state = self.state.lock().unwrap();
+        let mut messages = Vec::new();
+        if !state.summary.is_empty() {
+            messages.push(Message::new_system_message(format!(
+                "Summary of the earlier conversation:\n{}",
+                state.summary
+            )));
+        }
+        messages.extend(state.summarizing.iter().cloned());
+        messages.extend(state.messages.iter().cloned());
+        messages
+    }
+
+    fn add_message(&mut self, message: Message) {
+        self.state.lock().unwrap().messages.push(message);
+        self.maybe_summarize();
+    }
+
+    fn clear(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.summary.clear();
+        state.summarizing.clear();
+        state.messages.clear();
+        state.epoch += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::fake::FakeLLM;
+
+    use super::*;
+
+    async fn wait_for_summary(memory: &ConversationSummaryMemory) {
+        while memory.is_summarizing() {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_summary_memory() {
+        let llm = FakeLLM::new().with_responses(vec!["The user said hello twice."]);
+        let mut memory = ConversationSummaryMemory::new(llm.clone())
+            .with_max_token_limit(10)
+            .with_keep_recent(2);
+
+        memory.add_user_message(&"Hello there, how are you?");
+        memory.add_ai_message(&"Fine");
+        assert!(llm.calls().is_empty());
+
+        memory.add_user_message(&"Hello again, are you still there?");
+        wait_for_summary(&memory).await;
+
+        let messages = memory.messages();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].content.ends_with("The user said hello twice."));
+        assert_eq!(messages[1].content, "Fine");
+        assert_eq!(messages[2].content, "Hello again, are you still there?");
+        assert!(llm.last_call().unwrap()[0]
+            .content
+            .contains("Hello there, how are you?"));
+    }
+
+    #[tokio::test]
+    async fn test_summary_failure_keeps_messages() {
+        let mut memory = ConversationSummaryMemory::new(FakeLLM::new())
+            .with_max_token_limit(1)
+            .with_keep_recent(1);
+
+        memory.add_user_message(&"Hello there");
+        memory.add_ai_message(&"Hi");
+        wait_for_summary(&memory).await;
+
+        assert_eq!(memory.summary(), "");
+        assert_eq!(memory.messages().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_clear_during_summary() {
+        let llm = FakeLLM::new().with_responses(vec!["The user said hello."]);
+        let mut memory = ConversationSummaryMemory::new(llm.clone())
+            .with_max_token_limit(1)
+            .with_keep_recent(1);
+
+        memory.add_user_message(&"Hello there");
+        memory.add_ai_message(&"Hi");
+        assert!(memory.is_summarizing());
+
+        memory.clear();
+        assert!(memory.messages().is_empty());
+        while llm.calls().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        tokio::task::yield_now().await;
+
+        assert_eq!(memory.summary(), "");
+        assert!(memory.messages().is_empty());
+    }
+
+    #[test]
+    fn test_max_token_limit_from_context_window() {
+        let memory = ConversationSummaryMemory::new(FakeLLM::new());
+        assert_eq!(memory.max_token_limit(), 2000);
+
+        let memory = memory.with_context_model("gpt-4-0613");
+        assert_eq!(memory.max_token_limit(), 2048);
+
+        let memory = memory.with_max_token_limit(500);
+        assert_eq!(memory.max_token_limit(), 500);
+    }
+}
@@ -1,11 +1,35 @@
 use regex::Error as RegexError;
 use thiserror::Error;
 
+use crate::{language_models::LLMError, prompt::PromptError};
+
 #[derive(Error, Debug)]
 pub enum OutputParserError {
     #[error("Regex error: {0}")]
     RegexError(#[from] RegexError),
 
+    /// The output contains no JSON, or JSON that can't be repaired.
+    #[error("Invalid JSON in output: {0}")]
+    InvalidJson(String),
+
+    /// The output is valid JSON, but not of the expected shape.
+    #[error("Output doesn't match the expected schema: {0}")]
+    SchemaMismatch(String),
+
+    #[error("Missing field {field} in output: {output}")]
+    MissingField { field: String, output: String },
+
+    /// The pattern the parser looks for, like a code block, isn't in the output.
+    #[error("No {0} found in output")]
+    NoMatch(String),
+
     #[error("Parsing error: {0}")]
     ParsingError(String),
+
+    /// The LLM asked to fix the output failed, e.g. in `RetryOutputParser`.
+    #[error("LLM error: {0}")]
+    LLMError(#[from] LLMError),
+
+    #[error("Prompt error: {0}")]
+    PromptError(#[from] PromptError),
 }
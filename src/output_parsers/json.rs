@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+
+use serde_json::Value;
+
+/// Parses `s` as JSON. Unless `strict`, unclosed objects and arrays are closed first,
+/// which allows reading truncated or still streaming model output.
+pub(crate) fn parse_partial_json(s: &str, strict: bool) -> Option<Value> {
+    // First, attempt to parse the string as-is.
+    match serde_json::from_str::<Value>(s) {
+        Ok(val) => return Some(val),
+        Err(_) if !strict => (),
+        Err(_) => return None,
+    }
+
+    let mut new_s = String::new();
+    let mut stack: VecDeque<char> = VecDeque::new();
+    let mut is_inside_string = false;
+    let mut escaped = false;
+
+    for char in s.chars() {
+        match char {
+            '"' if !escaped => is_inside_string = !is_inside_string,
+            '{' if !is_inside_string => stack.push_back('}'),
+            '[' if !is_inside_string => stack.push_back(']'),
+            '}' | ']' if !is_inside_string => {
+                if let Some(c) = stack.pop_back() {
+                    if c != char {
+                        return None; // Mismatched closing character
+                    }
+                } else {
+                    return None; // Unbalanced closing character
+                }
+            }
+            '\\' if is_inside_string => escaped = !escaped,
+            _ => escaped = false,
+        }
+        new_s.push(char);
+    }
+
+    // Close any open structures.
+    while let Some(c) = stack.pop_back() {
+        new_s.push(c);
+    }
+
+    // Attempt to parse again.
+    serde_json::from_str(&new_s).ok()
+}
+
+/// Parses a model output holding JSON, either the whole output or the JSON of the markdown
+/// block it contains, see `extract_partial_json_block`.
+pub(crate) fn parse_json_output(output: &str) -> Option<Value> {
+    parse_partial_json(output.trim(), false).or_else(|| {
+        extract_partial_json_block(output).and_then(|json| parse_partial_json(json, false))
+    })
+}
+
+/// Returns the JSON of a possibly unterminated markdown block, or the text from the
+/// first `{` when there is no code fence.
+pub(crate) fn extract_partial_json_block(text: &str) -> Option<&str> {
+    let start = match text.find("```") {
+        Some(fence) => {
+            let after_fence = &text[fence + 3..];
+            let after_fence = after_fence.strip_prefix("json").unwrap_or(after_fence);
+            fence + (text.len() - fence - 3 - after_fence.len()) + 3
+        }
+        None => text.find('{')?,
+    };
+    let block = &text[start..];
+    let block = match block.find("```") {
+        Some(end) => &block[..end],
+        None => block,
+    };
+    let block = block.trim();
+    if block.is_empty() {
+        None
+    } else {
+        Some(block)
+    }
+}
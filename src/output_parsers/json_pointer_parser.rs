@@ -0,0 +1,93 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::{parse_json_output, OutputParser, OutputParserError};
+
+/// Parses the output as JSON, also inside a markdown code block, and returns the value at
+/// a [JSON Pointer](https://datatracker.ietf.org/doc/html/rfc6901). Strings are returned
+/// as is, any other value as JSON.
+///
+/// # Usage
+/// ```rust,ignore
+/// let parser = JsonPointerParser::new("/choices/0/answer");
+/// let answer = parser.parse(r#"{"choices": [{"answer": "42"}]}"#).await?;
+/// assert_eq!(answer, "42");
+/// ```
+pub struct JsonPointerParser {
+    pointer: String,
+}
+
+impl JsonPointerParser {
+    pub fn new<S: Into<String>>(pointer: S) -> Self {
+        Self {
+            pointer: pointer.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl OutputParser for JsonPointerParser {
+    async fn parse(&self, output: &str) -> Result<String, OutputParserError> {
+        let value = parse_json_output(output)
+            .ok_or_else(|| OutputParserError::InvalidJson(output.to_string()))?;
+
+        match value.pointer(&self.pointer) {
+            Some(Value::String(text)) => Ok(text.clone()),
+            Some(value) => Ok(value.to_string()),
+            None => Err(OutputParserError::MissingField {
+                field: self.pointer.clone(),
+                output: value.to_string(),
+            }),
+        }
+    }
+
+    fn get_format_instructions(&self) -> String {
+        "Respond with a JSON object.".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_json_pointer_parser() {
+        let output = r#"Here you go:
+```json
+{"choices": [{"answer": "42", "meta": {"n": 1}}]}
+```"#;
+
+        let answer = JsonPointerParser::new("/choices/0/answer")
+            .parse(output)
+            .await
+            .unwrap();
+        assert_eq!(answer, "42");
+
+        let meta = JsonPointerParser::new("/choices/0/meta")
+            .parse(output)
+            .await
+            .unwrap();
+        assert_eq!(meta, "{\"n\":1}");
+
+        let truncated = JsonPointerParser::new("/answer")
+            .parse(r#"{"answer": "partial", "sources": [1, 2"#)
+            .await
+            .unwrap();
+        assert_eq!(truncated, "partial");
+
+        let err = JsonPointerParser::new("/choices/1")
+            .parse(output)
+            .await
+            .unwrap_err();
+        match err {
+            OutputParserError::MissingField { field, .. } => assert_eq!(field, "/choices/1"),
+            err => panic!("unexpected error: {}", err),
+        }
+
+        let err = JsonPointerParser::new("/answer")
+            .parse("no json here")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, OutputParserError::InvalidJson(_)));
+    }
+}
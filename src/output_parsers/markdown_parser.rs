@@ -43,9 +43,7 @@ impl OutputParser for MarkdownParser {
                 Ok(find)
             }
         } else {
-            Err(OutputParserError::ParsingError(
-                "No code block found".into(),
-            ))
+            Err(OutputParserError::NoMatch("code block".into()))
         }
     }
 }
@@ -7,5 +7,14 @@ pub use markdown_parser::*;
 mod simple_parser;
 pub use simple_parser::*;
 
+mod retry_parser;
+pub use retry_parser::*;
+
+mod json_pointer_parser;
+pub use json_pointer_parser::*;
+
+mod json;
+pub(crate) use json::*;
+
 mod error;
 pub use error::*;
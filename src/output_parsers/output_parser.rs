@@ -5,6 +5,11 @@ use super::OutputParserError;
 #[async_trait]
 pub trait OutputParser: Send + Sync {
     async fn parse(&self, output: &str) -> Result<String, OutputParserError>;
+
+    /// Instructions describing the format the parser expects, to be included in prompts.
+    fn get_format_instructions(&self) -> String {
+        String::new()
+    }
 }
 
 impl<P> From<P> for Box<dyn OutputParser>
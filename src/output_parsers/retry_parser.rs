@@ -0,0 +1,131 @@
+use async_trait::async_trait;
+
+use crate::{language_models::llm::LLM, prompt::PromptFromatter, prompt_args, template_fstring};
+
+use super::{OutputParser, OutputParserError};
+
+const FIX_PROMPT: &str = r#"Instructions:
+--------------
+{instructions}
+--------------
+Completion:
+--------------
+{completion}
+--------------
+
+Above, the Completion did not satisfy the constraints given in the Instructions.
+Error:
+--------------
+{error}
+--------------
+
+Please try again. Please only respond with an answer that satisfies the constraints laid out in the Instructions:"#;
+
+/// Wraps an `OutputParser` and, when parsing fails, asks an LLM to fix the output
+/// using the parser's format instructions and the parse error, up to `max_retries` times.
+///
+/// # Usage
+/// ```rust,ignore
+/// let parser = RetryOutputParser::new(MarkdownParser::new(), OpenAI::default())
+///     .with_max_retries(2);
+/// let chain = LLMChainBuilder::new()
+///     .prompt(prompt)
+///     .llm(llm)
+///     .output_parser(parser)
+///     .build()?;
+/// ```
+pub struct RetryOutputParser {
+    parser: Box<dyn OutputParser>,
+    llm: Box<dyn LLM>,
+    max_retries: usize,
+}
+
+impl RetryOutputParser {
+    pub fn new<P: Into<Box<dyn OutputParser>>, L: Into<Box<dyn LLM>>>(parser: P, llm: L) -> Self {
+        Self {
+            parser: parser.into(),
+            llm: llm.into(),
+            max_retries: 1,
+        }
+    }
+
+    /// Maximum number of correction attempts. Default: 1
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    async fn fix(
+        &self,
+        completion: &str,
+        error: &OutputParserError,
+    ) -> Result<String, OutputParserError> {
+        let prompt = template_fstring!(FIX_PROMPT, "instructions", "completion", "error").format(
+            prompt_args! {
+                "instructions" => self.parser.get_format_instructions(),
+                "completion" => completion,
+                "error" => error.to_string(),
+            },
+        )?;
+        Ok(self.llm.invoke(&prompt).await?)
+    }
+}
+
+#[async_trait]
+impl OutputParser for RetryOutputParser {
+    async fn parse(&self, output: &str) -> Result<String, OutputParserError> {
+        let mut completion = output.to_string();
+        let mut attempt = 0;
+        loop {
+            match self.parser.parse(&completion).await {
+                Ok(parsed) => return Ok(parsed),
+                Err(e) if attempt < self.max_retries => {
+                    log::debug!("Output parsing failed, asking the LLM to fix it: {}", e);
+                    attempt += 1;
+                    completion = self.fix(&completion, &e).await?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn get_format_instructions(&self) -> String {
+        self.parser.get_format_instructions()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{llm::fake::FakeLLM, output_parsers::MarkdownParser};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retry_output_parser_fixes_output() {
+        let llm = FakeLLM::new().with_responses(vec!["still wrong", "```\nfixed\n```"]);
+        let parser = RetryOutputParser::new(MarkdownParser::new(), llm.clone()).with_max_retries(2);
+
+        let result = parser.parse("no code block").await.unwrap();
+
+        assert_eq!(result, "fixed");
+        assert_eq!(llm.calls().len(), 2);
+        assert!(llm.calls()[0][0].content.contains("No code block found"));
+        assert!(llm.calls()[1][0].content.contains("still wrong"));
+    }
+
+    #[tokio::test]
+    async fn test_retry_output_parser_gives_up() {
+        let llm = FakeLLM::new().with_responses(vec!["still wrong"]);
+        let parser = RetryOutputParser::new(MarkdownParser::new(), llm);
+
+        assert!(parser.parse("no code block").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_output_parser_propagates_llm_errors() {
+        let parser = RetryOutputParser::new(MarkdownParser::new(), FakeLLM::new());
+
+        let err = parser.parse("no code block").await.unwrap_err();
+        assert!(matches!(err, OutputParserError::LLMError(_)));
+    }
+}
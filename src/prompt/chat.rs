@@ -151,6 +151,28 @@ macro_rules! fmt_message {
     };
 }
 
+/// `fmt_tool_message` is a utility macro used to insert a tool response into the formatted
+/// sequence, for example to few-shot the OpenAI tools agent with example tool exchanges.
+/// It creates a `MessageOrTemplate::Message` wrapping `Message::new_tool_message`, so it is
+/// identical to the tool messages produced at runtime.
+///
+/// # Usage
+/// ```rust,ignore
+/// message_formatter![
+///     fmt_message!(Message::new_ai_message("").with_tool_calls(tool_calls)),
+///     fmt_tool_message!("25", "call_123"),
+/// ]
+/// ```
+#[macro_export]
+macro_rules! fmt_tool_message {
+    ($content:expr, $tool_call_id:expr) => {
+        $crate::prompt::MessageOrTemplate::Message($crate::schemas::Message::new_tool_message(
+            $content,
+            $tool_call_id,
+        ))
+    };
+}
+
 /// `fmt_template` is a utility macro used to create a `MessageOrTemplate::Template` variant.
 ///
 /// # Usage
@@ -197,6 +219,14 @@ impl MessageFormatterStruct {
         self.items.push(MessageOrTemplate::Message(message));
     }
 
+    pub fn add_tool_message<T: std::fmt::Display, S: Into<String>>(
+        &mut self,
+        content: T,
+        tool_call_id: S,
+    ) {
+        self.add_message(Message::new_tool_message(content, tool_call_id));
+    }
+
     pub fn add_template(&mut self, template: Box<dyn MessageFormatter>) {
         self.items.push(MessageOrTemplate::Template(template));
     }
@@ -352,4 +382,28 @@ mod tests {
         assert_eq!(formatted_messages[2].content, "Placeholder message 1");
         assert_eq!(formatted_messages[3].content, "Placeholder message 2");
     }
+
+    #[test]
+    fn test_tool_message_in_formatter() {
+        let tool_calls = serde_json::json!([{
+            "id": "call_123",
+            "type": "function",
+            "function": {"name": "Calculator", "arguments": "{\"input\":\"2+2\"}"}
+        }]);
+        let formatter = message_formatter![
+            fmt_message!(Message::new_ai_message("").with_tool_calls(tool_calls)),
+            fmt_tool_message!("4", "call_123"),
+        ];
+
+        let messages = formatter
+            .format_prompt(prompt_args! {})
+            .unwrap()
+            .to_chat_messages();
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            serde_json::to_value(&messages[1]).unwrap(),
+            serde_json::to_value(Message::new_tool_message("4", "call_123")).unwrap()
+        );
+    }
 }
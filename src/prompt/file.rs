@@ -0,0 +1,148 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use crate::schemas::prompt::PromptValue;
+
+use super::{FormatPrompter, PromptArgs, PromptError, PromptTemplate, TemplateFormat};
+
+/// Identifies a version of the file, so it is only read again when it changes.
+type FileVersion = (Option<SystemTime>, u64);
+
+struct CachedTemplate {
+    template: PromptTemplate,
+    version: FileVersion,
+}
+
+/// A `PromptTemplate` loaded from a file. The variables are taken from the template text.
+///
+/// With `with_watch(true)` the file is checked before every format and the template is
+/// reloaded when its modification time or size changes, so prompts can be edited without
+/// restarting the application. If the file can't be read anymore, the last loaded
+/// template keeps being used.
+///
+/// # Usage
+/// ```rust,ignore
+/// let prompt = FilePromptTemplate::new("prompts/qa.jinja", TemplateFormat::Jinja2)?
+///     .with_watch(true);
+/// let chain = LLMChainBuilder::new().prompt(prompt).llm(llm).build()?;
+/// ```
+pub struct FilePromptTemplate {
+    path: PathBuf,
+    format: TemplateFormat,
+    watch: bool,
+    cache: Mutex<CachedTemplate>,
+}
+
+impl FilePromptTemplate {
+    /// Loads the template at `path`, failing if it can't be read.
+    pub fn new<P: Into<PathBuf>>(path: P, format: TemplateFormat) -> Result<Self, PromptError> {
+        let path = path.into();
+        let cache = load(&path, &format)?;
+        Ok(Self {
+            path,
+            format,
+            watch: false,
+            cache: Mutex::new(cache),
+        })
+    }
+
+    /// Reload the template when the file changes.
+    /// Default: false
+    pub fn with_watch(mut self, watch: bool) -> Self {
+        self.watch = watch;
+        self
+    }
+
+    /// Returns the current template, reloading it first if watching and the file changed.
+    pub fn template(&self) -> PromptTemplate {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if self.watch {
+            match file_version(&self.path) {
+                Ok(version) if version == cache.version => {}
+                Ok(_) => match load(&self.path, &self.format) {
+                    Ok(reloaded) => {
+                        log::debug!("Reloaded prompt template {}", self.path.display());
+                        *cache = reloaded;
+                    }
+                    Err(e) => log::warn!("Keeping the previous prompt template: {}", e),
+                },
+                Err(e) => log::warn!("Keeping the previous prompt template: {}", e),
+            }
+        }
+        cache.template.clone()
+    }
+}
+
+impl FormatPrompter for FilePromptTemplate {
+    fn format_prompt(&self, input_variables: PromptArgs) -> Result<PromptValue, PromptError> {
+        self.template().format_prompt(input_variables)
+    }
+
+    fn get_input_variables(&self) -> Vec<String> {
+        self.template().get_input_variables()
+    }
+}
+
+fn file_version(path: &Path) -> Result<FileVersion, PromptError> {
+    let metadata = fs::metadata(path).map_err(|e| {
+        PromptError::OtherError(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+    Ok((metadata.modified().ok(), metadata.len()))
+}
+
+fn load(path: &Path, format: &TemplateFormat) -> Result<CachedTemplate, PromptError> {
+    let version = file_version(path)?;
+    let text = fs::read_to_string(path).map_err(|e| {
+        PromptError::OtherError(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+    let variables =
+        PromptTemplate::new(text.clone(), vec![], format.clone()).referenced_variables();
+    Ok(CachedTemplate {
+        template: PromptTemplate::new(text, variables, format.clone()),
+        version,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::prompt_args;
+
+    use super::*;
+
+    #[test]
+    fn test_file_prompt_template_reloads_on_change() {
+        let path = env::temp_dir().join(format!(
+            "file_prompt_template_test_{}.jinja",
+            std::process::id()
+        ));
+        fs::write(&path, "Hello {{name}}").unwrap();
+
+        let prompt = FilePromptTemplate::new(&path, TemplateFormat::Jinja2)
+            .unwrap()
+            .with_watch(true);
+        assert_eq!(prompt.get_input_variables(), vec!["name"]);
+        let value = prompt
+            .format_prompt(prompt_args! {"name" => "Luis"})
+            .unwrap();
+        assert_eq!(value.to_chat_messages()[0].content, "Hello Luis");
+
+        fs::write(&path, "Good morning {{name}}, today is {{day}}").unwrap();
+        assert_eq!(prompt.get_input_variables(), vec!["name", "day"]);
+        let value = prompt
+            .format_prompt(prompt_args! {"name" => "Luis", "day" => "Monday"})
+            .unwrap();
+        assert_eq!(
+            value.to_chat_messages()[0].content,
+            "Good morning Luis, today is Monday"
+        );
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(prompt.get_input_variables(), vec!["name", "day"]);
+    }
+}
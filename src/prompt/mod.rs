@@ -1,11 +1,13 @@
 mod chat;
 mod error;
+mod file;
 mod prompt;
 
 use std::collections::HashMap;
 
 pub use chat::*;
 pub use error::*;
+pub use file::*;
 pub use prompt::*;
 use serde_json::Value;
 
@@ -1,3 +1,7 @@
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+
 use crate::schemas::{messages::Message, prompt::PromptValue};
 
 use super::{FormatPrompter, PromptArgs, PromptError, PromptFromatter};
@@ -23,6 +27,88 @@ impl PromptTemplate {
             format,
         }
     }
+
+    /// Returns the variables referenced in the template, in order of appearance. Unlike
+    /// `variables`, this inspects the template text itself.
+    pub fn referenced_variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = Vec::new();
+        for cap in self.placeholder_regex().captures_iter(&self.template) {
+            let variable = cap[1].to_string();
+            if is_identifier(&variable) && !variables.contains(&variable) {
+                variables.push(variable);
+            }
+        }
+        variables
+    }
+
+    /// Returns the variables referenced in the template but missing from `variables`, so
+    /// the declared variables can be validated before calling `format`.
+    pub fn undeclared_variables(&self) -> Vec<String> {
+        self.referenced_variables()
+            .into_iter()
+            .filter(|variable| !self.variables.contains(variable))
+            .collect()
+    }
+
+    /// Renders the given variables and returns a new template that still expects
+    /// the remaining ones.
+    ///
+    /// # Usage
+    /// ```rust,ignore
+    /// let template = template_jinja2!("{{greeting}} {{name}}", "greeting", "name");
+    /// let partial = template.partial(prompt_args! {"greeting" => "Hello"});
+    /// assert_eq!(partial.variables(), vec!["name"]);
+    /// ```
+    pub fn partial(&self, input_variables: PromptArgs) -> PromptTemplate {
+        let variables = self
+            .variables
+            .iter()
+            .filter(|variable| !input_variables.contains_key(variable.as_str()))
+            .cloned()
+            .collect();
+        PromptTemplate {
+            template: self.render(self.template.clone(), input_variables),
+            variables,
+            format: self.format.clone(),
+        }
+    }
+
+    /// Matches `{name}` in f-strings and `{{name}}` in Jinja2 templates, allowing spaces
+    /// inside the braces of the latter, like Jinja2 does.
+    fn placeholder_regex(&self) -> &'static Regex {
+        static FSTRING: OnceLock<Regex> = OnceLock::new();
+        static JINJA2: OnceLock<Regex> = OnceLock::new();
+        match self.format {
+            TemplateFormat::FString => {
+                FSTRING.get_or_init(|| Regex::new(r"\{([^{}\s]+)\}").unwrap())
+            }
+            TemplateFormat::Jinja2 => {
+                JINJA2.get_or_init(|| Regex::new(r"\{\{\s*([^{}\s]+)\s*\}\}").unwrap())
+            }
+        }
+    }
+
+    /// Replaces the placeholders of the given variables in a single pass, so values that
+    /// look like placeholders are kept as is. Other placeholders are left untouched.
+    fn render(&self, template: String, input_variables: PromptArgs) -> String {
+        self.placeholder_regex()
+            .replace_all(&template, |cap: &Captures| {
+                match input_variables.get(&cap[1]) {
+                    Some(serde_json::Value::String(s)) => s.clone(),
+                    Some(value) => value.to_string(),
+                    None => cap[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
 //PromptTemplate will be default transformed to an Human Input when used as FromatPrompter
@@ -46,8 +132,6 @@ impl PromptFromatter for PromptTemplate {
     }
 
     fn format(&self, input_variables: PromptArgs) -> Result<String, PromptError> {
-        let mut prompt = self.template();
-
         // check if all variables are in the input variables
         for key in self.variables() {
             if !input_variables.contains_key(key.as_str()) {
@@ -55,17 +139,7 @@ impl PromptFromatter for PromptTemplate {
             }
         }
 
-        for (key, value) in input_variables {
-            let key = match self.format {
-                TemplateFormat::FString => format!("{{{}}}", key),
-                TemplateFormat::Jinja2 => format!("{{{{{}}}}}", key),
-            };
-            let value_str = match &value {
-                serde_json::Value::String(s) => s.clone(),
-                _ => value.to_string(),
-            };
-            prompt = prompt.replace(&key, &value_str);
-        }
+        let prompt = self.render(self.template(), input_variables);
 
         log::debug!("Formatted prompt: {}", prompt);
         Ok(prompt)
@@ -255,4 +329,41 @@ mod tests {
         let formatted_jinja2 = jinja2_template.format(input_variables_jinja2).unwrap();
         assert_eq!(formatted_jinja2, "Jinja2 Chat: Bob says Hi, Alice!");
     }
+
+    #[test]
+    fn test_undeclared_variables() {
+        let template = template_jinja2!("{{user}} says {{ message }} to {{user}}", "user");
+        assert_eq!(template.referenced_variables(), vec!["user", "message"]);
+        assert_eq!(template.undeclared_variables(), vec!["message"]);
+
+        let template = template_fstring!("{user} says {message}", "user", "message");
+        assert_eq!(template.referenced_variables(), vec!["user", "message"]);
+        assert!(template.undeclared_variables().is_empty());
+    }
+
+    #[test]
+    fn test_jinja2_renders_spaced_placeholders() {
+        let template = template_jinja2!("{{ user }} says {{message}}", "user", "message");
+        let result = template
+            .format(prompt_args! {"user" => "Bob", "message" => "{{user}}"})
+            .unwrap();
+        assert_eq!(result, "Bob says {{user}}");
+    }
+
+    #[test]
+    fn test_partial_template() {
+        let template = template_jinja2!(
+            "{{greeting}}, {{name}}! Today is {{day}}.",
+            "greeting",
+            "name",
+            "day"
+        );
+
+        let partial = template.partial(prompt_args! {"greeting" => "Hello", "day" => "Monday"});
+        assert_eq!(partial.variables(), vec!["name"]);
+        assert!(partial.format(prompt_args! {}).is_err());
+
+        let result = partial.format(prompt_args! {"name" => "Luis"}).unwrap();
+        assert_eq!(result, "Hello, Luis! Today is Monday.");
+    }
 }
@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tokio::sync::mpsc;
 
 pub enum ToolInput {
@@ -9,13 +10,30 @@ pub enum ToolInput {
     DictInput(HashMap<String, String>),
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct AgentAction {
     pub tool: String,
-    pub tool_input: String, //this should be ToolInput in the future
+    /// A plain string for text based agents, or the structured arguments of a tool call.
+    pub tool_input: Value,
     pub log: String,
 }
 
+impl AgentAction {
+    /// Returns the tool input as text: strings as they are, other values as JSON.
+    pub fn tool_input_string(&self) -> String {
+        match &self.tool_input {
+            Value::String(input) => input.clone(),
+            input => input.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for AgentAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.tool, self.tool_input_string())
+    }
+}
+
 ///Log tools is a struct used by the openai-like agents
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct LogTools {
@@ -23,18 +41,107 @@ pub struct LogTools {
     pub tools: String,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// The final answer of an agent. Build it with [`AgentFinish::new`] or
+/// [`AgentFinish::from_unparsed`]: it is `#[non_exhaustive]` since `parse_fallback` was
+/// added, so struct literals outside this crate no longer compile.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[non_exhaustive]
 pub struct AgentFinish {
     pub output: String,
+    /// Set when the output couldn't be parsed as an action or final answer, and the raw
+    /// model output is used as the answer instead.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub parse_fallback: bool,
+}
+
+impl AgentFinish {
+    pub fn new<S: Into<String>>(output: S) -> Self {
+        Self {
+            output: output.into(),
+            parse_fallback: false,
+        }
+    }
+
+    /// A finish made of model output that couldn't be parsed.
+    pub fn from_unparsed<S: Into<String>>(output: S) -> Self {
+        Self {
+            output: output.into(),
+            parse_fallback: true,
+        }
+    }
+}
+
+impl fmt::Display for AgentFinish {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Final Answer: {}", self.output)
+    }
 }
 
-#[derive(Debug)]
+/// Serialized as `{"type": "action", "data": [...]}` or `{"type": "finish", "data": {...}}`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum AgentEvent {
     Action(Vec<AgentAction>),
     Finish(AgentFinish),
 }
 
+impl fmt::Display for AgentEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgentEvent::Action(actions) => {
+                let actions = actions
+                    .iter()
+                    .map(|action| format!("Action: {}", action))
+                    .collect::<Vec<_>>();
+                write!(f, "{}", actions.join("\n"))
+            }
+            AgentEvent::Finish(finish) => write!(f, "{}", finish),
+        }
+    }
+}
+
 pub enum AgentPlan {
     Text(AgentEvent),
     Stream(mpsc::Receiver<Result<String, reqwest_eventsource::Error>>),
 }
+
+/// An item of a streamed planning call: the tokens of the final answer as they are
+/// generated, if the call produces one, followed by the planned event.
+#[derive(Debug, Clone)]
+pub enum AgentPlanChunk {
+    Answer(String),
+    Event(AgentEvent),
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_agent_event_round_trip_and_display() {
+        let log = LogTools {
+            tool_id: "call_1".into(),
+            tools: json!([{"id": "call_1", "type": "function"}]).to_string(),
+        };
+        let event = AgentEvent::Action(vec![AgentAction {
+            tool: "search".into(),
+            tool_input: "{\"query\": \"rust\"}".into(),
+            log: serde_json::to_string(&log).unwrap(),
+        }]);
+
+        let serialized = serde_json::to_value(&event).unwrap();
+        assert_eq!(serialized["type"], "action");
+        let restored: AgentEvent = serde_json::from_value(serialized).unwrap();
+        assert_eq!(restored, event);
+        if let AgentEvent::Action(actions) = &restored {
+            let restored_log: LogTools = serde_json::from_str(&actions[0].log).unwrap();
+            assert_eq!(restored_log.tool_id, "call_1");
+        }
+
+        assert_eq!(event.to_string(), "Action: search: {\"query\": \"rust\"}");
+        let finish = AgentEvent::Finish(AgentFinish::new("done"));
+        assert_eq!(finish.to_string(), "Final Answer: done");
+    }
+}
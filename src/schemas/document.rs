@@ -7,6 +7,8 @@ use serde_json::Value;
 /// The `page_content` field is a string that contains the content of the document.
 /// The `metadata` field is a `HashMap` where the keys represent metadata properties and the values represent property values.
 /// The `score` field represents a relevance score for the document and is a floating point number.
+/// Vector stores populate it with the similarity of the document to the query.
+/// When deserializing, `metadata` and `score` can be omitted.
 ///
 /// # Usage
 /// ```rust,ignore
@@ -21,7 +23,9 @@ use serde_json::Value;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Document {
     pub page_content: String,
+    #[serde(default)]
     pub metadata: HashMap<String, Value>,
+    #[serde(default)]
     pub score: f64,
 }
 
@@ -41,6 +45,16 @@ impl Document {
         self
     }
 
+    /// Inserts a single metadata entry, keeping the existing ones.
+    pub fn with_metadata_entry<K: Into<String>, V: Into<Value>>(
+        mut self,
+        key: K,
+        value: V,
+    ) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
     /// Sets the `score` of the `Document` to the provided float.
     pub fn with_score(mut self, score: f64) -> Self {
         self.score = score;
@@ -58,3 +72,27 @@ impl Default for Document {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize_document_without_metadata_and_score() {
+        let document: Document = serde_json::from_value(json!({"page_content": "Hello"})).unwrap();
+        assert_eq!(document.page_content, "Hello");
+        assert!(document.metadata.is_empty());
+        assert_eq!(document.score, 0.0);
+    }
+
+    #[test]
+    fn test_document_builder() {
+        let document = Document::new("Hello")
+            .with_metadata_entry("source", "manual")
+            .with_score(0.5);
+        assert_eq!(document.metadata["source"], json!("manual"));
+        assert_eq!(document.score, 0.5);
+    }
+}
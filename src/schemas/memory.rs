@@ -1,4 +1,27 @@
-use super::messages::Message;
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+    time::Duration,
+};
+
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+use super::messages::{Message, MessageType};
+
+/// How long chains and agents wait for the memory lock before giving up, unless configured
+/// otherwise, e.g. with `AgentExecutor::with_memory_lock_timeout`.
+pub const MEMORY_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Error, Debug)]
+pub enum MemoryError {
+    #[error("Timed out after {0:?} waiting for the memory lock")]
+    LockTimeout(Duration),
+
+    #[error("Memory operation panicked: {0}")]
+    Panicked(String),
+}
 
 pub trait BaseMemory: Send + Sync {
     fn messages(&self) -> Vec<Message>;
@@ -19,13 +42,62 @@ pub trait BaseMemory: Send + Sync {
 
     fn clear(&mut self);
 
-    fn to_string(&self) -> String {
+    /// Renders the conversation as a single string, one message per line, using the
+    /// given prefixes for human and AI messages. Other message types keep their type name.
+    fn buffer_as_string(&self, human_prefix: &str, ai_prefix: &str) -> String {
         self.messages()
             .iter()
-            .map(|msg| format!("{}: {}", msg.message_type.to_string(), msg.content))
+            .map(|msg| {
+                let prefix = match msg.message_type {
+                    MessageType::HumanMessage => human_prefix.to_string(),
+                    MessageType::AIMessage => ai_prefix.to_string(),
+                    _ => msg.message_type.to_string(),
+                };
+                format!("{}: {}", prefix, msg.content)
+            })
             .collect::<Vec<String>>()
             .join("\n")
     }
+
+    fn to_string(&self) -> String {
+        self.buffer_as_string("human", "ai")
+    }
+}
+
+/// Runs `f` on the locked memory, surfacing a lock that can't be acquired in time or a
+/// panicking memory implementation as a [`MemoryError`] instead of hanging or unwinding
+/// the caller.
+pub async fn with_memory<T, F>(memory: &Arc<Mutex<dyn BaseMemory>>, f: F) -> Result<T, MemoryError>
+where
+    F: FnOnce(&mut dyn BaseMemory) -> T,
+{
+    with_memory_timeout(memory, MEMORY_LOCK_TIMEOUT, f).await
+}
+
+/// Same as [`with_memory`], waiting at most `timeout` for the lock.
+pub async fn with_memory_timeout<T, F>(
+    memory: &Arc<Mutex<dyn BaseMemory>>,
+    timeout: Duration,
+    f: F,
+) -> Result<T, MemoryError>
+where
+    F: FnOnce(&mut dyn BaseMemory) -> T,
+{
+    let mut memory = tokio::time::timeout(timeout, memory.lock())
+        .await
+        .map_err(|_| MemoryError::LockTimeout(timeout))?;
+    panic::catch_unwind(AssertUnwindSafe(|| f(&mut *memory)))
+        .map_err(|e| MemoryError::Panicked(panic_message(e)))
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
 }
 
 impl<M> From<M> for Box<dyn BaseMemory>
@@ -36,3 +108,57 @@ where
         Box::new(memory)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::memory::SimpleMemory;
+
+    use super::*;
+
+    #[test]
+    fn test_buffer_as_string() {
+        let mut memory = SimpleMemory::new();
+        memory.add_message(Message::new_system_message("Be brief"));
+        memory.add_user_message(&"Hi");
+        memory.add_ai_message(&"Hello!");
+
+        assert_eq!(
+            memory.buffer_as_string("Human", "AI"),
+            "system: Be brief\nHuman: Hi\nAI: Hello!"
+        );
+        assert_eq!(
+            memory.to_string(),
+            "system: Be brief\nhuman: Hi\nai: Hello!"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_with_memory_catches_panics() {
+        let memory: Arc<Mutex<dyn BaseMemory>> = SimpleMemory::new().into();
+
+        with_memory(&memory, |memory| memory.add_user_message(&"Hi"))
+            .await
+            .unwrap();
+        let result = with_memory(&memory, |_| -> usize { panic!("broken memory") }).await;
+        assert!(matches!(
+            result,
+            Err(MemoryError::Panicked(message)) if message == "broken memory"
+        ));
+
+        // The lock is released after the panic, so the memory remains usable.
+        let messages = with_memory(&memory, |memory| memory.messages())
+            .await
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_with_memory_timeout() {
+        let memory: Arc<Mutex<dyn BaseMemory>> = SimpleMemory::new().into();
+        let _guard = memory.lock().await;
+
+        let timeout = Duration::from_millis(10);
+        let result = with_memory_timeout(&memory, timeout, |memory| memory.messages()).await;
+        assert!(matches!(result, Err(MemoryError::LockTimeout(t)) if t == timeout));
+    }
+}
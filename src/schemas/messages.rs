@@ -56,6 +56,42 @@ impl<S: AsRef<str>> From<S> for ImageContent {
     }
 }
 
+/// Where an image attached to a message comes from. It is turned into an
+/// [`ImageContent`], base64 data being sent as a `data:` URL.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageSource {
+    Url(String),
+    Base64 { media_type: String, data: String },
+}
+
+impl ImageSource {
+    pub fn url<S: Into<String>>(url: S) -> Self {
+        ImageSource::Url(url.into())
+    }
+
+    pub fn base64<M: Into<String>, D: Into<String>>(media_type: M, data: D) -> Self {
+        ImageSource::Base64 {
+            media_type: media_type.into(),
+            data: data.into(),
+        }
+    }
+}
+
+impl From<ImageSource> for ImageContent {
+    fn from(source: ImageSource) -> Self {
+        let image_url = match source {
+            ImageSource::Url(url) => url,
+            ImageSource::Base64 { media_type, data } => {
+                format!("data:{};base64,{}", media_type, data)
+            }
+        };
+        ImageContent {
+            image_url,
+            detail: None,
+        }
+    }
+}
+
 /// Struct `Message` represents a message with its content and type.
 ///
 /// # Usage
@@ -71,61 +107,62 @@ pub struct Message {
     pub id: Option<String>,
     pub tool_calls: Option<Value>,
     pub images: Option<Vec<ImageContent>>,
+    /// Name of the participant, sent to OpenAI to tell apart speakers with the same role.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Arbitrary data attached to the message, e.g. for tracing. It is kept by prompts
+    /// and memories but never sent to the LLM.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
 }
 
 impl Message {
+    /// Returns a [`MessageBuilder`], to set the role, content, images, tool call id and
+    /// metadata of a message in one place.
+    pub fn builder() -> MessageBuilder {
+        MessageBuilder::new()
+    }
+
     // Function to create a new Human message with a generic type that implements Display
     pub fn new_human_message<T: std::fmt::Display>(content: T) -> Self {
-        Message {
-            content: content.to_string(),
-            message_type: MessageType::HumanMessage,
-            id: None,
-            tool_calls: None,
-            images: None,
-        }
+        MessageBuilder::new().content(content).build()
     }
 
-    pub fn new_human_message_with_images<T: Into<ImageContent>>(images: Vec<T>) -> Self {
-        Message {
-            content: String::default(),
-            message_type: MessageType::HumanMessage,
-            id: None,
-            tool_calls: None,
-            images: Some(images.into_iter().map(|i| i.into()).collect()),
-        }
+    /// Creates a Human message with `content` as text and the given images attached.
+    /// Images can be `ImageSource`s or plain URLs (including `data:` URLs).
+    pub fn new_human_message_with_images<T: std::fmt::Display, I: Into<ImageContent>>(
+        content: T,
+        images: Vec<I>,
+    ) -> Self {
+        MessageBuilder::new()
+            .content(content)
+            .images(images)
+            .build()
     }
 
     // Function to create a new System message with a generic type that implements Display
     pub fn new_system_message<T: std::fmt::Display>(content: T) -> Self {
-        Message {
-            content: content.to_string(),
-            message_type: MessageType::SystemMessage,
-            id: None,
-            tool_calls: None,
-            images: None,
-        }
+        MessageBuilder::new()
+            .role(MessageType::SystemMessage)
+            .content(content)
+            .build()
     }
 
     // Function to create a new AI message with a generic type that implements Display
     pub fn new_ai_message<T: std::fmt::Display>(content: T) -> Self {
-        Message {
-            content: content.to_string(),
-            message_type: MessageType::AIMessage,
-            id: None,
-            tool_calls: None,
-            images: None,
-        }
+        MessageBuilder::new()
+            .role(MessageType::AIMessage)
+            .content(content)
+            .build()
     }
 
     // Function to create a new Tool message with a generic type that implements Display
     pub fn new_tool_message<T: std::fmt::Display, S: Into<String>>(content: T, id: S) -> Self {
-        Message {
-            content: content.to_string(),
-            message_type: MessageType::ToolMessage,
-            id: Some(id.into()),
-            tool_calls: None,
-            images: None,
-        }
+        MessageBuilder::new()
+            .role(MessageType::ToolMessage)
+            .content(content)
+            .tool_call_id(id)
+            .build()
     }
 
     /// Sets the tool calls for the OpenAI-like API call.
@@ -142,6 +179,18 @@ impl Message {
         self
     }
 
+    /// Sets the name of the participant, e.g. to run a conversation between several
+    /// personas.
+    pub fn with_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
     pub fn messages_from_value(value: &Value) -> Result<Vec<Message>, serde_json::error::Error> {
         serde_json::from_value(value.clone())
     }
@@ -154,3 +203,143 @@ impl Message {
             .join("\n")
     }
 }
+
+/// Builds a [`Message`]. The role defaults to `MessageType::HumanMessage`.
+///
+/// # Usage
+/// ```rust,ignore
+/// let message = MessageBuilder::new()
+///     .content("What is in this picture?")
+///     .image(ImageSource::url("https://example.com/cat.png"))
+///     .metadata(json!({"request_id": "42"}))
+///     .build();
+///
+/// let tool_message = MessageBuilder::new()
+///     .role(MessageType::ToolMessage)
+///     .content("25")
+///     .tool_call_id("call_1")
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MessageBuilder {
+    role: Option<MessageType>,
+    content: Option<String>,
+    images: Option<Vec<ImageContent>>,
+    tool_call_id: Option<String>,
+    tool_calls: Option<Value>,
+    name: Option<String>,
+    metadata: Option<Value>,
+}
+
+impl MessageBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn role(mut self, role: MessageType) -> Self {
+        self.role = Some(role);
+        self
+    }
+
+    pub fn content<T: std::fmt::Display>(mut self, content: T) -> Self {
+        self.content = Some(content.to_string());
+        self
+    }
+
+    /// Attaches an image, sent along the text content as another part of the message.
+    pub fn image<I: Into<ImageContent>>(mut self, image: I) -> Self {
+        self.images.get_or_insert_with(Vec::new).push(image.into());
+        self
+    }
+
+    pub fn images<I: Into<ImageContent>>(mut self, images: Vec<I>) -> Self {
+        self.images
+            .get_or_insert_with(Vec::new)
+            .extend(images.into_iter().map(Into::into));
+        self
+    }
+
+    /// The id of the tool call a tool message answers.
+    pub fn tool_call_id<S: Into<String>>(mut self, tool_call_id: S) -> Self {
+        self.tool_call_id = Some(tool_call_id.into());
+        self
+    }
+
+    /// The tool calls requested in an AI message, in the OpenAI format.
+    pub fn tool_calls(mut self, tool_calls: Value) -> Self {
+        self.tool_calls = Some(tool_calls);
+        self
+    }
+
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn build(self) -> Message {
+        Message {
+            content: self.content.unwrap_or_default(),
+            message_type: self.role.unwrap_or(MessageType::HumanMessage),
+            id: self.tool_call_id,
+            tool_calls: self.tool_calls,
+            images: self.images,
+            name: self.name,
+            metadata: self.metadata,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_name_and_metadata_round_trip() {
+        let messages = vec![
+            Message::new_human_message("hi")
+                .with_name("alice")
+                .with_metadata(json!({"persona": "critic"})),
+            Message::new_ai_message("hello"),
+        ];
+
+        let value = serde_json::to_value(&messages).unwrap();
+        assert!(value[1].get("name").is_none());
+        let restored = Message::messages_from_value(&value).unwrap();
+        assert_eq!(restored[0].name.as_deref(), Some("alice"));
+        assert_eq!(restored[0].metadata, Some(json!({"persona": "critic"})));
+        assert_eq!(restored[1].name, None);
+    }
+
+    #[test]
+    fn test_message_builder() {
+        let message = Message::builder()
+            .role(MessageType::ToolMessage)
+            .content(25)
+            .tool_call_id("call_1")
+            .build();
+        assert_eq!(message.message_type, MessageType::ToolMessage);
+        assert_eq!(message.content, "25");
+        assert_eq!(message.id.as_deref(), Some("call_1"));
+        assert!(message.images.is_none());
+
+        let message = MessageBuilder::new()
+            .content("Compare these")
+            .image("https://example.com/a.png")
+            .image(ImageSource::base64("image/png", "iVBORw0KGgo="))
+            .metadata(json!({"request_id": "42"}))
+            .build();
+        assert_eq!(message.message_type, MessageType::HumanMessage);
+        let images = message.images.unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].image_url, "https://example.com/a.png");
+        assert_eq!(images[1].image_url, "data:image/png;base64,iVBORw0KGgo=");
+        assert_eq!(message.metadata, Some(json!({"request_id": "42"})));
+    }
+}
@@ -3,6 +3,8 @@ use std::io::{self, Write};
 
 use crate::language_models::TokenUsage;
 
+use super::ToolCallDelta;
+
 #[derive(Debug, Clone)]
 pub struct StreamData {
     pub value: Value,
@@ -26,3 +28,27 @@ impl StreamData {
         handle.flush()
     }
 }
+
+/// A piece of a streamed generation, telling text apart from tool calls. See
+/// [`ToolCallDelta`] for how tool call fragments are put back together.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamDelta {
+    Text(String),
+    ToolCall(ToolCallDelta),
+}
+
+impl StreamDelta {
+    /// Splits a chunk into its text and its tool call deltas, in the OpenAI format.
+    pub fn from_stream_data(data: &StreamData) -> Vec<Self> {
+        let mut deltas = Vec::new();
+        if !data.content.is_empty() {
+            deltas.push(Self::Text(data.content.clone()));
+        }
+        deltas.extend(
+            ToolCallDelta::from_chunk(&data.value)
+                .into_iter()
+                .map(Self::ToolCall),
+        );
+        deltas
+    }
+}
@@ -1,17 +1,22 @@
-use std::ops::Deref;
+use std::{collections::BTreeMap, ops::Deref};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::tools::Tool;
+use crate::tools::{normalize_tool_name, Tool};
 
-#[derive(Clone, Debug)]
+/// Controls whether the model calls tools: `Auto` lets it decide, `Required` forces a
+/// call to any tool and `Named` forces a call to the given tool.
+#[derive(Clone, Debug, PartialEq)]
 pub enum FunctionCallBehavior {
     None,
     Auto,
+    Required,
     Named(String),
 }
 
+pub type ToolChoice = FunctionCallBehavior;
+
 #[derive(Clone, Debug)]
 pub struct FunctionDefinition {
     pub name: String,
@@ -20,10 +25,23 @@ pub struct FunctionDefinition {
 }
 
 impl FunctionDefinition {
-    pub fn new(name: &str, description: &str, parameters: Value) -> Self {
+    /// Defines a function the model can call without a [`Tool`] behind it, e.g. one your
+    /// app handles itself. `parameters` is the JSON schema of its arguments, and the name
+    /// is normalized like tool names are.
+    ///
+    /// # Usage
+    /// ```rust,ignore
+    /// let book = FunctionDefinition::new(
+    ///     "book_flight",
+    ///     "Books a flight to the given city",
+    ///     json!({"type": "object", "properties": {"city": {"type": "string"}}}),
+    /// );
+    /// llm.add_options(CallOptions::new().with_functions(vec![book]));
+    /// ```
+    pub fn new<N: AsRef<str>, D: Into<String>>(name: N, description: D, parameters: Value) -> Self {
         FunctionDefinition {
-            name: name.trim().replace(" ", "_"),
-            description: description.to_string(),
+            name: normalize_tool_name(name.as_ref()),
+            description: description.into(),
             parameters,
         }
     }
@@ -34,14 +52,14 @@ impl FunctionDefinition {
         T: Deref<Target = dyn Tool> + ?Sized,
     {
         FunctionDefinition {
-            name: tool.name().trim().replace(" ", "_"),
+            name: normalize_tool_name(&tool.name()),
             description: tool.description(),
             parameters: tool.parameters(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FunctionCallResponse {
     pub id: String,
     #[serde(rename = "type")]
@@ -49,7 +67,7 @@ pub struct FunctionCallResponse {
     pub function: FunctionDetail,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FunctionDetail {
     pub name: String,
     ///this should be an string, and this should be passed to the tool, to
@@ -62,3 +80,176 @@ impl FunctionCallResponse {
         serde_json::from_str(s)
     }
 }
+
+/// A fragment of a streamed tool call, as yielded by
+/// [`crate::language_models::llm::LLM::stream_deltas`].
+///
+/// Fragments with the same `index` belong to the same call. The first fragment of a call
+/// carries its `id`, `type` and `name`; the following ones only carry more `arguments`,
+/// which must be concatenated in the order they arrive. Providers emit calls one after
+/// the other, so a call is usually complete when a fragment with another index arrives,
+/// but only the end of the stream guarantees it: an agent executing a tool speculatively
+/// should check that the arguments parse as JSON first. [`ToolCallAccumulator`] does the
+/// assembly with `push_tool_call_delta`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolCallDelta {
+    pub index: u64,
+    pub id: Option<String>,
+    pub type_field: Option<String>,
+    pub name: Option<String>,
+    pub arguments: String,
+}
+
+impl ToolCallDelta {
+    /// Reads a delta in the OpenAI format, like
+    /// `{"index": 0, "id": "call_1", "function": {"name": "search", "arguments": "{\"q"}}`.
+    pub fn from_value(delta: &Value) -> Self {
+        Self {
+            index: delta["index"].as_u64().unwrap_or_default(),
+            id: delta["id"].as_str().map(String::from),
+            type_field: delta["type"].as_str().map(String::from),
+            name: delta["function"]["name"].as_str().map(String::from),
+            arguments: delta["function"]["arguments"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    /// Returns the tool call deltas of a streamed chat completion chunk, found at
+    /// `/choices/0/delta/tool_calls`.
+    pub fn from_chunk(chunk: &Value) -> Vec<Self> {
+        match chunk.pointer("/choices/0/delta/tool_calls") {
+            Some(Value::Array(deltas)) => deltas.iter().map(Self::from_value).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Assembles the tool calls of a streamed OpenAI response. Each chunk only carries a
+/// fragment of a call, identified by its `index`: the `id` and function name come in the
+/// first fragment and the `arguments` string is split across the following ones.
+///
+/// # Usage
+/// ```rust,ignore
+/// let mut tool_calls = ToolCallAccumulator::new();
+/// while let Some(data) = stream.next().await {
+///     tool_calls.push_chunk(&data?.value);
+/// }
+/// let calls: Vec<FunctionCallResponse> = tool_calls.finish();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ToolCallAccumulator {
+    calls: BTreeMap<u64, FunctionCallResponse>,
+}
+
+impl ToolCallAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the tool call deltas of a streamed chat completion chunk, found at
+    /// `/choices/0/delta/tool_calls`. Chunks without tool calls are ignored.
+    pub fn push_chunk(&mut self, chunk: &Value) {
+        for delta in ToolCallDelta::from_chunk(chunk) {
+            self.push_tool_call_delta(&delta);
+        }
+    }
+
+    /// Adds a single tool call delta, like
+    /// `{"index": 0, "id": "call_1", "function": {"name": "search", "arguments": "{\"q"}}`.
+    pub fn push_delta(&mut self, delta: &Value) {
+        self.push_tool_call_delta(&ToolCallDelta::from_value(delta));
+    }
+
+    /// Adds a delta yielded by `LLM::stream_deltas`, or read with `ToolCallDelta`.
+    pub fn push_tool_call_delta(&mut self, delta: &ToolCallDelta) {
+        let call = self
+            .calls
+            .entry(delta.index)
+            .or_insert_with(|| FunctionCallResponse {
+                id: String::new(),
+                type_field: "function".to_string(),
+                function: FunctionDetail {
+                    name: String::new(),
+                    arguments: String::new(),
+                },
+            });
+
+        if let Some(id) = &delta.id {
+            call.id = id.clone();
+        }
+        if let Some(type_field) = &delta.type_field {
+            call.type_field = type_field.clone();
+        }
+        if let Some(name) = &delta.name {
+            call.function.name.push_str(name);
+        }
+        call.function.arguments.push_str(&delta.arguments);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Returns the assembled tool calls, ordered by index.
+    pub fn finish(self) -> Vec<FunctionCallResponse> {
+        self.calls.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_function_definition_without_tool() {
+        let name = String::from("Book Flight");
+        let function = FunctionDefinition::new(
+            &name,
+            format!("Books a flight to {}", "a city"),
+            json!({"type": "object", "properties": {"city": {"type": "string"}}}),
+        );
+
+        assert_eq!(function.name, "Book_Flight");
+        assert_eq!(function.description, "Books a flight to a city");
+        assert_eq!(function.parameters["properties"]["city"]["type"], "string");
+    }
+
+    #[test]
+    fn test_tool_call_accumulator() {
+        let chunks = vec![
+            json!({"choices": [{"delta": {"tool_calls": [
+                {"index": 0, "id": "call_1", "type": "function",
+                 "function": {"name": "search", "arguments": ""}},
+            ]}}]}),
+            json!({"choices": [{"delta": {"tool_calls": [
+                {"index": 1, "id": "call_2", "type": "function",
+                 "function": {"name": "weather", "arguments": "{\"city\":"}},
+                {"index": 0, "function": {"arguments": "{\"q\": \"ru"}},
+            ]}}]}),
+            json!({"choices": [{"delta": {"content": null}}]}),
+            json!({"choices": [{"delta": {"tool_calls": [
+                {"index": 0, "function": {"arguments": "st\"}"}},
+                {"index": 1, "function": {"arguments": " \"Lima\"}"}},
+            ]}}]}),
+        ];
+
+        let mut accumulator = ToolCallAccumulator::new();
+        for chunk in &chunks {
+            accumulator.push_chunk(chunk);
+        }
+        let calls = accumulator.finish();
+
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].id, "call_1");
+        assert_eq!(calls[0].function.name, "search");
+        assert_eq!(calls[0].function.arguments, r#"{"q": "rust"}"#);
+        assert_eq!(calls[1].id, "call_2");
+        assert_eq!(calls[1].function.name, "weather");
+        assert_eq!(calls[1].function.arguments, r#"{"city": "Lima"}"#);
+        assert_eq!(calls[1].type_field, "function");
+    }
+}
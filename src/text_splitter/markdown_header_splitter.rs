@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::schemas::Document;
+
+use super::{SplitterOptions, TextSplitter, TextSplitterError, TokenSplitter};
+
+/// `MarkdownHeaderSplitter` splits markdown on heading boundaries and stores the
+/// heading hierarchy of every chunk in its metadata (by default under the `h1`, `h2`
+/// and `h3` keys). Sections larger than the configured chunk size are split again
+/// with a `TokenSplitter`.
+///
+/// # Usage
+/// ```rust,ignore
+/// let splitter = MarkdownHeaderSplitter::new(SplitterOptions::default().with_chunk_size(256));
+/// let documents = splitter.split_documents(&docs).await?;
+/// // documents[0].metadata["h1"] == "Introduction"
+/// ```
+pub struct MarkdownHeaderSplitter {
+    headers_to_split_on: Vec<(usize, String)>,
+    section_splitter: TokenSplitter,
+}
+
+impl Default for MarkdownHeaderSplitter {
+    fn default() -> Self {
+        MarkdownHeaderSplitter::new(SplitterOptions::default())
+    }
+}
+
+impl MarkdownHeaderSplitter {
+    pub fn new(options: SplitterOptions) -> MarkdownHeaderSplitter {
+        MarkdownHeaderSplitter {
+            headers_to_split_on: vec![
+                (1, "h1".to_string()),
+                (2, "h2".to_string()),
+                (3, "h3".to_string()),
+            ],
+            section_splitter: TokenSplitter::new(options),
+        }
+    }
+
+    /// Sets the heading levels to split on, and the metadata key used for each level.
+    /// For example `vec![(1, "title".into()), (2, "section".into())]`.
+    pub fn with_headers_to_split_on(mut self, headers: Vec<(usize, String)>) -> Self {
+        self.headers_to_split_on = headers;
+        self
+    }
+
+    fn header_key(&self, level: usize) -> Option<&String> {
+        self.headers_to_split_on
+            .iter()
+            .find(|(l, _)| *l == level)
+            .map(|(_, key)| key)
+    }
+
+    /// Splits the markdown into sections, each one with the headings that contain it.
+    fn split_sections(&self, text: &str) -> Vec<(HashMap<String, Value>, String)> {
+        let mut sections = Vec::new();
+        let mut headers: Vec<(usize, String)> = Vec::new();
+        let mut current = String::new();
+        let mut in_code_block = false;
+
+        let mut flush = |headers: &[(usize, String)], current: &mut String| {
+            if !current.trim().is_empty() {
+                let metadata = headers
+                    .iter()
+                    .filter_map(|(level, title)| {
+                        self.header_key(*level)
+                            .map(|key| (key.clone(), Value::String(title.clone())))
+                    })
+                    .collect();
+                sections.push((metadata, current.trim_end().to_string()));
+            }
+            current.clear();
+        };
+
+        for line in text.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code_block = !in_code_block;
+            }
+
+            if !in_code_block {
+                if let Some((level, title)) = parse_heading(trimmed) {
+                    if self.header_key(level).is_some() {
+                        flush(&headers, &mut current);
+                        headers.retain(|(l, _)| *l < level);
+                        headers.push((level, title));
+                    }
+                }
+            }
+
+            current.push_str(line);
+            current.push('\n');
+        }
+        flush(&headers, &mut current);
+
+        sections
+    }
+}
+
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim().to_string()))
+}
+
+#[async_trait]
+impl TextSplitter for MarkdownHeaderSplitter {
+    async fn split_text(&self, text: &str) -> Result<Vec<String>, TextSplitterError> {
+        let mut chunks = Vec::new();
+        for (_, section) in self.split_sections(text) {
+            chunks.extend(self.section_splitter.split_text(&section).await?);
+        }
+        Ok(chunks)
+    }
+
+    async fn create_documents(
+        &self,
+        text: &[String],
+        metadatas: &[HashMap<String, Value>],
+    ) -> Result<Vec<Document>, TextSplitterError> {
+        let mut metadatas = metadatas.to_vec();
+        if metadatas.is_empty() {
+            metadatas = vec![HashMap::new(); text.len()];
+        }
+
+        if text.len() != metadatas.len() {
+            return Err(TextSplitterError::MetadataTextMismatch);
+        }
+
+        let mut documents: Vec<Document> = Vec::new();
+        for (text, metadata) in text.iter().zip(metadatas) {
+            for (headers, section) in self.split_sections(text) {
+                let mut metadata = metadata.clone();
+                metadata.extend(headers);
+                for chunk in self.section_splitter.split_text(&section).await? {
+                    documents.push(Document::new(chunk).with_metadata(metadata.clone()));
+                }
+            }
+        }
+
+        Ok(documents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const MARKDOWN: &str = r#"# Guide
+Intro text.
+
+## Install
+Run the installer.
+
+```bash
+# not a heading
+cargo add langchain-rust
+```
+
+### Linux
+Use your package manager.
+
+## Usage
+Call the chain.
+"#;
+
+    #[tokio::test]
+    async fn test_split_keeps_header_hierarchy() {
+        let splitter = MarkdownHeaderSplitter::default();
+        let documents = splitter
+            .create_documents(&[MARKDOWN.to_string()], &[])
+            .await
+            .unwrap();
+
+        assert_eq!(documents.len(), 4);
+        assert_eq!(documents[0].metadata["h1"], json!("Guide"));
+        assert!(documents[0].metadata.get("h2").is_none());
+
+        assert_eq!(documents[1].metadata["h2"], json!("Install"));
+        assert!(documents[1].page_content.contains("# not a heading"));
+
+        assert_eq!(documents[2].metadata["h1"], json!("Guide"));
+        assert_eq!(documents[2].metadata["h2"], json!("Install"));
+        assert_eq!(documents[2].metadata["h3"], json!("Linux"));
+
+        assert_eq!(documents[3].metadata["h2"], json!("Usage"));
+        assert!(documents[3].metadata.get("h3").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_sections_are_split() {
+        let splitter = MarkdownHeaderSplitter::new(SplitterOptions::default().with_chunk_size(8));
+        let text = format!("# Title\n{}", "word ".repeat(50));
+        let documents = splitter.create_documents(&[text], &[]).await.unwrap();
+
+        assert!(documents.len() > 1);
+        assert!(documents.iter().all(|d| d.metadata["h1"] == json!("Title")));
+    }
+}
@@ -1,10 +1,12 @@
 mod error;
+mod markdown_header_splitter;
 mod markdown_splitter;
 mod options;
 mod text_splitter;
 mod token_splitter;
 
 pub use error::*;
+pub use markdown_header_splitter::*;
 pub use markdown_splitter::*;
 pub use options::*;
 pub use text_splitter::*;
@@ -0,0 +1,311 @@
+use std::{error::Error, process::Stdio, time::Duration};
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    process::Command,
+};
+
+use crate::tools::Tool;
+
+/// Runs code snippets written by the model in a subprocess and returns what they print.
+///
+/// The snippet is written to the stdin of the interpreter, which is killed when it runs
+/// longer than the timeout. Output beyond `max_output_bytes` is discarded, and on unix a
+/// virtual memory limit can be set with `with_memory_limit`. Failures, timeouts and
+/// non-zero exit codes are returned as observations so the model can fix its code.
+///
+/// # Security
+/// This is a plain subprocess, not a sandbox. The code runs with the same user,
+/// filesystem and network access as your application. Only use it with trusted models
+/// and inputs, or run the whole application inside a container or VM.
+///
+/// # Example
+/// ```rust,ignore
+/// let tool = CodeInterpreterTool::python()
+///     .with_timeout(Duration::from_secs(5))
+///     .with_memory_limit(256 * 1024 * 1024);
+/// ```
+pub struct CodeInterpreterTool {
+    language: String,
+    interpreter: String,
+    args: Vec<String>,
+    timeout: Duration,
+    max_output_bytes: usize,
+    memory_limit_bytes: Option<u64>,
+}
+
+impl CodeInterpreterTool {
+    /// Creates a tool running `language` snippets with `interpreter`, which must read the
+    /// program from stdin.
+    pub fn new<L: Into<String>, I: Into<String>>(language: L, interpreter: I) -> Self {
+        Self {
+            language: language.into(),
+            interpreter: interpreter.into(),
+            args: vec![],
+            timeout: Duration::from_secs(10),
+            max_output_bytes: 10_000,
+            memory_limit_bytes: None,
+        }
+    }
+
+    /// Runs Python snippets with `python3`.
+    pub fn python() -> Self {
+        Self::new("python", "python3")
+    }
+
+    /// Runs JavaScript snippets with `node`.
+    pub fn javascript() -> Self {
+        Self::new("javascript", "node")
+    }
+
+    pub fn with_interpreter<S: Into<String>>(mut self, interpreter: S) -> Self {
+        self.interpreter = interpreter.into();
+        self
+    }
+
+    /// Extra arguments passed to the interpreter, before the program is read from stdin.
+    pub fn with_args<S: Into<String>>(mut self, args: Vec<S>) -> Self {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Defaults to 10 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Maximum bytes kept from stdout and from stderr. Defaults to 10000.
+    pub fn with_max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
+
+    /// Limits the virtual memory of the interpreter with `ulimit -v`. Only applied on unix.
+    pub fn with_memory_limit(mut self, bytes: u64) -> Self {
+        self.memory_limit_bytes = Some(bytes);
+        self
+    }
+
+    fn command(&self) -> Command {
+        let mut command = match self.memory_limit_bytes {
+            Some(bytes) if cfg!(unix) => {
+                let mut command = Command::new("sh");
+                command
+                    .arg("-c")
+                    .arg(format!("ulimit -v {} && exec \"$0\" \"$@\"", bytes / 1024))
+                    .arg(&self.interpreter);
+                command
+            }
+            _ => Command::new(&self.interpreter),
+        };
+        command
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        command
+    }
+
+    async fn execute(&self, code: &str) -> Result<String, Box<dyn Error>> {
+        let mut child = match self.command().spawn() {
+            Ok(child) => child,
+            Err(err) => {
+                return Ok(format!(
+                    "Error: failed to start {}: {}",
+                    self.interpreter, err
+                ))
+            }
+        };
+
+        let mut stdin = child.stdin.take().ok_or("Failed to open stdin")?;
+        let stdout = child.stdout.take().ok_or("Failed to open stdout")?;
+        let stderr = child.stderr.take().ok_or("Failed to open stderr")?;
+        let max_output_bytes = self.max_output_bytes;
+        // Writing the program is part of the timeout, a process that never reads its stdin
+        // would block it once the pipe is full.
+        let execution = tokio::time::timeout(self.timeout, async {
+            let write = async {
+                let written = stdin.write_all(code.as_bytes()).await;
+                drop(stdin);
+                match written {
+                    // The process exited without reading the whole program.
+                    Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+                    written => written,
+                }
+            };
+            let (written, stdout, stderr) = tokio::join!(
+                write,
+                read_capped(stdout, max_output_bytes),
+                read_capped(stderr, max_output_bytes)
+            );
+            written?;
+            let status = child.wait().await?;
+            Ok::<_, std::io::Error>((status, stdout?, stderr?))
+        })
+        .await;
+
+        let (status, stdout, stderr) = match execution {
+            Ok(result) => result?,
+            Err(_) => {
+                let _ = child.kill().await;
+                return Ok(format!(
+                    "Error: execution timed out after {} seconds",
+                    self.timeout.as_secs_f32()
+                ));
+            }
+        };
+
+        let mut observation = String::new();
+        if !status.success() {
+            observation.push_str(&format!("Error: process exited with {}\n", status));
+        }
+        if !stdout.is_empty() {
+            observation.push_str(&stdout);
+        }
+        if !stderr.is_empty() {
+            if !observation.is_empty() && !observation.ends_with('\n') {
+                observation.push('\n');
+            }
+            observation.push_str(&format!("Stderr:\n{}", stderr));
+        }
+        if observation.is_empty() {
+            observation.push_str("Code executed successfully with no output.");
+        }
+        Ok(observation)
+    }
+}
+
+/// Reads at most `max_bytes`, then keeps draining the pipe so the process doesn't block
+/// on a full buffer.
+async fn read_capped<R: AsyncRead + Unpin>(
+    mut reader: R,
+    max_bytes: usize,
+) -> Result<String, std::io::Error> {
+    let mut buffer = Vec::new();
+    (&mut reader)
+        .take(max_bytes as u64)
+        .read_to_end(&mut buffer)
+        .await?;
+    let discarded = tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+
+    let mut output = String::from_utf8_lossy(&buffer).to_string();
+    if discarded > 0 {
+        output.push_str(&format!("\n... [{} bytes of output truncated]", discarded));
+    }
+    Ok(output)
+}
+
+/// Removes the markdown fence models often wrap code in.
+fn strip_code_fence(code: &str) -> &str {
+    let trimmed = code.trim();
+    match trimmed.strip_prefix("```") {
+        Some(rest) => {
+            let rest = rest.split_once('\n').map(|(_, body)| body).unwrap_or("");
+            rest.trim_end().trim_end_matches("```")
+        }
+        None => trimmed,
+    }
+}
+
+#[async_trait]
+impl Tool for CodeInterpreterTool {
+    fn name(&self) -> String {
+        format!("{}_interpreter", self.language)
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "Executes {} code and returns what it prints to stdout and stderr. \
+            Use print statements to see results, the value of the last expression is not returned.",
+            self.language
+        )
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "code": {
+                    "type": "string",
+                    "description": format!("The {} code to execute", self.language)
+                }
+            },
+            "required": ["code"]
+        })
+    }
+
+    async fn parse_input(&self, input: &str) -> Value {
+        match serde_json::from_str::<Value>(input) {
+            Ok(input) if input["code"].is_string() => self.parse_value(input).await,
+            _ => Value::String(input.to_string()),
+        }
+    }
+
+    async fn parse_value(&self, input: Value) -> Value {
+        match input["code"].as_str() {
+            Some(code) => Value::String(code.to_string()),
+            None => input,
+        }
+    }
+
+    async fn run(&self, input: Value) -> Result<String, Box<dyn Error>> {
+        let code = match &input {
+            Value::String(code) => code.as_str(),
+            input => input["code"].as_str().ok_or("Input should contain code")?,
+        };
+        self.execute(strip_code_fence(code)).await
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_code_interpreter() {
+        let tool = CodeInterpreterTool::new("shell", "sh");
+
+        let output = tool
+            .call(r#"{"code": "```sh\necho hello\n```"}"#)
+            .await
+            .unwrap();
+        assert_eq!(output, "hello\n");
+
+        let output = tool.call("echo oops >&2; exit 3").await.unwrap();
+        assert!(output.starts_with("Error: process exited with"));
+        assert!(output.contains("Stderr:\noops"));
+
+        let output = tool
+            .with_max_output_bytes(5)
+            .call("printf 'abcdefghij'")
+            .await
+            .unwrap();
+        assert_eq!(output, "abcde\n... [5 bytes of output truncated]");
+    }
+
+    #[tokio::test]
+    async fn test_code_interpreter_timeout() {
+        let tool = CodeInterpreterTool::new("shell", "sh").with_timeout(Duration::from_millis(100));
+
+        let output = tool.call("sleep 5").await.unwrap();
+        assert!(output.starts_with("Error: execution timed out"));
+
+        let tool = CodeInterpreterTool::new("shell", "sleep")
+            .with_args(vec!["5"])
+            .with_timeout(Duration::from_millis(100));
+        let output = tool.call(&"x".repeat(1 << 20)).await.unwrap();
+        assert!(output.starts_with("Error: execution timed out"));
+    }
+
+    #[tokio::test]
+    async fn test_code_interpreter_spawn_error() {
+        let tool = CodeInterpreterTool::new("shell", "no-such-interpreter");
+
+        let output = tool.call("echo hello").await.unwrap();
+        assert!(output.starts_with("Error: failed to start no-such-interpreter"));
+    }
+}
@@ -102,28 +102,26 @@ impl Tool for CommandExecutor {
     async fn parse_input(&self, input: &str) -> Value {
         log::info!("Parsing input: {}", input);
 
-        // Attempt to parse input string into CommandsWrapper struct first
-        let wrapper_result = serde_json::from_str::<CommandsWrapper>(input);
+        match serde_json::from_str::<Value>(input) {
+            Ok(input) => self.parse_value(input).await,
+            Err(err) => {
+                log::error!("Failed to parse input: {}", err);
+                Value::Null
+            }
+        }
+    }
 
-        if let Ok(wrapper) = wrapper_result {
-            // If successful, serialize the `commands` back into a serde_json::Value
-            // this is for llm like open ai tools
-            serde_json::to_value(wrapper.commands).unwrap_or_else(|err| {
+    async fn parse_value(&self, input: Value) -> Value {
+        // Attempt to parse input into CommandsWrapper struct first
+        // this is for llm like open ai tools
+        match serde_json::from_value::<CommandsWrapper>(input.clone()) {
+            Ok(wrapper) => serde_json::to_value(wrapper.commands).unwrap_or_else(|err| {
                 log::error!("Serialization error: {}", err);
                 Value::Null
-            })
-        } else {
-            // If the first attempt fails, try parsing it as Vec<CommandInput> directly
+            }),
+            // Otherwise it should be a Vec<CommandInput> directly, which `run` checks
             // This works on any llm
-            let commands_result = serde_json::from_str::<Vec<CommandInput>>(input);
-
-            commands_result.map_or_else(
-                |err| {
-                    log::error!("Failed to parse input: {}", err);
-                    Value::Null
-                },
-                |commands| serde_json::to_value(commands).unwrap_or(Value::Null),
-            )
+            Err(_) => input,
         }
     }
 
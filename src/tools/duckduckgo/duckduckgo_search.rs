@@ -1,7 +1,7 @@
 use std::{collections::HashMap, error::Error};
 
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -38,51 +38,48 @@ impl DuckDuckGoSearchResults {
         url.query_pairs_mut().extend_pairs(query_params.iter());
 
         let response = self.client.get(url).send().await?;
-        let body = response.text().await?;
-        let document = Html::parse_document(&body);
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Ok(
+                "DuckDuckGo is rate limiting the requests, wait a moment before searching again"
+                    .to_string(),
+            );
+        }
+        let body = response.error_for_status()?.text().await?;
+
+        let results = self.parse_results(&body);
+        if results.is_empty() {
+            return Ok(format!("No DuckDuckGo results were found for: {}", query));
+        }
+
+        Ok(serde_json::to_string(&results)?)
+    }
+
+    fn parse_results(&self, body: &str) -> Vec<SearchResult> {
+        let document = Html::parse_document(body);
 
         let result_selector = Selector::parse(".web-result").unwrap();
         let result_title_selector = Selector::parse(".result__a").unwrap();
         let result_url_selector = Selector::parse(".result__url").unwrap();
         let result_snippet_selector = Selector::parse(".result__snippet").unwrap();
 
-        let results = document
+        let select_text = |element: scraper::ElementRef, selector: &Selector| {
+            element
+                .select(selector)
+                .next()
+                .map(|e| e.text().collect::<Vec<_>>().join("").trim().to_string())
+        };
+
+        document
             .select(&result_selector)
-            .map(|result| {
-                let title = result
-                    .select(&result_title_selector)
-                    .next()
-                    .unwrap()
-                    .text()
-                    .collect::<Vec<_>>()
-                    .join("");
-                let link = result
-                    .select(&result_url_selector)
-                    .next()
-                    .unwrap()
-                    .text()
-                    .collect::<Vec<_>>()
-                    .join("")
-                    .trim()
-                    .to_string();
-                let snippet = result
-                    .select(&result_snippet_selector)
-                    .next()
-                    .unwrap()
-                    .text()
-                    .collect::<Vec<_>>()
-                    .join("");
-
-                SearchResult {
-                    title,
-                    link,
-                    snippet,
-                }
+            .filter_map(|result| {
+                Some(SearchResult {
+                    title: select_text(result, &result_title_selector)?,
+                    link: select_text(result, &result_url_selector)?,
+                    snippet: select_text(result, &result_snippet_selector).unwrap_or_default(),
+                })
             })
             .take(self.max_results)
-            .collect::<Vec<_>>();
-
-        Ok(serde_json::to_string(&results)?)
+            .collect::<Vec<_>>()
     }
 }
 
@@ -113,6 +110,13 @@ impl Tool for DuckDuckGoSearchResults {
         self.search(input).await
     }
 
+    async fn parse_value(&self, input: Value) -> Value {
+        match input["query"].as_str().or(input["input"].as_str()) {
+            Some(query) => Value::String(query.to_string()),
+            None => Value::String(input.to_string()),
+        }
+    }
+
     fn parameters(&self) -> Value {
         let prompt = r#"A wrapper around DuckDuckGo Search.
             Useful for when you need to answer questions about current events.
@@ -140,8 +144,46 @@ impl Default for DuckDuckGoSearchResults {
 
 #[cfg(test)]
 mod tests {
+    use crate::tools::Tool;
+
     use super::DuckDuckGoSearchResults;
 
+    #[test]
+    fn test_parse_results() {
+        let body = r#"
+            <div class="result web-result">
+                <a class="result__a" href="https://www.rust-lang.org">Rust</a>
+                <a class="result__url"> www.rust-lang.org </a>
+                <a class="result__snippet">A language empowering everyone.</a>
+            </div>
+            <div class="result web-result">
+                <a class="result__snippet">Result without title is skipped</a>
+            </div>
+            <div class="result web-result">
+                <a class="result__a">Crates</a>
+                <a class="result__url">crates.io</a>
+            </div>
+        "#;
+        let ddg = DuckDuckGoSearchResults::default();
+        let results = ddg.parse_results(body);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].title, "Rust");
+        assert_eq!(results[0].link, "www.rust-lang.org");
+        assert_eq!(results[1].snippet, "");
+
+        let ddg = DuckDuckGoSearchResults::default().with_max_results(1);
+        assert_eq!(ddg.parse_results(body).len(), 1);
+        assert!(ddg.parse_results("<html></html>").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_parse_input_accepts_query_key() {
+        let ddg = DuckDuckGoSearchResults::default();
+        assert_eq!(ddg.parse_input(r#"{"query": "rust"}"#).await, "rust");
+        assert_eq!(ddg.parse_input("plain rust").await, "plain rust");
+    }
+
     #[tokio::test]
     #[ignore]
     async fn duckduckgosearch_tool() {
@@ -0,0 +1,197 @@
+use std::{
+    error::Error,
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tokio::io::AsyncReadExt;
+
+use crate::tools::Tool;
+
+/// Reads text files inside a root directory. Paths are relative to the root; paths with
+/// `..` and symlinks resolving outside of the root are rejected. Missing files are reported
+/// as an observation, so the agent can try another path.
+///
+/// # Example
+/// ```rust,ignore
+/// let tool = FileReadTool::new("./docs").with_max_bytes(16 * 1024);
+/// ```
+pub struct FileReadTool {
+    root: PathBuf,
+    max_bytes: usize,
+}
+
+impl FileReadTool {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            max_bytes: 64 * 1024,
+        }
+    }
+
+    /// Files longer than `max_bytes` are truncated. Defaults to 64 KiB.
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    async fn read(&self, path: &str) -> Result<String, Box<dyn Error>> {
+        let relative = Path::new(path.trim());
+        if relative
+            .components()
+            .any(|component| matches!(component, Component::ParentDir))
+        {
+            return Err(format!("Access denied, the path contains `..`: {}", path).into());
+        }
+
+        let root = tokio::fs::canonicalize(&self.root).await?;
+        let relative = relative.strip_prefix(&root).unwrap_or(relative);
+        let relative = relative.strip_prefix("/").unwrap_or(relative);
+        let resolved = match tokio::fs::canonicalize(root.join(relative)).await {
+            Ok(resolved) => resolved,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Ok(format!("File not found: {}", path));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !resolved.starts_with(&root) {
+            return Err(format!("Access denied, the path is outside of the root: {}", path).into());
+        }
+        if resolved.is_dir() {
+            return Ok(format!("{} is a directory, not a file", path));
+        }
+
+        let file = tokio::fs::File::open(&resolved).await?;
+        let size = file.metadata().await?.len();
+        let mut content = Vec::new();
+        file.take(self.max_bytes as u64)
+            .read_to_end(&mut content)
+            .await?;
+        let truncated = size > self.max_bytes as u64;
+        if truncated {
+            // Cut before a character split by the limit instead of showing half of it.
+            if let Err(e) = std::str::from_utf8(&content) {
+                if e.error_len().is_none() {
+                    content.truncate(e.valid_up_to());
+                }
+            }
+        }
+        let shown = content.len();
+        let mut content = String::from_utf8_lossy(&content).into_owned();
+        if truncated {
+            content.push_str(&format!(
+                "\n[truncated, showing {} of {} bytes]",
+                shown, size
+            ));
+        }
+        Ok(content)
+    }
+}
+
+#[async_trait]
+impl Tool for FileReadTool {
+    fn name(&self) -> String {
+        String::from("Read_File")
+    }
+
+    fn description(&self) -> String {
+        String::from(
+            "Reads a text file and returns its content. \
+            The input should be the path of the file, relative to the directory the tool \
+            reads from.",
+        )
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The file path, relative to the directory the tool reads from"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    async fn parse_input(&self, input: &str) -> Value {
+        match serde_json::from_str::<Value>(input) {
+            Ok(value @ (Value::Object(_) | Value::String(_))) => self.parse_value(value).await,
+            _ => Value::String(input.to_string()),
+        }
+    }
+
+    async fn parse_value(&self, input: Value) -> Value {
+        match input {
+            Value::Object(object) => object
+                .get("path")
+                .or_else(|| object.get("input"))
+                .cloned()
+                .unwrap_or_default(),
+            input => input,
+        }
+    }
+
+    async fn run(&self, input: Value) -> Result<String, Box<dyn Error>> {
+        let path = input.as_str().ok_or("Input should be a file path")?;
+        self.read(path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_read_tool() {
+        let dir = env::temp_dir().join(format!("file_read_tool_test_{}", std::process::id()));
+        let root = dir.join("root");
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+        std::fs::write(root.join("notes/todo.txt"), "buy milk").unwrap();
+        std::fs::write(root.join("long.txt"), "0123456789").unwrap();
+        std::fs::write(root.join("accents.txt"), "héllo").unwrap();
+        std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+
+        let tool = FileReadTool::new(&root);
+        assert_eq!(tool.call("notes/todo.txt").await.unwrap(), "buy milk");
+        let input = json!({"path": "notes/todo.txt"});
+        assert_eq!(tool.call_value(&input).await.unwrap(), "buy milk");
+        assert!(tool
+            .call("missing.txt")
+            .await
+            .unwrap()
+            .starts_with("File not found"));
+        assert!(tool.call("../secret.txt").await.is_err());
+        assert!(tool
+            .call("/etc/passwd")
+            .await
+            .unwrap()
+            .starts_with("File not found"));
+
+        let tool = FileReadTool::new(&root).with_max_bytes(4);
+        assert_eq!(
+            tool.call("long.txt").await.unwrap(),
+            "0123\n[truncated, showing 4 of 10 bytes]"
+        );
+        let tool = FileReadTool::new(&root).with_max_bytes(2);
+        assert_eq!(
+            tool.call("accents.txt").await.unwrap(),
+            "h\n[truncated, showing 1 of 6 bytes]"
+        );
+
+        #[cfg(unix)]
+        {
+            let link = root.join("escape.txt");
+            let _ = std::fs::remove_file(&link);
+            std::os::unix::fs::symlink(dir.join("secret.txt"), &link).unwrap();
+            assert!(tool.call("escape.txt").await.is_err());
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
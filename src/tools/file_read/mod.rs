@@ -0,0 +1,2 @@
+mod file_read;
+pub use file_read::*;
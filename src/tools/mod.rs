@@ -19,5 +19,14 @@ pub use serpapi::*;
 mod command_executor;
 pub use command_executor::*;
 
+mod file_read;
+pub use file_read::*;
+
 mod text2speech;
 pub use text2speech::*;
+
+mod weather;
+pub use weather::*;
+
+mod code_interpreter;
+pub use code_interpreter::*;
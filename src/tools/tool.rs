@@ -1,5 +1,6 @@
 use std::error::Error;
 use std::string::String;
+use std::sync::{Arc, OnceLock};
 
 use async_trait::async_trait;
 use serde_json::{json, Value};
@@ -42,11 +43,15 @@ pub trait Tool: Send + Sync {
 
     /// Processes an input string and executes the tool's functionality, returning a `Result`.
     ///
-    /// This function utilizes `parse_input` to parse the input and then calls `run`.
-    /// Its used by the Agent
+    /// This function utilizes `parse_input` to parse the input and then calls `run_typed`,
+    /// which calls `run` unless overridden. Structured results are returned as JSON.
+    /// The agent executor calls it, through `call_value`, for string inputs.
     async fn call(&self, input: &str) -> Result<String, Box<dyn Error>> {
         let input = self.parse_input(input).await;
-        self.run(input).await
+        match self.run_typed(input).await? {
+            Value::String(output) => Ok(output),
+            output => Ok(output.to_string()),
+        }
     }
 
     /// Executes the core functionality of the tool.
@@ -60,21 +65,374 @@ pub trait Tool: Send + Sync {
     /// ```
     async fn run(&self, input: Value) -> Result<String, Box<dyn Error>>;
 
+    /// Processes an input string and executes the tool returning a structured result.
+    ///
+    /// This function utilizes `parse_input` to parse the input and then calls `run_typed`.
+    /// Its used by the Agent
+    async fn call_typed(&self, input: &str) -> Result<Value, Box<dyn Error>> {
+        let input = self.parse_input(input).await;
+        self.run_typed(input).await
+    }
+
+    /// Executes the tool with the input of an agent action, this is what the agent executor
+    /// calls. Strings go through `call`, so tools overriding it keep working, then
+    /// `parse_input` and `run_typed`. Structured inputs, like the arguments of an OpenAI
+    /// tool call, go through `parse_value` and straight to `run_typed`.
+    async fn call_value(&self, input: &Value) -> Result<Value, Box<dyn Error>> {
+        match input {
+            Value::String(input) => self.call(input).await.map(Value::String),
+            input => {
+                let input = self.parse_value(input.clone()).await;
+                self.run_typed(input).await
+            }
+        }
+    }
+
+    /// Executes the tool returning a JSON value, so rich results like tables or nested data
+    /// reach the model without being flattened. The default wraps the result of `run` in a
+    /// `Value::String`, override it when the tool produces structured data.
+    ///
+    /// Example implementation:
+    /// ```rust,ignore
+    /// async fn run_typed(&self, input: Value) -> Result<Value, Box<dyn Error>> {
+    ///     let rows = self.query(input.as_str().ok_or("Input should be a string")?).await?;
+    ///     Ok(json!({ "rows": rows }))
+    /// }
+    /// ```
+    async fn run_typed(&self, input: Value) -> Result<Value, Box<dyn Error>> {
+        self.run(input).await.map(Value::String)
+    }
+
+    /// Formats a successful result of the tool before the agent executor gives it to the
+    /// model as the observation, e.g. to pretty-print JSON or keep only its key fields.
+    /// The default returns it unchanged.
+    fn format_observation(&self, observation: &str) -> String {
+        observation.to_string()
+    }
+
     /// Parses the input string, which could be a JSON value or a raw string, depending on the LLM model.
     ///
-    /// Implement this function to extract the parameters needed for your tool. If a simple
-    /// string is sufficient, the default implementation can be used.
+    /// The default passes JSON to `parse_value` and keeps anything else as a string. If a
+    /// simple string is sufficient, the default implementation can be used.
     async fn parse_input(&self, input: &str) -> Value {
         log::info!("Using default implementation: {}", input);
         match serde_json::from_str::<Value>(input) {
-            Ok(input) => {
-                if input["input"].is_string() {
-                    Value::String(input["input"].as_str().unwrap().to_string())
-                } else {
-                    Value::String(input.to_string())
-                }
-            }
+            Ok(input) => self.parse_value(input).await,
             Err(_) => Value::String(input.to_string()),
         }
     }
+
+    /// Parses a structured input, like the arguments of an OpenAI tool call, into the value
+    /// given to `run_typed`. `call_value` calls it without turning the input into a string.
+    ///
+    /// Implement this function, along with `parse_input`, to extract the parameters needed
+    /// for your tool. The default keeps the `input` field, or the whole value as a string.
+    async fn parse_value(&self, input: Value) -> Value {
+        match input["input"].as_str() {
+            Some(input) => Value::String(input.to_string()),
+            None => Value::String(input.to_string()),
+        }
+    }
+}
+
+/// Returns the name under which a tool is shown to the model and looked up by the agent
+/// executor: whitespace is trimmed and inner runs of whitespace become `_`, as function
+/// names can't contain spaces.
+pub fn normalize_tool_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/// `DescribedTool` wraps a tool and overrides the description shown to the LLM,
+/// while the name, parameters and execution are delegated to the inner tool.
+///
+/// # Usage
+/// ```rust,ignore
+/// let search: Arc<dyn Tool> = Arc::new(SerpApi::default());
+/// let tool = DescribedTool::new(search, "Search recipes on the internet");
+/// ```
+pub struct DescribedTool {
+    tool: Arc<dyn Tool>,
+    description: String,
+}
+
+impl DescribedTool {
+    pub fn new<S: Into<String>>(tool: Arc<dyn Tool>, description: S) -> Self {
+        Self {
+            tool,
+            description: description.into(),
+        }
+    }
+
+    /// Wraps the tool only when a description override is provided.
+    pub fn wrap(tool: Arc<dyn Tool>, description: Option<String>) -> Arc<dyn Tool> {
+        match description {
+            Some(description) => Arc::new(Self::new(tool, description)),
+            None => tool,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for DescribedTool {
+    fn name(&self) -> String {
+        self.tool.name()
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    fn parameters(&self) -> Value {
+        self.tool.parameters()
+    }
+
+    async fn call(&self, input: &str) -> Result<String, Box<dyn Error>> {
+        self.tool.call(input).await
+    }
+
+    async fn run(&self, input: Value) -> Result<String, Box<dyn Error>> {
+        self.tool.run(input).await
+    }
+
+    async fn call_typed(&self, input: &str) -> Result<Value, Box<dyn Error>> {
+        self.tool.call_typed(input).await
+    }
+
+    async fn call_value(&self, input: &Value) -> Result<Value, Box<dyn Error>> {
+        self.tool.call_value(input).await
+    }
+
+    async fn run_typed(&self, input: Value) -> Result<Value, Box<dyn Error>> {
+        self.tool.run_typed(input).await
+    }
+
+    fn format_observation(&self, observation: &str) -> String {
+        self.tool.format_observation(observation)
+    }
+
+    async fn parse_input(&self, input: &str) -> Value {
+        self.tool.parse_input(input).await
+    }
+
+    async fn parse_value(&self, input: Value) -> Value {
+        self.tool.parse_value(input).await
+    }
+}
+
+type ToolFactory = Box<dyn Fn() -> Arc<dyn Tool> + Send + Sync>;
+
+/// `LazyTool` defers building a tool, e.g. one that opens a database connection, until the
+/// agent first calls it. The name, description and parameters are given upfront, since
+/// they're needed to build the agent prompt.
+///
+/// The factory is synchronous and runs on the async task making the first call, so it
+/// shouldn't block: connect inside the tool's `run` instead, or use
+/// `tokio::task::block_in_place` for a factory that must block.
+///
+/// # Usage
+/// ```rust,ignore
+/// let sql = LazyTool::new("sql", "Query the orders database", || {
+///     Arc::new(SqlTool::connect(&database_url))
+/// });
+/// let agent = ConversationalAgentBuilder::new().add_tool(Arc::new(sql)).build(llm)?;
+/// ```
+pub struct LazyTool {
+    name: String,
+    description: String,
+    parameters: Option<Value>,
+    factory: ToolFactory,
+    tool: OnceLock<Arc<dyn Tool>>,
+}
+
+impl LazyTool {
+    pub fn new<S, D, F>(name: S, description: D, factory: F) -> Self
+    where
+        S: Into<String>,
+        D: Into<String>,
+        F: Fn() -> Arc<dyn Tool> + Send + Sync + 'static,
+    {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            parameters: None,
+            factory: Box::new(factory),
+            tool: OnceLock::new(),
+        }
+    }
+
+    /// The parameters shown to OpenAI-like function calling. Defaults to a single `input`
+    /// string, like `Tool::parameters`.
+    pub fn with_parameters(mut self, parameters: Value) -> Self {
+        self.parameters = Some(parameters);
+        self
+    }
+
+    /// Whether the inner tool has been built.
+    pub fn is_initialized(&self) -> bool {
+        self.tool.get().is_some()
+    }
+
+    fn tool(&self) -> &Arc<dyn Tool> {
+        self.tool.get_or_init(|| (self.factory)())
+    }
+}
+
+#[async_trait]
+impl Tool for LazyTool {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    fn parameters(&self) -> Value {
+        match &self.parameters {
+            Some(parameters) => parameters.clone(),
+            None => json!({
+                "type": "object",
+                "properties": {
+                    "input": {
+                        "type": "string",
+                        "description": self.description
+                    }
+                },
+                "required": ["input"]
+            }),
+        }
+    }
+
+    async fn call(&self, input: &str) -> Result<String, Box<dyn Error>> {
+        self.tool().call(input).await
+    }
+
+    async fn run(&self, input: Value) -> Result<String, Box<dyn Error>> {
+        self.tool().run(input).await
+    }
+
+    async fn call_typed(&self, input: &str) -> Result<Value, Box<dyn Error>> {
+        self.tool().call_typed(input).await
+    }
+
+    async fn call_value(&self, input: &Value) -> Result<Value, Box<dyn Error>> {
+        self.tool().call_value(input).await
+    }
+
+    async fn run_typed(&self, input: Value) -> Result<Value, Box<dyn Error>> {
+        self.tool().run_typed(input).await
+    }
+
+    fn format_observation(&self, observation: &str) -> String {
+        self.tool().format_observation(observation)
+    }
+
+    async fn parse_input(&self, input: &str) -> Value {
+        self.tool().parse_input(input).await
+    }
+
+    async fn parse_value(&self, input: Value) -> Value {
+        self.tool().parse_value(input).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    struct Echo {}
+
+    #[async_trait]
+    impl Tool for Echo {
+        fn name(&self) -> String {
+            "echo".to_string()
+        }
+
+        fn description(&self) -> String {
+            "Echoes the input".to_string()
+        }
+
+        async fn run(&self, input: Value) -> Result<String, Box<dyn Error>> {
+            Ok(input.as_str().unwrap_or_default().to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lazy_tool() {
+        let built = Arc::new(AtomicUsize::new(0));
+        let counter = built.clone();
+        let tool = LazyTool::new("echo", "Echoes the input", move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Arc::new(Echo {}) as Arc<dyn Tool>
+        });
+
+        assert_eq!(tool.name(), "echo");
+        assert_eq!(tool.parameters()["required"][0], "input");
+        assert!(!tool.is_initialized());
+
+        assert_eq!(tool.call("hi").await.unwrap(), "hi");
+        assert_eq!(tool.call("again").await.unwrap(), "again");
+        assert_eq!(built.load(Ordering::SeqCst), 1);
+    }
+
+    struct Shouting {}
+
+    #[async_trait]
+    impl Tool for Shouting {
+        fn name(&self) -> String {
+            "shout".to_string()
+        }
+
+        fn description(&self) -> String {
+            "Shouts the input".to_string()
+        }
+
+        async fn call(&self, input: &str) -> Result<String, Box<dyn Error>> {
+            Ok(input.to_uppercase())
+        }
+
+        async fn run(&self, _input: Value) -> Result<String, Box<dyn Error>> {
+            Ok("unused".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_value_goes_through_call() {
+        let output = Shouting {}.call_value(&json!("hi")).await.unwrap();
+        assert_eq!(output, "HI");
+    }
+
+    struct Structured {}
+
+    #[async_trait]
+    impl Tool for Structured {
+        fn name(&self) -> String {
+            "structured".to_string()
+        }
+
+        fn description(&self) -> String {
+            "Returns its arguments".to_string()
+        }
+
+        async fn run(&self, _input: Value) -> Result<String, Box<dyn Error>> {
+            Ok("unused".to_string())
+        }
+
+        async fn run_typed(&self, input: Value) -> Result<Value, Box<dyn Error>> {
+            Ok(input)
+        }
+
+        async fn parse_value(&self, input: Value) -> Value {
+            input
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_value_passes_objects_to_run_typed() {
+        let input = json!({"ids": [1, 2], "filter": {"active": true}});
+        let output = Structured {}.call_value(&input).await.unwrap();
+        assert_eq!(output, input);
+    }
 }
@@ -0,0 +1,2 @@
+mod open_meteo;
+pub use open_meteo::*;
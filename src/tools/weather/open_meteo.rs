@@ -0,0 +1,310 @@
+use std::error::Error;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+
+use crate::tools::Tool;
+
+const CURRENT_VARIABLES: &str =
+    "temperature_2m,apparent_temperature,relative_humidity_2m,weather_code,wind_speed_10m";
+
+/// Where to get the weather for: a place name resolved with the Open-Meteo geocoding API,
+/// or explicit coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WeatherLocation {
+    Place(String),
+    Coordinates { latitude: f64, longitude: f64 },
+}
+
+/// Returns the current weather using the free [Open-Meteo](https://open-meteo.com) API.
+/// No API key is required.
+pub struct WeatherTool {
+    client: Client,
+    forecast_url: String,
+    geocoding_url: String,
+}
+
+impl WeatherTool {
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+            forecast_url: "https://api.open-meteo.com/v1/forecast".to_string(),
+            geocoding_url: "https://geocoding-api.open-meteo.com/v1/search".to_string(),
+        }
+    }
+
+    pub fn with_forecast_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.forecast_url = url.into();
+        self
+    }
+
+    pub fn with_geocoding_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.geocoding_url = url.into();
+        self
+    }
+
+    pub async fn current_weather(
+        &self,
+        location: &WeatherLocation,
+    ) -> Result<String, Box<dyn Error>> {
+        let (name, latitude, longitude) = match location {
+            WeatherLocation::Coordinates {
+                latitude,
+                longitude,
+            } => (
+                format!("{}, {}", latitude, longitude),
+                *latitude,
+                *longitude,
+            ),
+            WeatherLocation::Place(place) => match self.geocode(place).await? {
+                Some(found) => found,
+                None => return Ok(format!("Could not find a location named \"{}\"", place)),
+            },
+        };
+
+        let response: Value = self
+            .client
+            .get(&self.forecast_url)
+            .query(&[
+                ("latitude", latitude.to_string()),
+                ("longitude", longitude.to_string()),
+                ("current", CURRENT_VARIABLES.to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        format_current_weather(&name, &response)
+    }
+
+    async fn geocode(&self, place: &str) -> Result<Option<(String, f64, f64)>, Box<dyn Error>> {
+        let response: Value = self
+            .client
+            .get(&self.geocoding_url)
+            .query(&[("name", place), ("count", "1")])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(parse_geocoding(&response))
+    }
+}
+
+impl Default for WeatherTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_geocoding(response: &Value) -> Option<(String, f64, f64)> {
+    let result = response["results"].get(0)?;
+    let name = match result["country"].as_str() {
+        Some(country) => format!("{}, {}", result["name"].as_str()?, country),
+        None => result["name"].as_str()?.to_string(),
+    };
+    Some((
+        name,
+        result["latitude"].as_f64()?,
+        result["longitude"].as_f64()?,
+    ))
+}
+
+fn format_current_weather(name: &str, response: &Value) -> Result<String, Box<dyn Error>> {
+    let current = &response["current"];
+    let units = &response["current_units"];
+    let value = |key: &str| -> Result<String, Box<dyn Error>> {
+        let value = current[key]
+            .as_f64()
+            .ok_or_else(|| format!("Missing {} in Open-Meteo response", key))?;
+        Ok(format!("{}{}", value, units[key].as_str().unwrap_or("")))
+    };
+    let conditions = current["weather_code"]
+        .as_u64()
+        .map(weather_code_description)
+        .unwrap_or("unknown conditions");
+
+    Ok(format!(
+        "Current weather in {}: {}, temperature {} (feels like {}), humidity {}, wind {}",
+        name,
+        conditions,
+        value("temperature_2m")?,
+        value("apparent_temperature")?,
+        value("relative_humidity_2m")?,
+        value("wind_speed_10m")?,
+    ))
+}
+
+/// Description of the WMO weather interpretation codes used by Open-Meteo.
+fn weather_code_description(code: u64) -> &'static str {
+    match code {
+        0 => "clear sky",
+        1 => "mainly clear",
+        2 => "partly cloudy",
+        3 => "overcast",
+        45 | 48 => "fog",
+        51 | 53 | 55 => "drizzle",
+        56 | 57 => "freezing drizzle",
+        61 | 63 | 65 => "rain",
+        66 | 67 => "freezing rain",
+        71 | 73 | 75 | 77 => "snow",
+        80..=82 => "rain showers",
+        85 | 86 => "snow showers",
+        95 => "thunderstorm",
+        96 | 99 => "thunderstorm with hail",
+        _ => "unknown conditions",
+    }
+}
+
+#[async_trait]
+impl Tool for WeatherTool {
+    fn name(&self) -> String {
+        String::from("Weather")
+    }
+
+    fn description(&self) -> String {
+        String::from(
+            "Useful for when you need to know the current weather of a place. \
+            Input should be a place name (e.g. \"Lima, Peru\") or coordinates as \"latitude,longitude\".",
+        )
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "description": self.description(),
+            "type": "object",
+            "properties": {
+                "location": {
+                    "type": "string",
+                    "description": "Name of the place to get the weather for"
+                },
+                "latitude": {
+                    "type": "number",
+                    "description": "Latitude, used together with longitude instead of location"
+                },
+                "longitude": {
+                    "type": "number",
+                    "description": "Longitude, used together with latitude instead of location"
+                }
+            }
+        })
+    }
+
+    async fn parse_input(&self, input: &str) -> Value {
+        match serde_json::from_str::<Value>(input) {
+            Ok(value) if value.is_object() => self.parse_value(value).await,
+            _ => Value::String(input.to_string()),
+        }
+    }
+
+    async fn parse_value(&self, input: Value) -> Value {
+        input
+    }
+
+    async fn run(&self, input: Value) -> Result<String, Box<dyn Error>> {
+        let location =
+            parse_location(&input).ok_or("Input should be a place name or coordinates")?;
+        self.current_weather(&location).await
+    }
+}
+
+fn parse_location(input: &Value) -> Option<WeatherLocation> {
+    let latitude = input["latitude"].as_f64();
+    let longitude = input["longitude"].as_f64();
+    if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+        return Some(WeatherLocation::Coordinates {
+            latitude,
+            longitude,
+        });
+    }
+    let text = input["location"]
+        .as_str()
+        .or(input["input"].as_str())
+        .or(input.as_str())?
+        .trim();
+    if text.is_empty() {
+        return None;
+    }
+    if let Some((latitude, longitude)) = text.split_once(',') {
+        if let (Ok(latitude), Ok(longitude)) = (
+            latitude.trim().parse::<f64>(),
+            longitude.trim().parse::<f64>(),
+        ) {
+            return Some(WeatherLocation::Coordinates {
+                latitude,
+                longitude,
+            });
+        }
+    }
+    Some(WeatherLocation::Place(text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!(
+            parse_location(&json!("Lima, Peru")),
+            Some(WeatherLocation::Place("Lima, Peru".to_string()))
+        );
+        assert_eq!(
+            parse_location(&json!("-12.04, -77.03")),
+            Some(WeatherLocation::Coordinates {
+                latitude: -12.04,
+                longitude: -77.03
+            })
+        );
+        assert_eq!(
+            parse_location(&json!({"latitude": 1.5, "longitude": 2.0})),
+            Some(WeatherLocation::Coordinates {
+                latitude: 1.5,
+                longitude: 2.0
+            })
+        );
+        assert_eq!(parse_location(&json!("  ")), None);
+    }
+
+    #[test]
+    fn test_format_responses() {
+        assert_eq!(parse_geocoding(&json!({"generationtime_ms": 0.5})), None);
+        assert_eq!(
+            parse_geocoding(&json!({"results": [
+                {"name": "Lima", "country": "Peru", "latitude": -12.04, "longitude": -77.03}
+            ]})),
+            Some(("Lima, Peru".to_string(), -12.04, -77.03))
+        );
+
+        let forecast = json!({
+            "current_units": {
+                "temperature_2m": "°C",
+                "apparent_temperature": "°C",
+                "relative_humidity_2m": "%",
+                "wind_speed_10m": "km/h"
+            },
+            "current": {
+                "temperature_2m": 18.5,
+                "apparent_temperature": 17.9,
+                "relative_humidity_2m": 80,
+                "weather_code": 3,
+                "wind_speed_10m": 12.4
+            }
+        });
+        assert_eq!(
+            format_current_weather("Lima, Peru", &forecast).unwrap(),
+            "Current weather in Lima, Peru: overcast, temperature 18.5°C (feels like 17.9°C), humidity 80%, wind 12.4km/h"
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_weather_tool() {
+        let weather = WeatherTool::default();
+        let result = weather.call("Lima, Peru").await.unwrap();
+        println!("{}", result);
+    }
+}
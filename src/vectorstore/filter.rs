@@ -0,0 +1,170 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use serde_json::Value;
+
+/// A filter expression evaluated against the metadata of a document.
+///
+/// # Usage
+/// ```rust,ignore
+/// let filter = MetadataFilter::eq("source", "manual").and(MetadataFilter::gte("year", 2020));
+/// let options = VecStoreOptions::new().with_metadata_filter(filter);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataFilter {
+    Eq(String, Value),
+    Ne(String, Value),
+    Gt(String, Value),
+    Gte(String, Value),
+    Lt(String, Value),
+    Lte(String, Value),
+    In(String, Vec<Value>),
+    And(Vec<MetadataFilter>),
+    Or(Vec<MetadataFilter>),
+    Not(Box<MetadataFilter>),
+}
+
+impl MetadataFilter {
+    pub fn eq<K: Into<String>, V: Into<Value>>(key: K, value: V) -> Self {
+        MetadataFilter::Eq(key.into(), value.into())
+    }
+
+    pub fn ne<K: Into<String>, V: Into<Value>>(key: K, value: V) -> Self {
+        MetadataFilter::Ne(key.into(), value.into())
+    }
+
+    pub fn gt<K: Into<String>, V: Into<Value>>(key: K, value: V) -> Self {
+        MetadataFilter::Gt(key.into(), value.into())
+    }
+
+    pub fn gte<K: Into<String>, V: Into<Value>>(key: K, value: V) -> Self {
+        MetadataFilter::Gte(key.into(), value.into())
+    }
+
+    pub fn lt<K: Into<String>, V: Into<Value>>(key: K, value: V) -> Self {
+        MetadataFilter::Lt(key.into(), value.into())
+    }
+
+    pub fn lte<K: Into<String>, V: Into<Value>>(key: K, value: V) -> Self {
+        MetadataFilter::Lte(key.into(), value.into())
+    }
+
+    /// Matches when the value of `key` is any of `values`.
+    pub fn is_in<K: Into<String>, V: Into<Value>>(key: K, values: Vec<V>) -> Self {
+        MetadataFilter::In(key.into(), values.into_iter().map(Into::into).collect())
+    }
+
+    /// Matches when `min <= value <= max`.
+    pub fn between<K: Into<String>, V: Into<Value>>(key: K, min: V, max: V) -> Self {
+        let key = key.into();
+        MetadataFilter::And(vec![
+            MetadataFilter::gte(key.clone(), min),
+            MetadataFilter::lte(key, max),
+        ])
+    }
+
+    pub fn and(self, other: MetadataFilter) -> Self {
+        match self {
+            MetadataFilter::And(mut filters) => {
+                filters.push(other);
+                MetadataFilter::And(filters)
+            }
+            filter => MetadataFilter::And(vec![filter, other]),
+        }
+    }
+
+    pub fn or(self, other: MetadataFilter) -> Self {
+        match self {
+            MetadataFilter::Or(mut filters) => {
+                filters.push(other);
+                MetadataFilter::Or(filters)
+            }
+            filter => MetadataFilter::Or(vec![filter, other]),
+        }
+    }
+
+    pub fn not(self) -> Self {
+        MetadataFilter::Not(Box::new(self))
+    }
+
+    /// Returns true if the given metadata satisfies the filter.
+    /// Comparisons on missing keys or on values of different types never match.
+    pub fn matches(&self, metadata: &HashMap<String, Value>) -> bool {
+        match self {
+            MetadataFilter::Eq(key, value) => {
+                metadata.get(key).is_some_and(|v| values_eq(v, value))
+            }
+            MetadataFilter::Ne(key, value) => {
+                !metadata.get(key).is_some_and(|v| values_eq(v, value))
+            }
+            MetadataFilter::Gt(key, value) => compare(metadata, key, value, |o| o.is_gt()),
+            MetadataFilter::Gte(key, value) => compare(metadata, key, value, |o| o.is_ge()),
+            MetadataFilter::Lt(key, value) => compare(metadata, key, value, |o| o.is_lt()),
+            MetadataFilter::Lte(key, value) => compare(metadata, key, value, |o| o.is_le()),
+            MetadataFilter::In(key, values) => metadata
+                .get(key)
+                .is_some_and(|v| values.iter().any(|value| values_eq(v, value))),
+            MetadataFilter::And(filters) => filters.iter().all(|f| f.matches(metadata)),
+            MetadataFilter::Or(filters) => filters.iter().any(|f| f.matches(metadata)),
+            MetadataFilter::Not(filter) => !filter.matches(metadata),
+        }
+    }
+}
+
+fn values_eq(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn compare(
+    metadata: &HashMap<String, Value>,
+    key: &str,
+    value: &Value,
+    predicate: impl Fn(Ordering) -> bool,
+) -> bool {
+    let ordering = match (metadata.get(key), value) {
+        (Some(Value::Number(a)), Value::Number(b)) => a
+            .as_f64()
+            .zip(b.as_f64())
+            .and_then(|(a, b)| a.partial_cmp(&b)),
+        (Some(Value::String(a)), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    ordering.is_some_and(predicate)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn metadata(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_metadata_filter_matches() {
+        let doc = metadata(json!({"source": "manual", "year": 2021, "lang": "en"}));
+
+        assert!(MetadataFilter::eq("source", "manual").matches(&doc));
+        assert!(!MetadataFilter::eq("source", "blog").matches(&doc));
+        assert!(MetadataFilter::ne("source", "blog").matches(&doc));
+        assert!(MetadataFilter::eq("year", 2021.0).matches(&doc));
+        assert!(MetadataFilter::gte("year", 2020).matches(&doc));
+        assert!(!MetadataFilter::lt("year", 2021).matches(&doc));
+        assert!(MetadataFilter::between("year", 2020, 2021).matches(&doc));
+        assert!(MetadataFilter::is_in("lang", vec!["en", "es"]).matches(&doc));
+        assert!(!MetadataFilter::gt("missing", 1).matches(&doc));
+        assert!(!MetadataFilter::gt("source", 1).matches(&doc));
+
+        let filter = MetadataFilter::eq("source", "manual").and(MetadataFilter::gte("year", 2022));
+        assert!(!filter.matches(&doc));
+        assert!(filter
+            .clone()
+            .or(MetadataFilter::eq("lang", "en"))
+            .matches(&doc));
+        assert!(filter.not().matches(&doc));
+    }
+}
@@ -0,0 +1,297 @@
+use std::{error::Error, path::Path, sync::Arc, sync::RwLock};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    embedding::embedder_trait::Embedder,
+    schemas::Document,
+    semantic_router::utils::cosine_similarity,
+    vectorstore::{VecStoreOptions, VectorStore, VectorStoreSnapshot},
+};
+
+/// A vector store that keeps documents and their embeddings in memory.
+/// Useful for tests, demos and small corpora that don't need a database.
+///
+/// # Usage
+/// ```rust,ignore
+/// let store = InMemoryVectorStore::new(OpenAiEmbedder::default());
+/// add_documents!(store, &documents).await?;
+/// let results = similarity_search!(store, "query", 4).await?;
+/// ```
+pub struct InMemoryVectorStore {
+    embedder: Arc<dyn Embedder>,
+    entries: RwLock<Vec<(Document, Vec<f64>)>>,
+    dimensions: Option<usize>,
+}
+
+impl InMemoryVectorStore {
+    pub fn new<E: Embedder + 'static>(embedder: E) -> Self {
+        Self {
+            embedder: Arc::new(embedder),
+            entries: RwLock::new(Vec::new()),
+            dimensions: None,
+        }
+    }
+
+    /// Sets the dimensions of the embedder vectors, so loading a snapshot saved with another
+    /// embedder fails instead of returning meaningless scores.
+    pub fn with_dimensions(mut self, dimensions: usize) -> Self {
+        self.dimensions = Some(dimensions);
+        self
+    }
+
+    /// Number of documents stored.
+    pub fn len(&self) -> usize {
+        self.entries
+            .read()
+            .map(|entries| entries.len())
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl VectorStore for InMemoryVectorStore {
+    /// Add documents to the store.
+    /// Returns the positions of the documents in the store as IDs.
+    async fn add_documents(
+        &self,
+        docs: &[Document],
+        opt: &VecStoreOptions,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let embedder = opt.embedder.as_ref().unwrap_or(&self.embedder);
+        let texts: Vec<String> = docs.iter().map(|d| d.page_content.clone()).collect();
+        let vectors = embedder.embed_documents(&texts).await?;
+
+        let mut entries = self
+            .entries
+            .write()
+            .map_err(|_| "InMemoryVectorStore lock poisoned")?;
+        let first_id = entries.len();
+        entries.extend(docs.iter().cloned().zip(vectors));
+
+        Ok((first_id..entries.len()).map(|id| id.to_string()).collect())
+    }
+
+    /// Perform a similarity search on the store.
+    /// Documents not matching `metadata_filter` are discarded before ranking.
+    async fn similarity_search(
+        &self,
+        query: &str,
+        limit: usize,
+        opt: &VecStoreOptions,
+    ) -> Result<Vec<Document>, Box<dyn Error>> {
+        if opt.name_space.is_some() {
+            return Err("InMemoryVectorStore doesn't support namespaces".into());
+        }
+        if opt.filters.is_some() {
+            return Err(
+                "InMemoryVectorStore doesn't support `filters`, use `metadata_filter` instead"
+                    .into(),
+            );
+        }
+
+        let embedder = opt.embedder.as_ref().unwrap_or(&self.embedder);
+        let query_vector = embedder.embed_query(query).await?;
+
+        let entries = self
+            .entries
+            .read()
+            .map_err(|_| "InMemoryVectorStore lock poisoned")?;
+        let mut documents: Vec<Document> = entries
+            .iter()
+            .filter(|(doc, _)| {
+                opt.metadata_filter
+                    .as_ref()
+                    .map_or(true, |filter| filter.matches(&doc.metadata))
+            })
+            .map(|(doc, vector)| {
+                let mut doc = doc.clone();
+                doc.score = cosine_similarity(&query_vector, vector);
+                doc
+            })
+            .filter(|doc| {
+                opt.score_threshold
+                    .map_or(true, |threshold| doc.score >= threshold as f64)
+            })
+            .collect();
+
+        documents.sort_by(|a, b| b.score.total_cmp(&a.score));
+        documents.truncate(limit);
+
+        Ok(documents)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    dimensions: usize,
+    entries: Vec<SnapshotEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotEntry {
+    document: Document,
+    embedding: Vec<f64>,
+}
+
+/// Snapshots are JSON files holding the dimensions of their embeddings. Loading checks them
+/// against the dimensions set with `with_dimensions`, without calling the embedder.
+#[async_trait]
+impl VectorStoreSnapshot for InMemoryVectorStore {
+    async fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let json = {
+            let entries = self
+                .entries
+                .read()
+                .map_err(|_| "InMemoryVectorStore lock poisoned")?;
+            let snapshot = Snapshot {
+                dimensions: entries.first().map_or(0, |(_, vector)| vector.len()),
+                entries: entries
+                    .iter()
+                    .map(|(document, embedding)| SnapshotEntry {
+                        document: document.clone(),
+                        embedding: embedding.clone(),
+                    })
+                    .collect(),
+            };
+            serde_json::to_vec(&snapshot)?
+        };
+        tokio::fs::write(path, json).await?;
+        Ok(())
+    }
+
+    async fn load(&self, path: &Path) -> Result<usize, Box<dyn Error>> {
+        let json = tokio::fs::read(path).await?;
+        let snapshot: Snapshot = serde_json::from_slice(&json)?;
+        if let Some(entry) = snapshot
+            .entries
+            .iter()
+            .find(|entry| entry.embedding.len() != snapshot.dimensions)
+        {
+            return Err(format!(
+                "Snapshot embedding of {} dimensions, expected {}",
+                entry.embedding.len(),
+                snapshot.dimensions
+            )
+            .into());
+        }
+        if let Some(dimensions) = self.dimensions {
+            if !snapshot.entries.is_empty() && dimensions != snapshot.dimensions {
+                return Err(format!(
+                    "Snapshot embeddings have {} dimensions but the store expects {}",
+                    snapshot.dimensions, dimensions
+                )
+                .into());
+            }
+        }
+
+        let mut entries = self
+            .entries
+            .write()
+            .map_err(|_| "InMemoryVectorStore lock poisoned")?;
+        *entries = snapshot
+            .entries
+            .into_iter()
+            .map(|entry| (entry.document, entry.embedding))
+            .collect();
+        Ok(entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        add_documents, embedding::EmbedderError, similarity_search, vectorstore::MetadataFilter,
+    };
+
+    use super::*;
+
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl Embedder for KeywordEmbedder {
+        async fn embed_documents(
+            &self,
+            documents: &[String],
+        ) -> Result<Vec<Vec<f64>>, EmbedderError> {
+            let mut vectors = Vec::new();
+            for document in documents {
+                vectors.push(self.embed_query(document).await?);
+            }
+            Ok(vectors)
+        }
+
+        async fn embed_query(&self, text: &str) -> Result<Vec<f64>, EmbedderError> {
+            Ok(["rust", "python", "cooking"]
+                .iter()
+                .map(|keyword| text.matches(keyword).count() as f64 + 0.01)
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_similarity_search_with_filter() {
+        let store = InMemoryVectorStore::new(KeywordEmbedder);
+        let documents = vec![
+            Document::new("rust ownership rules")
+                .with_metadata_entry("source", json!("manual"))
+                .with_metadata_entry("year", json!(2019)),
+            Document::new("rust async rust")
+                .with_metadata_entry("source", json!("blog"))
+                .with_metadata_entry("year", json!(2023)),
+            Document::new("python cooking recipes")
+                .with_metadata_entry("source", json!("manual"))
+                .with_metadata_entry("year", json!(2022)),
+        ];
+        let ids = add_documents!(store, &documents).await.unwrap();
+        assert_eq!(ids, vec!["0", "1", "2"]);
+        assert_eq!(store.len(), 3);
+
+        let results = similarity_search!(store, "rust", 2).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|doc| doc.page_content.contains("rust")));
+
+        let options = VecStoreOptions::new().with_metadata_filter(
+            MetadataFilter::eq("source", "manual").and(MetadataFilter::gte("year", 2020)),
+        );
+        let results = similarity_search!(store, "rust", 2, &options)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].page_content, "python cooking recipes");
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_snapshot_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "in_memory_vector_store_snapshot_{}.json",
+            std::process::id()
+        ));
+        let store = InMemoryVectorStore::new(KeywordEmbedder);
+        let documents = vec![
+            Document::new("rust ownership rules").with_metadata_entry("source", json!("manual")),
+            Document::new("python cooking recipes"),
+        ];
+        add_documents!(store, &documents).await.unwrap();
+        store.save(&path).await.unwrap();
+
+        let restored = InMemoryVectorStore::new(KeywordEmbedder).with_dimensions(3);
+        assert_eq!(restored.load(&path).await.unwrap(), 2);
+        let results = similarity_search!(restored, "rust", 1).await.unwrap();
+        assert_eq!(results[0].page_content, "rust ownership rules");
+        assert_eq!(results[0].metadata["source"], json!("manual"));
+
+        let mismatched = InMemoryVectorStore::new(KeywordEmbedder).with_dimensions(8);
+        assert!(mismatched.load(&path).await.is_err());
+        assert!(mismatched.is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
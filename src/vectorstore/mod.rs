@@ -1,4 +1,9 @@
+mod filter;
+mod multi_query;
 mod options;
+mod snapshot;
+
+pub mod in_memory;
 
 #[cfg(feature = "postgres")]
 pub mod pgvector;
@@ -17,5 +22,8 @@ pub mod qdrant;
 
 mod vectorstore;
 
+pub use filter::*;
+pub use multi_query::*;
 pub use options::*;
+pub use snapshot::*;
 pub use vectorstore::*;
@@ -0,0 +1,195 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    error::Error,
+    hash::{Hash, Hasher},
+    sync::OnceLock,
+};
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use regex::Regex;
+
+use crate::{
+    language_models::llm::LLM,
+    schemas::{Document, Retriever},
+};
+
+const DEFAULT_MULTI_QUERY_PROMPT: &str = "You are an AI language model assistant. Your task is \
+to generate {num_queries} different versions of the given user question to retrieve relevant \
+documents from a vector database. By generating multiple perspectives on the user question, \
+your goal is to help the user overcome some of the limitations of distance-based similarity \
+search. Provide these alternative questions separated by newlines, without numbering.
+
+Original question: {question}";
+
+/// Wraps a retriever, asking an LLM for paraphrases of the query and retrieving documents
+/// for each of them, which improves recall for poorly phrased queries. The union of the
+/// documents is returned in retrieval order, documents with the same content only once.
+///
+/// # Usage
+/// ```rust,ignore
+/// let retriever = MultiQueryRetriever::new(Retriever::new(store, 4), OpenAI::default())
+///     .with_num_queries(3);
+/// let docs = retriever.get_relevant_documents("how do i rust lifetimes").await?;
+/// ```
+pub struct MultiQueryRetriever {
+    retriever: Box<dyn Retriever>,
+    llm: Box<dyn LLM>,
+    num_queries: usize,
+    include_original: bool,
+    prompt: String,
+}
+
+impl MultiQueryRetriever {
+    pub fn new<R: Into<Box<dyn Retriever>>, L: Into<Box<dyn LLM>>>(retriever: R, llm: L) -> Self {
+        Self {
+            retriever: retriever.into(),
+            llm: llm.into(),
+            num_queries: 3,
+            include_original: true,
+            prompt: DEFAULT_MULTI_QUERY_PROMPT.to_string(),
+        }
+    }
+
+    /// Number of paraphrases to generate. Defaults to 3.
+    pub fn with_num_queries(mut self, num_queries: usize) -> Self {
+        self.num_queries = num_queries;
+        self
+    }
+
+    /// Whether the original query is also used for retrieval. Defaults to `true`.
+    pub fn with_include_original(mut self, include_original: bool) -> Self {
+        self.include_original = include_original;
+        self
+    }
+
+    /// Replaces the prompt used to generate the queries, which receives `{question}` and
+    /// `{num_queries}`. The LLM must answer with one query per line.
+    pub fn with_prompt<S: Into<String>>(mut self, prompt: S) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+
+    /// Returns the queries used to retrieve documents for `query`.
+    pub async fn generate_queries(&self, query: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let prompt = self
+            .prompt
+            .replace("{question}", query)
+            .replace("{num_queries}", &self.num_queries.to_string());
+        let output = self.llm.invoke(&prompt).await?;
+
+        let paraphrases = output
+            .lines()
+            .map(clean_query_line)
+            .filter(|line| !line.is_empty() && *line != query)
+            .take(self.num_queries)
+            .map(str::to_string);
+        let mut queries = Vec::new();
+        if self.include_original {
+            queries.push(query.to_string());
+        }
+        queries.extend(paraphrases);
+        Ok(queries)
+    }
+}
+
+/// Drops list markers like `1.`, `2)` or `-` that models add despite the instructions.
+/// Numbers that are part of the query, like `2024 tax rules`, are kept.
+fn clean_query_line(line: &str) -> &str {
+    static LIST_MARKER: OnceLock<Regex> = OnceLock::new();
+    let line = line.trim();
+    let marker = LIST_MARKER
+        .get_or_init(|| Regex::new(r"^(?:\d+[.)]|[-*])\s+").unwrap())
+        .find(line);
+    match marker {
+        Some(marker) => line[marker.end()..].trim(),
+        None => line,
+    }
+}
+
+fn content_hash(document: &Document) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    document.page_content.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[async_trait]
+impl Retriever for MultiQueryRetriever {
+    async fn get_relevant_documents(&self, query: &str) -> Result<Vec<Document>, Box<dyn Error>> {
+        let queries = self.generate_queries(query).await?;
+        log::debug!("Retrieving documents for queries: {:?}", queries);
+
+        let results = join_all(
+            queries
+                .iter()
+                .map(|query| self.retriever.get_relevant_documents(query)),
+        )
+        .await;
+
+        let mut seen = HashSet::new();
+        let mut documents = Vec::new();
+        for result in results {
+            for document in result? {
+                if seen.insert(content_hash(&document)) {
+                    documents.push(document);
+                }
+            }
+        }
+        Ok(documents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::fake::FakeLLM;
+
+    use super::*;
+
+    struct KeywordRetriever {}
+
+    #[async_trait]
+    impl Retriever for KeywordRetriever {
+        async fn get_relevant_documents(
+            &self,
+            query: &str,
+        ) -> Result<Vec<Document>, Box<dyn Error>> {
+            Ok(query
+                .split_whitespace()
+                .map(|word| Document::new(format!("about {}", word.to_lowercase())))
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multi_query_retriever() {
+        let llm = FakeLLM::new().with_responses(vec!["1. Rust lifetimes\n2) Borrow checker\n\n"]);
+        let retriever = MultiQueryRetriever::new(KeywordRetriever {}, llm.clone());
+
+        let docs = retriever
+            .get_relevant_documents("rust borrow")
+            .await
+            .unwrap();
+        let contents: Vec<&str> = docs.iter().map(|d| d.page_content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec![
+                "about rust",
+                "about borrow",
+                "about lifetimes",
+                "about checker"
+            ]
+        );
+        assert!(llm.last_call().unwrap()[0].content.contains("rust borrow"));
+    }
+
+    #[test]
+    fn test_clean_query_line() {
+        assert_eq!(clean_query_line("1. Rust lifetimes"), "Rust lifetimes");
+        assert_eq!(clean_query_line("12) Borrow checker "), "Borrow checker");
+        assert_eq!(clean_query_line("- Ownership"), "Ownership");
+        assert_eq!(clean_query_line("* Traits"), "Traits");
+        assert_eq!(clean_query_line("2024 tax rules"), "2024 tax rules");
+        assert_eq!(clean_query_line("3.5 rounding"), "3.5 rounding");
+        assert_eq!(clean_query_line("-5 degrees"), "-5 degrees");
+    }
+}
@@ -156,6 +156,10 @@ impl VectorStore for Store {
         limit: usize,
         opt: &VecStoreOptions,
     ) -> Result<Vec<Document>, Box<dyn Error>> {
+        if opt.metadata_filter.is_some() {
+            return Err("OpenSearch doesn't support `metadata_filter`".into());
+        }
+
         let query_vector = self.embedder.embed_query(query).await?;
         let query = build_similarity_search_query(
             query_vector,
@@ -4,9 +4,11 @@ use serde_json::Value;
 
 use crate::embedding::embedder_trait::Embedder;
 
+use super::MetadataFilter;
+
 /// The `VecStoreOptions` struct is responsible for determining options when
 /// interacting with a Vector Store. The options include `name_space`, `score_threshold`,
-/// `filters`, and `embedder`.
+/// `filters`, `metadata_filter` and `embedder`.
 ///
 /// # Usage
 /// ```rust,ignore
@@ -14,12 +16,14 @@ use crate::embedding::embedder_trait::Embedder;
 ///     .with_name_space("my_custom_namespace")
 ///     .with_score_threshold(0.5)
 ///     .with_filters(json!({"genre": "Sci-Fi"}))
+///     .with_metadata_filter(MetadataFilter::gte("year", 2020))
 ///     .with_embedder(my_embedder);
 /// ```
 pub struct VecStoreOptions {
     pub name_space: Option<String>,
     pub score_threshold: Option<f32>,
     pub filters: Option<Value>,
+    pub metadata_filter: Option<MetadataFilter>,
     pub embedder: Option<Arc<dyn Embedder>>,
 }
 
@@ -35,6 +39,7 @@ impl VecStoreOptions {
             name_space: None,
             score_threshold: None,
             filters: None,
+            metadata_filter: None,
             embedder: None,
         }
     }
@@ -54,6 +59,12 @@ impl VecStoreOptions {
         self
     }
 
+    /// Backend-independent metadata filter, applied before ranking by stores that support it.
+    pub fn with_metadata_filter(mut self, metadata_filter: MetadataFilter) -> Self {
+        self.metadata_filter = Some(metadata_filter);
+        self
+    }
+
     pub fn with_embedder<E: Embedder + 'static>(mut self, embedder: E) -> Self {
         self.embedder = Some(Arc::new(embedder));
         self
@@ -6,7 +6,8 @@ use sqlx::{postgres::PgPoolOptions, Pool, Postgres, Row, Transaction};
 use crate::{embedding::embedder_trait::Embedder, vectorstore::VecStoreOptions};
 
 use super::{
-    HNSWIndex, Store, PG_LOCKID_EXTENSION, PG_LOCK_ID_COLLECTION_TABLE, PG_LOCK_ID_EMBEDDING_TABLE,
+    DistanceStrategy, HNSWIndex, Store, PG_LOCKID_EXTENSION, PG_LOCK_ID_COLLECTION_TABLE,
+    PG_LOCK_ID_EMBEDDING_TABLE,
 };
 
 const DEFAULT_COLLECTION_NAME: &str = "langchain";
@@ -27,8 +28,11 @@ pub struct StoreBuilder {
     collection_metadata: HashMap<String, Value>,
     vstore_options: VecStoreOptions,
     hns_index: Option<HNSWIndex>,
+    distance_strategy: DistanceStrategy,
 }
 
+pub type PgVectorStoreBuilder = StoreBuilder;
+
 impl StoreBuilder {
     // Returns a new StoreBuilder instance with default values for each option
     pub fn new() -> Self {
@@ -45,6 +49,7 @@ impl StoreBuilder {
             collection_metadata: HashMap::new(),
             vstore_options: VecStoreOptions::default(),
             hns_index: None,
+            distance_strategy: DistanceStrategy::default(),
         }
     }
 
@@ -103,6 +108,14 @@ impl StoreBuilder {
         self
     }
 
+    /// Distance used by `similarity_search`. Default: `DistanceStrategy::Cosine`.
+    /// An HNSW index created without an explicit operator class uses the matching one, see
+    /// [`DistanceStrategy::index_ops`].
+    pub fn distance_strategy(mut self, distance_strategy: DistanceStrategy) -> Self {
+        self.distance_strategy = distance_strategy;
+        self
+    }
+
     // Finalize the builder and construct the Store object
     pub async fn build(self) -> Result<Store, Box<dyn Error>> {
         if self.embedder.is_none() {
@@ -134,6 +147,7 @@ impl StoreBuilder {
             vector_dimensions: self.vector_dimensions,
             vstore_options: self.vstore_options,
             hns_index: self.hns_index,
+            distance_strategy: self.distance_strategy,
         })
     }
 
@@ -302,9 +316,14 @@ impl StoreBuilder {
         // See this for more details on HNWS indexes: https://github.com/pgvector/pgvector#hnsw
         match &self.hns_index {
             Some(hns_index) => {
+                let index_ops = if hns_index.distance_function.is_empty() {
+                    self.distance_strategy.index_ops()
+                } else {
+                    hns_index.distance_function.as_str()
+                };
                 let mut sql = format!(
                     r#"CREATE INDEX IF NOT EXISTS {}_embedding_hnsw ON {} USING hnsw (embedding {})"#,
-                    self.embedder_table_name, self.embedder_table_name, hns_index.distance_function
+                    self.embedder_table_name, self.embedder_table_name, index_ops
                 );
                 if hns_index.m > 0 && hns_index.ef_construction > 0 {
                     sql = format!(
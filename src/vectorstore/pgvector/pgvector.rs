@@ -9,7 +9,7 @@ use uuid::Uuid;
 use crate::{
     embedding::embedder_trait::Embedder,
     schemas::Document,
-    vectorstore::{VecStoreOptions, VectorStore},
+    vectorstore::{MetadataFilter, VecStoreOptions, VectorStore},
 };
 
 pub struct Store {
@@ -24,6 +24,50 @@ pub struct Store {
     pub(crate) vector_dimensions: i32,
     pub(crate) hns_index: Option<HNSWIndex>,
     pub(crate) vstore_options: VecStoreOptions,
+    pub(crate) distance_strategy: DistanceStrategy,
+}
+
+/// Name used in the LangChain documentation for this store.
+pub type PgVectorStore = Store;
+
+/// Distance used to rank the documents in `similarity_search`. The `score` of the
+/// returned documents is a similarity, higher meaning closer.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DistanceStrategy {
+    /// `<=>` operator, score is `1 - distance`.
+    #[default]
+    Cosine,
+    /// `<->` operator, score is `1 / (1 + distance)`.
+    Euclidean,
+    /// `<#>` operator (negative inner product), score is the inner product.
+    InnerProduct,
+}
+
+impl DistanceStrategy {
+    pub fn operator(&self) -> &'static str {
+        match self {
+            DistanceStrategy::Cosine => "<=>",
+            DistanceStrategy::Euclidean => "<->",
+            DistanceStrategy::InnerProduct => "<#>",
+        }
+    }
+
+    /// Operator class to use when creating an HNSW index for this distance.
+    pub fn index_ops(&self) -> &'static str {
+        match self {
+            DistanceStrategy::Cosine => "vector_cosine_ops",
+            DistanceStrategy::Euclidean => "vector_l2_ops",
+            DistanceStrategy::InnerProduct => "vector_ip_ops",
+        }
+    }
+
+    pub fn score(&self, distance: f64) -> f64 {
+        match self {
+            DistanceStrategy::Cosine => 1.0 - distance,
+            DistanceStrategy::Euclidean => 1.0 / (1.0 + distance),
+            DistanceStrategy::InnerProduct => -distance,
+        }
+    }
 }
 
 pub struct HNSWIndex {
@@ -33,6 +77,8 @@ pub struct HNSWIndex {
 }
 
 impl HNSWIndex {
+    /// `distance_function` is the operator class of the index, e.g. `vector_l2_ops`. Pass an
+    /// empty string to use the one matching the store's [`DistanceStrategy`].
     pub fn new(m: i32, ef_construction: i32, distance_function: &str) -> Self {
         HNSWIndex {
             m,
@@ -42,17 +88,102 @@ impl HNSWIndex {
     }
 }
 
+/// Parameter bound for a [`MetadataFilter`] translated to SQL.
+enum FilterParam {
+    Key(String),
+    Value(Value),
+}
+
+/// Translates `filter` into a SQL condition on the jsonb expression `column`. Keys and values
+/// are bound as parameters numbered from `params.len() + first_param`. Conditions on missing
+/// keys evaluate to false instead of NULL, so `Not` and `Ne` behave like
+/// [`MetadataFilter::matches`].
+fn metadata_filter_sql(
+    filter: &MetadataFilter,
+    column: &str,
+    first_param: usize,
+    params: &mut Vec<FilterParam>,
+) -> String {
+    let mut bind = |param: FilterParam| {
+        params.push(param);
+        format!("${}", first_param + params.len() - 1)
+    };
+    match filter {
+        MetadataFilter::Eq(key, value) => {
+            let (key, value) = (
+                bind(FilterParam::Key(key.clone())),
+                bind(FilterParam::Value(value.clone())),
+            );
+            format!("COALESCE(({} -> {}) = {}, false)", column, key, value)
+        }
+        MetadataFilter::Ne(key, value) => format!(
+            "NOT {}",
+            metadata_filter_sql(
+                &MetadataFilter::Eq(key.clone(), value.clone()),
+                column,
+                first_param,
+                params
+            )
+        ),
+        MetadataFilter::Gt(key, value)
+        | MetadataFilter::Gte(key, value)
+        | MetadataFilter::Lt(key, value)
+        | MetadataFilter::Lte(key, value) => {
+            let operator = match filter {
+                MetadataFilter::Gt(..) => ">",
+                MetadataFilter::Gte(..) => ">=",
+                MetadataFilter::Lt(..) => "<",
+                _ => "<=",
+            };
+            let (key, value) = (
+                bind(FilterParam::Key(key.clone())),
+                bind(FilterParam::Value(value.clone())),
+            );
+            // jsonb orders values of different types, only compare numbers with numbers and
+            // strings with strings.
+            format!(
+                "COALESCE(jsonb_typeof({column} -> {key}) = jsonb_typeof({value}) \
+                 AND jsonb_typeof({value}) IN ('number', 'string') \
+                 AND ({column} -> {key}) {operator} {value}, false)",
+            )
+        }
+        MetadataFilter::In(key, values) => {
+            let filters = values
+                .iter()
+                .map(|value| MetadataFilter::Eq(key.clone(), value.clone()))
+                .collect();
+            metadata_filter_sql(&MetadataFilter::Or(filters), column, first_param, params)
+        }
+        MetadataFilter::And(filters) | MetadataFilter::Or(filters) => {
+            let (separator, empty) = match filter {
+                MetadataFilter::And(_) => (" AND ", "true"),
+                _ => (" OR ", "false"),
+            };
+            if filters.is_empty() {
+                return empty.to_string();
+            }
+            let conditions: Vec<String> = filters
+                .iter()
+                .map(|filter| metadata_filter_sql(filter, column, first_param, params))
+                .collect();
+            format!("({})", conditions.join(separator))
+        }
+        MetadataFilter::Not(filter) => {
+            format!(
+                "NOT {}",
+                metadata_filter_sql(filter, column, first_param, params)
+            )
+        }
+    }
+}
+
 impl Store {
-    // getFilters return metadata filters, now only support map[key]value pattern
-    // TODO: should support more types like {"key1": {"key2":"values2"}} or {"key": ["value1", "values2"]}.
-    fn get_filters(&self, opt: &VecStoreOptions) -> Result<HashMap<String, Value>, Box<dyn Error>> {
+    // getFilters return the metadata filters, matched with jsonb containment (`@>`), so
+    // nested objects like {"key1": {"key2": "value2"}} and arrays are supported.
+    fn get_filters(&self, opt: &VecStoreOptions) -> Result<Value, Box<dyn Error>> {
         match &opt.filters {
-            Some(Value::Object(map)) => {
-                // Convert serde_json Map to HashMap<String, Value>
-                let filters = map.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
-                Ok(filters)
-            }
-            None => Ok(HashMap::new()), // No filters provided
+            Some(Value::Object(map)) => Ok(Value::Object(map.clone())),
+            None => Ok(json!({})), // No filters provided, `{}` is contained in everything
             _ => Err("Invalid filters format".into()), // Filters provided but not in the expected format
         }
     }
@@ -166,15 +297,21 @@ impl VectorStore for Store {
     ) -> Result<Vec<Document>, Box<dyn Error>> {
         let collection_name = self.get_name_space(opt);
         let filter = self.get_filters(opt)?;
-        let mut where_querys = filter
-            .iter()
-            .map(|(k, v)| format!("(data.cmetadata ->> '{}') = '{}'", k, v))
-            .collect::<Vec<String>>()
-            .join(" AND ");
-
-        if where_querys.is_empty() {
-            where_querys = "TRUE".to_string();
-        }
+        let score_threshold = self.get_score_threshold(opt)?;
+
+        let mut filter_params = Vec::new();
+        let metadata_filter = match &opt.metadata_filter {
+            Some(metadata_filter) => format!(
+                "AND {}",
+                metadata_filter_sql(
+                    metadata_filter,
+                    "data.cmetadata::jsonb",
+                    6,
+                    &mut filter_params
+                )
+            ),
+            None => String::new(),
+        };
 
         let sql = format!(
             r#"WITH filtered_embedding_dims AS MATERIALIZED (
@@ -192,46 +329,54 @@ impl VectorStore for Store {
             FROM (
                 SELECT
                     filtered_embedding_dims.*,
-                    embedding <=> $2 AS distance
+                    embedding {} $2 AS distance
                 FROM
                     filtered_embedding_dims
                     JOIN {} ON filtered_embedding_dims.collection_id = {}.uuid
-                WHERE {}.name = '{}'
+                WHERE {}.name = $4
             ) AS data
-            WHERE {}
+            WHERE data.cmetadata::jsonb @> $5 {}
             ORDER BY
-                data.distance DESC
+                data.distance ASC
             LIMIT $3"#,
             self.embedder_table_name,
+            self.distance_strategy.operator(),
             self.collection_table_name,
             self.collection_table_name,
             self.collection_table_name,
-            collection_name,
-            where_querys,
+            metadata_filter,
         );
 
         let query_vector = self.embedder.embed_query(query).await?;
 
         let vector_dims = query_vector.len();
 
-        let rows = sqlx::query(&sql)
+        let mut query = sqlx::query(&sql)
             .bind(vector_dims as i64)
-            .bind(&Vector::from(
+            .bind(Vector::from(
                 query_vector
                     .into_iter()
                     .map(|x| x as f32)
                     .collect::<Vec<f32>>(),
             ))
             .bind(limit as i32)
-            .fetch_all(&self.pool)
-            .await?;
+            .bind(&collection_name)
+            .bind(&filter);
+        for param in filter_params {
+            query = match param {
+                FilterParam::Key(key) => query.bind(key),
+                FilterParam::Value(value) => query.bind(value),
+            };
+        }
+        let rows = query.fetch_all(&self.pool).await?;
 
         let docs = rows
             .into_iter()
             .map(|row| {
                 let page_content: String = row.try_get(0)?;
                 let metadata_json: Value = row.try_get(1)?;
-                let score: f64 = row.try_get(2)?;
+                let distance: f64 = row.try_get(2)?;
+                let score = self.distance_strategy.score(distance);
 
                 let metadata = if let Value::Object(obj) = metadata_json {
                     obj.into_iter().collect()
@@ -247,6 +392,47 @@ impl VectorStore for Store {
             })
             .collect::<Result<Vec<Document>, sqlx::Error>>()?;
 
-        Ok(docs)
+        Ok(docs
+            .into_iter()
+            .filter(|doc| doc.score >= score_threshold as f64)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance_strategy_scores() {
+        assert_eq!(DistanceStrategy::default().operator(), "<=>");
+        assert_eq!(DistanceStrategy::Cosine.score(0.25), 0.75);
+        assert_eq!(DistanceStrategy::Euclidean.score(1.0), 0.5);
+        assert_eq!(DistanceStrategy::InnerProduct.score(-3.0), 3.0);
+        assert!(DistanceStrategy::Euclidean.score(0.1) > DistanceStrategy::Euclidean.score(2.0));
+    }
+
+    #[test]
+    fn test_metadata_filter_sql() {
+        let filter = MetadataFilter::eq("source", "manual")
+            .and(MetadataFilter::gte("year", 2020).or(MetadataFilter::is_in("lang", vec!["en"])))
+            .and(MetadataFilter::ne("draft", true));
+        let mut params = Vec::new();
+        let sql = metadata_filter_sql(&filter, "m", 6, &mut params);
+
+        assert!(sql.starts_with("(COALESCE((m -> $6) = $7, false) AND ("));
+        assert!(sql.contains("AND (m -> $8) >= $9, false)"));
+        assert!(sql.contains("(COALESCE((m -> $10) = $11, false))"));
+        assert!(sql.ends_with("NOT COALESCE((m -> $12) = $13, false))"));
+        assert_eq!(params.len(), 8);
+        assert!(matches!(&params[0], FilterParam::Key(key) if key == "source"));
+        assert!(matches!(&params[3], FilterParam::Value(value) if value == &json!(2020)));
+
+        let mut params = Vec::new();
+        assert_eq!(
+            metadata_filter_sql(&MetadataFilter::Or(vec![]), "m", 6, &mut params),
+            "false"
+        );
+        assert!(params.is_empty());
     }
 }
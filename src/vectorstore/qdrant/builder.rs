@@ -12,6 +12,9 @@ pub struct StoreBuilder {
     content_field: String,
     metadata_field: String,
     recreate_collection: bool,
+    create_collection: bool,
+    distance: Distance,
+    vector_size: Option<u64>,
     search_filter: Option<Filter>,
 }
 
@@ -32,6 +35,9 @@ impl StoreBuilder {
             content_field: "page_content".to_string(),
             metadata_field: "metadata".to_string(),
             recreate_collection: false,
+            create_collection: true,
+            distance: Distance::Cosine,
+            vector_size: None,
         }
     }
 
@@ -51,8 +57,9 @@ impl StoreBuilder {
     /// It is recommended to create a collection in advance, with the required configurations.
     /// https://qdrant.tech/documentation/concepts/collections/#create-a-collection
     ///
-    /// If the collection doesn't exist, it will be created with the embedding provider's dimension
-    /// and Cosine similarity metric.
+    /// If the collection doesn't exist, it will be created with the configured `vector_size`
+    /// (or the embedding provider's dimension) and `distance` metric, unless
+    /// `create_collection` is set to false.
     pub fn collection_name(mut self, collection_name: &str) -> Self {
         self.collection_name = Some(collection_name.to_string());
         self
@@ -73,12 +80,34 @@ impl StoreBuilder {
     }
 
     /// If set to true, the collection will be deleted and recreated using
-    /// the embedding provider's dimension and Cosine similarity metric.
+    /// the configured vector size and distance metric.
     pub fn recreate_collection(mut self, recreate_collection: bool) -> Self {
         self.recreate_collection = recreate_collection;
         self
     }
 
+    /// If set to false, building the Store fails when the collection doesn't exist,
+    /// instead of creating it.
+    /// Default: true
+    pub fn create_collection(mut self, create_collection: bool) -> Self {
+        self.create_collection = create_collection;
+        self
+    }
+
+    /// Distance metric used when the collection has to be created.
+    /// Default: `Distance::Cosine`
+    pub fn distance(mut self, distance: Distance) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    /// Dimension of the vectors used when the collection has to be created.
+    /// If not set, it is obtained by embedding a sample text with the embedder.
+    pub fn vector_size(mut self, vector_size: u64) -> Self {
+        self.vector_size = Some(vector_size);
+        self
+    }
+
     /// Filter to be applied to the search results.
     /// https://qdrant.tech/documentation/concepts/filtering/
     /// Instance of use `qdrant_client::qdrant::Filter`
@@ -103,18 +132,27 @@ impl StoreBuilder {
             client.delete_collection(&collection_name).await?;
         }
 
+        if !collection_exists && !self.recreate_collection && !self.create_collection {
+            return Err(format!("Collection '{}' doesn't exist", collection_name).into());
+        }
+
         // Create the collection if it doesn't exist or recreate_collection flag is set
         if !collection_exists || self.recreate_collection {
-            // Embed some text to get the dimension of the embeddings
-            let embeddings = embedder
-                .embed_query("Text to retrieve embeddings dimension")
-                .await?;
-            let embeddings_dimension = embeddings.len() as u64;
+            let embeddings_dimension = match self.vector_size {
+                Some(vector_size) => vector_size,
+                None => {
+                    // Embed some text to get the dimension of the embeddings
+                    let embeddings = embedder
+                        .embed_query("Text to retrieve embeddings dimension")
+                        .await?;
+                    embeddings.len() as u64
+                }
+            };
 
             client
                 .create_collection(
                     CreateCollectionBuilder::new(&collection_name).vectors_config(
-                        VectorParamsBuilder::new(embeddings_dimension, Distance::Cosine),
+                        VectorParamsBuilder::new(embeddings_dimension, self.distance),
                     ),
                 )
                 .await?;
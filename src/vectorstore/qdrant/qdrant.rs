@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use qdrant_client::client::Payload;
 use qdrant_client::qdrant::{Filter, PointStruct, SearchPointsBuilder, UpsertPointsBuilder};
-use serde_json::json;
+use serde_json::{json, Value};
 use std::error::Error;
 use std::sync::Arc;
 
@@ -52,7 +52,7 @@ impl VectorStore for Store {
 
         for (id, (vector, payload)) in ids.clone().zip(vectors.zip(payloads)) {
             let vector: Vec<f32> = vector.into_iter().map(|f| f as f32).collect();
-            let point = PointStruct::new(id, vector, Payload::try_from(payload).unwrap());
+            let point = PointStruct::new(id, vector, Payload::try_from(payload)?);
             points.push(point);
         }
 
@@ -71,6 +71,10 @@ impl VectorStore for Store {
         limit: usize,
         opt: &VecStoreOptions,
     ) -> Result<Vec<Document>, Box<dyn Error>> {
+        if opt.metadata_filter.is_some() {
+            return Err("Qdrant doesn't support `metadata_filter`".into());
+        }
+
         if opt.name_space.is_some() {
             return Err("Qdrant doesn't support namespaces".into());
         }
@@ -106,12 +110,18 @@ impl VectorStore for Store {
             .result
             .into_iter()
             .map(|scored_point| {
-                let payload = scored_point.payload;
-
-                let page_content = payload[&self.content_field].to_string();
-                let metadata =
-                    serde_json::from_value(payload[&self.metadata_field].clone().into_json())
-                        .unwrap();
+                let mut payload = scored_point.payload;
+
+                let content = payload.remove(&self.content_field).map(|v| v.into_json());
+                let page_content = match content {
+                    Some(Value::String(content)) => content,
+                    Some(other) => other.to_string(),
+                    None => String::new(),
+                };
+                let metadata = payload
+                    .remove(&self.metadata_field)
+                    .and_then(|v| serde_json::from_value(v.into_json()).ok())
+                    .unwrap_or_default();
                 let score = scored_point.score as f64;
                 Document {
                     page_content,
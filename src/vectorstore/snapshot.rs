@@ -0,0 +1,25 @@
+use std::{error::Error, path::Path};
+
+use async_trait::async_trait;
+
+/// A vector store whose documents and embeddings can be saved to a file and loaded back,
+/// so documents don't need to be embedded again on every start.
+///
+/// # Usage
+/// ```rust,ignore
+/// if Path::new("store.json").exists() {
+///     store.load("store.json".as_ref()).await?;
+/// } else {
+///     add_documents!(store, &documents).await?;
+///     store.save("store.json".as_ref()).await?;
+/// }
+/// ```
+#[async_trait]
+pub trait VectorStoreSnapshot {
+    /// Writes every document of the store, with its embedding, to `path`.
+    async fn save(&self, path: &Path) -> Result<(), Box<dyn Error>>;
+
+    /// Replaces the documents of the store with the ones saved at `path`, returning how
+    /// many were loaded.
+    async fn load(&self, path: &Path) -> Result<usize, Box<dyn Error>>;
+}
@@ -124,8 +124,12 @@ impl VectorStore for Store {
         &self,
         query: &str,
         limit: usize,
-        _opt: &VecStoreOptions,
+        opt: &VecStoreOptions,
     ) -> Result<Vec<Document>, Box<dyn Error>> {
+        if opt.metadata_filter.is_some() {
+            return Err("sqlite_vss doesn't support `metadata_filter`".into());
+        }
+
         let table = &self.table;
 
         let query_vector = json!(self.embedder.embed_query(query).await?);
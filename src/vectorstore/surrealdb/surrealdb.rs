@@ -171,6 +171,10 @@ impl<C: Connection> VectorStore for Store<C> {
         limit: usize,
         opt: &VecStoreOptions,
     ) -> Result<Vec<Document>, Box<dyn Error>> {
+        if opt.metadata_filter.is_some() {
+            return Err("SurrealDB doesn't support `metadata_filter`".into());
+        }
+
         let collection_name = &self.collection_name;
         let collection_table_name = self.get_collection_table_name();
 